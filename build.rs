@@ -2,30 +2,338 @@ use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
+use std::process::Command;
+
+/// Read a size in bytes from the environment, checking its alignment
+fn section_size(var: &str, default: u64, align: u64) -> u64 {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let value = env::var(var).unwrap_or_else(|_| default.to_string());
+    match value.parse::<u64>() {
+        Ok(size) if size % align == 0 => size,
+        _ => panic!("{} must be a multiple of {} bytes, got {:?}", var, align, value),
+    }
+}
+
+/// Base ISA and extensions of the target, from rustc's target cfg
+///
+/// riscv32imac / riscv64imac save integer registers only; a target with
+/// F or D (e.g. riscv64gc) also saves f0-f31 and fcsr on every context
+/// switch and trap.
+struct Isa {
+    /// Register width in bits (32 or 64)
+    xlen: u32,
+    /// Floating-point register width in bits (0 without F/D)
+    flen: u32,
+    extensions: Vec<String>,
+}
+
+impl Isa {
+    fn from_target() -> Self {
+        let xlen = env::var("CARGO_CFG_TARGET_POINTER_WIDTH")
+            .ok()
+            .and_then(|w| w.parse().ok())
+            .expect("No target pointer width");
+        let extensions: Vec<String> = env::var("CARGO_CFG_TARGET_FEATURE")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::to_string)
+            .collect();
+        let has = |ext: &str| extensions.iter().any(|e| e == ext);
+        let flen = if has("d") {
+            64
+        } else if has("f") {
+            32
+        } else {
+            0
+        };
+        Isa { xlen, flen, extensions }
+    }
+
+    /// `-march` for the assembler, e.g. rv64imac or rv64imafdc
+    fn march(&self) -> String {
+        let mut march = format!("rv{}i", self.xlen);
+        for ext in ["m", "a", "f", "d", "c"] {
+            if self.extensions.iter().any(|e| e == ext) {
+                march.push_str(ext);
+            }
+        }
+        march
+    }
+
+    /// `-mabi` matching rustc's float ABI for the target
+    fn mabi(&self) -> &'static str {
+        match (self.xlen, self.flen) {
+            (32, 0) => "ilp32",
+            (32, 32) => "ilp32f",
+            (32, _) => "ilp32d",
+            (_, 0) => "lp64",
+            (_, 32) => "lp64f",
+            _ => "lp64d",
+        }
+    }
+}
+
+/// Byte offsets of the context switch frame (switch.S) and the trap frame
+/// (trap.S), shared with arch and arch::trap through generated files
+struct FrameLayout {
+    regbytes: u32,
+    fpbytes: u32,
+    context_mstatus: u32,
+    context_fregs: u32,
+    context_fcsr: u32,
+    context_size: u32,
+    trap_mepc: u32,
+    trap_mstatus: u32,
+    trap_mcause: u32,
+    trap_mtval: u32,
+    trap_fregs: u32,
+    trap_fcsr: u32,
+    trap_frame_size: u32,
+}
+
+fn align_up(value: u32, align: u32) -> u32 {
+    value.div_ceil(align) * align
+}
+
+impl FrameLayout {
+    /// x1-x31 first, then the CSRs, then (with F/D) f0-f31 and fcsr;
+    /// both frames are padded to the 16-byte stack alignment
+    fn new(isa: &Isa) -> Self {
+        let regbytes = isa.xlen / 8;
+        let fpbytes = isa.flen / 8;
+        let fp_area = |start: u32| {
+            let fregs = align_up(start, fpbytes.max(1));
+            let fcsr = fregs + 32 * fpbytes;
+            let end = if fpbytes > 0 { fcsr + regbytes } else { start };
+            (fregs, fcsr, align_up(end, 16))
+        };
+
+        let context_mstatus = 31 * regbytes;
+        let (context_fregs, context_fcsr, context_size) = fp_area(32 * regbytes);
+        let (trap_fregs, trap_fcsr, trap_frame_size) = fp_area(35 * regbytes);
+        FrameLayout {
+            regbytes,
+            fpbytes,
+            context_mstatus,
+            context_fregs,
+            context_fcsr,
+            context_size,
+            trap_mepc: 31 * regbytes,
+            trap_mstatus: 32 * regbytes,
+            trap_mcause: 33 * regbytes,
+            trap_mtval: 34 * regbytes,
+            trap_fregs,
+            trap_fcsr,
+            trap_frame_size,
+        }
+    }
+
+    /// Constants and load/store macros for switch.S and trap.S
+    fn asm_header(&self, isa: &Isa) -> String {
+        let (store, load) = if self.regbytes == 8 { ("sd", "ld") } else { ("sw", "lw") };
+        let (fstore, fload) = if self.fpbytes == 8 { ("fsd", "fld") } else { ("fsw", "flw") };
+        let mut header = format!(
+            r#"# Generated by build.rs for {march} - frame layout, do not edit
+.equ REGBYTES, {regbytes}
+.equ FPBYTES, {fpbytes}
+.equ CONTEXT_MSTATUS, {context_mstatus}
+.equ CONTEXT_FREGS, {context_fregs}
+.equ CONTEXT_FCSR, {context_fcsr}
+.equ CONTEXT_SIZE, {context_size}
+.equ TRAP_MEPC, {trap_mepc}
+.equ TRAP_MSTATUS, {trap_mstatus}
+.equ TRAP_MCAUSE, {trap_mcause}
+.equ TRAP_MTVAL, {trap_mtval}
+.equ TRAP_FREGS, {trap_fregs}
+.equ TRAP_FCSR, {trap_fcsr}
+.equ TRAP_FRAME_SIZE, {trap_frame_size}
+
+.macro REG_S reg, offset, base=sp
+    {store}      \reg, (\offset)(\base)
+.endm
+.macro REG_L reg, offset, base=sp
+    {load}      \reg, (\offset)(\base)
+.endm
+"#,
+            march = isa.march(),
+            regbytes = self.regbytes,
+            fpbytes = self.fpbytes,
+            context_mstatus = self.context_mstatus,
+            context_fregs = self.context_fregs,
+            context_fcsr = self.context_fcsr,
+            context_size = self.context_size,
+            trap_mepc = self.trap_mepc,
+            trap_mstatus = self.trap_mstatus,
+            trap_mcause = self.trap_mcause,
+            trap_mtval = self.trap_mtval,
+            trap_fregs = self.trap_fregs,
+            trap_fcsr = self.trap_fcsr,
+            trap_frame_size = self.trap_frame_size,
+        );
+        if self.fpbytes > 0 {
+            header.push_str(&format!(
+                r#"
+# f0-f31 and fcsr at `base` (t0 is clobbered)
+.macro SAVE_FP base
+.irp n, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31
+    {fstore}     f\n, (\base+\n*FPBYTES)(sp)
+.endr
+    frcsr   t0
+    REG_S   t0, \base+32*FPBYTES
+.endm
+.macro LOAD_FP base
+.irp n, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31
+    {fload}     f\n, (\base+\n*FPBYTES)(sp)
+.endr
+    REG_L   t0, \base+32*FPBYTES
+    fscsr   t0
+.endm
+"#
+            ));
+        } else {
+            header.push_str("\n.macro SAVE_FP base\n.endm\n.macro LOAD_FP base\n.endm\n");
+        }
+        header
+    }
+
+    /// Constants for arch (context frame) and arch::trap (TrapFrame)
+    fn write_rust(&self, isa: &Isa, path: &Path) {
+        let mut f = File::create(path).expect("Could not create file");
+        writeln!(
+            f,
+            r#"// Generated by build.rs for {march}
+
+/// Target ISA the context and trap frames are laid out for
+pub const ISA: &str = "{march}";
+
+/// Floating-point register width in bytes (0: integer registers only)
+pub const FP_REG_BYTES: usize = {fpbytes};
+
+/// Size of a saved context frame in bytes (switch.S)
+pub const CONTEXT_SIZE: usize = {context_size};
+
+/// Size of a trap frame in bytes (trap.S)
+pub const TRAP_FRAME_SIZE: usize = {trap_frame_size};"#,
+            march = isa.march(),
+            fpbytes = self.fpbytes,
+            context_size = self.context_size,
+            trap_frame_size = self.trap_frame_size,
+        )
+        .expect("Could not write file");
+    }
+}
 
 /// Put the linker script somewhere the linker can find it.
 fn main() {
     let out_dir = env::var("OUT_DIR").expect("No out dir");
     let dest_path = Path::new(&out_dir);
-    let mut f = File::create(&dest_path.join("memory.x")).expect("Could not create file");
+    let mut f = File::create(dest_path.join("memory.x")).expect("Could not create file");
 
     f.write_all(include_bytes!("memory.x"))
         .expect("Could not write file");
 
+    // ========================================================================
+    // Kernel section sizes and checks (generated rtos_sections.x)
+    // ========================================================================
+
+    let heap_size = section_size("RTOS_HEAP_SIZE", 64 * 1024, 16);
+    let task_stacks_max = section_size("RTOS_TASK_STACKS_MAX", 256 * 1024, 16);
+    let trace_buffer_max = section_size("RTOS_TRACE_BUFFER_MAX", 16 * 1024, 8);
+    let coredump_max = section_size("RTOS_COREDUMP_MAX", 8 * 1024, 8);
+
+    let mut sections = File::create(dest_path.join("rtos_sections.x")).expect("Could not create file");
+    writeln!(
+        sections,
+        r#"/* Generated by build.rs */
+_heap_size = {heap_size};
+
+ASSERT(__sheap % 16 == 0, "heap must be 16-byte aligned");
+ASSERT(__stask_stacks % 16 == 0, ".task_stacks must be 16-byte aligned");
+ASSERT(__strace_buffer % 8 == 0, ".trace_buffer must be 8-byte aligned");
+ASSERT(__scoredump % 8 == 0, ".coredump must be 8-byte aligned");
+ASSERT(__etask_stacks - __stask_stacks <= {task_stacks_max}, ".task_stacks exceeds RTOS_TASK_STACKS_MAX");
+ASSERT(__etrace_buffer - __strace_buffer <= {trace_buffer_max}, ".trace_buffer exceeds RTOS_TRACE_BUFFER_MAX");
+ASSERT(__ecoredump - __scoredump <= {coredump_max}, ".coredump exceeds RTOS_COREDUMP_MAX");"#
+    )
+    .expect("Could not write file");
+
     println!("cargo:rustc-link-search={}", dest_path.display());
 
     println!("cargo:rerun-if-changed=memory.x");
     println!("cargo:rerun-if-changed=build.rs");
 
     // ========================================================================
-    // Assembly compilation for context switching
+    // Assembly compilation for context switching and trap entry
     // ========================================================================
-    
+
     println!("cargo:rerun-if-changed=src/arch/switch.S");
-    
-    cc::Build::new()
-        .file("src/arch/switch.S")
-        .flag("-march=rv64imac")  // RISC-V architecture flags
-        .flag("-mabi=lp64")       // 64-bit ABI
+    println!("cargo:rerun-if-changed=src/arch/trap.S");
+
+    let isa = Isa::from_target();
+    let layout = FrameLayout::new(&isa);
+    layout.write_rust(&isa, &dest_path.join("context_layout.rs"));
+
+    // The layout header is prepended to each source rather than included,
+    // so the files assemble with or without a C preprocessor
+    let header = layout.asm_header(&isa);
+    let mut build = cc::Build::new();
+    for name in ["switch.S", "trap.S"] {
+        let source = std::fs::read_to_string(Path::new("src/arch").join(name))
+            .expect("Could not read assembly source");
+        let generated = dest_path.join(name);
+        std::fs::write(&generated, format!("{}\n{}", header, source)).expect("Could not write file");
+        build.file(generated);
+    }
+    build
+        .flag(format!("-march={}", isa.march()))
+        .flag(format!("-mabi={}", isa.mabi()))
         .compile("context_switch");
+
+    // ========================================================================
+    // Build-time kernel configuration
+    // ========================================================================
+
+    println!("cargo:rerun-if-env-changed=RTOS_TICK_RATE_HZ");
+    println!("cargo:rerun-if-env-changed=RTOS_TICK_ROUNDING");
+
+    let tick_rate = env::var("RTOS_TICK_RATE_HZ").unwrap_or_else(|_| "1000".to_string());
+    match tick_rate.parse::<u64>() {
+        Ok(hz) if hz > 0 => {}
+        _ => panic!("RTOS_TICK_RATE_HZ must be a positive integer, got {:?}", tick_rate),
+    }
+
+    let tick_rounding = env::var("RTOS_TICK_ROUNDING").unwrap_or_else(|_| "up".to_string());
+    if !["up", "down", "nearest"].contains(&tick_rounding.as_str()) {
+        panic!("RTOS_TICK_ROUNDING must be up, down or nearest, got {:?}", tick_rounding);
+    }
+
+    println!("cargo:rustc-env=RTOS_TICK_RATE_HZ={}", tick_rate);
+    println!("cargo:rustc-env=RTOS_TICK_ROUNDING={}", tick_rounding);
+
+    // ========================================================================
+    // Version information for kernel::version()
+    // ========================================================================
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let git_dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| !o.stdout.is_empty())
+        .unwrap_or(false);
+
+    println!("cargo:rustc-env=RTOS_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=RTOS_GIT_DIRTY={}", if git_dirty { "1" } else { "0" });
 }
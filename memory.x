@@ -8,4 +8,64 @@ REGION_ALIAS("REGION_RODATA", RAM);
 REGION_ALIAS("REGION_DATA", RAM);
 REGION_ALIAS("REGION_BSS", RAM);
 REGION_ALIAS("REGION_HEAP", RAM);
-REGION_ALIAS("REGION_STACK", RAM);
\ No newline at end of file
+REGION_ALIAS("REGION_STACK", RAM);
+/* Panic message persistence: not zeroed at boot, survives a soft reset */
+PROVIDE(_panic_persist_size = 1K);
+
+/* Build-time section sizes and checks, generated by build.rs */
+INCLUDE rtos_sections.x
+
+/*
+ * Kernel sections. None of these are loaded or zeroed at boot; statics
+ * placed in them with #[link_section] must not rely on their initializer.
+ * Size limits and alignment checks are generated by build.rs.
+ */
+SECTIONS
+{
+  .panic_persist (NOLOAD) : ALIGN(8)
+  {
+    __spanic_persist = .;
+    . += _panic_persist_size;
+    . = ALIGN(8);
+    __epanic_persist = .;
+  } > RAM
+
+  /* Crash-dump record buffer */
+  .coredump (NOLOAD) : ALIGN(8)
+  {
+    __scoredump = .;
+    KEEP(*(.coredump .coredump.*));
+    . = ALIGN(8);
+    __ecoredump = .;
+  } > RAM
+
+  /* Trace recorder buffers */
+  .trace_buffer (NOLOAD) : ALIGN(8)
+  {
+    __strace_buffer = .;
+    KEEP(*(.trace_buffer .trace_buffer.*));
+    . = ALIGN(8);
+    __etrace_buffer = .;
+  } > RAM
+
+  /* Task stacks, grouped so overflow checks and dumps can find them */
+  .task_stacks (NOLOAD) : ALIGN(16)
+  {
+    __stask_stacks = .;
+    *(.task_stacks .task_stacks.*);
+    . = ALIGN(16);
+    __etask_stacks = .;
+  } > RAM
+} INSERT AFTER .uninit;
+
+/* Kernel symbol table, filled in after linking by `cargo xtask symtab` */
+SECTIONS
+{
+  .rtos_symtab : ALIGN(8)
+  {
+    __srtos_symtab = .;
+    KEEP(*(.rtos_symtab));
+    . = ALIGN(8);
+    __ertos_symtab = .;
+  } > REGION_RODATA
+} INSERT AFTER .rodata;
@@ -0,0 +1,193 @@
+// Memory-mapped I/O accessors
+//
+// Drivers touch device registers only through these functions. With the
+// `mmio-audit` feature every access is also recorded (address, value,
+// width, and the task or interrupt that made it) into a ring in the
+// .trace_buffer section, so the order in which drivers poked the hardware
+// can be read back after the fact, e.g. to find initialization order bugs
+// under QEMU. Without the feature the accessors are plain volatile loads
+// and stores.
+
+use core::ptr;
+
+macro_rules! accessors {
+    ($read:ident, $write:ident, $ty:ty) => {
+        /// Read a device register
+        ///
+        /// # Safety
+        /// `addr` must be a valid, suitably aligned device register
+        #[inline]
+        pub unsafe fn $read(addr: usize) -> $ty {
+            let value = ptr::read_volatile(addr as *const $ty);
+            #[cfg(feature = "mmio-audit")]
+            audit::record(addr, value as u64, core::mem::size_of::<$ty>() as u8, false);
+            value
+        }
+
+        /// Write a device register
+        ///
+        /// # Safety
+        /// `addr` must be a valid, suitably aligned device register
+        #[inline]
+        pub unsafe fn $write(addr: usize, value: $ty) {
+            #[cfg(feature = "mmio-audit")]
+            audit::record(addr, value as u64, core::mem::size_of::<$ty>() as u8, true);
+            ptr::write_volatile(addr as *mut $ty, value);
+        }
+    };
+}
+
+accessors!(read8, write8, u8);
+accessors!(read16, write16, u16);
+accessors!(read32, write32, u32);
+accessors!(read64, write64, u64);
+
+#[cfg(feature = "mmio-audit")]
+pub use audit::*;
+
+#[cfg(feature = "mmio-audit")]
+mod audit {
+    use crate::arch::{in_interrupt, read_mcycle};
+    use crate::kernel::scheduler::get_current_task;
+    use crate::kernel::task::TaskControlBlock;
+    use crate::kernel::types::config;
+    use core::fmt::{self, Write};
+    use core::ptr;
+    use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+    /// Who made an access
+    #[derive(Copy, Clone, PartialEq, Eq)]
+    pub enum MmioContext {
+        /// Before the scheduler picked a first task
+        Boot,
+        /// From a task
+        Task(*mut TaskControlBlock),
+        /// From an interrupt handler
+        Interrupt,
+    }
+
+    /// One recorded register access
+    #[derive(Copy, Clone)]
+    pub struct MmioAccess {
+        /// mcycle at the time of the access
+        pub cycle: u64,
+        pub addr: usize,
+        pub value: u64,
+        /// Access width in bytes
+        pub width: u8,
+        pub write: bool,
+        pub context: MmioContext,
+    }
+
+    // Not zeroed at boot: entries are only read below RECORDED
+    #[link_section = ".trace_buffer"]
+    static mut ACCESSES: [MmioAccess; config::MMIO_AUDIT_SLOTS] = [MmioAccess {
+        cycle: 0,
+        addr: 0,
+        value: 0,
+        width: 0,
+        write: false,
+        context: MmioContext::Boot,
+    }; config::MMIO_AUDIT_SLOTS];
+
+    /// Total accesses recorded (next slot = RECORDED % SLOTS)
+    static RECORDED: AtomicU64 = AtomicU64::new(0);
+
+    static ENABLED: AtomicBool = AtomicBool::new(true);
+    /// Only addresses in [FILTER_BASE, FILTER_BASE + FILTER_LEN) are recorded
+    static FILTER_BASE: AtomicUsize = AtomicUsize::new(0);
+    static FILTER_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    pub(super) fn record(addr: usize, value: u64, width: u8, write: bool) {
+        if !ENABLED.load(Ordering::Relaxed)
+            || addr.wrapping_sub(FILTER_BASE.load(Ordering::Relaxed)) >= FILTER_LEN.load(Ordering::Relaxed)
+        {
+            return;
+        }
+
+        let context = if in_interrupt() {
+            MmioContext::Interrupt
+        } else {
+            match get_current_task() {
+                task if task.is_null() => MmioContext::Boot,
+                task => MmioContext::Task(task),
+            }
+        };
+
+        crate::critical_section! {
+            let seq = RECORDED.load(Ordering::Relaxed);
+            let slot = (seq % config::MMIO_AUDIT_SLOTS as u64) as usize;
+            unsafe {
+                (*ptr::addr_of_mut!(ACCESSES))[slot] = MmioAccess {
+                    cycle: read_mcycle(),
+                    addr,
+                    value,
+                    width,
+                    write,
+                    context,
+                };
+            }
+            RECORDED.store(seq + 1, Ordering::Relaxed);
+        }
+    }
+
+    /// Turn recording on or off (on by default, so boot is captured)
+    pub fn mmio_audit_enable(enabled: bool) {
+        ENABLED.store(enabled, Ordering::Release);
+    }
+
+    /// Record only accesses to `len` bytes starting at `base`
+    ///
+    /// `mmio_audit_filter(0, usize::MAX)` records everything again.
+    pub fn mmio_audit_filter(base: usize, len: usize) {
+        FILTER_BASE.store(base, Ordering::Relaxed);
+        FILTER_LEN.store(len, Ordering::Relaxed);
+    }
+
+    /// Discard recorded accesses
+    pub fn mmio_audit_reset() {
+        RECORDED.store(0, Ordering::Relaxed);
+    }
+
+    /// Visit retained accesses, oldest first
+    pub fn mmio_audit_for_each<F: FnMut(&MmioAccess)>(mut f: F) {
+        let accesses = unsafe { &*ptr::addr_of!(ACCESSES) };
+        let recorded = RECORDED.load(Ordering::Acquire);
+        let first = recorded.saturating_sub(config::MMIO_AUDIT_SLOTS as u64);
+
+        for seq in first..recorded {
+            f(&accesses[(seq % config::MMIO_AUDIT_SLOTS as u64) as usize]);
+        }
+    }
+
+    struct SinkWriter(fn(&[u8]));
+
+    impl Write for SinkWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            (self.0)(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    /// Print retained accesses, one per line:
+    /// "<cycle> <task|isr|boot> W4 0x10000000 = 0x41"
+    pub fn mmio_audit_dump(sink: fn(&[u8])) {
+        let mut out = SinkWriter(sink);
+        mmio_audit_for_each(|a| {
+            let _ = write!(out, "{} ", a.cycle);
+            let _ = match a.context {
+                MmioContext::Boot => out.write_str("boot"),
+                MmioContext::Task(task) => out.write_str(unsafe { (*task).name_str() }),
+                MmioContext::Interrupt => out.write_str("isr"),
+            };
+            let _ = writeln!(
+                out,
+                " {}{} {:#x} = {:#x}",
+                if a.write { 'W' } else { 'R' },
+                a.width,
+                a.addr,
+                a.value
+            );
+        });
+    }
+}
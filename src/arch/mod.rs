@@ -1,29 +1,79 @@
-// Architecture-specific code for RISC-V 64-bit
-// INTEGER-ONLY VERSION (No Floating Point)
+// Architecture-specific code for RISC-V
+//
+// The context and trap frame layouts depend on the target's register
+// width and floating-point extensions; build.rs generates them for
+// switch.S, trap.S and the constants below.
 
 use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, ErrorKind, Result, RtosError};
 use core::arch::asm;
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
-/// Size of saved context on stack (in bytes)
-/// RISC-V has 32 registers, but x0 (zero) is hardwired to 0
-/// So we save 31 registers × 8 bytes = 248 bytes
-pub const CONTEXT_SIZE: usize = 31 * 8;
+pub mod mmio;
+pub mod plic;
+pub mod trap;
+
+use trap::TrapFrame;
+
+mod layout {
+    include!(concat!(env!("OUT_DIR"), "/context_layout.rs"));
+}
+
+pub use layout::{CONTEXT_SIZE, FP_REG_BYTES, ISA, TRAP_FRAME_SIZE};
+
+/// Number of words in a saved context frame
+/// x1-x31 (x0 is hardwired to 0) and mstatus, then f0-f31 and fcsr on
+/// F/D targets, padded to 16 bytes
+pub const CONTEXT_WORDS: usize = CONTEXT_SIZE / core::mem::size_of::<usize>();
+
+/// Frame slot holding the task's mstatus (see switch.S)
+pub const CONTEXT_MSTATUS_INDEX: usize = 31;
+
+/// Frame slot holding a0 (x10), the task entry argument
+pub const CONTEXT_A0_INDEX: usize = 9;
+
+/// mstatus.MIE - machine interrupts enabled
+pub const MSTATUS_MIE: usize = 1 << 3;
+
+/// mstatus.MPIE - interrupt enable prior to the last trap
+pub const MSTATUS_MPIE: usize = 1 << 7;
+
+/// mstatus.MPP = Machine mode
+pub const MSTATUS_MPP_MACHINE: usize = 0b11 << 11;
+
+/// Interrupt state a new task starts with
+///
+/// Tasks start with interrupts enabled; individual sources are still
+/// gated by the `mie` register.
+pub const INITIAL_TASK_MSTATUS: usize = MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP_MACHINE;
 
 /// Stack must be aligned to 16 bytes (RISC-V ABI requirement)
 pub const STACK_ALIGNMENT: usize = 16;
 
+/// Task entry point, called with the argument given at creation in a0
+pub type TaskEntry = extern "C" fn(*mut c_void) -> !;
+
+/// Entry point without an argument (pre-argument task ABI)
+pub type LegacyTaskEntry = extern "C" fn() -> !;
+
 /// Initialize a task's stack for first-time execution
 ///
 /// This creates a fake context on the stack so that when we "restore"
 /// it for the first time, the task starts running from its entry point.
+/// The rest of the stack is filled with config::STACK_FILL_BYTE for
+/// overflow detection and high-water marks (kernel::stack).
 ///
 /// # Arguments
 /// * `entry` - Task entry point function
+/// * `arg` - Value passed to `entry` (in a0), e.g. per-instance state
 /// * `stack` - Task's stack buffer (must be aligned)
 ///
 /// # Returns
 /// Pointer to top of initialized stack (where SP should point)
-pub fn initialize_task_stack(entry: extern "C" fn() -> !, stack: &mut [usize]) -> *mut usize {
+pub fn initialize_task_stack(entry: TaskEntry, arg: *mut c_void, stack: &mut [usize]) -> *mut usize {
+    stack.fill(crate::kernel::stack::STACK_FILL_WORD);
+
     // Get the top of the stack (stacks grow downward)
     let stack_top = unsafe { stack.as_mut_ptr().add(stack.len()) };
     
@@ -31,21 +81,11 @@ pub fn initialize_task_stack(entry: extern "C" fn() -> !, stack: &mut [usize]) -
     let aligned_top = (stack_top as usize) & !(STACK_ALIGNMENT - 1);
     let mut sp = aligned_top as *mut usize;
     
-    // Reserve space for context (31 registers)
-    sp = unsafe { sp.sub(31) };
+    // Reserve space for the context frame
+    sp = unsafe { sp.sub(CONTEXT_WORDS) };
     
-    // Initialize all registers to 0
-    for i in 0..31 {
-        unsafe {
-            *sp.add(i) = 0;
-        }
-    }
-    
-    // Set ra (x1) to task entry point
-    // When we "return" from the first context restore, we'll jump here
-    // Register order: x1 is at offset 0
     unsafe {
-        *sp = entry as usize;  // x1 (ra) = entry point
+        init_context_frame(sp, entry, arg);
     }
     
     // Return the stack pointer
@@ -53,6 +93,40 @@ pub fn initialize_task_stack(entry: extern "C" fn() -> !, stack: &mut [usize]) -
     sp
 }
 
+/// Write a fresh initial context frame at `sp`
+///
+/// Restoring this frame starts `entry(arg)` with all other registers
+/// zeroed. Also used to restart a task on its existing stack.
+///
+/// # Safety
+/// `sp` must point to CONTEXT_WORDS writable, 16-byte aligned words
+pub unsafe fn init_context_frame(sp: *mut usize, entry: TaskEntry, arg: *mut c_void) {
+    // Initialize all registers to 0
+    for i in 0..CONTEXT_WORDS {
+        *sp.add(i) = 0;
+    }
+    
+    // Set ra (x1) to task entry point
+    // When we "return" from the first context restore, we'll jump here
+    // Register order: x1 is at offset 0
+    *sp = entry as usize;  // x1 (ra) = entry point
+    *sp.add(CONTEXT_A0_INDEX) = arg as usize;  // x10 (a0) = argument
+    *sp.add(CONTEXT_MSTATUS_INDEX) = INITIAL_TASK_MSTATUS;
+}
+
+/// Initialize a stack for an entry point that takes no argument
+///
+/// Compatibility shim for the old task ABI: the real entry is passed as
+/// the argument to a trampoline that calls it.
+pub fn initialize_task_stack_legacy(entry: LegacyTaskEntry, stack: &mut [usize]) -> *mut usize {
+    initialize_task_stack(legacy_task_trampoline, entry as *mut c_void, stack)
+}
+
+extern "C" fn legacy_task_trampoline(arg: *mut c_void) -> ! {
+    let entry: LegacyTaskEntry = unsafe { core::mem::transmute(arg) };
+    entry()
+}
+
 /// Perform a context switch from one task to another
 ///
 /// This is a wrapper around the assembly function.
@@ -102,6 +176,18 @@ pub unsafe fn start_first_task(tcb: *mut TaskControlBlock) -> ! {
     unreachable!()
 }
 
+/// Abandon the current context and run a task (never returns)
+///
+/// Used when the running context cannot continue, e.g. after a task
+/// failed and was killed or restarted. Nothing is saved.
+///
+/// # Safety
+/// - Task must have a valid saved or initial context
+/// - Must already be set as the scheduler's current task
+pub unsafe fn enter_task(tcb: *mut TaskControlBlock) -> ! {
+    restore_context((*tcb).stack_top)
+}
+
 // ============================================================================
 // ASSEMBLY FUNCTIONS
 // ============================================================================
@@ -173,6 +259,311 @@ pub fn wait_for_interrupt() {
     }
 }
 
+// ============================================================================
+// INTERRUPT CONTEXT
+// ============================================================================
+
+/// Depth of nested interrupt handlers on this hart (0 = task context)
+static IRQ_NESTING: AtomicUsize = AtomicUsize::new(0);
+
+/// Note entry into an interrupt handler (called by the trap entry code)
+#[inline]
+pub fn irq_enter() {
+    IRQ_NESTING.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Note exit from an interrupt handler
+#[inline]
+pub fn irq_exit() {
+    IRQ_NESTING.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// True while running in an interrupt handler
+#[inline]
+pub fn in_interrupt() -> bool {
+    IRQ_NESTING.load(Ordering::Relaxed) > 0
+}
+
+// ============================================================================
+// SOFTWARE INTERRUPT (tasklets)
+// ============================================================================
+
+/// CLINT msip register of hart 0 (writing 1 raises its software interrupt)
+const CLINT_MSIP: usize = CLINT_BASE;
+
+/// Raise the machine software interrupt on this hart
+#[inline]
+pub fn raise_soft_interrupt() {
+    #[cfg(feature = "irq-latency")]
+    crate::kernel::irq_latency::soft_raised();
+    unsafe { mmio::write32(CLINT_MSIP, 1) };
+}
+
+/// Let the software interrupt in (it still needs mstatus.MIE)
+pub fn enable_soft_interrupt() {
+    unsafe { riscv::register::mie::set_msoft() };
+}
+
+/// Software interrupt: run queued tasklets
+///
+/// Tasklets run with interrupts enabled so hardware ISRs keep their
+/// latency. The software interrupt itself stays masked meanwhile; a
+/// tasklet queued during the run is picked up by the same loop or, if
+/// queued after it ended, by the next trap.
+fn machine_soft_interrupt(_frame: &mut TrapFrame) {
+    unsafe {
+        mmio::write32(CLINT_MSIP, 0);
+        riscv::register::mie::clear_msoft();
+    }
+
+    if unsafe { riscv::interrupt::machine::nested(crate::kernel::tasklet::run_pending_tasklets) } > 0 {
+        // Tasklets typically wake the task that finishes the work
+        trap::request_reschedule();
+    }
+
+    unsafe { riscv::register::mie::set_msoft() };
+}
+
+// ============================================================================
+// TICK TIMER (CLINT mtimecmp)
+// ============================================================================
+
+/// CLINT mtimecmp register of hart 0 (timer interrupt pends while mtime >= it)
+const CLINT_MTIMECMP: usize = CLINT_BASE + 0x4000;
+
+/// mtime deadline of the next tick
+static NEXT_TICK: AtomicU64 = AtomicU64::new(0);
+
+/// mtime deadline of the tick being (or next to be) taken
+#[cfg(feature = "irq-latency")]
+pub(crate) fn tick_deadline() -> u64 {
+    NEXT_TICK.load(Ordering::Relaxed)
+}
+
+/// Start the periodic tick at config::TICK_RATE_HZ
+///
+/// The first tick is due one period from now; it is taken once
+/// mstatus.MIE is set, i.e. when the first task starts.
+pub fn timer_init() {
+    let next = read_mtime() + config::MTIME_TICKS_PER_TICK;
+    NEXT_TICK.store(next, Ordering::Relaxed);
+    unsafe {
+        mmio::write64(CLINT_MTIMECMP, next);
+        riscv::register::mie::set_mtimer();
+    }
+}
+
+/// Program the deadline of the tick after the one being handled
+///
+/// Deadlines advance by whole periods so interrupt latency does not make
+/// the tick drift. If a long critical section made us miss a period
+/// entirely, the schedule restarts from now instead of firing the missed
+/// ticks back to back.
+fn timer_rearm() {
+    let now = read_mtime();
+    let mut next = NEXT_TICK.load(Ordering::Relaxed) + config::MTIME_TICKS_PER_TICK;
+    if next <= now {
+        next = now + config::MTIME_TICKS_PER_TICK;
+    }
+    NEXT_TICK.store(next, Ordering::Relaxed);
+    unsafe { mmio::write64(CLINT_MTIMECMP, next) };
+}
+
+/// Timer interrupt: advance the tick and let a higher-priority task that
+/// became ready preempt the running one
+fn machine_timer_interrupt(frame: &mut TrapFrame) {
+    timer_rearm();
+    crate::kernel::increment_tick();
+    #[cfg(feature = "profiler")]
+    crate::kernel::profiler::profiler_sample(frame.mepc);
+    #[cfg(not(feature = "profiler"))]
+    let _ = frame;
+    trap::request_reschedule();
+}
+
+// ============================================================================
+// SYSTEM CONTROL (QEMU virt SiFive test device)
+// ============================================================================
+
+/// SiFive test finisher on the QEMU virt machine
+const TEST_DEVICE_BASE: usize = 0x0010_0000;
+
+const TEST_PASS: u32 = 0x5555;
+const TEST_FAIL: u32 = 0x3333;
+const TEST_RESET: u32 = 0x7777;
+
+fn test_device_write(value: u32) -> ! {
+    unsafe {
+        mmio::write32(TEST_DEVICE_BASE, value);
+    }
+    // Not on QEMU (or the write was ignored): park the hart
+    loop {
+        wait_for_interrupt();
+    }
+}
+
+/// Reset the machine
+pub fn system_reset() -> ! {
+    test_device_write(TEST_RESET)
+}
+
+/// Power off the machine; a non-zero code makes QEMU exit with failure
+pub fn system_poweroff(exit_code: u16) -> ! {
+    if exit_code == 0 {
+        test_device_write(TEST_PASS)
+    } else {
+        test_device_write(((exit_code as u32) << 16) | TEST_FAIL)
+    }
+}
+
+// ============================================================================
+// BUSY-WAIT DELAYS
+// ============================================================================
+
+/// CLINT base address on the QEMU virt machine
+pub const CLINT_BASE: usize = 0x0200_0000;
+
+/// CLINT mtime register (free-running at config::MTIME_FREQ_HZ)
+pub const CLINT_MTIME: usize = CLINT_BASE + 0xBFF8;
+
+/// mcycle increments per microsecond, measured by `delay_calibrate`
+/// (0 = not calibrated, delays fall back to mtime)
+static CYCLES_PER_US: AtomicU64 = AtomicU64::new(0);
+
+/// Read the CLINT mtime counter
+#[inline]
+pub fn read_mtime() -> u64 {
+    unsafe { mmio::read64(CLINT_MTIME) }
+}
+
+/// Read the mcycle counter
+#[inline]
+pub fn read_mcycle() -> u64 {
+    let cycles: u64;
+    unsafe {
+        asm!("csrr {}, mcycle", out(reg) cycles);
+    }
+    cycles
+}
+
+/// Measure the core clock against mtime
+///
+/// Call once at boot, before interrupts are enabled. Takes about 1ms.
+pub fn delay_calibrate() {
+    const WINDOW_US: u64 = 1000;
+    let window = config::MTIME_FREQ_HZ * WINDOW_US / 1_000_000;
+
+    // Start on an mtime edge so the window is exact. If mtime is not
+    // running, give up and leave delays uncalibrated.
+    let edge = read_mtime();
+    if spin_until("delay_calibrate", config::SPIN_TIMEOUT_CYCLES, || read_mtime() != edge).is_err() {
+        return;
+    }
+
+    let start_time = read_mtime();
+    let start_cycles = read_mcycle();
+    while read_mtime().wrapping_sub(start_time) < window {}
+    let cycles = read_mcycle().wrapping_sub(start_cycles);
+
+    CYCLES_PER_US.store(cycles / WINDOW_US, Ordering::Relaxed);
+}
+
+/// Core clock in MHz as measured by `delay_calibrate` (0 if not run)
+pub fn cycles_per_us() -> u64 {
+    CYCLES_PER_US.load(Ordering::Relaxed)
+}
+
+/// Spin for at least `cycles` core clock cycles
+#[inline]
+pub fn delay_cycles(cycles: u64) {
+    let start = read_mcycle();
+    while read_mcycle().wrapping_sub(start) < cycles {}
+}
+
+/// Spin for at least `us` microseconds
+///
+/// For short driver timing (reset pulses, setup/hold times) well below one
+/// tick. Does not yield; use task delays for anything longer.
+pub fn delay_us(us: u32) {
+    let per_us = cycles_per_us();
+    if per_us != 0 {
+        delay_cycles(us as u64 * per_us);
+    } else {
+        // Not calibrated: mtime resolution (100ns on QEMU), rounded up
+        let ticks = (us as u64 * config::MTIME_FREQ_HZ).div_ceil(1_000_000);
+        let start = read_mtime();
+        while read_mtime().wrapping_sub(start) < ticks {}
+    }
+}
+
+// ============================================================================
+// SPIN TIMEOUT GUARDS
+// ============================================================================
+
+/// Number of polling loops that ran out of budget
+static SPIN_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+/// mcycle budget for a polling loop that might never finish
+///
+/// Hardware that stops responding should make a driver fail, not hang the
+/// hart. The first `check` after the budget is spent logs the call site
+/// and returns `Timeout`.
+///
+/// # Example
+/// ```
+/// let guard = SpinGuard::new("uart-tx-drain", config::SPIN_TIMEOUT_CYCLES);
+/// while !tx_empty() {
+///     guard.check()?;
+/// }
+/// ```
+pub struct SpinGuard {
+    site: &'static str,
+    start: u64,
+    budget: u64,
+}
+
+impl SpinGuard {
+    /// Start the budget now
+    pub fn new(site: &'static str, budget_cycles: u64) -> Self {
+        SpinGuard {
+            site,
+            start: read_mcycle(),
+            budget: budget_cycles,
+        }
+    }
+
+    /// Fail with `Timeout` once the budget is spent
+    pub fn check(&self) -> Result<()> {
+        let spent = read_mcycle().wrapping_sub(self.start);
+        if spent < self.budget {
+            return Ok(());
+        }
+
+        SPIN_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+        if in_interrupt() {
+            crate::klog_from_isr!("spin timeout: {} after {} cycles", self.site, spent);
+        } else {
+            crate::klog!(crate::fs::LogLevel::Error, "spin timeout: {} after {} cycles\n", self.site, spent);
+        }
+        Err(RtosError::new(ErrorKind::Timeout))
+    }
+}
+
+/// Poll `done` until it returns true, failing with `Timeout` after
+/// `budget_cycles`
+pub fn spin_until<F: FnMut() -> bool>(site: &'static str, budget_cycles: u64, mut done: F) -> Result<()> {
+    let guard = SpinGuard::new(site, budget_cycles);
+    while !done() {
+        guard.check()?;
+    }
+    Ok(())
+}
+
+/// Polling loops that have timed out since boot
+pub fn spin_timeouts() -> u64 {
+    SPIN_TIMEOUTS.load(Ordering::Relaxed)
+}
+
 // ============================================================================
 // CRITICAL SECTION GUARD
 // ============================================================================
@@ -0,0 +1,474 @@
+// PLIC driver (QEMU virt, hart 0 machine context)
+//
+// The platform-level interrupt controller funnels the device interrupt
+// lines (UART, virtio, ...) into the one machine external interrupt.
+// `plic_init` installs `plic_dispatch` as the handler for that interrupt;
+// each time it is taken, the dispatcher claims the highest-priority
+// pending source, runs the handler registered for it and completes it,
+// until nothing is pending.
+//
+// Drivers register a handler for their source with a priority from 1 to
+// PLIC_MAX_PRIORITY; that enables the source. A source without a handler
+// that somehow fires is reported and disabled so it cannot storm.
+//
+// A source can also be given a rate limit. One that interrupts more often
+// than allowed is masked for IRQ_THROTTLE_MS and its handler is called
+// from the `irqpoll` task instead, every IRQ_POLL_INTERVAL_MS, so a
+// misbehaving device slows down to polling speed instead of livelocking
+// the hart. Throttling and its end are logged.
+//
+// The PLIC only forwards an interrupt whose priority is above the
+// context's threshold. Raising the threshold is a finer tool than
+// clearing mstatus.MIE: a driver can keep its own (low-priority) IRQ out
+// while it updates shared state, and higher-priority devices and the
+// CLINT timer and software interrupts still get through. Deferral
+// windows nest: each one only ever raises the threshold and puts back
+// the value it found when it ends.
+
+use super::mmio;
+use super::trap::{set_interrupt_handler, InterruptSource, TrapFrame};
+use crate::fs::LogLevel;
+use crate::kernel::scheduler::{reschedule, task_delay};
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result, TickType};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// PLIC base address on the QEMU virt machine
+pub const PLIC_BASE: usize = 0x0c00_0000;
+
+/// Highest interrupt priority the PLIC implements
+pub const PLIC_MAX_PRIORITY: u32 = 7;
+
+/// Interrupt sources on the QEMU virt PLIC (source 0 means "none")
+pub const PLIC_NUM_SOURCES: usize = 96;
+
+/// First virtio-mmio device's source (the eight devices use 1-8)
+pub const VIRTIO0_IRQ: u32 = 1;
+
+/// UART0 (16550) source
+pub const UART0_IRQ: u32 = 10;
+
+/// Context 0 = hart 0, machine mode
+const PLIC_CONTEXT: usize = 0;
+
+const PLIC_PRIORITY: usize = PLIC_BASE;
+const PLIC_PENDING: usize = PLIC_BASE + 0x1000;
+const PLIC_ENABLE: usize = PLIC_BASE + 0x2000 + PLIC_CONTEXT * 0x80;
+const PLIC_THRESHOLD: usize = PLIC_BASE + 0x20_0000 + PLIC_CONTEXT * 0x1000;
+const PLIC_CLAIM: usize = PLIC_THRESHOLD + 4;
+
+/// Device interrupt handler; gets the source number
+pub type IrqHandler = fn(u32);
+
+static mut IRQ_HANDLERS: [Option<IrqHandler>; PLIC_NUM_SOURCES] = [None; PLIC_NUM_SOURCES];
+
+/// Interrupts dispatched per source since boot
+static IRQ_COUNTS: [AtomicU64; PLIC_NUM_SOURCES] = [const { AtomicU64::new(0) }; PLIC_NUM_SOURCES];
+
+fn check_source(irq: u32) -> Result<usize> {
+    match irq as usize {
+        0 | PLIC_NUM_SOURCES.. => Err(ErrorKind::InvalidParameter.on(ObjectKind::Irq).with_id(irq)),
+        index => Ok(index),
+    }
+}
+
+// ============================================================================
+// SOURCES
+// ============================================================================
+
+/// Reset the PLIC and route the external interrupt to `plic_dispatch`
+///
+/// Every source is disabled with priority 0 and the threshold is 0.
+/// Called once at boot before the Driver hooks run.
+pub fn plic_init() {
+    crate::critical_section! {
+        unsafe {
+            for irq in 1..PLIC_NUM_SOURCES {
+                mmio::write32(PLIC_PRIORITY + irq * 4, 0);
+            }
+            for word in 0..PLIC_NUM_SOURCES.div_ceil(32) {
+                mmio::write32(PLIC_ENABLE + word * 4, 0);
+            }
+            mmio::write32(PLIC_THRESHOLD, 0);
+        }
+        set_interrupt_handler(InterruptSource::External, Some(plic_dispatch));
+        unsafe { riscv::register::mie::set_mext() };
+    }
+}
+
+/// Set a source's priority (0 = never delivered)
+pub fn plic_set_priority(irq: u32, priority: u32) -> Result<()> {
+    let index = check_source(irq)?;
+    if priority > PLIC_MAX_PRIORITY {
+        return Err(ErrorKind::InvalidPriority.on(ObjectKind::Irq).with_id(irq));
+    }
+    unsafe { mmio::write32(PLIC_PRIORITY + index * 4, priority) };
+    Ok(())
+}
+
+/// A source's priority
+pub fn plic_priority(irq: u32) -> Result<u32> {
+    let index = check_source(irq)?;
+    Ok(unsafe { mmio::read32(PLIC_PRIORITY + index * 4) })
+}
+
+fn set_enabled(index: usize, enabled: bool) {
+    let word = PLIC_ENABLE + (index / 32) * 4;
+    let bit = 1 << (index % 32);
+    crate::critical_section! {
+        unsafe {
+            let bits = mmio::read32(word);
+            mmio::write32(word, if enabled { bits | bit } else { bits & !bit });
+        }
+    }
+}
+
+/// Let a source interrupt this hart
+pub fn plic_enable(irq: u32) -> Result<()> {
+    set_enabled(check_source(irq)?, true);
+    Ok(())
+}
+
+/// Stop a source from interrupting this hart
+pub fn plic_disable(irq: u32) -> Result<()> {
+    set_enabled(check_source(irq)?, false);
+    Ok(())
+}
+
+/// True if a source has an interrupt waiting to be claimed
+pub fn plic_is_pending(irq: u32) -> Result<bool> {
+    let index = check_source(irq)?;
+    let bits = unsafe { mmio::read32(PLIC_PENDING + (index / 32) * 4) };
+    Ok(bits & (1 << (index % 32)) != 0)
+}
+
+/// Claim the highest-priority pending source (0 if none)
+///
+/// The source is not delivered again until `plic_complete`.
+pub fn plic_claim() -> u32 {
+    unsafe { mmio::read32(PLIC_CLAIM) }
+}
+
+/// Tell the PLIC a claimed source has been handled
+pub fn plic_complete(irq: u32) {
+    unsafe { mmio::write32(PLIC_CLAIM, irq) };
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+/// Install a driver's handler for a source and enable it
+///
+/// `priority` (1 to PLIC_MAX_PRIORITY) orders it against other sources
+/// and the threshold. Fails with `InvalidParameter` for a bad source,
+/// `InvalidPriority` for a bad priority and `ResourceBusy` if the source
+/// already has a handler.
+///
+/// # Example
+/// ```
+/// fn uart_irq(_irq: u32) {
+///     while let Some(byte) = uart_try_read() {
+///         rx_ring.push(byte);
+///     }
+/// }
+///
+/// register_irq_handler(UART0_IRQ, 2, uart_irq)?;
+/// ```
+pub fn register_irq_handler(irq: u32, priority: u32, handler: IrqHandler) -> Result<()> {
+    let index = check_source(irq)?;
+    if priority == 0 || priority > PLIC_MAX_PRIORITY {
+        return Err(ErrorKind::InvalidPriority.on(ObjectKind::Irq).with_id(irq));
+    }
+
+    crate::critical_section! {
+        let handlers = unsafe { &mut *core::ptr::addr_of_mut!(IRQ_HANDLERS) };
+        if handlers[index].is_some() {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::Irq).with_id(irq));
+        }
+        plic_set_priority(irq, priority)?;
+        handlers[index] = Some(handler);
+        set_enabled(index, true);
+        Ok(())
+    }
+}
+
+/// Disable a source and remove its handler
+///
+/// Fails with `NotFound` if the source has no handler.
+pub fn unregister_irq_handler(irq: u32) -> Result<()> {
+    let index = check_source(irq)?;
+    crate::critical_section! {
+        let handlers = unsafe { &mut *core::ptr::addr_of_mut!(IRQ_HANDLERS) };
+        if handlers[index].take().is_none() {
+            return Err(ErrorKind::NotFound.on(ObjectKind::Irq).with_id(irq));
+        }
+        set_enabled(index, false);
+        unsafe { mmio::write32(PLIC_PRIORITY + index * 4, 0) };
+        RATES[index].throttled_until.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Number of interrupts dispatched for a source since boot
+pub fn irq_count(irq: u32) -> u64 {
+    match check_source(irq) {
+        Ok(index) => IRQ_COUNTS[index].load(Ordering::Relaxed),
+        Err(_) => 0,
+    }
+}
+
+/// Visit every source that has a handler, with its dispatch count
+pub fn for_each_irq<F: FnMut(u32, u64)>(mut f: F) {
+    let handlers = unsafe { &*core::ptr::addr_of!(IRQ_HANDLERS) };
+    for (index, handler) in handlers.iter().enumerate() {
+        if handler.is_some() {
+            f(index as u32, IRQ_COUNTS[index].load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// Machine external interrupt handler: serve every pending source
+fn plic_dispatch(_frame: &mut TrapFrame) {
+    loop {
+        let irq = plic_claim();
+        let index = irq as usize;
+        if irq == 0 || index >= PLIC_NUM_SOURCES {
+            break;
+        }
+
+        IRQ_COUNTS[index].fetch_add(1, Ordering::Relaxed);
+        if RATES[index].over_limit() {
+            throttle(index);
+        }
+        #[cfg(feature = "irq-latency")]
+        let stamp = crate::kernel::irq_latency::handler_enter(crate::kernel::irq_latency::IrqLine::Plic(irq), None);
+        match unsafe { (*core::ptr::addr_of!(IRQ_HANDLERS))[index] } {
+            Some(handler) => handler(irq),
+            None => {
+                crate::klog_from_isr!("plic: no handler for irq {}, disabling it\n", irq);
+                set_enabled(index, false);
+            }
+        }
+        #[cfg(feature = "irq-latency")]
+        crate::kernel::irq_latency::handler_exit(stamp);
+        plic_complete(irq);
+    }
+}
+
+// ============================================================================
+// RATE LIMITING
+// ============================================================================
+
+const MTIME_PER_MS: u64 = config::MTIME_FREQ_HZ / 1000;
+
+/// Per-source rate limit state
+///
+/// Time is read from mtime, not the tick: an interrupt storm can keep the
+/// (lower-priority) timer interrupt from being taken at all.
+struct RateState {
+    /// Interrupts allowed per window (0 = unlimited)
+    limit: AtomicU32,
+    /// Interrupts in the current window
+    count: AtomicU32,
+    /// mtime at which the current window started
+    window_start: AtomicU64,
+    /// mtime at which a throttled source is unmasked (0 = not throttled)
+    throttled_until: AtomicU64,
+    /// Times the source has been throttled
+    throttles: AtomicU64,
+}
+
+impl RateState {
+    const fn new() -> Self {
+        RateState {
+            limit: AtomicU32::new(0),
+            count: AtomicU32::new(0),
+            window_start: AtomicU64::new(0),
+            throttled_until: AtomicU64::new(0),
+            throttles: AtomicU64::new(0),
+        }
+    }
+
+    /// Count one interrupt; true if it goes over the limit
+    fn over_limit(&self) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        if limit == 0 {
+            return false;
+        }
+        let now = super::read_mtime();
+        let window = config::IRQ_RATE_WINDOW_MS * MTIME_PER_MS;
+        if now - self.window_start.load(Ordering::Relaxed) >= window {
+            self.window_start.store(now, Ordering::Relaxed);
+            self.count.store(0, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed) + 1 > limit
+    }
+}
+
+static RATES: [RateState; PLIC_NUM_SOURCES] = [const { RateState::new() }; PLIC_NUM_SOURCES];
+
+/// Set once the polling task exists
+static POLL_TASK_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Mask a source that went over its limit and hand it to the poller
+fn throttle(index: usize) {
+    let rate = &RATES[index];
+    set_enabled(index, false);
+    rate.throttled_until.store(super::read_mtime() + config::IRQ_THROTTLE_MS * MTIME_PER_MS, Ordering::Relaxed);
+    rate.throttles.fetch_add(1, Ordering::Relaxed);
+    crate::klog_from_isr!(
+        "plic: irq {} over {} per {} ms, polling it for {} ms\n",
+        index,
+        rate.limit.load(Ordering::Relaxed),
+        config::IRQ_RATE_WINDOW_MS,
+        config::IRQ_THROTTLE_MS
+    );
+}
+
+/// Limit a source to `max` interrupts per config::IRQ_RATE_WINDOW_MS
+///
+/// `None` removes the limit (a throttled source is unmasked at the end of
+/// its throttle period). The first limit set starts the `irqpoll` task.
+///
+/// # Example
+/// ```
+/// // A flaky sensor line may not take more than 2000 IRQs/s
+/// set_irq_rate_limit(SENSOR_IRQ, Some(20))?;
+/// ```
+pub fn set_irq_rate_limit(irq: u32, max: Option<u32>) -> Result<()> {
+    let index = check_source(irq)?;
+    if max == Some(0) {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Irq).with_id(irq));
+    }
+
+    if max.is_some() && !POLL_TASK_STARTED.swap(true, Ordering::AcqRel) {
+        let spawned = crate::create_task!(
+            name: "irqpoll",
+            entry: irq_poll_task,
+            priority: config::IRQ_POLL_TASK_PRIORITY,
+            stack: config::IRQ_POLL_TASK_STACK_SIZE
+        );
+        if let Err(e) = spawned {
+            POLL_TASK_STARTED.store(false, Ordering::Release);
+            return Err(e);
+        }
+    }
+
+    RATES[index].limit.store(max.unwrap_or(0), Ordering::Relaxed);
+    Ok(())
+}
+
+/// True while a source is masked for exceeding its rate limit
+pub fn is_irq_throttled(irq: u32) -> bool {
+    match check_source(irq) {
+        Ok(index) => RATES[index].throttled_until.load(Ordering::Relaxed) != 0,
+        Err(_) => false,
+    }
+}
+
+/// Times a source has been throttled since boot
+pub fn irq_throttle_count(irq: u32) -> u64 {
+    match check_source(irq) {
+        Ok(index) => RATES[index].throttles.load(Ordering::Relaxed),
+        Err(_) => 0,
+    }
+}
+
+/// Service throttled sources by polling and unmask them when their time
+/// is up
+extern "C" fn irq_poll_task(_arg: *mut c_void) -> ! {
+    loop {
+        let now = super::read_mtime();
+        for (index, rate) in RATES.iter().enumerate() {
+            let until = rate.throttled_until.load(Ordering::Relaxed);
+            if until == 0 {
+                continue;
+            }
+            let Some(handler) = (unsafe { (*core::ptr::addr_of!(IRQ_HANDLERS))[index] }) else {
+                continue;
+            };
+
+            // Handlers expect interrupt context
+            crate::critical_section! {
+                super::irq_enter();
+                handler(index as u32);
+                super::irq_exit();
+            }
+
+            if now >= until {
+                crate::critical_section! {
+                    rate.throttled_until.store(0, Ordering::Relaxed);
+                    rate.count.store(0, Ordering::Relaxed);
+                    rate.window_start.store(now, Ordering::Relaxed);
+                    set_enabled(index, true);
+                }
+                crate::klog!(LogLevel::Info, "plic: irq {} back on interrupts\n", index);
+            }
+        }
+
+        reschedule();
+        let _ = task_delay(TickType::from_ms(config::IRQ_POLL_INTERVAL_MS));
+    }
+}
+
+// ============================================================================
+// THRESHOLD
+// ============================================================================
+
+/// Current threshold (interrupts at or below it are masked)
+pub fn plic_threshold() -> u32 {
+    unsafe { mmio::read32(PLIC_THRESHOLD) }
+}
+
+/// Mask external interrupts with a priority below `priority`
+///
+/// Never lowers the threshold, so an enclosing window stays in force.
+/// Returns the previous threshold for `plic_restore_threshold`.
+pub fn plic_raise_threshold(priority: u32) -> u32 {
+    let wanted = priority.min(PLIC_MAX_PRIORITY + 1).saturating_sub(1);
+    crate::critical_section! {
+        let previous = plic_threshold();
+        if wanted > previous {
+            unsafe { mmio::write32(PLIC_THRESHOLD, wanted) };
+        }
+        previous
+    }
+}
+
+/// End a window opened by `plic_raise_threshold`
+pub fn plic_restore_threshold(saved: u32) {
+    crate::critical_section! {
+        unsafe { mmio::write32(PLIC_THRESHOLD, saved.min(PLIC_MAX_PRIORITY)) };
+    }
+}
+
+/// An interrupt deferral window; restores the threshold when dropped
+///
+/// Windows must end in the reverse order they were opened, which scoped
+/// guards do naturally.
+#[must_use = "the window ends as soon as the guard is dropped"]
+pub struct IrqDeferral {
+    saved: u32,
+}
+
+impl Drop for IrqDeferral {
+    fn drop(&mut self) {
+        plic_restore_threshold(self.saved);
+    }
+}
+
+/// Defer external interrupts below `priority` until the guard is dropped
+///
+/// # Example
+/// ```
+/// // The UART IRQ has priority 2: keep it (and anything lower) out
+/// // while the TX ring is updated; priority 3+ devices still interrupt.
+/// {
+///     let _window = defer_irqs_below(3);
+///     tx_ring.push(byte);
+/// }
+/// ```
+pub fn defer_irqs_below(priority: u32) -> IrqDeferral {
+    IrqDeferral { saved: plic_raise_threshold(priority) }
+}
@@ -0,0 +1,286 @@
+// Trap dispatch
+//
+// trap.S saves a TrapFrame for every trap and calls `rtos_trap_handler`,
+// which decodes mcause:
+//
+//   - interrupts go to the handler registered for their source (timer,
+//     software, external); the tick and tasklet handlers are installed
+//     by default
+//   - exceptions are reported on the console (cause, faulting PC and
+//     function, mtval, task) and then treated as a panic, so the panic
+//     handler's crash dump and task supervision apply
+//
+// Handlers run with interrupts disabled unless they enable them
+// (tasklets do). A handler that made a higher-priority task ready calls
+// `request_reschedule`; the switch happens once the outermost handler
+// has returned.
+//
+// With the `irq-latency` feature, interrupts are stamped on entry and
+// kernel::irq_latency keeps per-line latency histograms.
+
+use super::{in_interrupt, irq_enter, irq_exit, switch_context, TRAP_FRAME_SIZE};
+use crate::fs::LogLevel;
+use crate::kernel::backtrace::write_backtrace;
+use crate::kernel::dmesg::KlogWriter;
+#[cfg(feature = "irq-latency")]
+use crate::kernel::irq_latency::{self, IrqLine};
+use crate::kernel::symtab::symbolize;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Registers saved on trap entry (layout shared with trap.S)
+#[repr(C)]
+#[derive(Debug)]
+pub struct TrapFrame {
+    /// x1-x31; regs[n - 1] holds xn
+    pub regs: [usize; 31],
+    pub mepc: usize,
+    pub mstatus: usize,
+    pub mcause: usize,
+    pub mtval: usize,
+    /// Floating-point registers (F/D targets) and padding
+    _ext: [usize; TRAP_EXT_WORDS],
+}
+
+const TRAP_EXT_WORDS: usize = TRAP_FRAME_SIZE / core::mem::size_of::<usize>() - 35;
+
+const _: () = assert!(
+    core::mem::size_of::<TrapFrame>() == TRAP_FRAME_SIZE,
+    "TrapFrame must match trap.S"
+);
+
+/// mcause bit set for interrupts
+const MCAUSE_INTERRUPT: usize = 1 << (usize::BITS - 1);
+
+impl TrapFrame {
+    /// Integer register xn (x0 reads as 0)
+    pub fn reg(&self, n: usize) -> usize {
+        if n == 0 || n > 31 {
+            0
+        } else {
+            self.regs[n - 1]
+        }
+    }
+
+    pub fn ra(&self) -> usize {
+        self.reg(1)
+    }
+
+    /// Stack pointer at the time of the trap
+    pub fn sp(&self) -> usize {
+        self.reg(2)
+    }
+
+    pub fn is_interrupt(&self) -> bool {
+        self.mcause & MCAUSE_INTERRUPT != 0
+    }
+
+    /// Exception or interrupt code (mcause without the interrupt bit)
+    pub fn code(&self) -> usize {
+        self.mcause & !MCAUSE_INTERRUPT
+    }
+}
+
+// ============================================================================
+// INTERRUPT HANDLERS
+// ============================================================================
+
+/// Machine-level interrupt sources (mcause codes)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InterruptSource {
+    Software = 3,
+    Timer = 7,
+    External = 11,
+}
+
+impl InterruptSource {
+    fn from_code(code: usize) -> Option<Self> {
+        match code {
+            3 => Some(InterruptSource::Software),
+            7 => Some(InterruptSource::Timer),
+            11 => Some(InterruptSource::External),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            InterruptSource::Software => 0,
+            InterruptSource::Timer => 1,
+            InterruptSource::External => 2,
+        }
+    }
+}
+
+/// Interrupt handler; the frame is the interrupted context
+pub type InterruptHandler = fn(&mut TrapFrame);
+
+static mut HANDLERS: [Option<InterruptHandler>; 3] = [
+    Some(super::machine_soft_interrupt),
+    Some(super::machine_timer_interrupt),
+    None,
+];
+
+/// Interrupts taken per source since boot
+static INTERRUPT_COUNTS: [AtomicU64; 3] = [const { AtomicU64::new(0) }; 3];
+
+/// Set when a handler wants the scheduler to run before returning to a task
+static RESCHED_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Install the handler for an interrupt source, returning the previous one
+///
+/// `None` removes it; an interrupt without a handler is reported and
+/// masked in `mie` so it cannot storm.
+///
+/// # Example
+/// ```
+/// fn plic_dispatch(_frame: &mut TrapFrame) { /* claim, handle, complete */ }
+///
+/// set_interrupt_handler(InterruptSource::External, Some(plic_dispatch));
+/// ```
+pub fn set_interrupt_handler(source: InterruptSource, handler: Option<InterruptHandler>) -> Option<InterruptHandler> {
+    crate::critical_section! {
+        let handlers = unsafe { &mut *core::ptr::addr_of_mut!(HANDLERS) };
+        core::mem::replace(&mut handlers[source.index()], handler)
+    }
+}
+
+/// Number of interrupts taken from a source since boot
+pub fn interrupt_count(source: InterruptSource) -> u64 {
+    INTERRUPT_COUNTS[source.index()].load(Ordering::Relaxed)
+}
+
+/// True while a reschedule request is waiting for the trap exit path
+#[cfg(feature = "irq-latency")]
+pub(crate) fn reschedule_pending() -> bool {
+    RESCHED_PENDING.load(Ordering::Relaxed)
+}
+
+/// Ask for a scheduling decision once interrupt handling is done
+///
+/// Call from a handler that made a task ready. If a higher-priority task
+/// is ready by then it runs instead of the interrupted one.
+pub fn request_reschedule() {
+    RESCHED_PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Disable an interrupt code in mie
+fn mask_interrupt(code: usize) {
+    if code < 64 {
+        unsafe { core::arch::asm!("csrc mie, {}", in(reg) 1usize << code) };
+    }
+}
+
+fn handle_interrupt(frame: &mut TrapFrame) {
+    let Some(source) = InterruptSource::from_code(frame.code()) else {
+        crate::klog_from_isr!("trap: unexpected interrupt {}\n", frame.code());
+        mask_interrupt(frame.code());
+        return;
+    };
+
+    INTERRUPT_COUNTS[source.index()].fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "irq-latency")]
+    let stamp = match source {
+        InterruptSource::Timer => Some(irq_latency::handler_enter(IrqLine::Timer, Some(super::tick_deadline()))),
+        InterruptSource::Software => Some(irq_latency::handler_enter(IrqLine::Software, irq_latency::take_soft_raised())),
+        // plic_dispatch measures each PLIC source
+        InterruptSource::External => None,
+    };
+
+    let handler = unsafe { (*core::ptr::addr_of!(HANDLERS))[source.index()] };
+    match handler {
+        Some(handler) => handler(frame),
+        None => {
+            crate::klog_from_isr!("trap: no handler for {:?} interrupt, masking it\n", source);
+            mask_interrupt(source as usize);
+        }
+    }
+
+    #[cfg(feature = "irq-latency")]
+    if let Some(stamp) = stamp {
+        irq_latency::handler_exit(stamp);
+    }
+}
+
+/// Switch to a higher-priority task if a handler asked for it
+///
+/// Only at task level: a trap that interrupted another handler leaves
+/// the decision to the outer one.
+fn reschedule_if_requested() {
+    if in_interrupt() || !RESCHED_PENDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let current = crate::kernel::get_current_task();
+    let next = crate::kernel::preempt_check();
+    let switch = !next.is_null() && next != current;
+    #[cfg(feature = "irq-latency")]
+    irq_latency::rescheduled(switch);
+    if switch {
+        // Resumes here when the preempted task is switched back in
+        unsafe { switch_context(current, next) };
+    }
+}
+
+// ============================================================================
+// EXCEPTIONS
+// ============================================================================
+
+/// Name of a synchronous exception code
+pub fn exception_name(code: usize) -> &'static str {
+    match code {
+        0 => "instruction address misaligned",
+        1 => "instruction access fault",
+        2 => "illegal instruction",
+        3 => "breakpoint",
+        4 => "load address misaligned",
+        5 => "load access fault",
+        6 => "store address misaligned",
+        7 => "store access fault",
+        8 => "ecall from U-mode",
+        9 => "ecall from S-mode",
+        11 => "ecall from M-mode",
+        12 => "instruction page fault",
+        13 => "load page fault",
+        15 => "store page fault",
+        _ => "unknown exception",
+    }
+}
+
+/// Report a fatal exception and hand it to the panic handler
+fn handle_exception(frame: &mut TrapFrame) -> ! {
+    let name = exception_name(frame.code());
+    let task = crate::kernel::get_current_task();
+    let task_name = if task.is_null() { "<boot>" } else { unsafe { (*task).name_str() } };
+
+    crate::klog!(LogLevel::Critical, "\n*** EXCEPTION: {} (mcause {:#x})\n", name, frame.mcause);
+    match symbolize(frame.mepc) {
+        Some((func, offset)) => crate::klog!(LogLevel::Critical, "  pc    {:#018x} {}+{:#x}\n", frame.mepc, func, offset),
+        None => crate::klog!(LogLevel::Critical, "  pc    {:#018x}\n", frame.mepc),
+    }
+    crate::klog!(LogLevel::Critical, "  mtval {:#018x}\n", frame.mtval);
+    crate::klog!(LogLevel::Critical, "  ra    {:#018x}  sp {:#018x}\n", frame.ra(), frame.sp());
+    crate::klog!(LogLevel::Critical, "  task  '{}'\n", task_name);
+    crate::klog!(LogLevel::Critical, "  backtrace:\n");
+    let _ = write_backtrace(&mut KlogWriter(LogLevel::Critical), frame.mepc, frame.ra(), frame.sp());
+
+    panic!("{} at {:#x} (mtval {:#x})", name, frame.mepc, frame.mtval);
+}
+
+// ============================================================================
+// ENTRY
+// ============================================================================
+
+/// Called by trap.S for every trap
+#[no_mangle]
+extern "C" fn rtos_trap_handler(frame: &mut TrapFrame) {
+    if !frame.is_interrupt() {
+        handle_exception(frame);
+    }
+
+    #[cfg(feature = "irq-latency")]
+    irq_latency::trap_entered();
+    irq_enter();
+    handle_interrupt(frame);
+    irq_exit();
+
+    reschedule_if_requested();
+}
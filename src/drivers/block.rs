@@ -0,0 +1,32 @@
+// Block device interface
+//
+// Implemented by storage drivers (RAM disk, virtio-blk, SD) and consumed
+// by filesystems. Transfers are whole blocks addressed by LBA.
+
+use crate::kernel::types::Result;
+
+pub trait BlockDevice {
+    /// Size of one block in bytes
+    fn block_size(&self) -> usize;
+
+    /// Number of blocks on the device
+    fn block_count(&self) -> u64;
+
+    /// Read `buf.len() / block_size()` blocks starting at `lba`
+    ///
+    /// `buf.len()` must be a non-zero multiple of the block size
+    fn read_blocks(&self, lba: u64, buf: &mut [u8]) -> Result<()>;
+
+    /// Write `buf.len() / block_size()` blocks starting at `lba`
+    fn write_blocks(&self, lba: u64, buf: &[u8]) -> Result<()>;
+
+    /// Flush any cached writes to the medium
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Total capacity in bytes
+    fn capacity(&self) -> u64 {
+        self.block_count() * self.block_size() as u64
+    }
+}
@@ -0,0 +1,8 @@
+// Device drivers
+pub mod block;
+pub mod ramdisk;
+pub mod uart;
+
+pub use block::BlockDevice;
+pub use ramdisk::RamDisk;
+pub use uart::{Uart, UART0};
@@ -0,0 +1,83 @@
+// RAM disk - block device backed by a static buffer
+//
+// Useful for scratch storage, log spooling and filesystem testing
+// without attaching a disk image to QEMU. Contents are lost on reset.
+
+use crate::drivers::block::BlockDevice;
+use crate::kernel::types::{Result, ErrorKind, ObjectKind};
+
+pub struct RamDisk {
+    data: *mut u8,
+    block_size: usize,
+    block_count: u64,
+}
+
+impl RamDisk {
+    /// Create a RAM disk over `storage`
+    ///
+    /// `block_size` must be a non-zero power of two; any tail of `storage`
+    /// that does not fill a whole block is unused.
+    ///
+    /// # Example
+    /// ```
+    /// static mut DISK_STORAGE: [u8; 64 * 1024] = [0; 64 * 1024];
+    /// let disk = RamDisk::new(unsafe { &mut DISK_STORAGE }, 512).unwrap();
+    /// ```
+    pub fn new(storage: &'static mut [u8], block_size: usize) -> Result<Self> {
+        if block_size == 0 || !block_size.is_power_of_two() || storage.len() < block_size {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Device));
+        }
+        Ok(RamDisk {
+            data: storage.as_mut_ptr(),
+            block_size,
+            block_count: (storage.len() / block_size) as u64,
+        })
+    }
+
+    /// Byte offset of a transfer, after validating it
+    fn check_range(&self, lba: u64, len: usize) -> Result<usize> {
+        if len == 0 || !len.is_multiple_of(self.block_size) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Device));
+        }
+        let blocks = (len / self.block_size) as u64;
+        if lba >= self.block_count || blocks > self.block_count - lba {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Device));
+        }
+        Ok(lba as usize * self.block_size)
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn read_blocks(&self, lba: u64, buf: &mut [u8]) -> Result<()> {
+        let offset = self.check_range(lba, buf.len())?;
+        crate::critical_section! {
+            unsafe {
+                core::ptr::copy_nonoverlapping(self.data.add(offset), buf.as_mut_ptr(), buf.len());
+            }
+        }
+        Ok(())
+    }
+
+    fn write_blocks(&self, lba: u64, buf: &[u8]) -> Result<()> {
+        let offset = self.check_range(lba, buf.len())?;
+        crate::critical_section! {
+            unsafe {
+                core::ptr::copy_nonoverlapping(buf.as_ptr(), self.data.add(offset), buf.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Safety: all access to the backing buffer goes through critical sections
+// on a single core
+unsafe impl Send for RamDisk {}
+unsafe impl Sync for RamDisk {}
@@ -0,0 +1,455 @@
+// 16550 UART driver (QEMU virt UART0)
+//
+// Transmit and receive are interrupt driven. Bytes pass through two
+// single-producer, single-consumer rings that need no lock:
+//
+//   - RX: the interrupt handler drains the receive FIFO into the ring and
+//     wakes a waiting reader; tasks take bytes out with `read_byte` /
+//     `read_line` (blocking, with a timeout) or `read` (never blocks)
+//   - TX: `write` appends to the ring and starts the transmitter; the
+//     THR-empty interrupt refills the 16-byte FIFO until the ring is empty.
+//     Writers are serialized with each other by a short critical section.
+//
+// Until `init` installs the interrupt handler the UART runs polled, and
+// the panic handler switches back to polled mode (`enter_polled_mode`)
+// so its report goes out even with the scheduler wedged. If the failure
+// is contained or the task restarted, it returns to interrupts
+// (`leave_polled_mode`).
+
+use crate::arch::plic::{register_irq_handler, IrqHandler};
+use crate::arch::{self, mmio, SpinGuard};
+use crate::kernel::list::List;
+use crate::kernel::scheduler::{
+    block_current_task, block_current_task_until, get_tick_count, reschedule, wake_first_waiter,
+};
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result, TickType};
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// UART0 base address on the QEMU virt machine
+pub const UART0_BASE: usize = 0x1000_0000;
+
+// Register offsets
+const RBR: usize = 0; // receive buffer (read)
+const THR: usize = 0; // transmit holding (write)
+const IER: usize = 1;
+const FCR: usize = 2;
+const LCR: usize = 3;
+const MCR: usize = 4;
+const LSR: usize = 5;
+
+const IER_RX_AVAILABLE: u8 = 0x01;
+const IER_TX_EMPTY: u8 = 0x02;
+const FCR_ENABLE: u8 = 0x01;
+const FCR_CLEAR_RX: u8 = 0x02;
+const FCR_CLEAR_TX: u8 = 0x04;
+const LCR_8N1: u8 = 0x03;
+const MCR_OUT2: u8 = 0x08;
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_OVERRUN: u8 = 0x02;
+const LSR_THR_EMPTY: u8 = 0x20;
+const LSR_TX_IDLE: u8 = 0x40;
+
+/// Bytes the transmit FIFO takes once THR-empty is signalled
+const TX_FIFO_DEPTH: usize = 16;
+
+// ============================================================================
+// RING BUFFER
+// ============================================================================
+
+/// Single-producer, single-consumer byte ring
+///
+/// Indices run freely and wrap; N must be a power of two.
+struct ByteRing<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    /// Next byte to take (advanced by the consumer only)
+    head: AtomicUsize,
+    /// Next free slot (advanced by the producer only)
+    tail: AtomicUsize,
+}
+
+impl<const N: usize> ByteRing<N> {
+    const SIZE_CHECK: () = assert!(N.is_power_of_two(), "ring size must be a power of two");
+
+    const fn new() -> Self {
+        let () = Self::SIZE_CHECK;
+        ByteRing {
+            buf: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Producer side; false if the ring is full
+    fn push(&self, byte: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == N {
+            return false;
+        }
+        unsafe { (*self.buf.get())[tail % N] = byte };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Consumer side
+    fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let byte = unsafe { (*self.buf.get())[head % N] };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(byte)
+    }
+
+    fn len(&self) -> usize {
+        self.tail.load(Ordering::Acquire).wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ============================================================================
+// DRIVER
+// ============================================================================
+
+/// A 16550-compatible UART
+pub struct Uart {
+    base: usize,
+    irq: u32,
+    rx: ByteRing<{ config::UART_RX_BUFFER_SIZE }>,
+    tx: ByteRing<{ config::UART_TX_BUFFER_SIZE }>,
+    /// Tasks waiting for input
+    readers: UnsafeCell<List>,
+    lists_ready: AtomicBool,
+    /// No interrupt handler: transmit and receive by polling
+    polled: AtomicBool,
+    /// `init` installed the interrupt handler
+    irq_installed: AtomicBool,
+    /// Received bytes lost (RX ring full or hardware FIFO overrun)
+    rx_dropped: AtomicU64,
+    /// Bytes not sent because the transmitter stopped draining
+    tx_dropped: AtomicU64,
+}
+
+// The rings are SPSC and everything else is atomic or only touched
+// inside critical sections
+unsafe impl Sync for Uart {}
+
+/// UART0 of the QEMU virt machine
+pub static UART0: Uart = Uart::new(UART0_BASE, crate::arch::plic::UART0_IRQ);
+
+/// PLIC handler for UART0 (pass to `UART0.init`)
+pub fn uart0_irq(_irq: u32) {
+    UART0.handle_interrupt();
+}
+
+impl Uart {
+    pub const fn new(base: usize, irq: u32) -> Self {
+        Uart {
+            base,
+            irq,
+            rx: ByteRing::new(),
+            tx: ByteRing::new(),
+            readers: UnsafeCell::new(List::new()),
+            lists_ready: AtomicBool::new(false),
+            polled: AtomicBool::new(true),
+            irq_installed: AtomicBool::new(false),
+            rx_dropped: AtomicU64::new(0),
+            tx_dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn read_reg(&self, offset: usize) -> u8 {
+        unsafe { mmio::read8(self.base + offset) }
+    }
+
+    fn write_reg(&self, offset: usize, value: u8) {
+        unsafe { mmio::write8(self.base + offset, value) }
+    }
+
+    /// Configure the line (8N1, FIFOs on) and switch to interrupt mode
+    ///
+    /// `handler` must call `handle_interrupt` on this UART (for UART0:
+    /// `uart0_irq`). Bytes written before are sent first.
+    ///
+    /// # Example
+    /// ```
+    /// fn uart_init() -> Result<()> {
+    ///     UART0.init(uart0_irq)
+    /// }
+    /// ```
+    pub fn init(&self, handler: IrqHandler) -> Result<()> {
+        self.flush();
+        self.write_reg(IER, 0);
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(FCR, FCR_ENABLE | FCR_CLEAR_RX | FCR_CLEAR_TX);
+        self.write_reg(MCR, MCR_OUT2);
+
+        register_irq_handler(self.irq, config::UART_IRQ_PRIORITY, handler)?;
+        self.irq_installed.store(true, Ordering::Release);
+        self.polled.store(false, Ordering::Release);
+        self.write_reg(IER, IER_RX_AVAILABLE);
+        Ok(())
+    }
+
+    /// Check the line settings read back (a POST self test)
+    pub fn self_test(&self) -> Result<()> {
+        if self.read_reg(LCR) != LCR_8N1 {
+            return Err(ErrorKind::NotFound.on(ObjectKind::Device).with_id(self.irq));
+        }
+        Ok(())
+    }
+
+    /// Stop using interrupts, e.g. from the panic handler
+    ///
+    /// Pending output is sent by polling first.
+    pub fn enter_polled_mode(&self) {
+        crate::critical_section! {
+            self.write_reg(IER, 0);
+            self.polled.store(true, Ordering::Release);
+        }
+        self.flush();
+    }
+
+    /// Go back to interrupts after `enter_polled_mode`
+    ///
+    /// Does nothing before `init` installed the interrupt handler.
+    pub fn leave_polled_mode(&self) {
+        crate::critical_section! {
+            if self.irq_installed.load(Ordering::Acquire) {
+                self.polled.store(false, Ordering::Release);
+                self.tx_pump();
+            }
+        }
+    }
+
+    // ========================================================================
+    // TRANSMIT
+    // ========================================================================
+
+    /// Move bytes from the TX ring into the FIFO while it has room
+    ///
+    /// Leaves the THR-empty interrupt enabled only while bytes remain.
+    /// Called inside a critical section or from the interrupt handler.
+    fn tx_pump(&self) {
+        if self.read_reg(LSR) & LSR_THR_EMPTY != 0 {
+            for _ in 0..TX_FIFO_DEPTH {
+                match self.tx.pop() {
+                    Some(byte) => self.write_reg(THR, byte),
+                    None => break,
+                }
+            }
+        }
+
+        if !self.polled.load(Ordering::Acquire) {
+            let ier = if self.tx.is_empty() {
+                IER_RX_AVAILABLE
+            } else {
+                IER_RX_AVAILABLE | IER_TX_EMPTY
+            };
+            self.write_reg(IER, ier);
+        }
+    }
+
+    /// Queue bytes for transmission
+    ///
+    /// Returns once every byte is in the TX ring; while it is full the
+    /// caller feeds the transmitter itself. Bytes that still do not fit
+    /// after config::SPIN_TIMEOUT_CYCLES are dropped and counted. In
+    /// polled mode the bytes have been handed to the hardware on return.
+    pub fn write(&self, bytes: &[u8]) {
+        let mut guard = None;
+        for &byte in bytes {
+            loop {
+                let queued = crate::critical_section! {
+                    let queued = self.tx.push(byte);
+                    self.tx_pump();
+                    queued
+                };
+                if queued {
+                    break;
+                }
+                let guard = guard.get_or_insert_with(|| SpinGuard::new("uart-tx", config::SPIN_TIMEOUT_CYCLES));
+                if guard.check().is_err() {
+                    self.tx_dropped.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
+
+        if self.polled.load(Ordering::Acquire) {
+            self.drain_tx();
+        }
+    }
+
+    /// Send everything in the TX ring by polling
+    fn drain_tx(&self) {
+        let guard = SpinGuard::new("uart-tx-drain", config::SPIN_TIMEOUT_CYCLES);
+        while !self.tx.is_empty() {
+            crate::critical_section! { self.tx_pump() };
+            if guard.check().is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Wait until everything written has left the transmitter
+    pub fn flush(&self) {
+        self.drain_tx();
+        let _ = arch::spin_until("uart-tx-idle", config::SPIN_TIMEOUT_CYCLES, || {
+            self.read_reg(LSR) & LSR_TX_IDLE != 0
+        });
+    }
+
+    // ========================================================================
+    // RECEIVE
+    // ========================================================================
+
+    /// Move received bytes from the FIFO into the RX ring
+    fn rx_drain_fifo(&self) {
+        loop {
+            let lsr = self.read_reg(LSR);
+            if lsr & LSR_OVERRUN != 0 {
+                self.rx_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            if lsr & LSR_DATA_READY == 0 {
+                break;
+            }
+            if !self.rx.push(self.read_reg(RBR)) {
+                self.rx_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Service the UART's interrupt (call from its PLIC handler)
+    pub fn handle_interrupt(&self) {
+        self.rx_drain_fifo();
+        if !self.rx.is_empty() && !wake_first_waiter(unsafe { &mut *self.readers() }).is_null() {
+            reschedule();
+        }
+        self.tx_pump();
+    }
+
+    /// Take whatever input has arrived, without blocking
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        // Readers are serialized with each other (the ring has one consumer)
+        crate::critical_section! {
+            if self.polled.load(Ordering::Acquire) {
+                self.rx_drain_fifo();
+            }
+            let mut n = 0;
+            while n < buf.len() {
+                match self.rx.pop() {
+                    Some(byte) => {
+                        buf[n] = byte;
+                        n += 1;
+                    }
+                    None => break,
+                }
+            }
+            n
+        }
+    }
+
+    fn read_byte_until(&self, deadline: Option<TickType>) -> Result<u8> {
+        crate::critical_section! {
+            loop {
+                if self.polled.load(Ordering::Acquire) {
+                    self.rx_drain_fifo();
+                }
+                if let Some(byte) = self.rx.pop() {
+                    return Ok(byte);
+                }
+                // Nothing can wake a reader without the interrupt
+                if self.polled.load(Ordering::Acquire) {
+                    return Err(ErrorKind::Timeout.on(ObjectKind::Device).with_id(self.irq));
+                }
+                let waited = match deadline {
+                    Some(deadline) if get_tick_count().has_reached(deadline) => {
+                        return Err(ErrorKind::Timeout.on(ObjectKind::Device).with_id(self.irq))
+                    }
+                    Some(deadline) => unsafe {
+                        block_current_task_until(&mut *self.readers(), deadline)
+                    },
+                    None => unsafe { block_current_task(&mut *self.readers()) },
+                };
+                waited.map_err(|e| e.with_object(ObjectKind::Device).with_id(self.irq))?;
+            }
+        }
+    }
+
+    /// Wait for one input byte
+    ///
+    /// `timeout` is `None` to wait forever or the longest time to wait.
+    /// Fails with `Timeout` if nothing arrived in time, and at once if
+    /// nothing is buffered in polled mode (before `init`).
+    pub fn read_byte(&self, timeout: Option<TickType>) -> Result<u8> {
+        let deadline = timeout.map(|t| get_tick_count().wrapping_add(t));
+        self.read_byte_until(deadline)
+    }
+
+    /// Read a line of input into `buf`, echoing it
+    ///
+    /// Ends at CR or LF (not stored); backspace/DEL erases the last byte.
+    /// Bytes beyond `buf.len()` are discarded until the line ends.
+    /// `timeout` bounds the whole line. Returns the line length.
+    ///
+    /// # Example
+    /// ```
+    /// let mut line = [0u8; 80];
+    /// loop {
+    ///     UART0.write(b"> ");
+    ///     let n = UART0.read_line(&mut line, None)?;
+    ///     run_command(&line[..n]);
+    /// }
+    /// ```
+    pub fn read_line(&self, buf: &mut [u8], timeout: Option<TickType>) -> Result<usize> {
+        let deadline = timeout.map(|t| get_tick_count().wrapping_add(t));
+        let mut len = 0;
+        loop {
+            match self.read_byte_until(deadline)? {
+                b'\r' | b'\n' => {
+                    self.write(b"\r\n");
+                    return Ok(len);
+                }
+                0x08 | 0x7f if len > 0 => {
+                    len -= 1;
+                    self.write(b"\x08 \x08");
+                }
+                // Backspace on an empty line
+                0x08 | 0x7f => {}
+                byte if len < buf.len() => {
+                    buf[len] = byte;
+                    len += 1;
+                    self.write(&[byte]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Received bytes lost to overruns
+    pub fn rx_dropped(&self) -> u64 {
+        self.rx_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Output bytes dropped because the transmitter stalled
+    pub fn tx_dropped(&self) -> u64 {
+        self.tx_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Tasks blocked waiting for input
+    ///
+    /// # Safety
+    /// Call and dereference only inside a critical section or the handler.
+    unsafe fn readers(&self) -> *mut List {
+        if !self.lists_ready.load(Ordering::Relaxed) {
+            (*self.readers.get()).init();
+            self.lists_ready.store(true, Ordering::Relaxed);
+        }
+        self.readers.get()
+    }
+}
@@ -0,0 +1,228 @@
+// Console device
+//
+// Backs the default stdin/stdout/stderr of every task and the kernel log.
+// Output goes down a chain of sinks (UART, RTT, the dmesg ring, ...) that
+// can be attached and detached at runtime; each sink has a level filter,
+// so e.g. a network log stream can take everything while the serial port
+// only shows errors. Input comes from one reader installed by the board.
+// The console can also be mounted (e.g. at "/dev/console") so a
+// redirected task can reopen it.
+
+use crate::fs::vfs::FileSystem;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Console output function
+pub type ConsoleWrite = fn(&[u8]);
+
+/// Console input function, returns bytes read (0 = nothing available)
+pub type ConsoleRead = fn(&mut [u8]) -> usize;
+
+/// Push out anything a sink still buffers (e.g. a UART FIFO)
+pub type ConsoleFlush = fn();
+
+/// Message severity, most severe first
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Critical = 0,
+    Error = 1,
+    Warning = 2,
+    /// Normal output (task stdout/stderr, `klog!`)
+    Info = 3,
+    Debug = 4,
+}
+
+impl LogLevel {
+    fn from_u8(value: u8) -> LogLevel {
+        match value {
+            0 => LogLevel::Critical,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warning,
+            3 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+}
+
+/// An output in the console chain
+///
+/// # Example
+/// ```
+/// fn uart_write(bytes: &[u8]) { /* ... */ }
+/// static UART_SINK: ConsoleSink = ConsoleSink::new("uart", uart_write, LogLevel::Error);
+/// attach_console_sink(&UART_SINK).unwrap();
+/// ```
+pub struct ConsoleSink {
+    name: &'static str,
+    write: ConsoleWrite,
+    flush: Option<ConsoleFlush>,
+    /// Least severe level passed to this sink
+    level: AtomicU8,
+}
+
+impl ConsoleSink {
+    pub const fn new(name: &'static str, write: ConsoleWrite, level: LogLevel) -> Self {
+        ConsoleSink {
+            name,
+            write,
+            flush: None,
+            level: AtomicU8::new(level as u8),
+        }
+    }
+
+    /// Attach a flush function, called by `console_flush`
+    pub const fn with_flush(mut self, flush: ConsoleFlush) -> Self {
+        self.flush = Some(flush);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Change the level filter (takes effect on the next write)
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+}
+
+// ============================================================================
+// SINK CHAIN
+// ============================================================================
+
+/// Attached sinks. The dmesg ring is attached from the start so messages
+/// written before any board sink exists are kept.
+static mut SINKS: [Option<&'static ConsoleSink>; config::MAX_CONSOLE_SINKS] = {
+    let mut sinks = [None; config::MAX_CONSOLE_SINKS];
+    sinks[0] = Some(&crate::kernel::dmesg::DMESG_SINK);
+    sinks
+};
+
+static mut CONSOLE_IN: Option<ConsoleRead> = None;
+
+/// Add a sink to the console chain
+///
+/// Fails with `InvalidParameter` if a sink with the same name is attached
+/// and with `OutOfMemory` if the chain is full.
+pub fn attach_console_sink(sink: &'static ConsoleSink) -> Result<()> {
+    crate::critical_section! {
+        let sinks = unsafe { &mut *core::ptr::addr_of_mut!(SINKS) };
+
+        if sinks.iter().flatten().any(|s| s.name == sink.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::ConsoleSink));
+        }
+
+        match sinks.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(sink);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::ConsoleSink)),
+        }
+    }
+}
+
+/// Remove a sink from the console chain by name
+pub fn detach_console_sink(name: &str) -> Result<()> {
+    crate::critical_section! {
+        let sinks = unsafe { &mut *core::ptr::addr_of_mut!(SINKS) };
+        match sinks.iter_mut().find(|slot| matches!(slot, Some(s) if s.name == name)) {
+            Some(slot) => {
+                *slot = None;
+                Ok(())
+            }
+            None => Err(ErrorKind::NotFound.on(ObjectKind::ConsoleSink)),
+        }
+    }
+}
+
+/// Look up an attached sink (e.g. to change its level)
+pub fn find_console_sink(name: &str) -> Option<&'static ConsoleSink> {
+    let sinks = unsafe { &*core::ptr::addr_of!(SINKS) };
+    sinks.iter().flatten().find(|s| s.name == name).copied()
+}
+
+/// Visit every attached sink
+pub fn for_each_console_sink<F: FnMut(&'static ConsoleSink)>(mut f: F) {
+    let sinks = unsafe { &*core::ptr::addr_of!(SINKS) };
+    for sink in sinks.iter().flatten() {
+        f(sink);
+    }
+}
+
+/// Install the console input driver
+pub fn set_console_input(input: Option<ConsoleRead>) {
+    crate::critical_section! {
+        unsafe { CONSOLE_IN = input };
+    }
+}
+
+/// Write to every sink whose filter passes `level`
+pub fn console_write_level(level: LogLevel, buf: &[u8]) -> usize {
+    let sinks = unsafe { &*core::ptr::addr_of!(SINKS) };
+    for sink in sinks.iter().flatten() {
+        if level <= sink.level() {
+            (sink.write)(buf);
+        }
+    }
+    buf.len()
+}
+
+/// Flush every sink that buffers output (before reset or power-off)
+pub fn console_flush() {
+    let sinks = unsafe { &*core::ptr::addr_of!(SINKS) };
+    for sink in sinks.iter().flatten() {
+        if let Some(flush) = sink.flush {
+            flush();
+        }
+    }
+}
+
+/// Write normal (Info) output to the console
+pub fn console_write(buf: &[u8]) -> usize {
+    console_write_level(LogLevel::Info, buf)
+}
+
+/// Read from the console without blocking
+pub fn console_read(buf: &mut [u8]) -> usize {
+    match unsafe { CONSOLE_IN } {
+        Some(f) => f(buf),
+        None => 0,
+    }
+}
+
+/// Mountable console device (every path opens the console)
+pub struct ConsoleFs;
+
+/// Console device instance
+pub static CONSOLE: ConsoleFs = ConsoleFs;
+
+impl FileSystem for ConsoleFs {
+    fn name(&self) -> &'static str {
+        "console"
+    }
+
+    fn open(&self, _path: &str, _flags: u32) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn close(&self, _handle: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn read(&self, _handle: usize, _offset: u64, buf: &mut [u8]) -> Result<usize> {
+        Ok(console_read(buf))
+    }
+
+    fn write(&self, _handle: usize, _offset: u64, buf: &[u8]) -> Result<usize> {
+        Ok(console_write(buf))
+    }
+
+    fn size(&self, _handle: usize) -> Result<u64> {
+        Err(ErrorKind::InvalidParameter.on(ObjectKind::File))
+    }
+}
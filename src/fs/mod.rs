@@ -0,0 +1,14 @@
+// Filesystems and the virtual filesystem layer
+pub mod console;
+pub mod mux;
+pub mod vfs;
+
+pub use console::{
+    attach_console_sink, console_flush, console_write, detach_console_sink, find_console_sink, set_console_input,
+    ConsoleSink, LogLevel, CONSOLE,
+};
+pub use mux::{mux_write, set_mux_output, MuxChannel, MUX_SINK};
+pub use vfs::{
+    close, dup, dup2, mount, open, read, redirect_task_stdio, seek, sync_all, unmount, write, Fd,
+    FileSystem, SeekFrom, STDERR, STDIN, STDOUT,
+};
@@ -0,0 +1,133 @@
+// Console multiplexer
+//
+// Lets kernel logs, shell I/O and defmt frames share one serial port.
+// Every write becomes one self-contained frame tagged with its channel, so
+// a host-side demux tool can split the stream back into separate
+// terminals instead of showing interleaved garbage when several
+// subsystems print at once. A frame is emitted with interrupts disabled,
+// so frames never interleave with each other.
+//
+// Wire format: each frame is COBS-encoded (no zero bytes inside) and
+// terminated by a single 0x00, so the host resynchronises at the next
+// zero after line noise or a dropped byte. Decoded frame (integers
+// little-endian):
+//
+//   u8   channel (MuxChannel discriminant)
+//   u8   sequence number, per channel, wrapping (gaps = lost frames)
+//   [n]  payload, 1..=MUX_MAX_PAYLOAD bytes
+//   u32  crc32 (IEEE 802.3, as in the core dump) of channel..payload
+//
+// Writes longer than MUX_MAX_PAYLOAD are split over several frames. Host
+// tools must ignore channels they do not know. Input from the host is not
+// framed; it still goes to the console reader.
+
+use crate::fs::console::{ConsoleSink, ConsoleWrite, LogLevel};
+use crate::kernel::coredump::crc32;
+use crate::kernel::types::config;
+
+/// Channel a frame belongs to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MuxChannel {
+    /// Console output: task stdout/stderr and `klog!`
+    Log = 0,
+    /// Interactive shell output
+    Shell = 1,
+    /// defmt-encoded log frames
+    Defmt = 2,
+}
+
+const CHANNEL_COUNT: usize = 3;
+
+/// Channel + sequence + payload + crc
+const RAW_MAX: usize = 2 + config::MUX_MAX_PAYLOAD + 4;
+
+/// COBS adds one byte per 254 raw bytes, plus the 0x00 delimiter
+const FRAME_MAX: usize = RAW_MAX + RAW_MAX / 254 + 2;
+
+/// Raw byte output the frames go to (e.g. the UART)
+static mut MUX_OUT: Option<ConsoleWrite> = None;
+
+/// Next sequence number of each channel
+static mut SEQUENCE: [u8; CHANNEL_COUNT] = [0; CHANNEL_COUNT];
+
+/// Console sink that frames console output on the `Log` channel
+///
+/// # Example
+/// ```
+/// set_mux_output(Some(uart_write));
+/// detach_console_sink("uart").unwrap();
+/// attach_console_sink(&MUX_SINK).unwrap();
+/// ```
+pub static MUX_SINK: ConsoleSink = ConsoleSink::new("mux", mux_console_write, LogLevel::Debug);
+
+fn mux_console_write(bytes: &[u8]) {
+    mux_write(MuxChannel::Log, bytes);
+}
+
+/// Set the raw output frames are written to (None stops output)
+pub fn set_mux_output(output: Option<ConsoleWrite>) {
+    crate::critical_section! {
+        unsafe { MUX_OUT = output };
+    }
+}
+
+/// Send `buf` on `channel`, split into frames as needed
+///
+/// Returns the number of bytes sent: all of `buf`, or 0 if no output is
+/// set.
+pub fn mux_write(channel: MuxChannel, buf: &[u8]) -> usize {
+    let Some(output) = (unsafe { MUX_OUT }) else {
+        return 0;
+    };
+
+    for chunk in buf.chunks(config::MUX_MAX_PAYLOAD) {
+        crate::critical_section! {
+            let sequence = unsafe { &mut (*core::ptr::addr_of_mut!(SEQUENCE))[channel as usize] };
+            let mut frame = [0u8; FRAME_MAX];
+            let len = encode_frame(channel, *sequence, chunk, &mut frame);
+            *sequence = sequence.wrapping_add(1);
+            output(&frame[..len]);
+        }
+    }
+    buf.len()
+}
+
+/// Build the frame for one chunk in `out`, returning its length
+fn encode_frame(channel: MuxChannel, sequence: u8, payload: &[u8], out: &mut [u8; FRAME_MAX]) -> usize {
+    let mut raw = [0u8; RAW_MAX];
+    raw[0] = channel as u8;
+    raw[1] = sequence;
+    raw[2..2 + payload.len()].copy_from_slice(payload);
+    let crc_at = 2 + payload.len();
+    let crc = crc32(&raw[..crc_at]);
+    raw[crc_at..crc_at + 4].copy_from_slice(&crc.to_le_bytes());
+
+    let len = cobs_encode(&raw[..crc_at + 4], out);
+    out[len] = 0;
+    len + 1
+}
+
+/// COBS-encode `data` into `out` (no delimiter), returning the length
+///
+/// `out` must hold `data.len() + data.len() / 254 + 1` bytes.
+fn cobs_encode(data: &[u8], out: &mut [u8]) -> usize {
+    let mut code_at = 0;
+    let mut code: u8 = 1;
+    let mut len = 1;
+
+    for &byte in data {
+        if byte != 0 {
+            out[len] = byte;
+            len += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_at] = code;
+            code_at = len;
+            code = 1;
+            len += 1;
+        }
+    }
+    out[code_at] = code;
+    len
+}
@@ -0,0 +1,518 @@
+// Virtual filesystem layer
+//
+// Filesystem drivers (tmpfs, FAT32, littlefs, 9p) implement `FileSystem`
+// and are mounted at a path. Applications use one descriptor-based API
+// (open/read/write/seek/close) regardless of what backs the file.
+//
+// Three tables are involved:
+//   - mount table: path prefix -> filesystem
+//   - open-file table: global, holds the fs handle, offset and flags
+//   - fd table: per task (in the TCB), maps small integers to open files
+//
+// Descriptors 0-2 are the task's stdin/stdout/stderr. While a task has not
+// redirected one of them it refers to the console.
+
+use crate::fs::console;
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+
+/// File descriptor (index into the calling task's fd table)
+pub type Fd = usize;
+
+/// Standard input
+pub const STDIN: Fd = 0;
+/// Standard output
+pub const STDOUT: Fd = 1;
+/// Standard error
+pub const STDERR: Fd = 2;
+
+/// Lowest descriptor handed out by `open` / `dup` (0-2 are stdio)
+pub const FIRST_USER_FD: Fd = 3;
+
+/// Open for reading
+pub const O_READ: u32 = 1 << 0;
+/// Open for writing
+pub const O_WRITE: u32 = 1 << 1;
+/// Open for reading and writing
+pub const O_RDWR: u32 = O_READ | O_WRITE;
+/// Create the file if it does not exist
+pub const O_CREATE: u32 = 1 << 2;
+/// Truncate the file to zero length on open
+pub const O_TRUNC: u32 = 1 << 3;
+/// Every write goes to the end of the file
+pub const O_APPEND: u32 = 1 << 4;
+
+/// Marker for an unused slot in a task's fd table
+pub const FD_UNUSED: i16 = -1;
+
+/// Seek origin
+#[derive(Copy, Clone, Debug)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// Interface implemented by every filesystem driver
+///
+/// Paths passed in are relative to the mount point and always start with
+/// '/'. Handles are private to the driver; the VFS only stores them.
+/// Drivers do their own locking - the VFS does not hold a critical section
+/// across driver calls.
+pub trait FileSystem: Sync {
+    /// Short driver name, e.g. "tmpfs"
+    fn name(&self) -> &'static str;
+
+    /// Open a file and return a driver handle
+    fn open(&self, path: &str, flags: u32) -> Result<usize>;
+
+    /// Release a handle returned by `open`
+    fn close(&self, handle: usize) -> Result<()>;
+
+    /// Read at an absolute offset, returning bytes read (0 = end of file)
+    fn read(&self, handle: usize, offset: u64, buf: &mut [u8]) -> Result<usize>;
+
+    /// Write at an absolute offset, returning bytes written
+    fn write(&self, handle: usize, offset: u64, buf: &[u8]) -> Result<usize>;
+
+    /// Current file size in bytes
+    fn size(&self, handle: usize) -> Result<u64>;
+
+    /// Remove a file
+    fn unlink(&self, _path: &str) -> Result<()> {
+        Err(ErrorKind::InvalidParameter.on(ObjectKind::File))
+    }
+
+    /// Flush cached data for a handle
+    fn sync(&self, _handle: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+// ============================================================================
+// TABLES
+// ============================================================================
+
+#[derive(Copy, Clone)]
+struct Mount {
+    path: &'static str,
+    fs: &'static dyn FileSystem,
+}
+
+#[derive(Copy, Clone)]
+struct OpenFile {
+    mount: usize,
+    handle: usize,
+    offset: u64,
+    flags: u32,
+    /// Number of fd slots referring to this entry (0 = free)
+    refs: usize,
+}
+
+impl OpenFile {
+    const fn empty() -> Self {
+        OpenFile { mount: 0, handle: 0, offset: 0, flags: 0, refs: 0 }
+    }
+}
+
+static mut MOUNTS: [Option<Mount>; config::MAX_MOUNTS] = [None; config::MAX_MOUNTS];
+static mut OPEN_FILES: [OpenFile; config::MAX_OPEN_FILES] = [OpenFile::empty(); config::MAX_OPEN_FILES];
+
+/// fd table used before the scheduler starts (no current task)
+static mut KERNEL_FDS: [i16; config::MAX_TASK_FDS] = [FD_UNUSED; config::MAX_TASK_FDS];
+
+const _: () = assert!(config::MAX_OPEN_FILES <= i16::MAX as usize);
+
+/// fd table of the calling task
+///
+/// Must be called inside a critical section.
+unsafe fn fd_table() -> &'static mut [i16; config::MAX_TASK_FDS] {
+    let current = get_current_task();
+    if current.is_null() {
+        &mut *core::ptr::addr_of_mut!(KERNEL_FDS)
+    } else {
+        &mut (*current).fds
+    }
+}
+
+/// Open-file index behind an fd of the calling task
+unsafe fn file_index(fd: Fd) -> Result<usize> {
+    let fds = fd_table();
+    match fds.get(fd) {
+        Some(&slot) if slot != FD_UNUSED => Ok(slot as usize),
+        _ => Err(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(fd as u32)),
+    }
+}
+
+/// True if `fd` is a stdio descriptor still pointing at the console
+fn is_console(fd: Fd) -> bool {
+    fd < FIRST_USER_FD && crate::critical_section! { unsafe { fd_table()[fd] == FD_UNUSED } }
+}
+
+/// Snapshot of an open file and its filesystem
+unsafe fn lookup(fd: Fd) -> Result<(usize, OpenFile, &'static dyn FileSystem)> {
+    let index = file_index(fd)?;
+    let file = OPEN_FILES[index];
+    match MOUNTS[file.mount] {
+        Some(m) => Ok((index, file, m.fs)),
+        None => Err(ErrorKind::NotFound.on(ObjectKind::Mount).with_id(file.mount as u32)),
+    }
+}
+
+/// Split a path into (mount index, path within the mount)
+///
+/// Picks the longest mount point that is a whole-component prefix of
+/// `path`. Only absolute paths are accepted; "." and ".." are not
+/// interpreted.
+fn resolve(path: &str) -> Result<(usize, &str)> {
+    if !path.starts_with('/') {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Mount));
+    }
+
+    let mut best: Option<(usize, usize)> = None;
+    crate::critical_section! {
+        let mounts = unsafe { &*core::ptr::addr_of!(MOUNTS) };
+        for (i, entry) in mounts.iter().enumerate() {
+            let Some(m) = entry else { continue };
+            let prefix = m.path.trim_end_matches('/');
+            let matches = path.starts_with(prefix)
+                && (path.len() == prefix.len() || path.as_bytes()[prefix.len()] == b'/');
+            if matches && best.is_none_or(|(_, len)| prefix.len() > len) {
+                best = Some((i, prefix.len()));
+            }
+        }
+    }
+
+    match best {
+        Some((index, len)) if len == path.len() => Ok((index, "/")),
+        Some((index, len)) => Ok((index, &path[len..])),
+        None => Err(ErrorKind::NotFound.on(ObjectKind::Mount)),
+    }
+}
+
+// ============================================================================
+// MOUNT TABLE
+// ============================================================================
+
+/// Mount a filesystem at an absolute path ("/" for the root)
+pub fn mount(path: &'static str, fs: &'static dyn FileSystem) -> Result<()> {
+    if !path.starts_with('/') {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Mount));
+    }
+
+    crate::critical_section! {
+        let mounts = unsafe { &mut *core::ptr::addr_of_mut!(MOUNTS) };
+        if mounts.iter().flatten().any(|m| m.path == path) {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::Mount));
+        }
+        match mounts.iter_mut().find(|m| m.is_none()) {
+            Some(slot) => {
+                *slot = Some(Mount { path, fs });
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::Mount)),
+        }
+    }
+}
+
+/// Unmount the filesystem at `path`
+///
+/// Fails with ResourceBusy while any file on it is open.
+pub fn unmount(path: &str) -> Result<()> {
+    crate::critical_section! {
+        let mounts = unsafe { &mut *core::ptr::addr_of_mut!(MOUNTS) };
+        let files = unsafe { &*core::ptr::addr_of!(OPEN_FILES) };
+        let index = mounts
+            .iter()
+            .position(|m| m.is_some_and(|m| m.path == path))
+            .ok_or(ErrorKind::NotFound.on(ObjectKind::Mount))?;
+        if files.iter().any(|f| f.refs > 0 && f.mount == index) {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::Mount).with_id(index as u32));
+        }
+        mounts[index] = None;
+        Ok(())
+    }
+}
+
+/// Visit every mount as (mount point, driver name)
+pub fn for_each_mount<F: FnMut(&str, &str)>(mut f: F) {
+    let mounts = unsafe { *core::ptr::addr_of!(MOUNTS) };
+    for m in mounts.iter().flatten() {
+        f(m.path, m.fs.name());
+    }
+}
+
+// ============================================================================
+// FILE API
+// ============================================================================
+
+/// Open a file and return the lowest free descriptor
+pub fn open(path: &str, flags: u32) -> Result<Fd> {
+    if flags & O_RDWR == 0 {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::File));
+    }
+    let (mount, rel) = resolve(path)?;
+    let fs = unsafe { MOUNTS[mount] }
+        .ok_or(ErrorKind::NotFound.on(ObjectKind::Mount).with_id(mount as u32))?
+        .fs;
+    let handle = fs.open(rel, flags)?;
+
+    let installed = crate::critical_section! {
+        unsafe { install(OpenFile { mount, handle, offset: 0, flags, refs: 1 }) }
+    };
+    if installed.is_err() {
+        let _ = fs.close(handle);
+    }
+    installed
+}
+
+/// Place a new open file in the global table and the caller's fd table
+unsafe fn install(file: OpenFile) -> Result<Fd> {
+    let files = &mut *core::ptr::addr_of_mut!(OPEN_FILES);
+    let fds = fd_table();
+    let fd = (FIRST_USER_FD..config::MAX_TASK_FDS)
+        .find(|&fd| fds[fd] == FD_UNUSED)
+        .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::File))?;
+    let index = files
+        .iter()
+        .position(|f| f.refs == 0)
+        .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::File))?;
+    files[index] = file;
+    fds[fd] = index as i16;
+    Ok(fd)
+}
+
+/// Drop one reference to an open file, returning the driver handle to
+/// close if it was the last one
+unsafe fn release(index: usize) -> Option<(usize, usize)> {
+    let file = &mut (*core::ptr::addr_of_mut!(OPEN_FILES))[index];
+    file.refs -= 1;
+    if file.refs == 0 {
+        Some((file.mount, file.handle))
+    } else {
+        None
+    }
+}
+
+/// Close a descriptor
+pub fn close(fd: Fd) -> Result<()> {
+    let last = crate::critical_section! {
+        unsafe {
+            let index = file_index(fd)?;
+            fd_table()[fd] = FD_UNUSED;
+            release(index)
+        }
+    };
+
+    if let Some((mount, handle)) = last {
+        if let Some(m) = unsafe { MOUNTS[mount] } {
+            return m.fs.close(handle);
+        }
+    }
+    Ok(())
+}
+
+/// Read from the current offset, advancing it
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize> {
+    if is_console(fd) {
+        return Ok(console::console_read(buf));
+    }
+    let (index, file, fs) = crate::critical_section! { unsafe { lookup(fd)? } };
+    if file.flags & O_READ == 0 {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(fd as u32));
+    }
+
+    let n = fs.read(file.handle, file.offset, buf)?;
+    crate::critical_section! {
+        unsafe { OPEN_FILES[index].offset = file.offset + n as u64; }
+    }
+    Ok(n)
+}
+
+/// Write at the current offset (or the end with O_APPEND), advancing it
+pub fn write(fd: Fd, buf: &[u8]) -> Result<usize> {
+    if is_console(fd) {
+        return Ok(console::console_write(buf));
+    }
+    let (index, file, fs) = crate::critical_section! { unsafe { lookup(fd)? } };
+    if file.flags & O_WRITE == 0 {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(fd as u32));
+    }
+
+    let offset = if file.flags & O_APPEND != 0 {
+        fs.size(file.handle)?
+    } else {
+        file.offset
+    };
+    let n = fs.write(file.handle, offset, buf)?;
+    crate::critical_section! {
+        unsafe { OPEN_FILES[index].offset = offset + n as u64; }
+    }
+    Ok(n)
+}
+
+/// Move the file offset, returning the new absolute position
+pub fn seek(fd: Fd, pos: SeekFrom) -> Result<u64> {
+    let (index, file, fs) = crate::critical_section! { unsafe { lookup(fd)? } };
+
+    let (base, delta) = match pos {
+        SeekFrom::Start(offset) => (0, offset as i64),
+        SeekFrom::Current(delta) => (file.offset, delta),
+        SeekFrom::End(delta) => (fs.size(file.handle)?, delta),
+    };
+    let offset = base
+        .checked_add_signed(delta)
+        .ok_or(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(fd as u32))?;
+
+    crate::critical_section! {
+        unsafe { OPEN_FILES[index].offset = offset; }
+    }
+    Ok(offset)
+}
+
+/// Size of an open file in bytes
+pub fn file_size(fd: Fd) -> Result<u64> {
+    let (_, file, fs) = crate::critical_section! { unsafe { lookup(fd)? } };
+    fs.size(file.handle)
+}
+
+/// Flush cached data for an open file
+pub fn sync(fd: Fd) -> Result<()> {
+    let (_, file, fs) = crate::critical_section! { unsafe { lookup(fd)? } };
+    fs.sync(file.handle)
+}
+
+/// Number of open-file table entries in use
+pub fn open_file_count() -> usize {
+    crate::critical_section! {
+        let files = unsafe { &*core::ptr::addr_of!(OPEN_FILES) };
+        files.iter().filter(|f| f.refs > 0).count()
+    }
+}
+
+/// Flush every open file on every mount (before power-off)
+///
+/// Keeps going after a failure and returns the first error.
+pub fn sync_all() -> Result<()> {
+    let mut result = Ok(());
+    let files = unsafe { &*core::ptr::addr_of!(OPEN_FILES) };
+    for file in files {
+        let target = crate::critical_section! {
+            unsafe {
+                let file = *file;
+                match MOUNTS[file.mount] {
+                    Some(m) if file.refs > 0 => Some((m.fs, file.handle)),
+                    _ => None,
+                }
+            }
+        };
+        if let Some((fs, handle)) = target {
+            if let Err(e) = fs.sync(handle) {
+                result = result.and(Err(e));
+            }
+        }
+    }
+    result
+}
+
+/// Remove a file by path
+pub fn unlink(path: &str) -> Result<()> {
+    let (mount, rel) = resolve(path)?;
+    let fs = unsafe { MOUNTS[mount] }
+        .ok_or(ErrorKind::NotFound.on(ObjectKind::Mount).with_id(mount as u32))?
+        .fs;
+    fs.unlink(rel)
+}
+
+/// Duplicate a descriptor onto the lowest free user descriptor
+pub fn dup(fd: Fd) -> Result<Fd> {
+    crate::critical_section! {
+        unsafe {
+            let index = file_index(fd)?;
+            let fds = fd_table();
+            let new_fd = (FIRST_USER_FD..config::MAX_TASK_FDS)
+                .find(|&fd| fds[fd] == FD_UNUSED)
+                .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::File))?;
+            OPEN_FILES[index].refs += 1;
+            fds[new_fd] = index as i16;
+            Ok(new_fd)
+        }
+    }
+}
+
+/// Make `new_fd` refer to the same open file as `old_fd`
+///
+/// Whatever `new_fd` referred to is closed first. This is how a task
+/// redirects its own stdio, e.g. `dup2(log_fd, STDOUT)`.
+pub fn dup2(old_fd: Fd, new_fd: Fd) -> Result<Fd> {
+    if new_fd >= config::MAX_TASK_FDS {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(new_fd as u32));
+    }
+    if old_fd == new_fd {
+        return crate::critical_section! { unsafe { file_index(old_fd).map(|_| new_fd) } };
+    }
+
+    let replaced = crate::critical_section! {
+        unsafe {
+            let index = file_index(old_fd)?;
+            let fds = fd_table();
+            let previous = fds[new_fd];
+            OPEN_FILES[index].refs += 1;
+            fds[new_fd] = index as i16;
+            if previous == FD_UNUSED { None } else { release(previous as usize) }
+        }
+    };
+    close_released(replaced);
+    Ok(new_fd)
+}
+
+/// Point another task's stdin/stdout/stderr at one of the caller's files
+///
+/// Used by a launcher (e.g. the shell) to capture a task's output in a
+/// file or stream it over a socket before the task starts. Passing `None`
+/// restores the console.
+pub fn redirect_task_stdio(tcb: *mut TaskControlBlock, stdio: Fd, fd: Option<Fd>) -> Result<()> {
+    if tcb.is_null() || stdio >= FIRST_USER_FD {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::File).with_id(stdio as u32));
+    }
+
+    let replaced = crate::critical_section! {
+        unsafe {
+            let slot = match fd {
+                Some(fd) => {
+                    let index = file_index(fd)?;
+                    OPEN_FILES[index].refs += 1;
+                    index as i16
+                }
+                None => FD_UNUSED,
+            };
+            let previous = core::mem::replace(&mut (*tcb).fds[stdio], slot);
+            if previous == FD_UNUSED { None } else { release(previous as usize) }
+        }
+    };
+    close_released(replaced);
+    Ok(())
+}
+
+/// Close the driver handle of an open file whose last reference went away
+fn close_released(released: Option<(usize, usize)>) {
+    if let Some((mount, handle)) = released {
+        if let Some(m) = unsafe { MOUNTS[mount] } {
+            let _ = m.fs.close(handle);
+        }
+    }
+}
+
+/// Close every descriptor in a task's fd table (on task deletion)
+pub fn close_all(fds: &mut [i16; config::MAX_TASK_FDS]) {
+    for slot in fds.iter_mut() {
+        if *slot == FD_UNUSED {
+            continue;
+        }
+        let index = *slot as usize;
+        *slot = FD_UNUSED;
+
+        close_released(crate::critical_section! { unsafe { release(index) } });
+    }
+}
@@ -0,0 +1,94 @@
+// Stack-scan backtraces
+//
+// The kernel is built without frame pointers, so there is no frame chain
+// to follow. Instead the stack is scanned upward from the failing sp and
+// every word that points into .text is reported as a frame, symbolized
+// through kernel::symtab. Return addresses saved by function prologues
+// come out innermost first; stale return addresses left over from earlier
+// calls and function pointers kept in locals match too, so a report may
+// contain a few frames that are not on the call path.
+//
+// The scan ends at the top of the stack sp lies in (the running task's
+// stack or the boot stack), after config::BACKTRACE_SCAN_WORDS words or
+// after config::BACKTRACE_MAX_FRAMES frames. Without a memory map (a
+// failure before `memory_map_init`) only pc and ra are reported.
+
+use crate::kernel::memmap::{find_region, find_region_by_kind, RegionKind};
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::symtab::symbolize;
+use crate::kernel::types::config;
+use core::fmt::{self, Write};
+use core::mem::size_of;
+
+/// Whether `addr` can be a return address
+fn is_code(addr: usize) -> bool {
+    match find_region_by_kind(RegionKind::Text) {
+        Some(text) => text.contains(addr),
+        None => false,
+    }
+}
+
+/// End of the stack `sp` points into, if it is a known stack
+fn stack_top(sp: usize) -> Option<usize> {
+    // The task stacks share one region: stop at the running task's end
+    if let Some(task) = unsafe { get_current_task().as_ref() } {
+        let (low, high) = task.stack_bounds();
+        if sp >= low && sp < high {
+            return Some(high);
+        }
+    }
+    match find_region(sp) {
+        Some(region) if region.kind == RegionKind::Stack => Some(region.end),
+        _ => None,
+    }
+}
+
+/// Visit the code addresses of a failing context, innermost first
+///
+/// `pc` and `ra` are the failing context's program counter and return
+/// address (either may be 0 if unknown); the stack above `sp` is scanned
+/// for further return addresses.
+pub fn for_each_frame<F: FnMut(usize)>(pc: usize, ra: usize, sp: usize, mut f: F) {
+    let mut frames = 0;
+    let mut last = 0;
+    let mut report = |addr: usize| {
+        if frames >= config::BACKTRACE_MAX_FRAMES || addr == last || !is_code(addr) {
+            return;
+        }
+        f(addr);
+        last = addr;
+        frames += 1;
+    };
+
+    report(pc);
+    report(ra);
+
+    let Some(top) = stack_top(sp) else { return };
+    let start = sp & !(size_of::<usize>() - 1);
+    let words = ((top - start) / size_of::<usize>()).min(config::BACKTRACE_SCAN_WORDS);
+    for i in 0..words {
+        let word = unsafe { core::ptr::read_volatile((start as *const usize).add(i)) };
+        report(word);
+    }
+}
+
+/// Print a backtrace of a failing context, one frame per line
+///
+/// ```text
+///   #0  0x0000000080002a14 app::parse_frame+0x3c
+///   #1  0x0000000080002b70 app::rx_task+0x48
+///   #2  0x0000000080001f02
+/// ```
+pub fn write_backtrace(out: &mut dyn Write, pc: usize, ra: usize, sp: usize) -> fmt::Result {
+    let mut result = Ok(());
+    let mut index = 0;
+    for_each_frame(pc, ra, sp, |addr| {
+        let line = match symbolize(addr) {
+            Some((func, offset)) => writeln!(out, "  #{:<2} {:#018x} {}+{:#x}", index, addr, func, offset),
+            None => writeln!(out, "  #{:<2} {:#018x}", index, addr),
+        };
+        result = result.and(line);
+        index += 1;
+    });
+    result
+}
@@ -0,0 +1,238 @@
+// Boot sequence
+//
+// Startup runs in fixed phases so drivers exist before tasks use them:
+//
+//   1. early_init   - interrupts off: RTT, memory map, heap, then Early hooks
+//                     (console, clocks)
+//   2. driver_init  - scheduler, tasklets, RCU and poison scrubbing set
+//                     up, then Driver hooks, then the registered task
+//                     table is instantiated
+//   3. app_init     - after the scheduler starts, the power-on self test
+//                     (if enabled) and then App hooks run in a short-lived
+//                     init task that deletes itself when done
+//
+// Boards and applications register hooks for a phase before boot;
+// hooks in a phase run in registration order. A hook may also carry a
+// shutdown function; `system::shutdown` runs those in reverse order.
+// A driver's hook can carry a self-test function for the POST.
+
+use crate::arch::{self, initialize_task_stack};
+use crate::fs::LogLevel;
+use crate::kernel::scheduler::{add_task_to_scheduler, delete_task, get_current_task, init_scheduler};
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use crate::kernel::{heap, memmap, poison, post, rcu, rtt, supervisor, task_table};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Boot phase a hook runs in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BootPhase {
+    /// Before interrupts and the scheduler exist
+    Early = 1,
+    /// Scheduler initialized, no task running yet
+    Driver = 2,
+    /// In the init task, after the scheduler has started
+    App = 3,
+}
+
+/// An initialization function registered for a boot phase
+///
+/// # Example
+/// ```
+/// fn uart_init() -> Result<()> { /* ... */ Ok(()) }
+/// static UART_INIT: BootHook = BootHook::new("uart", BootPhase::Driver, uart_init);
+/// register_boot_hook(&UART_INIT).unwrap();
+/// ```
+pub struct BootHook {
+    name: &'static str,
+    phase: BootPhase,
+    init: fn() -> Result<()>,
+    shutdown: Option<fn() -> Result<()>>,
+    self_test: Option<fn() -> Result<()>>,
+}
+
+impl BootHook {
+    pub const fn new(name: &'static str, phase: BootPhase, init: fn() -> Result<()>) -> Self {
+        BootHook { name, phase, init, shutdown: None, self_test: None }
+    }
+
+    /// Attach a teardown function, run at shutdown if `init` has run
+    pub const fn with_shutdown(mut self, shutdown: fn() -> Result<()>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Attach a check of the initialized device, run by the power-on
+    /// self test (kernel::post)
+    pub const fn with_self_test(mut self, self_test: fn() -> Result<()>) -> Self {
+        self.self_test = Some(self_test);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn phase(&self) -> BootPhase {
+        self.phase
+    }
+
+    pub fn self_test(&self) -> Option<fn() -> Result<()>> {
+        self.self_test
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut HOOKS: [Option<&'static BootHook>; config::MAX_BOOT_HOOKS] =
+    [None; config::MAX_BOOT_HOOKS];
+
+/// Last phase that has completed (0 = none)
+static COMPLETED_PHASE: AtomicU8 = AtomicU8::new(0);
+
+/// Register a hook for a later boot phase
+///
+/// Fails with `InvalidParameter` if the hook's phase has already run and
+/// with `OutOfMemory` if the registry is full.
+pub fn register_boot_hook(hook: &'static BootHook) -> Result<()> {
+    if hook.phase as u8 <= COMPLETED_PHASE.load(Ordering::Acquire) {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::BootHook));
+    }
+
+    crate::critical_section! {
+        let hooks = unsafe { &mut *core::ptr::addr_of_mut!(HOOKS) };
+        match hooks.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(hook);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::BootHook)),
+        }
+    }
+}
+
+/// Visit every registered hook, in registration order
+pub fn for_each_boot_hook<F: FnMut(&'static BootHook)>(mut f: F) {
+    let hooks = unsafe { &*core::ptr::addr_of!(HOOKS) };
+    for hook in hooks.iter().flatten() {
+        f(hook);
+    }
+}
+
+/// Visit every hook whose phase has completed, in registration order
+pub fn for_each_completed_boot_hook<F: FnMut(&'static BootHook)>(mut f: F) {
+    let completed = COMPLETED_PHASE.load(Ordering::Acquire);
+    for_each_boot_hook(|hook| {
+        if hook.phase as u8 <= completed {
+            f(hook);
+        }
+    });
+}
+
+/// Run every hook of a phase; a failing hook stops boot
+fn run_phase(phase: BootPhase) {
+    let hooks = unsafe { &*core::ptr::addr_of!(HOOKS) };
+    for hook in hooks.iter().flatten().filter(|h| h.phase == phase) {
+        if let Err(e) = (hook.init)() {
+            panic!("boot hook '{}' failed: {}", hook.name, e);
+        }
+    }
+    COMPLETED_PHASE.store(phase as u8, Ordering::Release);
+}
+
+/// Run the shutdown functions of hooks whose phase has run, in reverse
+/// init order (last phase first, newest hook first)
+///
+/// A failing shutdown function is reported and the rest still run.
+/// Called by `system::shutdown`.
+pub fn run_shutdown_hooks() {
+    let completed = COMPLETED_PHASE.load(Ordering::Acquire);
+    let hooks = unsafe { &*core::ptr::addr_of!(HOOKS) };
+    for phase in [BootPhase::App, BootPhase::Driver, BootPhase::Early] {
+        if phase as u8 > completed {
+            continue;
+        }
+        for hook in hooks.iter().rev().flatten().filter(|h| h.phase == phase) {
+            let Some(shutdown) = hook.shutdown else { continue };
+            if let Err(e) = shutdown() {
+                crate::klog!(LogLevel::Error, "shutdown: hook '{}' failed: {}\n", hook.name, e);
+            }
+        }
+    }
+}
+
+/// Phase 1: core kernel services and Early hooks
+///
+/// Call first thing in `main`, with interrupts still disabled.
+pub fn early_init(dtb: usize) {
+    rtt::rtt_init();
+    memmap::memory_map_init(dtb);
+    heap::heap_init();
+    run_phase(BootPhase::Early);
+}
+
+/// Phase 2: scheduler, Driver hooks and the application's task table
+pub fn driver_init() {
+    init_scheduler();
+    arch::enable_soft_interrupt();
+    arch::plic::plic_init();
+    if let Err(e) = rcu::rcu_init().and_then(|_| poison::poison_init()) {
+        panic!("idle chore setup failed: {}", e);
+    }
+    run_phase(BootPhase::Driver);
+    task_table::spawn_task_table();
+}
+
+// ============================================================================
+// INIT TASK
+// ============================================================================
+
+#[link_section = ".task_stacks"]
+static mut INIT_STACK: [usize; config::INIT_TASK_STACK_SIZE] = [0; config::INIT_TASK_STACK_SIZE];
+static mut INIT_TCB: Option<TaskControlBlock> = None;
+
+/// Phase 3: runs the self test and App hooks, then deletes itself
+extern "C" fn init_task(_arg: *mut c_void) -> ! {
+    if config::POWER_ON_SELF_TEST {
+        post::run_post();
+    }
+    run_phase(BootPhase::App);
+
+    unsafe {
+        let current = get_current_task();
+        arch::disable_interrupts();
+        delete_task(&mut *current);
+        supervisor::run_next_task();
+    }
+
+    // Nothing else to run
+    loop {
+        arch::wait_for_interrupt();
+    }
+}
+
+/// Create the init task that will run the App phase
+///
+/// Call after `driver_init` and before starting the scheduler. The init
+/// task runs at the highest priority so applications are set up before
+/// any other task gets the CPU.
+pub fn spawn_init_task() {
+    unsafe {
+        let stack = &mut *core::ptr::addr_of_mut!(INIT_STACK);
+        let sp = initialize_task_stack(init_task, core::ptr::null_mut(), stack);
+        let init_tcb = &mut *core::ptr::addr_of_mut!(INIT_TCB);
+        *init_tcb = Some(TaskControlBlock::new(
+            "init",
+            config::MAX_PRIORITIES - 1,
+            sp,
+            config::INIT_TASK_STACK_SIZE,
+        ));
+        if let Some(ref mut tcb) = init_tcb {
+            tcb.update_list_item_owners();
+            add_task_to_scheduler(tcb);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+// Machine-readable core dump emitted on fatal errors
+//
+// The dump is a binary record, base64-encoded and framed by text markers
+// so it survives a plain serial terminal:
+//
+//   -----BEGIN RTOS COREDUMP-----
+//   <base64, 76 chars per line>
+//   -----END RTOS COREDUMP-----
+//
+// Decoded stream (all integers little-endian):
+//
+//   u32  payload_len
+//   [payload_len bytes] payload
+//   u32  crc32 (IEEE 802.3, reflected, poly 0xEDB88320) of payload
+//
+// Payload:
+//
+//   u32  magic = "RTCD"
+//   u16  version = COREDUMP_VERSION
+//   u16  section count
+//   sections, each: u16 kind, u16 reserved (0), u32 length, [length] data
+//
+// Decoders must skip section kinds they do not know.
+//
+// Section kinds:
+//
+//   1 REASON     u32 line, u32 column, u16 file_len, file bytes,
+//                u16 msg_len, message bytes (UTF-8, truncated)
+//   2 REGISTERS  u64 ra, sp, gp, tp, mstatus, mepc, mcause, mtval
+//   3 TASKS      u16 count, then per task: name[16], u32 priority,
+//                u8 state (TaskState discriminant), u8 is_current,
+//                u16 reserved, u64 tcb address, u64 stack_top
+//   4 STACK      u64 tcb address (0 = panicking context),
+//                u64 start address, then raw memory words
+//   5 MEMMAP     u16 count, then per region: u8 kind (RegionKind
+//                discriminant), u8 flags, u16 reserved, u64 start,
+//                u64 end, name[16]
+
+use crate::kernel::memmap::{self, RegionKind};
+use crate::kernel::scheduler;
+use crate::kernel::task::{TaskControlBlock, MAX_TASK_NAME_LEN};
+use crate::kernel::types::{config, TaskState};
+use core::fmt::Write;
+use core::panic::PanicInfo;
+
+/// Schema version, bump on any layout change
+pub const COREDUMP_VERSION: u16 = 1;
+
+pub const SECTION_REASON: u16 = 1;
+pub const SECTION_REGISTERS: u16 = 2;
+pub const SECTION_TASKS: u16 = 3;
+pub const SECTION_STACK: u16 = 4;
+pub const SECTION_MEMMAP: u16 = 5;
+
+const MAGIC: &[u8; 4] = b"RTCD";
+const MAX_MESSAGE_LEN: usize = 128;
+const BASE64_LINE_LEN: usize = 76;
+
+/// Registers captured at the point of failure
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CrashRegisters {
+    pub ra: usize,
+    pub sp: usize,
+    pub gp: usize,
+    pub tp: usize,
+    pub mstatus: usize,
+    pub mepc: usize,
+    pub mcause: usize,
+    pub mtval: usize,
+}
+
+impl CrashRegisters {
+    /// Snapshot the caller's registers and trap CSRs
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let mut regs = CrashRegisters::default();
+        unsafe {
+            core::arch::asm!(
+                "mv {0}, ra",
+                "mv {1}, sp",
+                "mv {2}, gp",
+                "mv {3}, tp",
+                out(reg) regs.ra,
+                out(reg) regs.sp,
+                out(reg) regs.gp,
+                out(reg) regs.tp,
+            );
+            core::arch::asm!("csrr {}, mstatus", out(reg) regs.mstatus);
+            core::arch::asm!("csrr {}, mepc", out(reg) regs.mepc);
+            core::arch::asm!("csrr {}, mcause", out(reg) regs.mcause);
+            core::arch::asm!("csrr {}, mtval", out(reg) regs.mtval);
+        }
+        regs
+    }
+}
+
+// ============================================================================
+// RECORD BUFFER
+// ============================================================================
+
+/// Bounded byte buffer; writes past the end are dropped and flagged
+struct RecordBuffer {
+    data: [u8; config::COREDUMP_BUFFER_SIZE],
+    len: usize,
+    overflowed: bool,
+}
+
+impl RecordBuffer {
+    const fn new() -> Self {
+        RecordBuffer {
+            data: [0; config::COREDUMP_BUFFER_SIZE],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.len
+    }
+
+    fn put(&mut self, bytes: &[u8]) {
+        if bytes.len() > self.remaining() {
+            self.overflowed = true;
+            return;
+        }
+        self.data[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn put_u8(&mut self, v: u8) {
+        self.put(&[v]);
+    }
+
+    fn put_u16(&mut self, v: u16) {
+        self.put(&v.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, v: u32) {
+        self.put(&v.to_le_bytes());
+    }
+
+    fn put_u64(&mut self, v: u64) {
+        self.put(&v.to_le_bytes());
+    }
+
+    fn patch_u16(&mut self, at: usize, v: u16) {
+        self.data[at..at + 2].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn patch_u32(&mut self, at: usize, v: u32) {
+        self.data[at..at + 4].copy_from_slice(&v.to_le_bytes());
+    }
+
+    /// Start a section, returns the offset of its length field
+    fn begin_section(&mut self, kind: u16) -> usize {
+        self.put_u16(kind);
+        self.put_u16(0);
+        let at = self.len;
+        self.put_u32(0);
+        at
+    }
+
+    /// Close a section by back-patching its length
+    fn end_section(&mut self, length_at: usize) {
+        if length_at + 4 <= self.len {
+            let length = self.len - length_at - 4;
+            self.patch_u32(length_at, length as u32);
+        }
+    }
+}
+
+/// Formats the panic message into a fixed buffer, truncating as needed
+struct MessageBuffer {
+    data: [u8; MAX_MESSAGE_LEN],
+    len: usize,
+}
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = core::cmp::min(s.len(), self.data.len() - self.len);
+        self.data[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+// Reset on every use, so it can live in the uninitialized crash-dump region
+#[link_section = ".coredump"]
+static mut RECORD: RecordBuffer = RecordBuffer::new();
+
+// ============================================================================
+// SECTION WRITERS
+// ============================================================================
+
+fn write_reason(buf: &mut RecordBuffer, info: &PanicInfo) {
+    let at = buf.begin_section(SECTION_REASON);
+
+    let (file, line, column) = match info.location() {
+        Some(loc) => (loc.file(), loc.line(), loc.column()),
+        None => ("", 0, 0),
+    };
+    buf.put_u32(line);
+    buf.put_u32(column);
+    buf.put_u16(file.len() as u16);
+    buf.put(file.as_bytes());
+
+    let mut msg = MessageBuffer { data: [0; MAX_MESSAGE_LEN], len: 0 };
+    let _ = write!(msg, "{}", info.message());
+    buf.put_u16(msg.len as u16);
+    buf.put(&msg.data[..msg.len]);
+
+    buf.end_section(at);
+}
+
+fn write_registers(buf: &mut RecordBuffer, regs: &CrashRegisters) {
+    let at = buf.begin_section(SECTION_REGISTERS);
+    for value in [
+        regs.ra, regs.sp, regs.gp, regs.tp,
+        regs.mstatus, regs.mepc, regs.mcause, regs.mtval,
+    ] {
+        buf.put_u64(value as u64);
+    }
+    buf.end_section(at);
+}
+
+fn state_code(state: TaskState) -> u8 {
+    match state {
+        TaskState::Ready => 0,
+        TaskState::Running => 1,
+        TaskState::Blocked => 2,
+        TaskState::Suspended => 3,
+        TaskState::Deleted => 4,
+    }
+}
+
+fn write_tasks(buf: &mut RecordBuffer) {
+    let current = scheduler::get_current_task();
+    let at = buf.begin_section(SECTION_TASKS);
+    let count_at = buf.len;
+    buf.put_u16(0);
+
+    let mut count: u16 = 0;
+    scheduler::for_each_task(|tcb| unsafe {
+        let task: &TaskControlBlock = &*tcb;
+        buf.put(&task.name[..MAX_TASK_NAME_LEN]);
+        buf.put_u32(task.priority as u32);
+        buf.put_u8(state_code(task.state));
+        buf.put_u8((tcb == current) as u8);
+        buf.put_u16(0);
+        buf.put_u64(tcb as u64);
+        buf.put_u64(task.stack_top as u64);
+        count += 1;
+    });
+
+    if count_at + 2 <= buf.len {
+        buf.patch_u16(count_at, count);
+    }
+    buf.end_section(at);
+}
+
+fn write_stack(buf: &mut RecordBuffer, owner: usize, start: usize, words: usize) {
+    let at = buf.begin_section(SECTION_STACK);
+    buf.put_u64(owner as u64);
+    buf.put_u64(start as u64);
+    for i in 0..words {
+        let word = unsafe { core::ptr::read_volatile((start as *const usize).add(i)) };
+        buf.put_u64(word as u64);
+    }
+    buf.end_section(at);
+}
+
+fn region_code(kind: RegionKind) -> u8 {
+    match kind {
+        RegionKind::Ram => 0,
+        RegionKind::Text => 1,
+        RegionKind::Rodata => 2,
+        RegionKind::Data => 3,
+        RegionKind::Bss => 4,
+        RegionKind::Uninit => 5,
+        RegionKind::Heap => 6,
+        RegionKind::Stack => 7,
+        RegionKind::Mmio => 8,
+    }
+}
+
+fn write_memmap(buf: &mut RecordBuffer) {
+    let regions = memmap::memory_regions();
+    let at = buf.begin_section(SECTION_MEMMAP);
+    buf.put_u16(regions.len() as u16);
+    for region in regions {
+        let mut name = [0u8; memmap::REGION_NAME_LEN];
+        let bytes = region.name_str().as_bytes();
+        name[..bytes.len()].copy_from_slice(bytes);
+
+        buf.put_u8(region_code(region.kind));
+        buf.put_u8(region.flags);
+        buf.put_u16(0);
+        buf.put_u64(region.start as u64);
+        buf.put_u64(region.end as u64);
+        buf.put(&name);
+    }
+    buf.end_section(at);
+}
+
+// ============================================================================
+// ENCODING
+// ============================================================================
+
+/// CRC-32 (IEEE), bitwise - no table to keep the dump path small
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Streaming base64 encoder that wraps lines
+struct Base64Writer {
+    sink: fn(u8),
+    pending: [u8; 3],
+    pending_len: usize,
+    column: usize,
+}
+
+impl Base64Writer {
+    const ALPHABET: &'static [u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    fn new(sink: fn(u8)) -> Self {
+        Base64Writer { sink, pending: [0; 3], pending_len: 0, column: 0 }
+    }
+
+    fn emit(&mut self, c: u8) {
+        (self.sink)(c);
+        self.column += 1;
+        if self.column == BASE64_LINE_LEN {
+            (self.sink)(b'\r');
+            (self.sink)(b'\n');
+            self.column = 0;
+        }
+    }
+
+    fn flush_group(&mut self) {
+        let [a, b, c] = self.pending;
+        let n = self.pending_len;
+        self.emit(Self::ALPHABET[(a >> 2) as usize]);
+        self.emit(Self::ALPHABET[(((a & 0x03) << 4) | (b >> 4)) as usize]);
+        if n > 1 {
+            self.emit(Self::ALPHABET[(((b & 0x0f) << 2) | (c >> 6)) as usize]);
+        } else {
+            self.emit(b'=');
+        }
+        if n > 2 {
+            self.emit(Self::ALPHABET[(c & 0x3f) as usize]);
+        } else {
+            self.emit(b'=');
+        }
+        self.pending = [0; 3];
+        self.pending_len = 0;
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.pending[self.pending_len] = b;
+            self.pending_len += 1;
+            if self.pending_len == 3 {
+                self.flush_group();
+            }
+        }
+    }
+
+    fn finish(mut self) {
+        if self.pending_len > 0 {
+            self.flush_group();
+        }
+        if self.column != 0 {
+            (self.sink)(b'\r');
+            (self.sink)(b'\n');
+        }
+    }
+}
+
+fn put_line(sink: fn(u8), s: &str) {
+    for b in s.bytes() {
+        sink(b);
+    }
+    sink(b'\r');
+    sink(b'\n');
+}
+
+// ============================================================================
+// PUBLIC API
+// ============================================================================
+
+/// Emit a core dump for a panic through `sink` (one byte at a time)
+///
+/// Safe to call from the panic handler: uses only a static buffer and
+/// never allocates. If the buffer fills up, later sections are dropped
+/// but the record stays well-formed.
+pub fn write_core_dump(info: &PanicInfo, regs: &CrashRegisters, sink: fn(u8)) {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(RECORD) };
+    buf.len = 0;
+    buf.overflowed = false;
+
+    buf.put(MAGIC);
+    buf.put_u16(COREDUMP_VERSION);
+    let sections_at = buf.len;
+    buf.put_u16(0);
+
+    // Each section is only kept if it fit entirely
+    let mut sections: u16 = 0;
+    let mut add_section = |buf: &mut RecordBuffer, f: &mut dyn FnMut(&mut RecordBuffer)| {
+        let mark = buf.len;
+        f(buf);
+        if buf.overflowed {
+            buf.len = mark;
+            buf.overflowed = false;
+        } else {
+            sections += 1;
+        }
+    };
+
+    add_section(buf, &mut |b| write_reason(b, info));
+    add_section(buf, &mut |b| write_registers(b, regs));
+    add_section(buf, &mut |b| write_tasks(b));
+    add_section(buf, &mut |b| write_memmap(b));
+    add_section(buf, &mut |b| {
+        write_stack(b, 0, regs.sp, config::COREDUMP_STACK_WINDOW_WORDS)
+    });
+
+    // Saved context frame of every task that isn't running
+    let current = scheduler::get_current_task();
+    scheduler::for_each_task(|tcb| {
+        if tcb != current {
+            let top = unsafe { (*tcb).stack_top as usize };
+            add_section(buf, &mut |b| {
+                write_stack(b, tcb as usize, top, config::COREDUMP_TASK_WINDOW_WORDS)
+            });
+        }
+    });
+
+    buf.patch_u16(sections_at, sections);
+
+    let payload = &buf.data[..buf.len];
+    put_line(sink, "-----BEGIN RTOS COREDUMP-----");
+    let mut encoder = Base64Writer::new(sink);
+    encoder.write(&(payload.len() as u32).to_le_bytes());
+    encoder.write(payload);
+    encoder.write(&crc32(payload).to_le_bytes());
+    encoder.finish();
+    put_line(sink, "-----END RTOS COREDUMP-----");
+}
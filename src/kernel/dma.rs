@@ -0,0 +1,86 @@
+// DMA buffers
+//
+// Devices that access memory themselves (virtio queues, block and network
+// controllers) need more from a buffer than a `Box` promises: a stated
+// alignment (a virtqueue descriptor table wants 16 bytes, some
+// controllers a whole page), one contiguous range, and memory the device
+// can reach at all. A `DmaBuffer` is a zeroed heap block with all three:
+// it comes from heap regions marked REGION_DMA (main RAM, not core-local
+// SRAM/DTIM), aligned as asked. The kernel runs without address
+// translation, so the address the CPU uses is the one to program into
+// the device.
+
+use crate::kernel::heap::{heap_alloc, heap_free};
+use crate::kernel::memmap::REGION_DMA;
+use crate::kernel::types::{ErrorKind, ObjectKind, Result};
+use core::alloc::Layout;
+use core::ptr::{self, NonNull};
+
+/// A contiguous, aligned, device-reachable heap buffer, freed on drop
+///
+/// # Example
+/// ```
+/// // Descriptor table of a 64-entry virtqueue
+/// let descriptors = DmaBuffer::new(16 * 64, 16)?;
+/// mmio::write64(queue_desc_reg, descriptors.addr() as u64);
+/// ```
+pub struct DmaBuffer {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// The buffer is owned memory like a Box<[u8]>
+unsafe impl Send for DmaBuffer {}
+
+impl DmaBuffer {
+    /// Allocate `size` zeroed bytes aligned to `align` (a power of two)
+    ///
+    /// Fails with `InvalidParameter` for a zero size or an alignment that
+    /// is not a power of two, and with `OutOfMemory` if no DMA-capable
+    /// heap region has room.
+    pub fn new(size: usize, align: usize) -> Result<Self> {
+        let layout = match Layout::from_size_align(size, align) {
+            Ok(layout) if size > 0 => layout,
+            _ => return Err(ErrorKind::InvalidParameter.on(ObjectKind::DmaBuffer)),
+        };
+        let ptr = NonNull::new(heap_alloc(layout, REGION_DMA))
+            .ok_or_else(|| ErrorKind::OutOfMemory.on(ObjectKind::DmaBuffer))?;
+        unsafe { ptr::write_bytes(ptr.as_ptr(), 0, size) };
+        Ok(DmaBuffer { ptr, layout })
+    }
+
+    /// Address to give the device
+    pub fn addr(&self) -> usize {
+        self.ptr.as_ptr() as usize
+    }
+
+    pub fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    pub fn align(&self) -> usize {
+        self.layout.align()
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.size()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.size()) }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        unsafe { heap_free(self.ptr.as_ptr(), self.layout) };
+    }
+}
@@ -0,0 +1,130 @@
+// Kernel message ring (dmesg)
+//
+// Every kernel log line is copied into a fixed-size byte ring, whether or
+// not a console exists yet, so messages from early boot (before the UART
+// is set up) can still be read back later. When the ring is full the
+// oldest bytes are overwritten.
+//
+// The ring is a console sink, attached before any other, so `klog!`
+// output reaches it along with the UART and RTT. `dmesg` dumps the
+// retained text; `dmesg_read` lets a reader follow the log incrementally.
+
+use crate::fs::console::{console_write_level, ConsoleSink, LogLevel};
+use crate::kernel::types::config;
+use core::fmt;
+
+static mut DMESG_BUF: [u8; config::DMESG_SIZE] = [0; config::DMESG_SIZE];
+
+/// Total bytes ever written (position of the next byte)
+static mut DMESG_WRITTEN: u64 = 0;
+
+/// Append bytes to the ring
+pub fn dmesg_write(bytes: &[u8]) {
+    crate::critical_section! {
+        let buf = unsafe { &mut *core::ptr::addr_of_mut!(DMESG_BUF) };
+        let written = unsafe { &mut *core::ptr::addr_of_mut!(DMESG_WRITTEN) };
+
+        // Only the tail of an oversized write can be kept
+        let skip = bytes.len().saturating_sub(config::DMESG_SIZE);
+        *written += skip as u64;
+
+        for &b in &bytes[skip..] {
+            buf[(*written % config::DMESG_SIZE as u64) as usize] = b;
+            *written += 1;
+        }
+    }
+}
+
+/// Console sink feeding the ring (keeps every level by default)
+pub static DMESG_SINK: ConsoleSink = ConsoleSink::new("dmesg", dmesg_write, LogLevel::Debug);
+
+/// Oldest retained position and next write position
+pub fn dmesg_bounds() -> (u64, u64) {
+    let written = unsafe { *core::ptr::addr_of!(DMESG_WRITTEN) };
+    (written.saturating_sub(config::DMESG_SIZE as u64), written)
+}
+
+/// Copy log text starting at `*pos` into `out`, advancing `*pos`
+///
+/// Start with `*pos = 0` to read everything retained. If the reader fell
+/// behind and bytes were overwritten, `*pos` jumps forward to the oldest
+/// retained byte. Returns the number of bytes copied (0 = caught up).
+pub fn dmesg_read(pos: &mut u64, out: &mut [u8]) -> usize {
+    crate::critical_section! {
+        let buf = unsafe { &*core::ptr::addr_of!(DMESG_BUF) };
+        let (oldest, written) = dmesg_bounds();
+
+        if *pos < oldest {
+            *pos = oldest;
+        }
+
+        let mut copied = 0;
+        while copied < out.len() && *pos < written {
+            out[copied] = buf[(*pos % config::DMESG_SIZE as u64) as usize];
+            copied += 1;
+            *pos += 1;
+        }
+        copied
+    }
+}
+
+/// Dump the retained log, oldest first (backs the `dmesg` command)
+pub fn dmesg(sink: fn(&[u8])) {
+    let mut pos = 0;
+    let mut chunk = [0u8; 64];
+    loop {
+        let n = dmesg_read(&mut pos, &mut chunk);
+        if n == 0 {
+            break;
+        }
+        sink(&chunk[..n]);
+    }
+}
+
+/// Discard the retained log
+pub fn dmesg_clear() {
+    crate::critical_section! {
+        // Readers restart from the oldest retained byte, which is now the next one
+        let written = unsafe { &mut *core::ptr::addr_of_mut!(DMESG_WRITTEN) };
+        *written += config::DMESG_SIZE as u64;
+    }
+}
+
+// ============================================================================
+// KLOG
+// ============================================================================
+
+/// `fmt::Write` adapter logging everything written at one level
+pub struct KlogWriter(pub LogLevel);
+
+impl fmt::Write for KlogWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        console_write_level(self.0, s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Write a formatted kernel log message. Use `klog!` instead.
+pub fn klog_write(level: LogLevel, args: fmt::Arguments) {
+    let _ = fmt::Write::write_fmt(&mut KlogWriter(level), args);
+}
+
+/// Log a kernel message to the console sinks (dmesg ring included)
+///
+/// The level defaults to Info. Not for interrupt handlers that must not
+/// wait on the console; use `klog_from_isr!` there.
+///
+/// # Example
+/// ```
+/// klog!("virtio-blk: {} sectors\n", capacity);
+/// klog!(LogLevel::Error, "virtio-blk: bad status {:#x}\n", status);
+/// ```
+#[macro_export]
+macro_rules! klog {
+    ($level:path, $($arg:tt)*) => {
+        $crate::kernel::dmesg::klog_write($level, format_args!($($arg)*))
+    };
+    ($($arg:tt)*) => {
+        $crate::kernel::dmesg::klog_write($crate::fs::console::LogLevel::Info, format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,186 @@
+// Event counters with threshold wakeups
+//
+// Producers add to a count, from tasks or interrupt handlers; a consumer
+// blocks until the count reaches the counter's threshold and then takes
+// exactly one threshold's worth, leaving any excess for the next round.
+// This suits "process every N samples" pipelines: the ADC ISR signals once
+// per sample and the filter task wakes once per block.
+//
+// A woken consumer of higher priority than the producer runs at once (or
+// when the signalling interrupt handler returns). Dropping a counter wakes
+// its consumers with `ObjectDeleted`.
+
+use crate::kernel::list::List;
+use crate::kernel::scheduler::{
+    block_current_task, reschedule, wake_all_waiters_deleted, wake_first_waiter,
+};
+use crate::kernel::types::{Result, ErrorKind, ObjectKind};
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// A counting event with a wakeup threshold
+///
+/// # Example
+/// ```
+/// static SAMPLES: EventCounter = EventCounter::new("adc", 64);
+///
+/// // ADC ISR, once per conversion:
+/// SAMPLES.signal();
+///
+/// // Filter task:
+/// loop {
+///     SAMPLES.wait()?;
+///     process_block();
+/// }
+/// ```
+pub struct EventCounter {
+    name: &'static str,
+    count: AtomicU32,
+    threshold: AtomicU32,
+    /// Times the count saturated and events were lost
+    overflows: AtomicU64,
+    /// Consumers blocked until the threshold is reached
+    waiters: UnsafeCell<List>,
+    waiters_ready: AtomicBool,
+}
+
+// The waiter list is only touched inside critical sections
+unsafe impl Sync for EventCounter {}
+
+impl EventCounter {
+    /// Create a counter; a threshold of 0 is treated as 1
+    pub const fn new(name: &'static str, threshold: u32) -> Self {
+        EventCounter {
+            name,
+            count: AtomicU32::new(0),
+            threshold: AtomicU32::new(if threshold == 0 { 1 } else { threshold }),
+            overflows: AtomicU64::new(0),
+            waiters: UnsafeCell::new(List::new()),
+            waiters_ready: AtomicBool::new(false),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Events counted and not yet consumed
+    pub fn count(&self) -> u32 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold.load(Ordering::Relaxed)
+    }
+
+    /// Number of signals lost because the count was saturated
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    /// Change the threshold, waking a consumer if it is already met
+    ///
+    /// Fails with `InvalidParameter` for 0.
+    pub fn set_threshold(&self, threshold: u32) -> Result<()> {
+        if threshold == 0 {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::EventCounter));
+        }
+        crate::critical_section! {
+            self.threshold.store(threshold, Ordering::Relaxed);
+            self.wake_if_due();
+        }
+        reschedule();
+        Ok(())
+    }
+
+    /// Count one event
+    pub fn signal(&self) {
+        self.signal_n(1);
+    }
+
+    /// Count `n` events at once (e.g. a DMA half-buffer)
+    ///
+    /// Safe from interrupt handlers. Wakes one consumer if the threshold
+    /// is reached.
+    pub fn signal_n(&self, n: u32) {
+        crate::critical_section! {
+            let count = self.count.load(Ordering::Relaxed);
+            let (sum, overflowed) = count.overflowing_add(n);
+            if overflowed {
+                self.overflows.fetch_add(1, Ordering::Relaxed);
+            }
+            self.count.store(if overflowed { u32::MAX } else { sum }, Ordering::Relaxed);
+            self.wake_if_due();
+        }
+        reschedule();
+    }
+
+    /// Consume one threshold's worth of events without blocking
+    ///
+    /// Returns false if the count is below the threshold.
+    pub fn try_wait(&self) -> bool {
+        crate::critical_section! {
+            self.try_consume()
+        }
+    }
+
+    /// Block the calling task until the threshold is reached, then consume it
+    ///
+    /// Returns immediately if enough events are already counted. Must be
+    /// called from a task. Fails with `ObjectDeleted` if the counter is
+    /// dropped while waiting.
+    pub fn wait(&self) -> Result<()> {
+        crate::critical_section! {
+            while !self.try_consume() {
+                unsafe { block_current_task(&mut *self.waiters())? };
+            }
+            Ok(())
+        }
+    }
+
+    /// Discard all counted events
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+    }
+
+    /// Must be called inside a critical section
+    fn try_consume(&self) -> bool {
+        let count = self.count.load(Ordering::Relaxed);
+        let threshold = self.threshold();
+        if count < threshold {
+            return false;
+        }
+        self.count.store(count - threshold, Ordering::Relaxed);
+        // A burst may have covered several rounds: pass it on
+        self.wake_if_due();
+        true
+    }
+
+    /// Must be called inside a critical section
+    fn wake_if_due(&self) {
+        if self.count() >= self.threshold() {
+            wake_first_waiter(unsafe { &mut *self.waiters() });
+        }
+    }
+
+    /// Consumers blocked on the counter
+    ///
+    /// # Safety
+    /// Call and dereference only inside a critical section.
+    unsafe fn waiters(&self) -> *mut List {
+        let list = self.waiters.get();
+        if !self.waiters_ready.load(Ordering::Relaxed) {
+            (*list).init();
+            self.waiters_ready.store(true, Ordering::Relaxed);
+        }
+        list
+    }
+}
+
+impl Drop for EventCounter {
+    fn drop(&mut self) {
+        if *self.waiters_ready.get_mut() {
+            wake_all_waiters_deleted(self.waiters.get_mut());
+        }
+    }
+}
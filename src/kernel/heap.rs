@@ -0,0 +1,556 @@
+// Kernel heap
+//
+// The global allocator behind `alloc::boxed::Box`, `Vec` and friends.
+// The heap is made of up to config::MAX_HEAP_REGIONS separate regions:
+// `heap_init` registers the linker's heap region (link::heap(), sized by
+// RTOS_HEAP_SIZE) and every heap region of the memory map, such as
+// on-chip SRAM found in the device tree, and boards may add more with
+// `heap_add_region`.
+//
+// Each region keeps an address-ordered list of free blocks: allocation
+// takes the first block that fits (first fit) and splits off the rest,
+// freeing merges a block with free neighbours so the heap does not
+// crumble into pieces. All list operations run in a critical section, so
+// tasks and interrupt handlers may allocate, but the time taken grows
+// with the number of free blocks; time-critical paths should allocate up
+// front or use static objects.
+//
+// Regions carry memory map attribute flags (REGION_FAST etc.). Small
+// allocations (up to config::HEAP_FAST_ALLOC_MAX) try fast regions first
+// and larger ones the others first, so a small tightly-coupled RAM is
+// not used up by a few big buffers; either falls back to the remaining
+// regions. `heap_alloc` takes the flags a region must have, for memory
+// that has to be fast (or anything else a region is marked with).
+//
+// Block sizes are multiples of HEAP_ALIGN and every free block holds its
+// own list node, so nothing is stored in allocated blocks.
+//
+// With config::POISON_FREED_MEMORY freed blocks are poisoned
+// (kernel::poison) and checked when they are handed out again.
+//
+// When an allocation fails the failure hook runs; if it reports that it
+// released memory the allocation is tried once more. A final failure
+// ends in the alloc error panic.
+
+use crate::fs::LogLevel;
+use crate::kernel::link;
+use crate::kernel::memmap::{memory_regions, RegionKind, REGION_DMA, REGION_FAST, REGION_READ, REGION_WRITE};
+use crate::kernel::poison::{poison_free, poison_reclaim};
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result};
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::ptr;
+
+/// Alignment and size granularity of every block
+pub const HEAP_ALIGN: usize = 16;
+
+/// Free-list node at the start of every free block
+struct FreeBlock {
+    /// Block size in bytes, node included
+    size: usize,
+    /// Next free block at a higher address
+    next: *mut FreeBlock,
+}
+
+const NODE_SIZE: usize = (size_of::<FreeBlock>() + HEAP_ALIGN - 1) & !(HEAP_ALIGN - 1);
+
+/// Called when an allocation fails; returns true to retry it once
+pub type AllocFailureHook = fn(Layout) -> bool;
+
+/// Heap usage and fragmentation figures
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HeapStats {
+    /// Heap regions registered
+    pub regions: usize,
+    /// Bytes managed by the allocator
+    pub size: usize,
+    /// Bytes in allocated blocks
+    pub used: usize,
+    /// Most bytes ever allocated at once
+    pub peak_used: usize,
+    /// Number of free blocks
+    pub free_blocks: usize,
+    /// Size of the largest free block in any region (the largest allocation possible)
+    pub largest_free: usize,
+    /// Blocks currently allocated
+    pub live_allocations: usize,
+    /// Allocations since boot
+    pub allocations: u64,
+    /// Allocations that failed (after the failure hook)
+    pub failures: u64,
+}
+
+impl HeapStats {
+    pub fn free(&self) -> usize {
+        self.size - self.used
+    }
+
+    /// Share of free memory not in the largest free block, in percent
+    ///
+    /// 0 means all free memory is one block; near 100 means it is
+    /// scattered in pieces too small for larger allocations.
+    pub fn fragmentation(&self) -> usize {
+        match self.free() {
+            0 => 0,
+            free => 100 - self.largest_free * 100 / free,
+        }
+    }
+}
+
+/// Usage of one heap region
+#[derive(Copy, Clone, Debug)]
+pub struct HeapRegionStats {
+    pub start: usize,
+    pub end: usize,
+    /// Memory map attribute flags (REGION_FAST etc.)
+    pub flags: u8,
+    /// Bytes in allocated blocks
+    pub used: usize,
+    pub free_blocks: usize,
+    pub largest_free: usize,
+}
+
+impl HeapRegionStats {
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// One contiguous range of heap memory and its free list
+#[derive(Copy, Clone)]
+struct HeapRegion {
+    start: usize,
+    end: usize,
+    flags: u8,
+    head: *mut FreeBlock,
+    used: usize,
+}
+
+impl HeapRegion {
+    const EMPTY: HeapRegion = HeapRegion { start: 0, end: 0, flags: 0, head: ptr::null_mut(), used: 0 };
+
+    fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    fn stats(&self) -> HeapRegionStats {
+        let mut stats = HeapRegionStats {
+            start: self.start,
+            end: self.end,
+            flags: self.flags,
+            used: self.used,
+            free_blocks: 0,
+            largest_free: 0,
+        };
+        let mut block = self.head;
+        while let Some(b) = unsafe { block.as_ref() } {
+            stats.free_blocks += 1;
+            stats.largest_free = stats.largest_free.max(b.size);
+            block = b.next;
+        }
+        stats
+    }
+}
+
+struct HeapState {
+    regions: [HeapRegion; config::MAX_HEAP_REGIONS],
+    count: usize,
+    stats: HeapStats,
+    failure_hook: Option<AllocFailureHook>,
+}
+
+/// First-fit allocator over one or more heap regions
+pub struct KernelHeap {
+    state: UnsafeCell<HeapState>,
+}
+
+// State is only touched inside critical sections
+unsafe impl Sync for KernelHeap {}
+
+#[global_allocator]
+static HEAP: KernelHeap = KernelHeap::new();
+
+fn block_size(layout: Layout) -> usize {
+    (layout.size().max(1) + HEAP_ALIGN - 1) & !(HEAP_ALIGN - 1)
+}
+
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+/// Who frees a block, for poison reports
+fn owner() -> &'static str {
+    match unsafe { get_current_task().as_ref() } {
+        Some(task) => task.name_str(),
+        None => "heap",
+    }
+}
+
+/// Poison the part of a free block after its list node
+unsafe fn poison_block(block: *mut FreeBlock) {
+    poison_free(block as usize + NODE_SIZE, (*block).size - NODE_SIZE, owner());
+}
+
+/// Check and stop tracking the poison of a free block before reuse
+unsafe fn reclaim_block(block: *mut FreeBlock) {
+    poison_reclaim(block as usize + NODE_SIZE, (*block).size - NODE_SIZE);
+}
+
+unsafe fn write_block(addr: usize, size: usize, next: *mut FreeBlock) -> *mut FreeBlock {
+    let block = addr as *mut FreeBlock;
+    block.write(FreeBlock { size, next });
+    block
+}
+
+impl KernelHeap {
+    pub const fn new() -> Self {
+        KernelHeap {
+            state: UnsafeCell::new(HeapState {
+                regions: [HeapRegion::EMPTY; config::MAX_HEAP_REGIONS],
+                count: 0,
+                stats: HeapStats {
+                    regions: 0,
+                    size: 0,
+                    used: 0,
+                    peak_used: 0,
+                    free_blocks: 0,
+                    largest_free: 0,
+                    live_allocations: 0,
+                    allocations: 0,
+                    failures: 0,
+                },
+                failure_hook: None,
+            }),
+        }
+    }
+
+    /// Hand `[start, end)` to the allocator as a new region
+    ///
+    /// Ranges too small to hold a block are ignored.
+    ///
+    /// # Safety
+    /// The range must be unused memory that stays reserved for the heap.
+    unsafe fn add_region(&self, start: usize, end: usize, flags: u8) -> Result<()> {
+        let start = align_up(start, HEAP_ALIGN);
+        let end = end & !(HEAP_ALIGN - 1);
+        if end <= start || end - start < NODE_SIZE {
+            return Ok(());
+        }
+
+        crate::critical_section! {
+            let state = &mut *self.state.get();
+            if state.regions[..state.count].iter().any(|r| start < r.end && r.start < end) {
+                return Err(ErrorKind::InvalidParameter.on(ObjectKind::HeapRegion));
+            }
+            if state.count == state.regions.len() {
+                return Err(ErrorKind::OutOfMemory.on(ObjectKind::HeapRegion));
+            }
+            let head = write_block(start, end - start, ptr::null_mut());
+            state.regions[state.count] = HeapRegion { start, end, flags, head, used: 0 };
+            state.count += 1;
+            state.stats.regions = state.count;
+            state.stats.size += end - start;
+            Ok(())
+        }
+    }
+
+    /// Take `size` bytes aligned to `align` from a region with all of
+    /// `required` flags, preferring fast regions for small sizes
+    unsafe fn take(&self, size: usize, align: usize, required: u8) -> *mut u8 {
+        crate::critical_section! {
+            let state = &mut *self.state.get();
+            let prefer_fast = size <= config::HEAP_FAST_ALLOC_MAX;
+            // First pass: the preferred kind of region; second: the others
+            for preferred in [true, false] {
+                for region in state.regions[..state.count].iter_mut() {
+                    let fast = region.flags & REGION_FAST != 0;
+                    if region.flags & required != required || (fast == prefer_fast) != preferred {
+                        continue;
+                    }
+                    let block = take_from(region, size, align);
+                    if !block.is_null() {
+                        let stats = &mut state.stats;
+                        stats.used += size;
+                        stats.peak_used = stats.peak_used.max(stats.used);
+                        stats.live_allocations += 1;
+                        stats.allocations += 1;
+                        return block;
+                    }
+                }
+            }
+            ptr::null_mut()
+        }
+    }
+
+    /// Return a block to the region it came from
+    unsafe fn give(&self, addr: usize, size: usize) {
+        crate::critical_section! {
+            let state = &mut *self.state.get();
+            if let Some(region) = state.regions[..state.count].iter_mut().find(|r| r.contains(addr)) {
+                give_to(region, addr, size);
+                state.stats.used -= size;
+                state.stats.live_allocations -= 1;
+            }
+        }
+    }
+
+    fn stats(&self) -> HeapStats {
+        crate::critical_section! {
+            let state = unsafe { &*self.state.get() };
+            let mut stats = state.stats;
+            for region in &state.regions[..state.count] {
+                let region = region.stats();
+                stats.free_blocks += region.free_blocks;
+                stats.largest_free = stats.largest_free.max(region.largest_free);
+            }
+            stats
+        }
+    }
+
+    /// Allocate from regions with all of `required` flags, running the
+    /// failure hook once if nothing fits
+    unsafe fn alloc_with(&self, layout: Layout, required: u8) -> *mut u8 {
+        let size = block_size(layout);
+        let align = layout.align().max(HEAP_ALIGN);
+
+        let mut block = self.take(size, align, required);
+        if block.is_null() {
+            let hook = crate::critical_section! { (*self.state.get()).failure_hook };
+            if hook.is_some_and(|hook| hook(layout)) {
+                block = self.take(size, align, required);
+            }
+        }
+        if block.is_null() {
+            crate::critical_section! {
+                (*self.state.get()).stats.failures += 1;
+            }
+        }
+        block
+    }
+}
+
+/// Take `size` bytes aligned to `align` from the first block of `region`
+/// that fits (inside a critical section)
+unsafe fn take_from(region: &mut HeapRegion, size: usize, align: usize) -> *mut u8 {
+    let mut link: *mut *mut FreeBlock = &mut region.head;
+
+    while !(*link).is_null() {
+        let block = *link;
+        let start = block as usize;
+        let end = start + (*block).size;
+
+        // The gap before an aligned start must itself be a free block
+        let mut addr = align_up(start, align);
+        if addr != start && addr - start < NODE_SIZE {
+            addr = align_up(start + NODE_SIZE, align);
+        }
+        if addr + size > end {
+            link = &mut (*block).next;
+            continue;
+        }
+
+        reclaim_block(block);
+        let next = (*block).next;
+        let mut rest = next;
+        if addr + size < end {
+            rest = write_block(addr + size, end - addr - size, next);
+            poison_block(rest);
+        }
+        if addr > start {
+            (*block).size = addr - start;
+            (*block).next = rest;
+            poison_block(block);
+        } else {
+            *link = rest;
+        }
+
+        region.used += size;
+        return addr as *mut u8;
+    }
+    ptr::null_mut()
+}
+
+/// Return a block to the free list of `region`, merging it with adjacent
+/// free blocks (inside a critical section)
+unsafe fn give_to(region: &mut HeapRegion, addr: usize, size: usize) {
+    let mut prev: *mut FreeBlock = ptr::null_mut();
+    let mut next = region.head;
+    while !next.is_null() && (next as usize) < addr {
+        prev = next;
+        next = (*next).next;
+    }
+
+    let mut block_size = size;
+    if !next.is_null() && addr + size == next as usize {
+        reclaim_block(next);
+        block_size += (*next).size;
+        next = (*next).next;
+    }
+
+    let block = if !prev.is_null() && prev as usize + (*prev).size == addr {
+        reclaim_block(prev);
+        (*prev).size += block_size;
+        (*prev).next = next;
+        prev
+    } else {
+        let block = write_block(addr, block_size, next);
+        if prev.is_null() {
+            region.head = block;
+        } else {
+            (*prev).next = block;
+        }
+        block
+    };
+    poison_block(block);
+
+    region.used -= size;
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.alloc_with(layout, 0)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.give(ptr as usize, block_size(layout));
+    }
+}
+
+/// Give the linker heap region and the memory map's heap regions to the
+/// allocator
+///
+/// Called once from `early_init`, after `memory_map_init`; allocations
+/// before it fail. A memory map heap region that overlaps an image
+/// section (the image was linked into it) is skipped.
+pub fn heap_init() {
+    let heap = link::heap();
+    let _ = unsafe { HEAP.add_region(heap.start, heap.end, REGION_READ | REGION_WRITE | REGION_DMA) };
+
+    let regions = memory_regions();
+    for region in regions.iter().filter(|r| r.kind == RegionKind::Heap) {
+        let in_use = regions.iter().any(|r| {
+            !matches!(r.kind, RegionKind::Ram | RegionKind::Heap | RegionKind::Mmio)
+                && r.start < region.end
+                && region.start < r.end
+        });
+        // .heap is in the map too and was added above
+        if in_use || region.contains(heap.start) {
+            continue;
+        }
+        if let Err(e) = unsafe { HEAP.add_region(region.start, region.end, region.flags) } {
+            crate::klog!(LogLevel::Warning, "heap: {} not added: {}\n", region.name_str(), e);
+        }
+    }
+}
+
+/// Add `[start, end)` to the heap as another region
+///
+/// `flags` are memory map attributes (REGION_FAST etc.) that steer which
+/// allocations land in the region. Fails with `InvalidParameter` if the
+/// range overlaps a heap region, or `OutOfMemory` when
+/// config::MAX_HEAP_REGIONS regions are registered.
+///
+/// # Safety
+/// The range must be unused RAM that stays reserved for the heap.
+///
+/// # Example
+/// ```
+/// // 64 KiB of tightly-coupled RAM not described in the device tree
+/// unsafe { heap_add_region(0x0800_0000, 0x0801_0000, REGION_READ | REGION_WRITE | REGION_FAST)? };
+/// ```
+pub unsafe fn heap_add_region(start: usize, end: usize, flags: u8) -> Result<()> {
+    HEAP.add_region(start, end, flags)
+}
+
+/// Allocate from heap regions that have all of `flags`
+///
+/// Like the global allocator (including the failure hook), but only
+/// regions with every bit of `flags` set are considered; returns null if
+/// none has room. Free the block with `heap_free` (or let a `Box`
+/// built with `from_raw` drop it).
+///
+/// # Example
+/// ```
+/// let layout = Layout::new::<[u32; 64]>();
+/// let table = heap_alloc(layout, REGION_FAST) as *mut [u32; 64];
+/// ```
+pub fn heap_alloc(layout: Layout, flags: u8) -> *mut u8 {
+    unsafe { HEAP.alloc_with(layout, flags) }
+}
+
+/// Allocate `size` bytes aligned to `align` (a power of two)
+///
+/// For alignments beyond what a type's `Layout` gives, e.g. page-aligned
+/// tables. Returns null if `align` is not a power of two or nothing has
+/// room. Free the block with `free_aligned` and the same size and
+/// alignment; device buffers are simpler as a `DmaBuffer`.
+///
+/// # Example
+/// ```
+/// let table = alloc_aligned(4096, 4096);
+/// ```
+pub fn alloc_aligned(size: usize, align: usize) -> *mut u8 {
+    match Layout::from_size_align(size, align) {
+        Ok(layout) => heap_alloc(layout, 0),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free a block from `alloc_aligned`
+///
+/// # Safety
+/// `ptr` must come from `alloc_aligned(size, align)` and must not be used
+/// afterwards.
+pub unsafe fn free_aligned(ptr: *mut u8, size: usize, align: usize) {
+    heap_free(ptr, Layout::from_size_align_unchecked(size, align))
+}
+
+/// Free a block from `heap_alloc`
+///
+/// # Safety
+/// `ptr` must come from `heap_alloc` (or the global allocator) with the
+/// same `layout`, and must not be used afterwards.
+pub unsafe fn heap_free(ptr: *mut u8, layout: Layout) {
+    HEAP.dealloc(ptr, layout)
+}
+
+/// Visit the heap regions with their usage, in registration order
+pub fn for_each_heap_region<F: FnMut(HeapRegionStats)>(mut f: F) {
+    let mut regions = [None; config::MAX_HEAP_REGIONS];
+    crate::critical_section! {
+        let state = unsafe { &*HEAP.state.get() };
+        for (slot, region) in regions.iter_mut().zip(&state.regions[..state.count]) {
+            *slot = Some(region.stats());
+        }
+    }
+    for region in regions.into_iter().flatten() {
+        f(region);
+    }
+}
+
+/// Current heap usage and fragmentation, over all regions
+///
+/// Walks the free lists, so it takes longer the more fragmented the heap.
+pub fn heap_stats() -> HeapStats {
+    HEAP.stats()
+}
+
+/// Install the allocation failure hook, returning the previous one
+///
+/// The hook runs outside the heap lock and may free memory (e.g. drop a
+/// cache); returning true retries the allocation once.
+///
+/// # Example
+/// ```
+/// fn shed_cache(_layout: Layout) -> bool {
+///     FRAME_CACHE.clear() > 0
+/// }
+/// set_alloc_failure_hook(Some(shed_cache));
+/// ```
+pub fn set_alloc_failure_hook(hook: Option<AllocFailureHook>) -> Option<AllocFailureHook> {
+    crate::critical_section! {
+        unsafe { core::mem::replace(&mut (*HEAP.state.get()).failure_hook, hook) }
+    }
+}
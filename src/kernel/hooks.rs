@@ -0,0 +1,113 @@
+// Scheduler instrumentation callbacks
+//
+// Lets profilers and trace recorders observe scheduling events without
+// modifying kernel sources. All hooks are optional plain function pointers,
+// called with the affected task's TCB from inside the scheduler.
+
+use crate::kernel::list::List;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Priority};
+use core::ptr;
+
+/// Signature shared by all scheduler hooks
+pub type TaskHook = fn(*mut TaskControlBlock);
+
+/// Table of scheduler instrumentation hooks
+///
+/// Hooks run in scheduler context (possibly with interrupts disabled),
+/// so they must be short and must not call back into the scheduler.
+#[derive(Copy, Clone)]
+pub struct SchedulerHooks {
+    /// Task is about to start running
+    pub task_switched_in: Option<TaskHook>,
+    /// Task is about to stop running
+    pub task_switched_out: Option<TaskHook>,
+    /// Task was registered with the scheduler
+    pub task_created: Option<TaskHook>,
+    /// Task was removed from the scheduler
+    pub task_deleted: Option<TaskHook>,
+    /// Task moved from a non-ready state to Ready
+    pub task_ready: Option<TaskHook>,
+    /// Task left the ready lists to wait (delay, event, suspend)
+    pub task_blocked: Option<TaskHook>,
+}
+
+impl SchedulerHooks {
+    /// Hook table with every hook disabled
+    pub const fn none() -> Self {
+        SchedulerHooks {
+            task_switched_in: None,
+            task_switched_out: None,
+            task_created: None,
+            task_deleted: None,
+            task_ready: None,
+            task_blocked: None,
+        }
+    }
+}
+
+impl Default for SchedulerHooks {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Read-only view of the ready lists handed to a pick-next hook
+pub struct ReadyView<'a> {
+    lists: &'a [List; config::MAX_PRIORITIES],
+    bitmap: u64,
+}
+
+impl<'a> ReadyView<'a> {
+    pub(crate) fn new(lists: &'a [List; config::MAX_PRIORITIES], bitmap: u64) -> Self {
+        ReadyView { lists, bitmap }
+    }
+
+    /// Bit N set = at least one task is ready at priority N
+    pub fn bitmap(&self) -> u64 {
+        self.bitmap
+    }
+
+    /// Number of ready tasks at a priority
+    pub fn count_at(&self, priority: Priority) -> usize {
+        if priority < config::MAX_PRIORITIES {
+            self.lists[priority].len()
+        } else {
+            0
+        }
+    }
+
+    /// First task in round-robin order at a priority (null if none)
+    pub fn head_at(&self, priority: Priority) -> *mut TaskControlBlock {
+        if priority >= config::MAX_PRIORITIES {
+            return ptr::null_mut();
+        }
+        self.lists[priority]
+            .get_head()
+            .map_or(ptr::null_mut(), |node| node.get_owner::<TaskControlBlock>())
+    }
+
+    /// Visit ready tasks at a priority in round-robin order
+    pub fn for_each_at<F: FnMut(*mut TaskControlBlock)>(&self, priority: Priority, mut f: F) {
+        if priority < config::MAX_PRIORITIES {
+            self.lists[priority].for_each(|node| f(node.get_owner::<TaskControlBlock>()));
+        }
+    }
+}
+
+/// Custom "pick next task" policy
+///
+/// Receives the ready lists and the task that was running. Returning null
+/// (or a task that is not ready) falls back to the built-in policy:
+/// highest priority first, round-robin among equals.
+pub type PickNextHook = fn(&ReadyView, *mut TaskControlBlock) -> *mut TaskControlBlock;
+
+/// Invoke an optional hook
+#[inline]
+pub fn call_hook(hook: Option<TaskHook>, tcb: *mut TaskControlBlock) {
+    if let Some(f) = hook {
+        if !tcb.is_null() {
+            f(tcb);
+        }
+    }
+}
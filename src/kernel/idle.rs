@@ -0,0 +1,282 @@
+// Idle task and idle-time housekeeping
+//
+// The kernel's idle task runs at IDLE_PRIORITY and is always ready, so
+// the scheduler has something to pick when every other task is blocked.
+// Each pass it runs housekeeping, calls the application's idle hook and
+// then sleeps.
+//
+// Background chores (stack checks, heap scrubbing, TCB reaping) run from
+// the idle task in small slices between `wfi`s. Each idle pass runs due
+// chores round-robin until a cycle budget is spent, then lets the idle
+// task sleep, so housekeeping never eats all of the idle time.
+//
+// How the idle task sleeps is an `IdlePolicy`, chosen per product and
+// switchable at runtime (e.g. spin while measuring wake latency).
+
+use crate::arch::{read_mcycle, switch_context, wait_for_interrupt};
+use crate::kernel::scheduler::{get_current_task, get_tick_count, select_next_different_task};
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind, TickType};
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+/// A background chore run by the idle task
+///
+/// `run` should do one bounded slice of work and return; it is called
+/// again on a later idle pass. It runs in idle context and must not block.
+///
+/// # Example
+/// ```
+/// fn scrub_heap() { /* check a few blocks */ }
+/// static HEAP_SCRUB: IdleChore = IdleChore::new("heap_scrub", scrub_heap, 100);
+/// register_idle_chore(&HEAP_SCRUB).unwrap();
+/// ```
+pub struct IdleChore {
+    name: &'static str,
+    run: fn(),
+    /// Minimum ticks between runs (0 = every idle pass)
+    interval_ticks: u64,
+    last_run: AtomicU64,
+    runs: AtomicU64,
+    total_cycles: AtomicU64,
+    max_cycles: AtomicU64,
+}
+
+impl IdleChore {
+    pub const fn new(name: &'static str, run: fn(), interval_ticks: u64) -> Self {
+        IdleChore {
+            name,
+            run,
+            interval_ticks,
+            last_run: AtomicU64::new(0),
+            runs: AtomicU64::new(0),
+            total_cycles: AtomicU64::new(0),
+            max_cycles: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Number of times the chore has run
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    /// Total mcycles spent in the chore
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles.load(Ordering::Relaxed)
+    }
+
+    /// Longest single slice in mcycles
+    pub fn max_cycles(&self) -> u64 {
+        self.max_cycles.load(Ordering::Relaxed)
+    }
+
+    fn is_due(&self, now: TickType) -> bool {
+        self.runs() == 0
+            || now.as_u64().wrapping_sub(self.last_run.load(Ordering::Relaxed)) >= self.interval_ticks
+    }
+
+    /// Run one slice and account for it, returning the cycles used
+    fn run_slice(&self, now: TickType) -> u64 {
+        let start = read_mcycle();
+        (self.run)();
+        let cycles = read_mcycle().wrapping_sub(start);
+
+        self.last_run.store(now.as_u64(), Ordering::Relaxed);
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.total_cycles.fetch_add(cycles, Ordering::Relaxed);
+        self.max_cycles.fetch_max(cycles, Ordering::Relaxed);
+        cycles
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut CHORES: [Option<&'static IdleChore>; config::MAX_IDLE_CHORES] =
+    [None; config::MAX_IDLE_CHORES];
+
+/// Chore the next idle pass starts with (round-robin fairness)
+static NEXT_CHORE: AtomicUsize = AtomicUsize::new(0);
+
+/// Register a chore with the idle task
+///
+/// Fails with `InvalidParameter` if the name is already taken and with
+/// `OutOfMemory` if the registry is full.
+pub fn register_idle_chore(chore: &'static IdleChore) -> Result<()> {
+    crate::critical_section! {
+        let chores = unsafe { &mut *core::ptr::addr_of_mut!(CHORES) };
+
+        if chores.iter().flatten().any(|c| c.name == chore.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::IdleChore));
+        }
+
+        match chores.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(chore);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::IdleChore)),
+        }
+    }
+}
+
+/// Run due chores for one idle pass
+///
+/// Called by the idle task before it sleeps. Stops once
+/// config::IDLE_CHORE_BUDGET_CYCLES have been used; the next pass resumes
+/// with the chore after the last one run. Returns the number run.
+pub fn idle_housekeeping() -> usize {
+    let chores = unsafe { &*core::ptr::addr_of!(CHORES) };
+    let now = get_tick_count();
+    let start = NEXT_CHORE.load(Ordering::Relaxed);
+    let mut spent: u64 = 0;
+    let mut ran = 0;
+
+    for i in 0..config::MAX_IDLE_CHORES {
+        let index = (start + i) % config::MAX_IDLE_CHORES;
+        let Some(chore) = chores[index] else { continue };
+        if !chore.is_due(now) {
+            continue;
+        }
+
+        spent += chore.run_slice(now);
+        ran += 1;
+        NEXT_CHORE.store((index + 1) % config::MAX_IDLE_CHORES, Ordering::Relaxed);
+
+        if spent >= config::IDLE_CHORE_BUDGET_CYCLES {
+            break;
+        }
+    }
+    ran
+}
+
+/// Visit every registered chore (e.g. to print accounting)
+pub fn for_each_idle_chore<F: FnMut(&'static IdleChore)>(mut f: F) {
+    let chores = unsafe { &*core::ptr::addr_of!(CHORES) };
+    for chore in chores.iter().flatten() {
+        f(chore);
+    }
+}
+
+// ============================================================================
+// IDLE POLICY
+// ============================================================================
+
+/// How the idle task waits for work
+#[derive(Copy, Clone)]
+pub enum IdlePolicy {
+    /// Busy-spin: lowest wake latency, highest power
+    Spin,
+    /// `wfi` until the next interrupt
+    Wfi,
+    /// Board-supplied deep sleep
+    ///
+    /// The function enters the low-power state and returns after wakeup.
+    /// It must arm its own wakeup source (e.g. the next timer deadline)
+    /// and restore any clocks it gated.
+    DeepSleep(fn()),
+}
+
+static mut IDLE_POLICY: IdlePolicy = IdlePolicy::Wfi;
+
+/// Select the idle policy (takes effect on the next idle pass)
+pub fn set_idle_policy(policy: IdlePolicy) {
+    crate::critical_section! {
+        unsafe { *core::ptr::addr_of_mut!(IDLE_POLICY) = policy };
+    }
+}
+
+/// Currently selected idle policy
+pub fn idle_policy() -> IdlePolicy {
+    crate::critical_section! {
+        unsafe { *core::ptr::addr_of!(IDLE_POLICY) }
+    }
+}
+
+/// Wait for work according to the idle policy
+///
+/// Called by the idle task after `idle_housekeeping`.
+pub fn idle_sleep() {
+    match idle_policy() {
+        IdlePolicy::Spin => core::hint::spin_loop(),
+        IdlePolicy::Wfi => wait_for_interrupt(),
+        IdlePolicy::DeepSleep(enter) => enter(),
+    }
+}
+
+// ============================================================================
+// IDLE TASK
+// ============================================================================
+
+/// Application callback run on every idle pass
+///
+/// Runs in the idle task after housekeeping and before sleeping; it must
+/// not block (the idle task has to stay ready).
+pub type IdleHook = fn();
+
+static mut IDLE_HOOK: Option<IdleHook> = None;
+
+/// The idle task once `spawn_idle_task` has run
+static IDLE_TCB: AtomicPtr<TaskControlBlock> = AtomicPtr::new(ptr::null_mut());
+
+/// Install the idle hook, returning the previous one
+///
+/// # Example
+/// ```
+/// fn pet_watchdog() { /* ... */ }
+/// set_idle_hook(Some(pet_watchdog));
+/// ```
+pub fn set_idle_hook(hook: Option<IdleHook>) -> Option<IdleHook> {
+    crate::critical_section! {
+        unsafe { core::mem::replace(&mut *ptr::addr_of_mut!(IDLE_HOOK), hook) }
+    }
+}
+
+fn idle_hook() -> Option<IdleHook> {
+    unsafe { *ptr::addr_of!(IDLE_HOOK) }
+}
+
+extern "C" fn idle_task(_arg: *mut c_void) -> ! {
+    loop {
+        idle_housekeeping();
+        if let Some(hook) = idle_hook() {
+            hook();
+        }
+        idle_sleep();
+
+        // Share the CPU with application tasks at idle priority
+        crate::critical_section! {
+            let current = get_current_task();
+            let next = select_next_different_task();
+            if !next.is_null() && next != current {
+                unsafe { switch_context(current, next) };
+            }
+        }
+    }
+}
+
+/// Create the idle task
+///
+/// Must run before the first task starts. Fails with `ResourceBusy` if
+/// the idle task already exists.
+pub fn spawn_idle_task() -> Result<&'static mut TaskControlBlock> {
+    let tcb = crate::create_task!(
+        name: "idle",
+        entry: idle_task,
+        priority: config::IDLE_PRIORITY,
+        stack: config::IDLE_TASK_STACK_SIZE,
+    )?;
+    IDLE_TCB.store(tcb, Ordering::Release);
+    Ok(tcb)
+}
+
+/// The idle task (null before `spawn_idle_task`)
+pub fn idle_task_handle() -> *mut TaskControlBlock {
+    IDLE_TCB.load(Ordering::Acquire)
+}
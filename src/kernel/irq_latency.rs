@@ -0,0 +1,328 @@
+// Interrupt latency measurement
+//
+// Every interrupt gets an mtime stamp at trap entry. Two latencies are
+// measured against it, per interrupt line (the CLINT timer, the CLINT
+// software interrupt and each PLIC source):
+//
+//   entry   from the moment the source raised the interrupt to trap
+//           entry. Known for the tick (its programmed mtimecmp deadline)
+//           and the software interrupt (stamped by raise_soft_interrupt).
+//           A driver whose device can tell when it raised its line (a
+//           timestamp register, a deadline it programmed) reports that
+//           with `irq_latency_report_raised` from its handler; other PLIC
+//           sources have no entry latency.
+//   wakeup  from trap entry to the switch into a task the handler made
+//           ready, for interrupts whose reschedule request preempted the
+//           interrupted task.
+//
+// Each latency is kept as min/avg/max plus log2 buckets, in mtime ticks
+// (config::MTIME_FREQ_HZ), so a worst-case latency claim can be checked
+// on the real kernel and workload.
+//
+// Only built with the `irq-latency` feature.
+
+use crate::arch::plic::PLIC_NUM_SOURCES;
+use crate::arch::read_mtime;
+use crate::kernel::types::config;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// An interrupt line latencies are kept for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IrqLine {
+    /// CLINT timer (the tick)
+    Timer,
+    /// CLINT software interrupt (tasklets)
+    Software,
+    /// PLIC source
+    Plic(u32),
+}
+
+impl IrqLine {
+    fn index(self) -> Option<usize> {
+        match self {
+            IrqLine::Timer => Some(0),
+            IrqLine::Software => Some(1),
+            IrqLine::Plic(irq) if (irq as usize) < PLIC_NUM_SOURCES => Some(2 + irq as usize),
+            IrqLine::Plic(_) => None,
+        }
+    }
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => IrqLine::Timer,
+            1 => IrqLine::Software,
+            _ => IrqLine::Plic((index - 2) as u32),
+        }
+    }
+}
+
+impl fmt::Display for IrqLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IrqLine::Timer => f.write_str("timer"),
+            IrqLine::Software => f.write_str("soft"),
+            IrqLine::Plic(irq) => write!(f, "plic{}", irq),
+        }
+    }
+}
+
+const LINES: usize = 2 + PLIC_NUM_SOURCES;
+
+/// Latencies of one kind, in mtime ticks
+///
+/// Bucket 0 counts zero latencies; bucket i counts latencies of
+/// [2^(i-1), 2^i) ticks. The last bucket also takes everything longer.
+pub struct LatencyHistogram {
+    buckets: [AtomicU32; config::IRQ_LATENCY_BUCKETS],
+    samples: AtomicU64,
+    total: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl LatencyHistogram {
+    const fn new() -> Self {
+        LatencyHistogram {
+            buckets: [const { AtomicU32::new(0) }; config::IRQ_LATENCY_BUCKETS],
+            samples: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, ticks: u64) {
+        let bits = (u64::BITS - ticks.leading_zeros()) as usize;
+        self.buckets[bits.min(config::IRQ_LATENCY_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(ticks, Ordering::Relaxed);
+        self.min.fetch_min(ticks, Ordering::Relaxed);
+        self.max.fetch_max(ticks, Ordering::Relaxed);
+    }
+
+    /// Latencies recorded
+    pub fn samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    /// Shortest latency (None before the first sample)
+    pub fn min_ticks(&self) -> Option<u64> {
+        match self.min.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            ticks => Some(ticks),
+        }
+    }
+
+    /// Longest latency seen
+    pub fn max_ticks(&self) -> u64 {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    /// Mean latency (0 before the first sample)
+    pub fn mean_ticks(&self) -> u64 {
+        self.total.load(Ordering::Relaxed) / self.samples().max(1)
+    }
+
+    /// Visit every non-empty bucket as (low, high, count); the last
+    /// bucket's high is u64::MAX
+    pub fn for_each_bucket<F: FnMut(u64, u64, u32)>(&self, mut f: F) {
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            let count = bucket.load(Ordering::Relaxed);
+            if count == 0 {
+                continue;
+            }
+            let low = if index == 0 { 0 } else { 1u64 << (index - 1) };
+            let high = if index + 1 == config::IRQ_LATENCY_BUCKETS { u64::MAX } else { 1u64 << index };
+            f(low, high, count);
+        }
+    }
+
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.samples.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Both latencies of one interrupt line
+pub struct IrqLatency {
+    /// Source raised the interrupt -> trap entry
+    pub entry: LatencyHistogram,
+    /// Trap entry -> switch into the task the handler woke
+    pub wakeup: LatencyHistogram,
+}
+
+impl IrqLatency {
+    const fn new() -> Self {
+        IrqLatency { entry: LatencyHistogram::new(), wakeup: LatencyHistogram::new() }
+    }
+
+    fn has_samples(&self) -> bool {
+        self.entry.samples() > 0 || self.wakeup.samples() > 0
+    }
+}
+
+static LATENCY: [IrqLatency; LINES] = [const { IrqLatency::new() }; LINES];
+
+/// mtime at entry of the latest trap
+static TRAP_ENTRY: AtomicU64 = AtomicU64::new(0);
+
+/// mtime the software interrupt was raised at (0 = not raised)
+static SOFT_RAISED: AtomicU64 = AtomicU64::new(0);
+
+/// Line (index + 1, 0 = none) whose handler asked for the pending
+/// reschedule, and its trap entry stamp
+static WAKE_LINE: AtomicUsize = AtomicUsize::new(0);
+static WAKE_ENTRY: AtomicU64 = AtomicU64::new(0);
+
+// ============================================================================
+// HOOKS (called by the trap path)
+// ============================================================================
+
+/// One handler invocation, from `handler_enter` to `handler_exit`
+pub struct HandlerStamp {
+    line: Option<usize>,
+    entry: u64,
+    resched_before: bool,
+}
+
+/// Stamp trap entry; first thing the trap handler does for an interrupt
+#[inline]
+pub fn trap_entered() {
+    TRAP_ENTRY.store(read_mtime(), Ordering::Relaxed);
+}
+
+/// Note that the software interrupt is being raised
+#[inline]
+pub fn soft_raised() {
+    let _ = SOFT_RAISED.compare_exchange(0, read_mtime(), Ordering::Relaxed, Ordering::Relaxed);
+}
+
+/// Software interrupt raise stamp, cleared for the next one
+pub fn take_soft_raised() -> Option<u64> {
+    match SOFT_RAISED.swap(0, Ordering::Relaxed) {
+        0 => None,
+        raised => Some(raised),
+    }
+}
+
+/// Before running `line`'s handler; `raised` is when the source raised
+/// the interrupt, if known
+pub fn handler_enter(line: IrqLine, raised: Option<u64>) -> HandlerStamp {
+    let entry = TRAP_ENTRY.load(Ordering::Relaxed);
+    let index = line.index();
+    if let (Some(index), Some(raised)) = (index, raised) {
+        LATENCY[index].entry.record(entry.saturating_sub(raised));
+    }
+    HandlerStamp { line: index, entry, resched_before: crate::arch::trap::reschedule_pending() }
+}
+
+/// After the handler ran: remember it if it asked for a reschedule
+pub fn handler_exit(stamp: HandlerStamp) {
+    if let Some(index) = stamp.line {
+        if !stamp.resched_before && crate::arch::trap::reschedule_pending() {
+            WAKE_ENTRY.store(stamp.entry, Ordering::Relaxed);
+            WAKE_LINE.store(index + 1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The trap exit path made its scheduling decision; `switched` is true
+/// right before it switches to the task a handler made ready
+pub fn rescheduled(switched: bool) {
+    let line = WAKE_LINE.swap(0, Ordering::Relaxed);
+    if switched && line > 0 {
+        let entry = WAKE_ENTRY.load(Ordering::Relaxed);
+        LATENCY[line - 1].wakeup.record(read_mtime().saturating_sub(entry));
+    }
+}
+
+// ============================================================================
+// PUBLIC API
+// ============================================================================
+
+/// Report when a PLIC source raised the interrupt being handled
+///
+/// Call from the source's handler with the mtime its device raised the
+/// line at; records the entry latency for `irq`.
+///
+/// # Example
+/// ```
+/// fn capture_irq(irq: u32) {
+///     // The capture unit latched mtime when the edge arrived
+///     irq_latency_report_raised(irq, capture_timestamp());
+///     ...
+/// }
+/// ```
+pub fn irq_latency_report_raised(irq: u32, raised: u64) {
+    if let Some(index) = IrqLine::Plic(irq).index() {
+        LATENCY[index].entry.record(TRAP_ENTRY.load(Ordering::Relaxed).saturating_sub(raised));
+    }
+}
+
+/// Latencies of one line
+pub fn irq_latency(line: IrqLine) -> Option<&'static IrqLatency> {
+    line.index().map(|index| &LATENCY[index])
+}
+
+/// Visit every line with at least one sample
+pub fn for_each_irq_latency<F: FnMut(IrqLine, &'static IrqLatency)>(mut f: F) {
+    for (index, latency) in LATENCY.iter().enumerate() {
+        if latency.has_samples() {
+            f(IrqLine::from_index(index), latency);
+        }
+    }
+}
+
+/// Discard every recorded latency
+pub fn irq_latency_reset() {
+    for latency in &LATENCY {
+        latency.entry.reset();
+        latency.wakeup.reset();
+    }
+}
+
+struct SinkWriter(fn(&[u8]));
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s.as_bytes());
+        Ok(())
+    }
+}
+
+fn ticks_to_ns(ticks: u64) -> u64 {
+    (ticks as u128 * 1_000_000_000 / config::MTIME_FREQ_HZ as u128) as u64
+}
+
+fn write_histogram(out: &mut SinkWriter, what: &str, h: &LatencyHistogram) -> fmt::Result {
+    match h.min_ticks() {
+        Some(min) => write!(
+            out,
+            "  {:<7}n={} min={} avg={} max={} ns",
+            what,
+            h.samples(),
+            ticks_to_ns(min),
+            ticks_to_ns(h.mean_ticks()),
+            ticks_to_ns(h.max_ticks())
+        ),
+        None => write!(out, "  {:<7}-", what),
+    }
+}
+
+/// Print one line per interrupt line with samples:
+/// "<line>:  entry  n=.. min=.. avg=.. max=.. ns  wakeup n=.. ..."
+pub fn irq_latency_dump(sink: fn(&[u8])) {
+    let mut out = SinkWriter(sink);
+    for_each_irq_latency(|line, latency| {
+        let _ = write!(out, "{}:", line);
+        let _ = write_histogram(&mut out, "entry", &latency.entry);
+        let _ = write_histogram(&mut out, "wakeup", &latency.wakeup);
+        let _ = out.write_str("\n");
+    });
+}
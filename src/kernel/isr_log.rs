@@ -0,0 +1,148 @@
+// ISR-safe deferred logging
+//
+// Interrupt handlers must not take the console or wait on UART TX. With
+// `klog_from_isr!` they format into a fixed-size slot of a lock-free ring
+// instead; a logger task drains the ring later with `isr_log_drain`.
+// Records that do not fit are dropped and counted, never waited for.
+
+use crate::kernel::scheduler::get_tick_count;
+use crate::kernel::types::{config, TickType};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// One deferred log record
+#[derive(Copy, Clone)]
+pub struct IsrLogRecord {
+    /// Tick at which the record was written
+    pub tick: TickType,
+    len: usize,
+    text: [u8; config::ISR_LOG_MSG_LEN],
+}
+
+impl IsrLogRecord {
+    pub const fn empty() -> Self {
+        IsrLogRecord {
+            tick: TickType::zero(),
+            len: 0,
+            text: [0; config::ISR_LOG_MSG_LEN],
+        }
+    }
+
+    /// Message text (truncated to ISR_LOG_MSG_LEN bytes)
+    pub fn message(&self) -> &str {
+        // Truncation may split a UTF-8 sequence; keep the valid prefix
+        let bytes = &self.text[..self.len];
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+        }
+    }
+}
+
+impl fmt::Write for IsrLogRecord {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = core::cmp::min(s.len(), self.text.len() - self.len);
+        self.text[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+struct Slot {
+    /// Set by the producer once the record is complete
+    ready: AtomicBool,
+    record: UnsafeCell<IsrLogRecord>,
+}
+
+// Safety: a slot's record is only written by the producer that reserved
+// it and only read by the consumer after `ready` is published
+unsafe impl Sync for Slot {}
+
+static SLOTS: [Slot; config::ISR_LOG_SLOTS] = [const {
+    Slot {
+        ready: AtomicBool::new(false),
+        record: UnsafeCell::new(IsrLogRecord::empty()),
+    }
+}; config::ISR_LOG_SLOTS];
+
+/// Next sequence number to reserve (producers)
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+/// Next sequence number to consume (logger task)
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+/// Records lost because the ring was full
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Append a record; returns false if it was dropped
+///
+/// Never blocks and never disables interrupts, so it is safe from any
+/// context including nested interrupts. Use `klog_from_isr!` instead of
+/// calling this directly.
+pub fn isr_log_write(args: fmt::Arguments) -> bool {
+    // Reserve a slot; multiple producers may race (nested interrupts)
+    let mut head = HEAD.load(Ordering::Relaxed);
+    loop {
+        let tail = TAIL.load(Ordering::Acquire);
+        if head.wrapping_sub(tail) >= config::ISR_LOG_SLOTS {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        match HEAD.compare_exchange_weak(head, head.wrapping_add(1), Ordering::AcqRel, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(current) => head = current,
+        }
+    }
+
+    let slot = &SLOTS[head % config::ISR_LOG_SLOTS];
+    let record = unsafe { &mut *slot.record.get() };
+    record.tick = get_tick_count();
+    record.len = 0;
+    let _ = fmt::write(record, args);
+    slot.ready.store(true, Ordering::Release);
+    true
+}
+
+/// Take the oldest record, if it is complete
+///
+/// Single consumer: only the logger task may call this.
+pub fn isr_log_pop(out: &mut IsrLogRecord) -> bool {
+    let tail = TAIL.load(Ordering::Relaxed);
+    let slot = &SLOTS[tail % config::ISR_LOG_SLOTS];
+    if !slot.ready.load(Ordering::Acquire) {
+        return false;
+    }
+
+    *out = unsafe { *slot.record.get() };
+    slot.ready.store(false, Ordering::Relaxed);
+    TAIL.store(tail.wrapping_add(1), Ordering::Release);
+    true
+}
+
+/// Hand every pending record to `f`, oldest first; returns the count
+pub fn isr_log_drain<F: FnMut(&IsrLogRecord)>(mut f: F) -> usize {
+    let mut record = IsrLogRecord::empty();
+    let mut count = 0;
+    while isr_log_pop(&mut record) {
+        f(&record);
+        count += 1;
+    }
+    count
+}
+
+/// Number of records dropped since boot
+pub fn isr_log_dropped() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Log from interrupt context without blocking
+///
+/// # Example
+/// ```
+/// klog_from_isr!("uart rx overrun, lsr={:#x}", lsr);
+/// ```
+#[macro_export]
+macro_rules! klog_from_isr {
+    ($($arg:tt)*) => {
+        $crate::kernel::isr_log::isr_log_write(format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,86 @@
+// Linker-defined memory sections
+//
+// Bounds of the sections laid out by memory.x (and riscv-rt's link.x),
+// for kernel code that needs to find them at runtime: stack checks, the
+// heap, trace and crash-dump buffers.
+//
+// Place a static in a kernel section with
+//   #[link_section = ".task_stacks"]   (also ".trace_buffer", ".coredump")
+// These sections are NOLOAD: their contents are not initialized at boot.
+
+use core::ptr;
+
+/// Address range [start, end) of a linker section
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Section {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+}
+
+extern "C" {
+    static __stask_stacks: u8;
+    static __etask_stacks: u8;
+    static __sheap: u8;
+    static __eheap: u8;
+    static __strace_buffer: u8;
+    static __etrace_buffer: u8;
+    static __scoredump: u8;
+    static __ecoredump: u8;
+    static __spanic_persist: u8;
+    static __epanic_persist: u8;
+    static __srtos_symtab: u8;
+    static __ertos_symtab: u8;
+}
+
+macro_rules! section {
+    ($start:ident, $end:ident) => {
+        Section {
+            start: ptr::addr_of!($start) as usize,
+            end: ptr::addr_of!($end) as usize,
+        }
+    };
+}
+
+/// Statically allocated task stacks
+pub fn task_stacks() -> Section {
+    section!(__stask_stacks, __etask_stacks)
+}
+
+/// Kernel heap (sized by RTOS_HEAP_SIZE at build time)
+pub fn heap() -> Section {
+    section!(__sheap, __eheap)
+}
+
+/// Trace recorder buffers
+pub fn trace_buffer() -> Section {
+    section!(__strace_buffer, __etrace_buffer)
+}
+
+/// Crash-dump record buffer
+pub fn coredump() -> Section {
+    section!(__scoredump, __ecoredump)
+}
+
+/// Panic message kept across soft resets
+pub fn panic_persist() -> Section {
+    section!(__spanic_persist, __epanic_persist)
+}
+
+/// Embedded symbol table (filled by `cargo xtask symtab`)
+pub fn symtab() -> Section {
+    section!(__srtos_symtab, __ertos_symtab)
+}
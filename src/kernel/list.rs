@@ -64,6 +64,21 @@ impl ListNode {
     }
 }
 
+/// Structural damage found by `List::check_invariants`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ListCorruption {
+    /// `init()` was never called (end marker links are null)
+    Uninitialized,
+    /// End marker lost its u64::MAX sentinel value
+    EndMarker,
+    /// A next/prev pointer is null or not mirrored by its neighbour
+    BrokenLink,
+    /// A linked node names a different list as its container
+    WrongContainer,
+    /// Stored length differs from the number of linked nodes (or a cycle)
+    LengthMismatch,
+}
+
 #[derive(Debug)]
 pub struct List {
     length: usize,
@@ -88,15 +103,23 @@ impl List {
         self.index = &mut self.end_marker as *mut ListNode;
     }
 
+    /// Insert item in ascending value order
+    ///
+    /// Items with equal values keep insertion order (FIFO), so waiters of
+    /// the same priority are woken in the order they arrived.
     pub fn insert_sorted(&mut self, item: &mut ListNode) {
         unsafe {
             let item_value = item.value;
-            let mut iterator = &mut self.end_marker as *mut ListNode;
+            let end_marker = &mut self.end_marker as *mut ListNode;
+            let mut iterator = end_marker;
 
-            loop {
-                iterator = (*iterator).next;
-                if (*iterator).value >= item_value {
-                    break;
+            // The end marker holds u64::MAX; such items simply go last
+            if item_value != u64::MAX {
+                loop {
+                    iterator = (*iterator).next;
+                    if (*iterator).value > item_value {
+                        break;
+                    }
                 }
             }
 
@@ -175,6 +198,75 @@ impl List {
         }
     }
 
+    /// Visit every item in list order (end marker excluded)
+    pub fn for_each<F: FnMut(&ListNode)>(&self, mut f: F) {
+        if self.is_empty() {
+            return;
+        }
+
+        let end_marker = &self.end_marker as *const ListNode;
+        let mut iterator = self.end_marker.next as *const ListNode;
+
+        unsafe {
+            while iterator != end_marker {
+                // Read next first so the callback can't disturb traversal
+                let next = (*iterator).next;
+                f(&*iterator);
+                iterator = next;
+            }
+        }
+    }
+
+    /// Verify the list's structure
+    ///
+    /// Walks the list once checking that the end marker is intact, every
+    /// node's prev/next pointers mirror each other, every node points back
+    /// at this list, and the stored length matches. The walk is bounded by
+    /// the stored length, so a cycle is reported instead of hanging.
+    ///
+    /// # Example
+    /// ```
+    /// debug_assert_eq!(list.check_invariants(), Ok(()));
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), ListCorruption> {
+        let end_marker = &self.end_marker as *const ListNode as *mut ListNode;
+        if self.end_marker.next.is_null() || self.end_marker.prev.is_null() {
+            return Err(ListCorruption::Uninitialized);
+        }
+        if self.end_marker.value != u64::MAX {
+            return Err(ListCorruption::EndMarker);
+        }
+
+        let mut count = 0;
+        let mut prev = end_marker;
+        let mut node = self.end_marker.next;
+
+        unsafe {
+            while node != end_marker {
+                if node.is_null() || (*node).prev != prev {
+                    return Err(ListCorruption::BrokenLink);
+                }
+                if (*node).container as *const List != self as *const List {
+                    return Err(ListCorruption::WrongContainer);
+                }
+                count += 1;
+                if count > self.length {
+                    return Err(ListCorruption::LengthMismatch);
+                }
+                prev = node;
+                node = (*node).next;
+            }
+        }
+
+        if self.end_marker.prev != prev {
+            return Err(ListCorruption::BrokenLink);
+        }
+        if count != self.length {
+            return Err(ListCorruption::LengthMismatch);
+        }
+        Ok(())
+    }
+
     /// Check if list is empty
     pub fn is_empty(&self) -> bool {
         self.length == 0
@@ -185,3 +277,72 @@ impl List {
         self.length
     }
 }
+
+// ============================================================================
+// PROOFS (cargo kani --features verification)
+// ============================================================================
+
+#[cfg(all(kani, feature = "verification"))]
+mod proofs {
+    use super::*;
+
+    const NODES: usize = 3;
+
+    /// Insert a node by either method, with an arbitrary value
+    fn insert_any(list: &mut List, node: &mut ListNode) {
+        node.set_value(kani::any());
+        if kani::any() {
+            list.insert_sorted(node);
+        } else {
+            list.insert_end(node);
+        }
+    }
+
+    /// Any sequence of inserts and removes keeps the list well formed, and
+    /// a removed node is fully unlinked
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn insert_remove_keeps_invariants() {
+        let mut list = List::new();
+        list.init();
+        let mut nodes = [ListNode::new(), ListNode::new(), ListNode::new()];
+
+        for node in nodes.iter_mut() {
+            insert_any(&mut list, node);
+            assert_eq!(list.check_invariants(), Ok(()));
+        }
+        assert_eq!(list.len(), NODES);
+
+        let victim: usize = kani::any();
+        kani::assume(victim < NODES);
+        assert!(list.remove(&mut nodes[victim]));
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert_eq!(list.len(), NODES - 1);
+        assert!(!nodes[victim].is_in_list());
+        assert!(nodes[victim].get_container().is_null());
+
+        // Removing twice is refused instead of corrupting the list
+        assert!(!list.remove(&mut nodes[victim]));
+        assert_eq!(list.len(), NODES - 1);
+    }
+
+    /// insert_sorted keeps values in ascending order
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn insert_sorted_orders_values() {
+        let mut list = List::new();
+        list.init();
+        let mut nodes = [ListNode::new(), ListNode::new(), ListNode::new()];
+
+        for node in nodes.iter_mut() {
+            node.set_value(kani::any());
+            list.insert_sorted(node);
+        }
+
+        let mut last = 0;
+        list.for_each(|node| {
+            assert!(node.get_value() >= last);
+            last = node.get_value();
+        });
+    }
+}
@@ -0,0 +1,219 @@
+// Memory usage summary
+//
+// One report of where RAM went: image sections and kernel buffers from
+// the memory map (linker symbols), the heap, the boot stack and the
+// static task stacks, plus how full each kernel object registry and
+// object pool is.
+// `meminfo` returns the numbers; `meminfo_dump` prints them in the
+// layout of `free`, for a shell command or a boot banner.
+
+use crate::fs::{console, vfs};
+use crate::kernel::heap::{for_each_heap_region, heap_stats, HeapStats};
+use crate::kernel::memmap::{memory_regions, RegionKind, REGION_DMA, REGION_FAST};
+use crate::kernel::types::config;
+use crate::kernel::{boot, idle, link, pool, scheduler, service, shm, system, tunables};
+use core::fmt::{self, Write};
+
+/// Used / capacity of a kernel object registry
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ObjectCount {
+    pub used: usize,
+    pub capacity: usize,
+}
+
+/// Kernel objects in use
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ObjectCounts {
+    /// Tasks known to the scheduler (no fixed limit)
+    pub tasks: usize,
+    pub boot_hooks: ObjectCount,
+    pub idle_chores: ObjectCount,
+    pub console_sinks: ObjectCount,
+    pub services: ObjectCount,
+    pub tunables: ObjectCount,
+    pub cancel_tokens: ObjectCount,
+    pub shm_regions: ObjectCount,
+    pub mounts: ObjectCount,
+    pub open_files: ObjectCount,
+    pub pools: ObjectCount,
+}
+
+/// Memory usage in bytes
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MemInfo {
+    /// Physical RAM (all RAM banks)
+    pub ram: usize,
+    pub text: usize,
+    pub rodata: usize,
+    pub data: usize,
+    pub bss: usize,
+    /// Buffers not zeroed at boot (.uninit, panic persist, crash dump, trace)
+    pub uninit: usize,
+    pub heap: usize,
+    /// Heap allocator usage
+    pub heap_stats: HeapStats,
+    /// Boot and interrupt stack
+    pub boot_stack: usize,
+    /// Static task stacks (.task_stacks)
+    pub task_stacks: usize,
+    pub objects: ObjectCounts,
+}
+
+impl MemInfo {
+    /// Bytes taken by the kernel image and its static buffers
+    pub fn image(&self) -> usize {
+        self.text + self.rodata + self.data + self.bss + self.uninit
+    }
+
+    /// Bytes reserved by the image, heap and stacks
+    pub fn used(&self) -> usize {
+        self.image() + self.heap + self.boot_stack + self.task_stacks
+    }
+
+    /// RAM not claimed by anything above
+    pub fn free(&self) -> usize {
+        self.ram.saturating_sub(self.used())
+    }
+}
+
+fn count_of<F: FnOnce(&mut usize)>(capacity: usize, count: F) -> ObjectCount {
+    let mut used = 0;
+    count(&mut used);
+    ObjectCount { used, capacity }
+}
+
+/// Gather the current memory usage
+pub fn meminfo() -> MemInfo {
+    let mut info = MemInfo::default();
+
+    for region in memory_regions() {
+        let size = region.size();
+        match region.kind {
+            RegionKind::Ram => info.ram += size,
+            RegionKind::Text => info.text += size,
+            RegionKind::Rodata => info.rodata += size,
+            RegionKind::Data => info.data += size,
+            RegionKind::Bss => info.bss += size,
+            RegionKind::Uninit => info.uninit += size,
+            RegionKind::Heap | RegionKind::Stack | RegionKind::Mmio => {}
+        }
+    }
+    info.heap = link::heap().len();
+    info.heap_stats = heap_stats();
+    info.task_stacks = link::task_stacks().len();
+    info.boot_stack = memory_regions()
+        .iter()
+        .filter(|r| r.kind == RegionKind::Stack && !link::task_stacks().contains(r.start))
+        .map(|r| r.size())
+        .sum();
+
+    info.objects = ObjectCounts {
+        tasks: scheduler::get_task_count(),
+        boot_hooks: count_of(config::MAX_BOOT_HOOKS, |n| boot::for_each_boot_hook(|_| *n += 1)),
+        idle_chores: count_of(config::MAX_IDLE_CHORES, |n| idle::for_each_idle_chore(|_| *n += 1)),
+        console_sinks: count_of(config::MAX_CONSOLE_SINKS, |n| console::for_each_console_sink(|_| *n += 1)),
+        services: count_of(config::MAX_SERVICES, |n| service::for_each_service(|_, _| *n += 1)),
+        tunables: count_of(config::MAX_TUNABLES, |n| tunables::for_each_tunable(|_| *n += 1)),
+        cancel_tokens: count_of(config::MAX_CANCEL_TOKENS, |n| system::for_each_cancel_token(|_| *n += 1)),
+        shm_regions: count_of(config::MAX_SHM_REGIONS, |n| shm::for_each_shm_region(|_, _| *n += 1)),
+        mounts: count_of(config::MAX_MOUNTS, |n| vfs::for_each_mount(|_, _| *n += 1)),
+        open_files: ObjectCount { used: vfs::open_file_count(), capacity: config::MAX_OPEN_FILES },
+        pools: count_of(config::MAX_POOLS, |n| pool::for_each_pool(|_| *n += 1)),
+    };
+    info
+}
+
+struct SinkWriter(fn(&[u8]));
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Print the memory summary, `free` style, in bytes
+///
+/// ```text
+///               total        used        free
+/// Mem:      134217728      412672   133805056
+///   text        98304
+///   ...
+/// Heap:          65536        1024       64512
+///   peak 2048  allocs 3  failed 0  free blocks 2  largest 63488  frag 1%
+///   region 0x80012000-0x80022000      1024 used  largest 63488
+///   region 0x08000000-0x08010000 fast       0 used  largest 65536
+/// Objects:  tasks 4  hooks 3/16  chores 2/8  ...
+/// Pools:    timers 3/8 (peak 5) queues 1/4 (peak 1)
+/// ```
+pub fn meminfo_dump(sink: fn(&[u8])) {
+    let info = meminfo();
+    let mut out = SinkWriter(sink);
+
+    let _ = writeln!(out, "          {:>10}  {:>10}  {:>10}", "total", "used", "free");
+    let _ = writeln!(out, "Mem:      {:>10}  {:>10}  {:>10}", info.ram, info.used(), info.free());
+    for (name, size) in [
+        ("text", info.text),
+        ("rodata", info.rodata),
+        ("data", info.data),
+        ("bss", info.bss),
+        ("uninit", info.uninit),
+        ("heap", info.heap),
+        ("stack", info.boot_stack),
+        ("tasks", info.task_stacks),
+    ] {
+        let _ = writeln!(out, "  {:<8}{:>10}", name, size);
+    }
+    let h = &info.heap_stats;
+    let _ = writeln!(out, "Heap:     {:>10}  {:>10}  {:>10}", h.size, h.used, h.free());
+    let _ = writeln!(
+        out,
+        "  peak {}  allocs {}  failed {}  free blocks {}  largest {}  frag {}%",
+        h.peak_used,
+        h.live_allocations,
+        h.failures,
+        h.free_blocks,
+        h.largest_free,
+        h.fragmentation()
+    );
+    if h.regions > 1 {
+        for_each_heap_region(|r| {
+            let _ = writeln!(
+                out,
+                "  region {:#x}-{:#x} {}{}{:>10} used  largest {}",
+                r.start,
+                r.end,
+                if r.flags & REGION_FAST != 0 { "fast " } else { "" },
+                if r.flags & REGION_DMA != 0 { "dma " } else { "" },
+                r.used,
+                r.largest_free
+            );
+        });
+    }
+
+    let o = &info.objects;
+    let _ = write!(out, "Objects:  tasks {}", o.tasks);
+    for (name, count) in [
+        ("hooks", o.boot_hooks),
+        ("chores", o.idle_chores),
+        ("sinks", o.console_sinks),
+        ("services", o.services),
+        ("tunables", o.tunables),
+        ("tokens", o.cancel_tokens),
+        ("shm", o.shm_regions),
+        ("mounts", o.mounts),
+        ("files", o.open_files),
+        ("pools", o.pools),
+    ] {
+        let _ = write!(out, "  {} {}/{}", name, count.used, count.capacity);
+    }
+    let _ = writeln!(out);
+
+    if o.pools.used > 0 {
+        let _ = write!(out, "Pools:   ");
+        pool::for_each_pool(|p| {
+            let _ = write!(out, " {} {}/{} (peak {})", p.name(), p.used(), p.capacity(), p.peak());
+        });
+        let _ = writeln!(out);
+    }
+}
@@ -0,0 +1,340 @@
+// Memory map introspection
+//
+// Builds the kernel's view of memory at boot: image sections from the
+// riscv-rt linker symbols, RAM and MMIO windows from the device tree blob
+// handed over by firmware/QEMU in a1. Without a DTB the QEMU virt layout
+// is assumed.
+//
+// On-chip RAM described in the DTB (`sram` and `dtim` nodes) is not used
+// by the image, so it is listed as a fast heap region; `heap_init` hands
+// every heap region to the allocator.
+
+use crate::kernel::types::config;
+use core::ptr;
+
+/// What a region of the address space is used for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Whole physical RAM bank
+    Ram,
+    Text,
+    Rodata,
+    Data,
+    Bss,
+    /// Not zeroed at boot (.uninit, .panic_persist, .coredump, .trace_buffer)
+    Uninit,
+    /// Heap memory (.heap, on-chip RAM from the DTB)
+    Heap,
+    /// Boot/ISR stack or task stacks
+    Stack,
+    /// Device registers
+    Mmio,
+}
+
+/// Region is readable
+pub const REGION_READ: u8 = 1 << 0;
+/// Region is writable
+pub const REGION_WRITE: u8 = 1 << 1;
+/// Region is executable
+pub const REGION_EXEC: u8 = 1 << 2;
+/// Region is fast on-chip RAM (SRAM, tightly-coupled memory)
+pub const REGION_FAST: u8 = 1 << 3;
+/// Region is reachable by DMA-capable devices (main RAM)
+pub const REGION_DMA: u8 = 1 << 4;
+
+pub const REGION_NAME_LEN: usize = 16;
+
+/// One contiguous address range [start, end)
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryRegion {
+    pub kind: RegionKind,
+    pub start: usize,
+    pub end: usize,
+    /// REGION_READ / REGION_WRITE / REGION_EXEC
+    pub flags: u8,
+    name: [u8; REGION_NAME_LEN],
+}
+
+impl MemoryRegion {
+    const fn empty() -> Self {
+        MemoryRegion {
+            kind: RegionKind::Ram,
+            start: 0,
+            end: 0,
+            flags: 0,
+            name: [0; REGION_NAME_LEN],
+        }
+    }
+
+    fn new(name: &[u8], kind: RegionKind, start: usize, end: usize, flags: u8) -> Self {
+        let mut region = MemoryRegion { kind, start, end, flags, name: [0; REGION_NAME_LEN] };
+        let len = core::cmp::min(name.len(), REGION_NAME_LEN - 1);
+        region.name[..len].copy_from_slice(&name[..len]);
+        region
+    }
+
+    /// Region name (section name or device tree node name)
+    pub fn name_str(&self) -> &str {
+        let len = self.name.iter().position(|&c| c == 0).unwrap_or(REGION_NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("<invalid>")
+    }
+
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+}
+
+// ============================================================================
+// LINKER SYMBOLS (riscv-rt link.x + memory.x)
+// ============================================================================
+
+extern "C" {
+    static _stext: u8;
+    static _stack_start: u8;
+    static __stext: u8;
+    static __etext: u8;
+    static __srodata: u8;
+    static __erodata: u8;
+    static __sdata: u8;
+    static __edata: u8;
+    static __sbss: u8;
+    static __ebss: u8;
+    static __suninit: u8;
+    static __euninit: u8;
+    static __spanic_persist: u8;
+    static __epanic_persist: u8;
+    static __scoredump: u8;
+    static __ecoredump: u8;
+    static __strace_buffer: u8;
+    static __etrace_buffer: u8;
+    static __stask_stacks: u8;
+    static __etask_stacks: u8;
+    static __sheap: u8;
+    static __eheap: u8;
+    static __estack: u8;
+    static __sstack: u8;
+}
+
+macro_rules! sym {
+    ($name:ident) => {
+        ptr::addr_of!($name) as usize
+    };
+}
+
+// ============================================================================
+// GLOBAL MEMORY MAP
+// ============================================================================
+
+struct MemoryMap {
+    regions: [MemoryRegion; config::MAX_MEMORY_REGIONS],
+    count: usize,
+}
+
+impl MemoryMap {
+    fn push(&mut self, region: MemoryRegion) {
+        // Zero-sized regions (e.g. an empty heap) carry no information
+        if region.start < region.end && self.count < self.regions.len() {
+            self.regions[self.count] = region;
+            self.count += 1;
+        }
+    }
+}
+
+static mut MEMORY_MAP: MemoryMap = MemoryMap {
+    regions: [MemoryRegion::empty(); config::MAX_MEMORY_REGIONS],
+    count: 0,
+};
+
+/// QEMU virt devices assumed when no DTB is available
+const QEMU_VIRT_MMIO: &[(&[u8], usize, usize)] = &[
+    (b"test", 0x0010_0000, 0x1000),
+    (b"clint", 0x0200_0000, 0x1_0000),
+    (b"plic", 0x0c00_0000, 0x60_0000),
+    (b"uart", 0x1000_0000, 0x100),
+    (b"virtio_mmio", 0x1000_1000, 0x8000),
+];
+
+/// Populate the memory map
+///
+/// `dtb` is the device tree pointer passed in a1 at boot (0 if unknown).
+/// Must be called once, early in `main`, before anyone queries the map.
+pub fn memory_map_init(dtb: usize) {
+    let map = unsafe { &mut *ptr::addr_of_mut!(MEMORY_MAP) };
+    map.count = 0;
+
+    let rw = REGION_READ | REGION_WRITE;
+    map.push(MemoryRegion::new(b".text", RegionKind::Text, sym!(__stext), sym!(__etext), REGION_READ | REGION_EXEC));
+    map.push(MemoryRegion::new(b".rodata", RegionKind::Rodata, sym!(__srodata), sym!(__erodata), REGION_READ));
+    map.push(MemoryRegion::new(b".data", RegionKind::Data, sym!(__sdata), sym!(__edata), rw));
+    map.push(MemoryRegion::new(b".bss", RegionKind::Bss, sym!(__sbss), sym!(__ebss), rw));
+    map.push(MemoryRegion::new(b".uninit", RegionKind::Uninit, sym!(__suninit), sym!(__euninit), rw));
+    map.push(MemoryRegion::new(b".panic_persist", RegionKind::Uninit, sym!(__spanic_persist), sym!(__epanic_persist), rw));
+    map.push(MemoryRegion::new(b".coredump", RegionKind::Uninit, sym!(__scoredump), sym!(__ecoredump), rw));
+    map.push(MemoryRegion::new(b".trace_buffer", RegionKind::Uninit, sym!(__strace_buffer), sym!(__etrace_buffer), rw));
+    map.push(MemoryRegion::new(b".task_stacks", RegionKind::Stack, sym!(__stask_stacks), sym!(__etask_stacks), rw));
+    map.push(MemoryRegion::new(b".heap", RegionKind::Heap, sym!(__sheap), sym!(__eheap), rw | REGION_DMA));
+    map.push(MemoryRegion::new(b".stack", RegionKind::Stack, sym!(__estack), sym!(__sstack), rw));
+
+    if !unsafe { fdt::parse(dtb, map) } {
+        // No usable DTB: RAM as described by memory.x plus the QEMU virt devices
+        map.push(MemoryRegion::new(b"memory", RegionKind::Ram, sym!(_stext), sym!(_stack_start), rw | REGION_EXEC | REGION_DMA));
+        for &(name, base, size) in QEMU_VIRT_MMIO {
+            map.push(MemoryRegion::new(name, RegionKind::Mmio, base, base + size, rw));
+        }
+    }
+}
+
+/// All known regions, in discovery order
+pub fn memory_regions() -> &'static [MemoryRegion] {
+    let map = unsafe { &*ptr::addr_of!(MEMORY_MAP) };
+    &map.regions[..map.count]
+}
+
+/// Find the most specific region containing `addr`
+///
+/// Image sections are preferred over the RAM bank that contains them.
+pub fn find_region(addr: usize) -> Option<&'static MemoryRegion> {
+    let mut found: Option<&'static MemoryRegion> = None;
+    for region in memory_regions() {
+        if region.contains(addr) && found.is_none_or(|f| region.size() < f.size()) {
+            found = Some(region);
+        }
+    }
+    found
+}
+
+/// Find the first region of a given kind
+pub fn find_region_by_kind(kind: RegionKind) -> Option<&'static MemoryRegion> {
+    memory_regions().iter().find(|r| r.kind == kind)
+}
+
+// ============================================================================
+// MINIMAL FLATTENED DEVICE TREE WALKER
+// ============================================================================
+
+mod fdt {
+    use super::{MemoryMap, MemoryRegion, RegionKind, REGION_DMA, REGION_EXEC, REGION_FAST, REGION_READ, REGION_WRITE};
+
+    const FDT_MAGIC: u32 = 0xd00d_feed;
+    const FDT_BEGIN_NODE: u32 = 1;
+    const FDT_END_NODE: u32 = 2;
+    const FDT_PROP: u32 = 3;
+    const FDT_NOP: u32 = 4;
+    const FDT_END: u32 = 9;
+
+    const MAX_DEPTH: usize = 8;
+
+    unsafe fn be32(addr: usize) -> u32 {
+        u32::from_be(core::ptr::read_volatile(addr as *const u32))
+    }
+
+    unsafe fn cstr_len(addr: usize) -> usize {
+        let mut len = 0;
+        while core::ptr::read_volatile((addr + len) as *const u8) != 0 {
+            len += 1;
+        }
+        len
+    }
+
+    unsafe fn read_cells(addr: usize, cells: u32) -> usize {
+        let mut value: usize = 0;
+        for i in 0..cells as usize {
+            value = (value << 32) | be32(addr + i * 4) as usize;
+        }
+        value
+    }
+
+    /// Walk the structure block, adding RAM, on-chip RAM and MMIO regions to `map`
+    ///
+    /// Only the first (address, size) pair of each `reg` is recorded.
+    /// Returns false if `dtb` does not point at a valid blob.
+    pub unsafe fn parse(dtb: usize, map: &mut MemoryMap) -> bool {
+        if dtb == 0 || dtb & 3 != 0 || be32(dtb) != FDT_MAGIC {
+            return false;
+        }
+
+        let struct_base = dtb + be32(dtb + 8) as usize;
+        let strings_base = dtb + be32(dtb + 12) as usize;
+        let struct_size = be32(dtb + 36) as usize;
+
+        // #address-cells / #size-cells declared by each open node,
+        // index = depth. Spec defaults are 2 and 1.
+        let mut address_cells = [2u32; MAX_DEPTH + 1];
+        let mut size_cells = [1u32; MAX_DEPTH + 1];
+        let mut names: [(usize, usize); MAX_DEPTH + 1] = [(0, 0); MAX_DEPTH + 1];
+        let mut depth: usize = 0;
+        let mut offset = 0;
+
+        while offset < struct_size {
+            let token = be32(struct_base + offset);
+            offset += 4;
+
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = struct_base + offset;
+                    let len = cstr_len(name);
+                    offset += (len + 1 + 3) & !3;
+                    depth += 1;
+                    if depth > MAX_DEPTH {
+                        return true;
+                    }
+                    names[depth] = (name, len);
+                    address_cells[depth] = 2;
+                    size_cells[depth] = 1;
+                }
+                FDT_END_NODE => {
+                    depth = depth.saturating_sub(1);
+                }
+                FDT_PROP => {
+                    let len = be32(struct_base + offset) as usize;
+                    let name_off = be32(struct_base + offset + 4) as usize;
+                    let value = struct_base + offset + 8;
+                    offset += 8 + ((len + 3) & !3);
+
+                    let prop = core::slice::from_raw_parts(
+                        (strings_base + name_off) as *const u8,
+                        cstr_len(strings_base + name_off),
+                    );
+
+                    match prop {
+                        b"#address-cells" => address_cells[depth] = be32(value),
+                        b"#size-cells" => size_cells[depth] = be32(value),
+                        b"reg" if depth >= 2 => {
+                            // reg is encoded with the parent's cell sizes
+                            let ac = address_cells[depth - 1];
+                            let sc = size_cells[depth - 1];
+                            if sc == 0 || ac > 2 || sc > 2 || len < ((ac + sc) * 4) as usize {
+                                continue; // cpus, or not a memory-mapped node
+                            }
+                            let base = read_cells(value, ac);
+                            let size = read_cells(value + ac as usize * 4, sc);
+
+                            let (name_addr, name_len) = names[depth];
+                            let full = core::slice::from_raw_parts(name_addr as *const u8, name_len);
+                            // Drop the unit address: "uart@10000000" -> "uart"
+                            let short = full.split(|&c| c == b'@').next().unwrap_or(full);
+
+                            let rw = REGION_READ | REGION_WRITE;
+                            if short == b"memory" {
+                                map.push(MemoryRegion::new(short, RegionKind::Ram, base, base + size, rw | REGION_EXEC | REGION_DMA));
+                            } else if short == b"sram" || short == b"dtim" {
+                                map.push(MemoryRegion::new(short, RegionKind::Heap, base, base + size, rw | REGION_FAST));
+                            } else {
+                                map.push(MemoryRegion::new(short, RegionKind::Mmio, base, base + size, rw));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                FDT_NOP => {}
+                FDT_END => break,
+                _ => return true, // corrupt blob, keep what we have
+            }
+        }
+        true
+    }
+}
@@ -1,32 +1,152 @@
 // Kernel module - Core RTOS functionality
+pub mod backtrace;
+pub mod boot;
+pub mod coredump;
+pub mod dma;
+pub mod dmesg;
+pub mod event_counter;
+pub mod heap;
+pub mod hooks;
+pub mod idle;
+#[cfg(feature = "irq-latency")]
+pub mod irq_latency;
+pub mod isr_log;
+pub mod link;
 pub mod list;
+pub mod meminfo;
+pub mod memmap;
+pub mod panic_persist;
+pub mod poison;
+pub mod pool;
+pub mod post;
+#[cfg(feature = "profiler")]
+pub mod profiler;
+pub mod queue;
+pub mod rcu;
+pub mod rpc;
+pub mod rtt;
+#[cfg(not(feature = "tiny"))]
+pub mod run_histogram;
 pub mod scheduler;
+pub mod service;
+pub mod shm;
+pub mod stack;
+pub mod supervisor;
+pub mod symtab;
+pub mod system;
 pub mod task;
+pub mod task_table;
+pub mod tasklet;
+#[cfg(feature = "rtos-test")]
+pub mod testing;
+pub mod timer;
+pub mod trace;
+pub mod tunables;
 pub mod types;
+pub mod version;
 
 // Re-export commonly used items
-pub use list::{List, ListNode};
-pub use task::TaskControlBlock;
-pub use types::{config, Priority, Result, RtosError, TaskState, TickType};
+pub use backtrace::{for_each_frame, write_backtrace};
+pub use boot::{register_boot_hook, BootHook, BootPhase};
+pub use dma::DmaBuffer;
+pub use dmesg::{dmesg, dmesg_clear, dmesg_read, dmesg_write};
+pub use event_counter::EventCounter;
+pub use heap::{
+    alloc_aligned, for_each_heap_region, free_aligned, heap_add_region, heap_alloc, heap_free, heap_stats,
+    set_alloc_failure_hook, AllocFailureHook, HeapRegionStats, HeapStats,
+};
+pub use hooks::{PickNextHook, ReadyView, SchedulerHooks, TaskHook};
+pub use idle::{
+    idle_housekeeping, idle_sleep, idle_task_handle, register_idle_chore, set_idle_hook, set_idle_policy,
+    spawn_idle_task, IdleChore, IdleHook, IdlePolicy,
+};
+pub use isr_log::{isr_log_drain, isr_log_dropped, IsrLogRecord};
+pub use list::{List, ListCorruption, ListNode};
+pub use meminfo::{meminfo, meminfo_dump, MemInfo, ObjectCount, ObjectCounts};
+pub use memmap::{find_region, memory_map_init, memory_regions, MemoryRegion, RegionKind};
+pub use pool::{for_each_pool, Handle, Pool, PoolInfo};
+pub use post::{post_failures, run_post, PostResult};
+pub use queue::Queue;
+pub use rcu::{call_rcu, rcu_read_lock, rcu_read_unlock, RcuCell};
+pub use rpc::{ReplyPort, RpcClient, RpcEndpoint, RpcRequest};
+pub use rtt::{rtt_init, rtt_read, rtt_set_mode, rtt_write, rtt_write_str};
+#[cfg(not(feature = "tiny"))]
+pub use run_histogram::RunHistogram;
+pub use service::{service_ready, wait_for_service, wait_for_services};
+pub use shm::{
+    for_each_shm_region, shm_attach, shm_detach_task, shm_register, shm_unregister, SharedRegion, ShmAccess,
+    ShmMapping,
+};
+pub use stack::{set_stack_overflow_hook, stack_high_water_mark, stack_overflowed, StackOverflowHook};
+pub use supervisor::{supervise, RestartPolicy, TaskFailure};
+pub use system::{is_shutting_down, register_cancel_token, shutdown, unregister_cancel_token, CancelToken};
+pub use task::{
+    TaskControlBlock, TASK_FLAG_CRITICAL, TASK_FLAG_NO_PREEMPT, TASK_FLAG_PRIVILEGED,
+};
+pub use task_table::{register_task_table, TaskSpec};
+pub use tasklet::{tasklet_schedule, Tasklet};
+pub use timer::{for_each_timer, timer_list, Timer, TimerCallback, TimerMode};
+pub use tunables::{register_tunable, tunable_get, tunable_set, Tunable};
+pub use types::{config, ErrorKind, ObjectKind, Priority, Result, RtosError, SchedPolicy, TaskState, TickCounter, TickDiff, TickRaw, TickRounding, TickType};
+pub use version::{has_feature, version, version_str, Feature, KernelVersion};
 
 pub use scheduler::{
     add_task_to_scheduler,
+    block_current_task,
+    block_current_task_until,
+    clear_task_flags,
+    debug_check_ready_lists,
     debug_count_non_empty_ready_lists,
     debug_get_ready_list_address,
     debug_is_ready_list_empty,
+    delayed_task_count,
+    delete_task,
+    detach_task,
+    for_each_task,
     get_current_task,
+    get_ready_bitmap,
     get_task_count,
+    get_task_flags,
+    get_task_policy,
     get_tick_count,
     get_top_ready_priority,
     increment_tick,
     init_scheduler,
     is_scheduler_running,
     is_scheduler_suspended,
+    make_task_ready,
+    place_on_event_list,
+    preempt_check,
     remove_task_from_scheduler,
+    reschedule,
     resume_scheduler,
+    resume_task,
+    resume_task_from_isr,
     select_next_task,
     select_next_different_task,
     set_current_task,
+    set_current_task_name,
+    set_pick_next_hook,
+    set_scheduler_hooks,
+    set_task_flags,
+    set_task_name,
+    set_task_policy,
+    set_task_priority,
+    start_scheduler,
     suspend_scheduler,
+    suspend_task,
+    suspended_task_count,
+    task_delay,
+    task_delay_until,
+    wake_all_waiters_deleted,
+    wake_first_waiter,
+    with_raised_priority,
     yield_current_task,
+    yield_to,
+};
+
+#[cfg(not(feature = "tiny"))]
+pub use scheduler::{
+    reset_sched_stats, reset_task_runtime_stats, sched_stats, set_task_run_histogram, task_run_histogram,
+    task_runtime_stats, SchedStats, TaskRuntime,
 };
@@ -0,0 +1,112 @@
+// Panic message persistence across soft resets
+//
+// The panic handler formats its message into a linker-reserved RAM region
+// (`.panic_persist` in memory.x) that the runtime never zeroes. On the next
+// boot the application can retrieve the message for logging or upload.
+
+use crate::kernel::link;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use core::ptr;
+
+/// Marks a valid record ("PNIC")
+const PERSIST_MAGIC: u32 = 0x43494e50;
+
+/// Record header, followed by the message bytes
+#[repr(C)]
+struct PersistHeader {
+    magic: u32,
+    len: u32,
+}
+
+fn region() -> (*mut u8, usize) {
+    let section = link::panic_persist();
+    (section.start as *mut u8, section.len())
+}
+
+/// Capacity available for the message text
+fn capacity() -> usize {
+    region().1.saturating_sub(core::mem::size_of::<PersistHeader>())
+}
+
+fn header() -> *mut PersistHeader {
+    region().0 as *mut PersistHeader
+}
+
+fn message_ptr() -> *mut u8 {
+    unsafe { region().0.add(core::mem::size_of::<PersistHeader>()) }
+}
+
+/// Writes into the persist region, truncating at capacity
+struct PersistWriter {
+    len: usize,
+}
+
+impl Write for PersistWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = core::cmp::min(s.len(), capacity() - self.len);
+        unsafe {
+            ptr::copy_nonoverlapping(s.as_ptr(), message_ptr().add(self.len), n);
+        }
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Store the panic location and message in the persist region
+///
+/// Called from the panic handler. Any previous record is overwritten.
+pub fn persist_panic(info: &PanicInfo) {
+    if capacity() == 0 {
+        return;
+    }
+
+    let mut writer = PersistWriter { len: 0 };
+    if let Some(location) = info.location() {
+        let _ = write!(writer, "{}:{}:{}: ", location.file(), location.line(), location.column());
+    }
+    let _ = write!(writer, "{}", info.message());
+
+    unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!((*header()).len), writer.len as u32);
+        // Magic last: a reset mid-write leaves no half-valid record
+        ptr::write_volatile(ptr::addr_of_mut!((*header()).magic), PERSIST_MAGIC);
+    }
+}
+
+/// Retrieve the message left by a panic before the last reset, and clear it
+///
+/// Returns None after a cold boot (RAM contents are random, so the magic and
+/// length are both validated) or if the record was already taken.
+pub fn take_panic_message() -> Option<&'static str> {
+    if capacity() == 0 {
+        return None;
+    }
+
+    unsafe {
+        let magic = ptr::read_volatile(ptr::addr_of!((*header()).magic));
+        let len = ptr::read_volatile(ptr::addr_of!((*header()).len)) as usize;
+
+        if magic != PERSIST_MAGIC || len > capacity() {
+            return None;
+        }
+
+        ptr::write_volatile(ptr::addr_of_mut!((*header()).magic), 0);
+
+        let bytes = core::slice::from_raw_parts(message_ptr(), len);
+        // Truncation may have split a UTF-8 sequence - keep the valid prefix
+        match core::str::from_utf8(bytes) {
+            Ok(s) => Some(s),
+            Err(e) => Some(core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()])),
+        }
+    }
+}
+
+/// Discard any stored panic message
+pub fn clear_panic_message() {
+    if capacity() != 0 {
+        unsafe {
+            ptr::write_volatile(ptr::addr_of_mut!((*header()).magic), 0);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Poisoning of freed memory
+//
+// Freed heap blocks and the stacks of deleted tasks are filled with
+// config::POISON_BYTE and remembered along with their previous owner.
+// Poisoned memory should never change, so a modified byte means someone
+// still holds a pointer into it (use after free). It is checked:
+//
+//   - when the memory is handed out again (`poison_reclaim`)
+//   - in the background by an idle chore that scrubs one region per pass
+//
+// A finding is logged with the previous owner's name, counted, and passed
+// to an optional hook.
+//
+// Enabled by config::POISON_FREED_MEMORY (debug builds by default).
+
+use crate::fs::LogLevel;
+use crate::kernel::idle::{register_idle_chore, IdleChore};
+use crate::kernel::task::MAX_TASK_NAME_LEN;
+use crate::kernel::types::{config, Result};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A modified byte found in poisoned memory
+#[derive(Copy, Clone, Debug)]
+pub struct PoisonViolation {
+    /// Start of the freed region
+    pub start: usize,
+    /// Offset of the first modified byte
+    pub offset: usize,
+    /// Value found there
+    pub value: u8,
+    owner: [u8; MAX_TASK_NAME_LEN],
+}
+
+impl PoisonViolation {
+    /// Who owned the region before it was freed
+    pub fn owner(&self) -> &str {
+        let len = self.owner.iter().position(|&c| c == 0).unwrap_or(MAX_TASK_NAME_LEN);
+        core::str::from_utf8(&self.owner[..len]).unwrap_or("<invalid>")
+    }
+}
+
+/// Called for every violation found
+pub type PoisonHook = fn(&PoisonViolation);
+
+#[derive(Copy, Clone)]
+struct PoisonedRegion {
+    start: usize,
+    len: usize,
+    owner: [u8; MAX_TASK_NAME_LEN],
+}
+
+static mut REGIONS: [Option<PoisonedRegion>; config::MAX_POISONED_REGIONS] =
+    [None; config::MAX_POISONED_REGIONS];
+
+static mut HOOK: Option<PoisonHook> = None;
+
+/// Violations found since boot
+static VIOLATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Region the scrubber checks next
+static NEXT_SCRUB: AtomicUsize = AtomicUsize::new(0);
+
+/// Install a hook called for each violation (e.g. to trigger a crash dump)
+pub fn set_poison_hook(hook: Option<PoisonHook>) {
+    crate::critical_section! {
+        unsafe { HOOK = hook };
+    }
+}
+
+/// Number of violations found since boot
+pub fn poison_violations() -> usize {
+    VIOLATIONS.load(Ordering::Relaxed)
+}
+
+/// Fill freed memory with the poison pattern and track it
+///
+/// `owner` names the previous user (task or allocation site) for
+/// reports. If the tracking table is full the memory is still poisoned
+/// but only checked on reclaim.
+///
+/// # Safety
+/// `[start, start + len)` must be memory nobody is allowed to use
+pub unsafe fn poison_free(start: usize, len: usize, owner: &str) {
+    if !config::POISON_FREED_MEMORY || len == 0 {
+        return;
+    }
+
+    core::ptr::write_bytes(start as *mut u8, config::POISON_BYTE, len);
+
+    let mut name = [0u8; MAX_TASK_NAME_LEN];
+    let n = core::cmp::min(owner.len(), MAX_TASK_NAME_LEN - 1);
+    name[..n].copy_from_slice(&owner.as_bytes()[..n]);
+
+    crate::critical_section! {
+        let regions = &mut *core::ptr::addr_of_mut!(REGIONS);
+        if let Some(slot) = regions.iter_mut().find(|r| r.is_none()) {
+            *slot = Some(PoisonedRegion { start, len, owner: name });
+        }
+    }
+}
+
+/// Verify poisoned memory before reusing it and stop tracking it
+///
+/// Returns the first violation found in `[start, start + len)`, if any.
+///
+/// # Safety
+/// `[start, start + len)` must be readable
+pub unsafe fn poison_reclaim(start: usize, len: usize) -> Option<PoisonViolation> {
+    if !config::POISON_FREED_MEMORY {
+        return None;
+    }
+
+    let region = crate::critical_section! {
+        let regions = &mut *core::ptr::addr_of_mut!(REGIONS);
+        regions
+            .iter_mut()
+            .find(|r| matches!(r, Some(p) if p.start < start + len && start < p.start + p.len))
+            .and_then(|r| r.take())
+    };
+
+    let region = region.unwrap_or(PoisonedRegion { start, len, owner: [0; MAX_TASK_NAME_LEN] });
+    check_region(&region)
+}
+
+/// Scan a region and report the first modified byte
+unsafe fn check_region(region: &PoisonedRegion) -> Option<PoisonViolation> {
+    let bytes = core::slice::from_raw_parts(region.start as *const u8, region.len);
+    let offset = bytes.iter().position(|&b| b != config::POISON_BYTE)?;
+
+    let violation = PoisonViolation {
+        start: region.start,
+        offset,
+        value: bytes[offset],
+        owner: region.owner,
+    };
+
+    VIOLATIONS.fetch_add(1, Ordering::Relaxed);
+    crate::klog!(
+        LogLevel::Error,
+        "use after free: {:#x}+{:#x} = {:#04x} (freed by '{}')\n",
+        violation.start,
+        violation.offset,
+        violation.value,
+        violation.owner()
+    );
+    if let Some(hook) = *core::ptr::addr_of!(HOOK) {
+        hook(&violation);
+    }
+    Some(violation)
+}
+
+/// Check one tracked region (background scrubbing)
+///
+/// A region found modified is dropped from tracking so it is reported
+/// once. Returns true if a violation was found.
+pub fn poison_scrub() -> bool {
+    let index = NEXT_SCRUB.load(Ordering::Relaxed) % config::MAX_POISONED_REGIONS;
+    NEXT_SCRUB.store(index + 1, Ordering::Relaxed);
+
+    let region = unsafe { (*core::ptr::addr_of!(REGIONS))[index] };
+    let Some(region) = region else {
+        return false;
+    };
+
+    if unsafe { check_region(&region) }.is_some() {
+        crate::critical_section! {
+            unsafe { (*core::ptr::addr_of_mut!(REGIONS))[index] = None };
+        }
+        return true;
+    }
+    false
+}
+
+fn poison_chore() {
+    poison_scrub();
+}
+
+static POISON_CHORE: IdleChore = IdleChore::new("poison_scrub", poison_chore, config::POISON_SCRUB_INTERVAL_TICKS);
+
+/// Start background scrubbing from the idle task (no-op when disabled)
+pub fn poison_init() -> Result<()> {
+    if !config::POISON_FREED_MEMORY {
+        return Ok(());
+    }
+    register_idle_chore(&POISON_CHORE)
+}
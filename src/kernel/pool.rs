@@ -0,0 +1,310 @@
+// Static object pools
+//
+// A Pool<T, N> is a static slab of N slots for kernel objects such as
+// queues and timers, so a system without a heap can still create and
+// destroy them at runtime. `create` moves an object into a free slot and
+// returns a `&'static` reference, which is what timers and other
+// registered objects need; `destroy` drops it and frees the slot. When
+// every slot is taken `create` fails with `OutOfMemory` on the pool's
+// object kind.
+//
+// A pool joins the pool registry on its first `create`, so `meminfo_dump`
+// can show how full each pool is and how close it has come to running
+// out.
+//
+// Destroying an object drops it, and blocking objects wake their waiters
+// with `ObjectDeleted` when dropped, so no task stays blocked on a freed
+// slot. Plain references are not tracked, though: a task still holding
+// one after `destroy` has a use-after-free, which is why `destroy` is
+// unsafe. Stop a timer before destroying it.
+//
+// Code that must survive the object going away holds a `Handle` instead:
+// every slot has a generation that `destroy` bumps, and `get` resolves a
+// handle only while its generation matches, failing with `ObjectDeleted`
+// once the object is gone, even if the slot has been reused since.
+
+use crate::kernel::scheduler::reschedule;
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result};
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// Occupancy of a pool, shared by all pool types for the registry
+pub struct PoolInfo {
+    name: &'static str,
+    kind: ObjectKind,
+    capacity: usize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    failures: AtomicU64,
+    registered: AtomicBool,
+}
+
+impl PoolInfo {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Kind of object the pool holds
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Slots in use
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Most slots ever in use at once
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// `create` calls that found the pool full
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// A generation-checked reference to a pooled object
+///
+/// Copyable and safe to keep after the object is destroyed: resolving it
+/// with `Pool::get` then fails instead of reaching a reused slot.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _object: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> core::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Handle({}#{})", self.index, self.generation)
+    }
+}
+
+/// A fixed pool of `N` objects of type `T`
+///
+/// # Example
+/// ```
+/// static TIMER_POOL: Pool<Timer, 8> = Pool::new("timers", ObjectKind::Timer);
+///
+/// let blink = TIMER_POOL.create(Timer::new(TickType::from_ms(250), toggle_led))?;
+/// blink.start()?;
+/// // ...
+/// blink.stop();
+/// unsafe { TIMER_POOL.destroy(blink)? };
+///
+/// // With a handle, users notice the queue going away:
+/// let requests = QUEUE_POOL.create_handle(Queue::new())?;
+/// QUEUE_POOL.get(requests)?.send(req, None)?;
+/// unsafe { QUEUE_POOL.destroy_handle(requests)? };
+/// assert!(matches!(QUEUE_POOL.get(requests), Err(e) if e == ErrorKind::ObjectDeleted));
+/// ```
+pub struct Pool<T, const N: usize> {
+    info: PoolInfo,
+    slots: UnsafeCell<[MaybeUninit<T>; N]>,
+    in_use: UnsafeCell<[bool; N]>,
+    /// Bumped each time a slot's object is destroyed
+    generations: UnsafeCell<[u32; N]>,
+}
+
+// Slot state is only touched inside critical sections; pooled objects are
+// used like statics, so they need what a static needs
+unsafe impl<T: Sync, const N: usize> Sync for Pool<T, N> {}
+
+impl<T, const N: usize> Pool<T, N> {
+    /// An empty pool; `kind` tags the errors and the meminfo line
+    pub const fn new(name: &'static str, kind: ObjectKind) -> Self {
+        Pool {
+            info: PoolInfo {
+                name,
+                kind,
+                capacity: N,
+                used: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                failures: AtomicU64::new(0),
+                registered: AtomicBool::new(false),
+            },
+            slots: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+            in_use: UnsafeCell::new([false; N]),
+            generations: UnsafeCell::new([0; N]),
+        }
+    }
+
+    pub fn info(&self) -> &PoolInfo {
+        &self.info
+    }
+
+    /// Move `value` into a free slot
+    ///
+    /// Fails with `OutOfMemory` (on the pool's object kind) when all `N`
+    /// slots are in use, or on `Pool` when config::MAX_POOLS pools are
+    /// already registered.
+    pub fn create(&'static self, value: T) -> Result<&'static T> {
+        let index = self.insert(value)?;
+        Ok(unsafe { (*self.slots.get())[index].assume_init_ref() })
+    }
+
+    /// Move `value` into a free slot and return a handle to it
+    ///
+    /// Fails like `create`.
+    pub fn create_handle(&'static self, value: T) -> Result<Handle<T>> {
+        let index = self.insert(value)?;
+        let generation = crate::critical_section! {
+            unsafe { (*self.generations.get())[index] }
+        };
+        Ok(Handle { index: index as u32, generation, _object: PhantomData })
+    }
+
+    /// Resolve a handle to its object
+    ///
+    /// Fails with `ObjectDeleted` if the object has been destroyed, and
+    /// with `InvalidParameter` if the handle is not from this pool.
+    pub fn get(&'static self, handle: Handle<T>) -> Result<&'static T> {
+        let index = self.check(handle)?;
+        Ok(unsafe { (*self.slots.get())[index].assume_init_ref() })
+    }
+
+    /// Drop a pooled object and free its slot
+    ///
+    /// Blocked waiters of the object are woken with `ObjectDeleted` (by
+    /// its `Drop`). Fails with `InvalidParameter` if `object` did not come
+    /// from this pool or was already destroyed.
+    ///
+    /// # Safety
+    /// No task, interrupt handler or registry may use `object` afterwards.
+    pub unsafe fn destroy(&'static self, object: &'static T) -> Result<()> {
+        let offset = (object as *const T as usize).wrapping_sub(self.slots.get() as usize);
+        let size = core::mem::size_of::<T>().max(1);
+        let index = offset / size;
+
+        crate::critical_section! {
+            if !offset.is_multiple_of(size) || index >= N || !(*self.in_use.get())[index] {
+                return Err(ErrorKind::InvalidParameter.on(self.info.kind));
+            }
+            self.remove(index);
+        }
+        reschedule();
+        Ok(())
+    }
+
+    /// Drop the object behind a handle and free its slot
+    ///
+    /// Invalidates every copy of the handle. Fails with `ObjectDeleted` if
+    /// the object was already destroyed.
+    ///
+    /// # Safety
+    /// No task may still use a reference obtained from `get` (waiting on
+    /// the object is fine: the wait fails with `ObjectDeleted`).
+    pub unsafe fn destroy_handle(&'static self, handle: Handle<T>) -> Result<()> {
+        crate::critical_section! {
+            let index = self.check(handle)?;
+            self.remove(index);
+        }
+        reschedule();
+        Ok(())
+    }
+
+    /// Claim a slot for `value`, returning its index
+    fn insert(&'static self, value: T) -> Result<usize> {
+        crate::critical_section! {
+            register(&self.info)?;
+
+            let in_use = unsafe { &mut *self.in_use.get() };
+            let Some(index) = in_use.iter().position(|used| !used) else {
+                self.info.failures.fetch_add(1, Ordering::Relaxed);
+                return Err(ErrorKind::OutOfMemory.on(self.info.kind));
+            };
+            in_use[index] = true;
+
+            let used = self.info.used.fetch_add(1, Ordering::Relaxed) + 1;
+            self.info.peak.fetch_max(used, Ordering::Relaxed);
+
+            unsafe { (*self.slots.get())[index].write(value) };
+            Ok(index)
+        }
+    }
+
+    /// Index of a handle's slot if its object is still alive
+    fn check(&self, handle: Handle<T>) -> Result<usize> {
+        let index = handle.index as usize;
+        if index >= N {
+            return Err(ErrorKind::InvalidParameter.on(self.info.kind));
+        }
+        crate::critical_section! {
+            let alive = unsafe {
+                (*self.in_use.get())[index] && (*self.generations.get())[index] == handle.generation
+            };
+            if !alive {
+                return Err(ErrorKind::ObjectDeleted.on(self.info.kind).with_id(handle.index));
+            }
+            Ok(index)
+        }
+    }
+
+    /// Drop the object in a used slot (inside a critical section)
+    unsafe fn remove(&self, index: usize) {
+        let generation = &mut (*self.generations.get())[index];
+        *generation = generation.wrapping_add(1);
+        ptr::drop_in_place((*self.slots.get())[index].as_mut_ptr());
+        (*self.in_use.get())[index] = false;
+        self.info.used.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Free slots
+    pub fn available(&self) -> usize {
+        N - self.info.used()
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut POOLS: [Option<&'static PoolInfo>; config::MAX_POOLS] = [None; config::MAX_POOLS];
+
+/// Add a pool the first time it is used (inside a critical section)
+fn register(info: &'static PoolInfo) -> Result<()> {
+    if info.registered.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let pools = unsafe { &mut *ptr::addr_of_mut!(POOLS) };
+    match pools.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(info);
+            info.registered.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(ErrorKind::OutOfMemory.on(ObjectKind::Pool)),
+    }
+}
+
+/// Visit every pool that has been used
+pub fn for_each_pool<F: FnMut(&'static PoolInfo)>(mut f: F) {
+    let pools = unsafe { &*ptr::addr_of!(POOLS) };
+    for pool in pools.iter().flatten() {
+        f(pool);
+    }
+}
@@ -0,0 +1,217 @@
+// Power-on self test
+//
+// With config::POWER_ON_SELF_TEST the init task checks the machine
+// before the App hooks start application work:
+//
+//   - context switch: a probe task must run and hand the CPU back
+//   - tick: the tick interrupt must arrive at roughly the configured rate
+//   - heap: every word of the heap region must hold both bit patterns
+//     (tested in place, the original contents are restored)
+//   - drivers: the self-test function of every boot hook that has run
+//     (`BootHook::with_self_test`)
+//
+// Each result is logged. If anything fails and config::POST_FAILURE_FATAL
+// is set, boot stops with a panic, so the failure is also kept by
+// panic_persist and reported as the reason on the next boot.
+
+use crate::arch::{self, spin_until};
+use crate::fs::LogLevel;
+use crate::kernel::boot;
+use crate::kernel::link;
+use crate::kernel::scheduler::{delete_task, get_current_task, get_tick_count, yield_to};
+use crate::kernel::supervisor;
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result, RtosError};
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Outcome of one self test
+#[derive(Copy, Clone, Debug)]
+pub struct PostResult {
+    pub name: &'static str,
+    pub result: Result<()>,
+}
+
+/// Built-in tests plus one per boot hook
+const MAX_POST_RESULTS: usize = 3 + config::MAX_BOOT_HOOKS;
+
+static mut RESULTS: [Option<PostResult>; MAX_POST_RESULTS] = [None; MAX_POST_RESULTS];
+
+/// Set by the probe task when it runs
+static PROBE_RAN: AtomicBool = AtomicBool::new(false);
+
+/// Words of heap tested per critical section
+const HEAP_CHUNK_WORDS: usize = 256;
+
+fn record(name: &'static str, result: Result<()>) {
+    match result {
+        Ok(()) => crate::klog!(LogLevel::Info, "post: {} ok\n", name),
+        Err(e) => crate::klog!(LogLevel::Error, "post: {} FAILED: {}\n", name, e),
+    }
+
+    crate::critical_section! {
+        let results = unsafe { &mut *ptr::addr_of_mut!(RESULTS) };
+        if let Some(slot) = results.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(PostResult { name, result });
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+extern "C" fn switch_probe(_arg: *mut c_void) -> ! {
+    PROBE_RAN.store(true, Ordering::Release);
+
+    unsafe {
+        let current = get_current_task();
+        arch::disable_interrupts();
+        delete_task(&mut *current);
+        supervisor::run_next_task();
+    }
+
+    loop {
+        arch::wait_for_interrupt();
+    }
+}
+
+/// Switch to a probe task at the init task's priority and back
+fn test_context_switch() -> Result<()> {
+    let current = get_current_task();
+    if current.is_null() {
+        return Err(ErrorKind::TaskNotFound.on(ObjectKind::Task));
+    }
+
+    let probe = crate::create_task!(
+        name: "post",
+        entry: switch_probe,
+        priority: config::MAX_PRIORITIES - 1,
+        stack: config::MIN_STACK_SIZE
+    )?;
+
+    crate::critical_section! {
+        let next = yield_to(probe);
+        if next != current {
+            unsafe { arch::switch_context(current, next) };
+        }
+    }
+
+    if PROBE_RAN.load(Ordering::Acquire) {
+        Ok(())
+    } else {
+        Err(ErrorKind::ResourceBusy.on(ObjectKind::Task))
+    }
+}
+
+/// Wait for the next tick and return the mtime it was seen at
+fn next_tick_edge() -> Result<u64> {
+    let start = get_tick_count();
+    spin_until("post-tick", config::SPIN_TIMEOUT_CYCLES, || get_tick_count() != start)?;
+    Ok(arch::read_mtime())
+}
+
+/// The tick must advance, one tick per MTIME_TICKS_PER_TICK (within half
+/// a period either way)
+fn test_tick() -> Result<()> {
+    let first = next_tick_edge()?;
+    let second = next_tick_edge()?;
+
+    let period = second - first;
+    let expected = config::MTIME_TICKS_PER_TICK;
+    if period < expected / 2 || period > expected + expected / 2 {
+        return Err(RtosError::new(ErrorKind::Timeout).with_id(period as u32));
+    }
+    Ok(())
+}
+
+/// Check that every heap word holds both bit patterns, restoring it after
+///
+/// Runs in chunks with interrupts disabled, so nothing sees the patterns.
+/// The error ID is the offset of the first failing word.
+fn test_heap() -> Result<()> {
+    const PATTERN: usize = usize::MAX / 3; // 0101...01
+
+    let heap = link::heap();
+    let base = heap.start as *mut usize;
+    let words = heap.len() / core::mem::size_of::<usize>();
+
+    for chunk in (0..words).step_by(HEAP_CHUNK_WORDS) {
+        let end = (chunk + HEAP_CHUNK_WORDS).min(words);
+        crate::critical_section! {
+            for index in chunk..end {
+                unsafe {
+                    let word = base.add(index);
+                    let saved = ptr::read_volatile(word);
+                    ptr::write_volatile(word, PATTERN);
+                    let first = ptr::read_volatile(word);
+                    ptr::write_volatile(word, !PATTERN);
+                    let second = ptr::read_volatile(word);
+                    ptr::write_volatile(word, saved);
+
+                    if first != PATTERN || second != !PATTERN {
+                        let offset = (index * core::mem::size_of::<usize>()) as u32;
+                        return Err(RtosError::new(ErrorKind::InvalidParameter).with_id(offset));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// RUNNER
+// ============================================================================
+
+/// Run every self test and log the results
+///
+/// Called by the init task before the App hooks when
+/// config::POWER_ON_SELF_TEST is set. Returns the number of failures, or
+/// panics on the first failure if config::POST_FAILURE_FATAL is set.
+pub fn run_post() -> usize {
+    crate::klog!(LogLevel::Info, "post: starting\n");
+
+    record("context-switch", test_context_switch());
+    record("tick", test_tick());
+    record("heap", test_heap());
+    boot::for_each_completed_boot_hook(|hook| {
+        if let Some(self_test) = hook.self_test() {
+            record(hook.name(), self_test());
+        }
+    });
+
+    let failures = post_failures();
+    let mut first = None;
+    for_each_post_result(|r| {
+        if let (None, Err(e)) = (first, r.result) {
+            first = Some((r.name, e));
+        }
+    });
+
+    match first {
+        None => crate::klog!(LogLevel::Info, "post: passed\n"),
+        Some((name, e)) if config::POST_FAILURE_FATAL => panic!("POST failed: {}: {}", name, e),
+        Some(_) => crate::klog!(LogLevel::Warning, "post: {} test(s) failed, continuing\n", failures),
+    }
+    failures
+}
+
+/// Visit the result of every test that has run, in order
+pub fn for_each_post_result<F: FnMut(&PostResult)>(mut f: F) {
+    let results = unsafe { &*ptr::addr_of!(RESULTS) };
+    for result in results.iter().flatten() {
+        f(result);
+    }
+}
+
+/// Number of self tests that failed
+pub fn post_failures() -> usize {
+    let mut failures = 0;
+    for_each_post_result(|r| {
+        if r.result.is_err() {
+            failures += 1;
+        }
+    });
+    failures
+}
@@ -0,0 +1,117 @@
+// Sampling profiler
+//
+// The timer interrupt records the interrupted PC and the running task on
+// every tick. Samples are aggregated in a fixed hash table and exported
+// as folded stacks ("task;0xPC count"), which flamegraph.pl and
+// speedscope read directly. Resolve addresses on the host with
+// `addr2line -e <elf>`.
+//
+// Only built with the `profiler` feature.
+
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::config;
+use core::fmt::{self, Write};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[derive(Copy, Clone)]
+struct Sample {
+    pc: usize,
+    task: *mut TaskControlBlock,
+    count: u32,
+}
+
+impl Sample {
+    const fn empty() -> Self {
+        Sample { pc: 0, task: ptr::null_mut(), count: 0 }
+    }
+}
+
+static mut SAMPLES: [Sample; config::PROFILER_SLOTS] = [Sample::empty(); config::PROFILER_SLOTS];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Samples lost because the table was full
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Start collecting samples
+pub fn profiler_start() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Stop collecting samples (the table is kept for export)
+pub fn profiler_stop() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+/// Discard all collected samples
+pub fn profiler_reset() {
+    crate::critical_section! {
+        unsafe {
+            (*ptr::addr_of_mut!(SAMPLES)).fill(Sample::empty());
+        }
+        DROPPED.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Record one sample
+///
+/// Called from the timer interrupt with the interrupted PC (mepc).
+pub fn profiler_sample(pc: usize) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let task = get_current_task();
+    let samples = unsafe { &mut *ptr::addr_of_mut!(SAMPLES) };
+
+    // Open addressing keyed on (pc, task); instructions are 2-byte aligned
+    let mut index = ((pc >> 1) ^ (task as usize >> 4)) % config::PROFILER_SLOTS;
+    for _ in 0..config::PROFILER_SLOTS {
+        let slot = &mut samples[index];
+        if slot.count == 0 {
+            *slot = Sample { pc, task, count: 1 };
+            return;
+        }
+        if slot.pc == pc && slot.task == task {
+            slot.count = slot.count.saturating_add(1);
+            return;
+        }
+        index = (index + 1) % config::PROFILER_SLOTS;
+    }
+    DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Number of samples lost to a full table
+pub fn profiler_dropped() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+struct SinkWriter(fn(&[u8]));
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Export samples in folded-stack format, one "task;0xPC count" per line
+///
+/// Sampling is paused for the duration of the export.
+pub fn profiler_dump(sink: fn(&[u8])) {
+    let was_enabled = ENABLED.swap(false, Ordering::AcqRel);
+    let mut out = SinkWriter(sink);
+
+    let samples = unsafe { &*ptr::addr_of!(SAMPLES) };
+    for sample in samples.iter().filter(|s| s.count > 0) {
+        let name = if sample.task.is_null() {
+            "<kernel>"
+        } else {
+            unsafe { (*sample.task).name_str() }
+        };
+        let _ = writeln!(out, "{};{:#x} {}", name, sample.pc, sample.count);
+    }
+
+    ENABLED.store(was_enabled, Ordering::Release);
+}
@@ -0,0 +1,275 @@
+// Fixed-size message queues
+//
+// A Queue<T, N> holds up to N messages of type T in FIFO order, stored
+// inline so a queue can be a plain static. Senders block while it is
+// full and receivers while it is empty, each on their own waiter list
+// (highest priority first), optionally with a timeout. Interrupt
+// handlers use the non-blocking `send_from_isr` / `receive_from_isr`.
+//
+// Handing a message to a blocked task of higher priority switches to it
+// right away (or when the interrupt handler returns).
+//
+// Dropping a queue wakes every blocked sender and receiver with
+// `ObjectDeleted`; the messages it still holds are dropped with it.
+
+use crate::kernel::list::List;
+use crate::kernel::scheduler::{
+    block_current_task, block_current_task_until, get_tick_count, reschedule,
+    wake_all_waiters_deleted, wake_first_waiter,
+};
+use crate::kernel::types::{Result, ErrorKind, ObjectKind, TickType};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// A bounded FIFO of `N` messages
+///
+/// # Example
+/// ```
+/// static READINGS: Queue<u16, 8> = Queue::new();
+///
+/// // ADC ISR:
+/// READINGS.send_from_isr(sample).ok();
+///
+/// // Consumer task, giving up after 100ms:
+/// match READINGS.receive(Some(TickType::from_ms(100))) {
+///     Ok(sample) => filter(sample),
+///     Err(e) if e == ErrorKind::Timeout => report_stall(),
+///     Err(e) => return Err(e),
+/// }
+/// ```
+pub struct Queue<T, const N: usize> {
+    slots: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// Index of the oldest message
+    head: UnsafeCell<usize>,
+    /// Messages held
+    count: UnsafeCell<usize>,
+    /// Tasks waiting for space
+    senders: UnsafeCell<List>,
+    /// Tasks waiting for a message
+    receivers: UnsafeCell<List>,
+    lists_ready: AtomicBool,
+    /// Messages rejected because the queue was full (from ISRs or timeouts)
+    dropped: AtomicU64,
+}
+
+// All state is only touched inside critical sections
+unsafe impl<T: Send, const N: usize> Sync for Queue<T, N> {}
+
+impl<T, const N: usize> Queue<T, N> {
+    const CAPACITY_CHECK: () = assert!(N > 0, "a queue needs at least one slot");
+
+    pub const fn new() -> Self {
+        let () = Self::CAPACITY_CHECK;
+        Queue {
+            slots: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+            head: UnsafeCell::new(0),
+            count: UnsafeCell::new(0),
+            senders: UnsafeCell::new(List::new()),
+            receivers: UnsafeCell::new(List::new()),
+            lists_ready: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Messages currently queued
+    pub fn len(&self) -> usize {
+        crate::critical_section! {
+            unsafe { *self.count.get() }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Messages that could not be queued because it was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // ========================================================================
+    // SENDING
+    // ========================================================================
+
+    /// Append a message, waiting for space if the queue is full
+    ///
+    /// `timeout` is `None` to wait forever or the longest time to wait;
+    /// `Some(TickType::zero())` never blocks. Fails with `Timeout` if no
+    /// space became available in time (the message is dropped), with
+    /// `ObjectDeleted` if the queue was dropped while waiting and with
+    /// `InvalidParameter` if it would have to block outside task context.
+    pub fn send(&self, item: T, timeout: Option<TickType>) -> Result<()> {
+        let deadline = timeout.map(|t| get_tick_count().wrapping_add(t));
+        crate::critical_section! {
+            loop {
+                if self.has_space() {
+                    self.push(item);
+                    break;
+                }
+                let waited = match deadline {
+                    Some(deadline) if get_tick_count().has_reached(deadline) => {
+                        Err(ErrorKind::Timeout.on(ObjectKind::Queue))
+                    }
+                    Some(deadline) => unsafe {
+                        block_current_task_until(&mut *self.senders(), deadline)
+                    },
+                    None => unsafe { block_current_task(&mut *self.senders()) },
+                };
+                if let Err(e) = waited {
+                    // A deleted queue must not be touched again
+                    if e != ErrorKind::ObjectDeleted {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return Err(e.with_object(ObjectKind::Queue));
+                }
+            }
+        }
+        reschedule();
+        Ok(())
+    }
+
+    /// Append a message without blocking
+    pub fn try_send(&self, item: T) -> Result<()> {
+        self.send(item, Some(TickType::zero()))
+    }
+
+    /// Append a message from an interrupt handler
+    ///
+    /// Never blocks; fails with `Timeout` if the queue is full. A receiver
+    /// woken by it runs when the handler returns if it has priority.
+    pub fn send_from_isr(&self, item: T) -> Result<()> {
+        self.try_send(item)
+    }
+
+    // ========================================================================
+    // RECEIVING
+    // ========================================================================
+
+    /// Take the oldest message, waiting for one if the queue is empty
+    ///
+    /// `timeout` works as for `send`.
+    pub fn receive(&self, timeout: Option<TickType>) -> Result<T> {
+        let deadline = timeout.map(|t| get_tick_count().wrapping_add(t));
+        let item = crate::critical_section! {
+            loop {
+                if let Some(item) = self.pop() {
+                    break item;
+                }
+                let waited = match deadline {
+                    Some(deadline) if get_tick_count().has_reached(deadline) => {
+                        return Err(ErrorKind::Timeout.on(ObjectKind::Queue))
+                    }
+                    Some(deadline) => unsafe {
+                        block_current_task_until(&mut *self.receivers(), deadline)
+                    },
+                    None => unsafe { block_current_task(&mut *self.receivers()) },
+                };
+                waited.map_err(|e| e.with_object(ObjectKind::Queue))?;
+            }
+        };
+        reschedule();
+        Ok(item)
+    }
+
+    /// Take the oldest message without blocking
+    pub fn try_receive(&self) -> Result<T> {
+        self.receive(Some(TickType::zero()))
+    }
+
+    /// Take the oldest message from an interrupt handler (never blocks)
+    pub fn receive_from_isr(&self) -> Result<T> {
+        self.try_receive()
+    }
+
+    // ========================================================================
+    // INTERNALS (all called inside a critical section)
+    // ========================================================================
+
+    fn has_space(&self) -> bool {
+        unsafe { *self.count.get() < N }
+    }
+
+    /// Append a message (there must be space) and wake a receiver
+    fn push(&self, item: T) {
+        unsafe {
+            let count = &mut *self.count.get();
+            let tail = (*self.head.get() + *count) % N;
+            (*self.slots.get())[tail].write(item);
+            *count += 1;
+        }
+        wake_first_waiter(unsafe { &mut *self.receivers() });
+    }
+
+    /// Remove the oldest message and wake a sender
+    fn pop(&self) -> Option<T> {
+        unsafe {
+            let count = &mut *self.count.get();
+            if *count == 0 {
+                return None;
+            }
+            let head = &mut *self.head.get();
+            let item = (*self.slots.get())[*head].assume_init_read();
+            *head = (*head + 1) % N;
+            *count -= 1;
+            wake_first_waiter(&mut *self.senders());
+            Some(item)
+        }
+    }
+
+    fn init_lists(&self) {
+        if !self.lists_ready.load(Ordering::Relaxed) {
+            unsafe {
+                (*self.senders.get()).init();
+                (*self.receivers.get()).init();
+            }
+            self.lists_ready.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Tasks waiting for space
+    ///
+    /// # Safety
+    /// Dereference only inside a critical section.
+    unsafe fn senders(&self) -> *mut List {
+        self.init_lists();
+        self.senders.get()
+    }
+
+    /// Tasks waiting for a message
+    ///
+    /// # Safety
+    /// Dereference only inside a critical section.
+    unsafe fn receivers(&self) -> *mut List {
+        self.init_lists();
+        self.receivers.get()
+    }
+}
+
+impl<T, const N: usize> Drop for Queue<T, N> {
+    fn drop(&mut self) {
+        if *self.lists_ready.get_mut() {
+            wake_all_waiters_deleted(self.senders.get_mut());
+            wake_all_waiters_deleted(self.receivers.get_mut());
+        }
+        let (head, count) = (*self.head.get_mut(), *self.count.get_mut());
+        let slots = self.slots.get_mut();
+        for i in 0..count {
+            unsafe { slots[(head + i) % N].assume_init_drop() };
+        }
+    }
+}
+
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,203 @@
+// Read-copy-update for read-mostly kernel data
+//
+// Readers of an `RcuCell` never take a lock: they bump a counter in their
+// own TCB, load the published pointer and use it. Writers build a new
+// copy, publish it, and hand the old copy to `call_rcu`; the reclaim
+// callback runs from the idle task once no task can still be reading it.
+//
+// Grace periods are tracked with a global sequence number. Publishing
+// starts a new one. A task that entered its read-side section before the
+// publish may hold the old copy; once every such task has left its
+// section the grace period is over.
+//
+// Rules for readers:
+//   - Read-side sections must not block (they may be preempted)
+//   - References must not escape the section
+//   - ISRs may read without rcu_read_lock(): a task cannot reclaim
+//     anything while an ISR is running on top of it
+
+use crate::kernel::idle::{register_idle_chore, IdleChore};
+use crate::kernel::scheduler::{for_each_task, get_current_task};
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, AtomicPtr, AtomicU64, Ordering};
+
+/// Grace-period sequence, incremented on every publish
+static GP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Enter a read-side section (nests)
+pub fn rcu_read_lock() {
+    let current = get_current_task();
+    if current.is_null() {
+        return;
+    }
+    let task = unsafe { &mut *current };
+    if task.rcu_nesting == 0 {
+        task.rcu_lock_seq = GP_SEQ.load(Ordering::Relaxed);
+    }
+    task.rcu_nesting += 1;
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Leave a read-side section
+pub fn rcu_read_unlock() {
+    let current = get_current_task();
+    if current.is_null() {
+        return;
+    }
+    compiler_fence(Ordering::SeqCst);
+    let task = unsafe { &mut *current };
+    debug_assert!(task.rcu_nesting > 0, "rcu_read_unlock without rcu_read_lock");
+    task.rcu_nesting = task.rcu_nesting.saturating_sub(1);
+}
+
+/// Check whether grace period `seq` has ended
+///
+/// True once no task is still in a read-side section it entered before
+/// the publish that started `seq`.
+pub fn rcu_grace_period_done(seq: u64) -> bool {
+    let mut done = true;
+    crate::critical_section! {
+        for_each_task(|tcb| {
+            let task = unsafe { &*tcb };
+            if task.rcu_nesting > 0 && task.rcu_lock_seq < seq {
+                done = false;
+            }
+        });
+    }
+    done
+}
+
+// ============================================================================
+// RCU CELL
+// ============================================================================
+
+/// A pointer to read-mostly data that is replaced wholesale by writers
+///
+/// # Example
+/// ```
+/// static DEFAULT: Settings = Settings::new();
+/// static SETTINGS: RcuCell<Settings> = RcuCell::new(&DEFAULT);
+///
+/// let rate = SETTINGS.read(|s| s.sample_rate);
+///
+/// let old = SETTINGS.publish(new_settings);
+/// call_rcu(old as *const Settings as *const (), free_settings).unwrap();
+/// ```
+pub struct RcuCell<T: 'static> {
+    ptr: AtomicPtr<T>,
+    _marker: PhantomData<&'static T>,
+}
+
+impl<T: 'static> RcuCell<T> {
+    pub const fn new(initial: &'static T) -> Self {
+        RcuCell {
+            ptr: AtomicPtr::new(initial as *const T as *mut T),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Run `f` on the current copy inside a read-side section
+    pub fn read<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        rcu_read_lock();
+        let result = f(unsafe { self.get() });
+        rcu_read_unlock();
+        result
+    }
+
+    /// Load the current copy
+    ///
+    /// # Safety
+    /// Caller must be inside rcu_read_lock()/rcu_read_unlock() (or an ISR)
+    /// and must not use the reference after leaving the section.
+    pub unsafe fn get(&self) -> &T {
+        &*self.ptr.load(Ordering::Acquire)
+    }
+
+    /// Publish a new copy, returning the old one
+    ///
+    /// The old copy stays valid for current readers; pass it to
+    /// `call_rcu` rather than reusing it directly. Concurrent writers must
+    /// serialize among themselves.
+    pub fn publish(&self, new: &'static T) -> &'static T {
+        let old = self.ptr.swap(new as *const T as *mut T, Ordering::AcqRel);
+        GP_SEQ.fetch_add(1, Ordering::Release);
+        unsafe { &*old }
+    }
+}
+
+// Safety: readers only get shared references; publish is an atomic swap
+unsafe impl<T: Sync + 'static> Sync for RcuCell<T> {}
+
+// ============================================================================
+// DEFERRED RECLAMATION
+// ============================================================================
+
+/// Reclaim function, called with the pointer given to `call_rcu`
+pub type RcuCallback = fn(*const ());
+
+#[derive(Copy, Clone)]
+struct PendingReclaim {
+    seq: u64,
+    func: RcuCallback,
+    ptr: *const (),
+}
+
+static mut PENDING: [Option<PendingReclaim>; config::MAX_RCU_CALLBACKS] =
+    [None; config::MAX_RCU_CALLBACKS];
+
+/// Run `func(ptr)` once the current grace period has ended
+///
+/// Call right after `publish`. Fails with `OutOfMemory` if too many
+/// reclaims are already pending.
+pub fn call_rcu(ptr: *const (), func: RcuCallback) -> Result<()> {
+    let seq = GP_SEQ.load(Ordering::Acquire);
+    crate::critical_section! {
+        let pending = unsafe { &mut *core::ptr::addr_of_mut!(PENDING) };
+        match pending.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(PendingReclaim { seq, func, ptr });
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::RcuCallback)),
+        }
+    }
+}
+
+/// Run every reclaim whose grace period has ended
+///
+/// Called from the idle task (see `rcu_init`). Returns the number run.
+pub fn rcu_poll() -> usize {
+    let mut ran = 0;
+    for i in 0..config::MAX_RCU_CALLBACKS {
+        let entry = crate::critical_section! {
+            let pending = unsafe { &mut *core::ptr::addr_of_mut!(PENDING) };
+            match pending[i] {
+                Some(p) if rcu_grace_period_done(p.seq) => pending[i].take(),
+                _ => None,
+            }
+        };
+        if let Some(p) = entry {
+            (p.func)(p.ptr);
+            ran += 1;
+        }
+    }
+    ran
+}
+
+/// Number of reclaims waiting for a grace period
+pub fn rcu_pending() -> usize {
+    let pending = unsafe { &*core::ptr::addr_of!(PENDING) };
+    pending.iter().flatten().count()
+}
+
+fn rcu_chore() {
+    rcu_poll();
+}
+
+static RCU_CHORE: IdleChore = IdleChore::new("rcu", rcu_chore, config::RCU_POLL_INTERVAL_TICKS);
+
+/// Have the idle task run pending reclaims
+pub fn rcu_init() -> Result<()> {
+    register_idle_chore(&RCU_CHORE)
+}
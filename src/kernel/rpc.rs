@@ -0,0 +1,201 @@
+// Request/response calls over queues
+//
+// A server task owns an `RpcEndpoint` (a queue of requests); each client
+// owns an `RpcClient` (a queue for its replies). `call` tags the request
+// with a fresh correlation ID and the client's reply port, then waits for
+// the reply carrying that ID, within one timeout covering both the send
+// and the wait.
+//
+// A call that times out leaves its request with the server. When the
+// server answers it later, the reply carries the old ID: the next call on
+// the same client drops it (counted in `late_replies`) instead of taking
+// it for its own answer. Servers reply without blocking, so a client that
+// stopped listening cannot stall them; replies that do not fit are
+// counted in `dropped_replies`.
+//
+// A client serves one call at a time. Tasks calling the same server
+// concurrently each use their own client.
+
+use crate::kernel::queue::Queue;
+use crate::kernel::scheduler::get_tick_count;
+use crate::kernel::types::{ErrorKind, ObjectKind, Result, RtosError, TickType};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// Where a server sends the reply to a request
+pub trait ReplyPort<Resp>: Sync {
+    /// Queue the reply to request `id` without blocking
+    fn deliver(&self, id: u32, body: Resp) -> Result<()>;
+}
+
+/// A request as received by the server
+pub struct RpcRequest<Req, Resp: 'static> {
+    id: u32,
+    body: Req,
+    reply_to: &'static dyn ReplyPort<Resp>,
+}
+
+impl<Req, Resp: 'static> RpcRequest<Req, Resp> {
+    /// Correlation ID, unique per client
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn body(&self) -> &Req {
+        &self.body
+    }
+
+    /// Answer the request
+    ///
+    /// Never blocks. Fails with `Timeout` if the client's reply queue is
+    /// full (the reply is dropped).
+    pub fn reply(self, body: Resp) -> Result<()> {
+        self.reply_to.deliver(self.id, body)
+    }
+}
+
+/// The request queue of a server
+///
+/// # Example
+/// ```
+/// static SENSOR_RPC: RpcEndpoint<Command, Reading, 4> = RpcEndpoint::new();
+///
+/// // Server task:
+/// loop {
+///     let request = SENSOR_RPC.receive(None)?;
+///     let reading = sensor.execute(request.body());
+///     request.reply(reading).ok();
+/// }
+/// ```
+pub struct RpcEndpoint<Req, Resp: 'static, const N: usize> {
+    requests: Queue<RpcRequest<Req, Resp>, N>,
+}
+
+impl<Req: Send, Resp: 'static, const N: usize> RpcEndpoint<Req, Resp, N> {
+    pub const fn new() -> Self {
+        RpcEndpoint { requests: Queue::new() }
+    }
+
+    /// Take the next request, waiting up to `timeout` (`None` = forever)
+    pub fn receive(&self, timeout: Option<TickType>) -> Result<RpcRequest<Req, Resp>> {
+        self.requests.receive(timeout)
+    }
+
+    /// Take the next request without blocking
+    pub fn try_receive(&self) -> Result<RpcRequest<Req, Resp>> {
+        self.requests.try_receive()
+    }
+
+    /// Requests waiting for the server
+    pub fn pending(&self) -> usize {
+        self.requests.len()
+    }
+}
+
+struct RpcReply<Resp> {
+    id: u32,
+    body: Resp,
+}
+
+/// A client's reply queue and correlation state
+///
+/// # Example
+/// ```
+/// static SENSOR_CLIENT: RpcClient<Reading, 2> = RpcClient::new();
+///
+/// match SENSOR_CLIENT.call(&SENSOR_RPC, Command::Sample, Some(TickType::from_ms(50))) {
+///     Ok(reading) => log(reading),
+///     Err(e) if e == ErrorKind::Timeout => retry_later(),
+///     Err(e) => return Err(e),
+/// }
+/// ```
+pub struct RpcClient<Resp, const M: usize> {
+    replies: Queue<RpcReply<Resp>, M>,
+    next_id: AtomicU32,
+    /// Set while a call is in progress
+    busy: AtomicBool,
+    /// Replies to calls that had already timed out
+    late: AtomicU64,
+    /// Replies the server could not queue
+    dropped: AtomicU64,
+}
+
+impl<Resp: Send, const M: usize> RpcClient<Resp, M> {
+    pub const fn new() -> Self {
+        RpcClient {
+            replies: Queue::new(),
+            next_id: AtomicU32::new(1),
+            busy: AtomicBool::new(false),
+            late: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Send `body` to `endpoint` and wait for the reply
+    ///
+    /// `timeout` bounds the whole call (`None` = wait forever). Fails with
+    /// `Timeout` (the error ID is the correlation ID) if the request could
+    /// not be queued or no reply came in time, and with `ResourceBusy` if
+    /// another call on this client is in progress.
+    pub fn call<Req: Send, const N: usize>(
+        &'static self,
+        endpoint: &RpcEndpoint<Req, Resp, N>,
+        body: Req,
+        timeout: Option<TickType>,
+    ) -> Result<Resp> {
+        if self.busy.swap(true, Ordering::Acquire) {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::Rpc));
+        }
+        let result = self.exchange(endpoint, body, timeout);
+        self.busy.store(false, Ordering::Release);
+        result
+    }
+
+    fn exchange<Req: Send, const N: usize>(
+        &'static self,
+        endpoint: &RpcEndpoint<Req, Resp, N>,
+        body: Req,
+        timeout: Option<TickType>,
+    ) -> Result<Resp> {
+        let deadline = timeout.map(|t| get_tick_count().wrapping_add(t));
+        let remaining =
+            || deadline.map(|d| get_tick_count().ticks_until(d).unwrap_or(TickType::zero()));
+
+        let mut id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if id == 0 {
+            id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        }
+        let tag = |e: RtosError| e.with_object(ObjectKind::Rpc).with_id(id);
+
+        endpoint
+            .requests
+            .send(RpcRequest { id, body, reply_to: self }, remaining())
+            .map_err(tag)?;
+
+        loop {
+            let reply = self.replies.receive(remaining()).map_err(tag)?;
+            if reply.id == id {
+                return Ok(reply.body);
+            }
+            self.late.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Replies discarded because their call had timed out
+    pub fn late_replies(&self) -> u64 {
+        self.late.load(Ordering::Relaxed)
+    }
+
+    /// Replies lost because the reply queue was full
+    pub fn dropped_replies(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<Resp: Send, const M: usize> ReplyPort<Resp> for RpcClient<Resp, M> {
+    fn deliver(&self, id: u32, body: Resp) -> Result<()> {
+        self.replies.try_send(RpcReply { id, body }).map_err(|e| {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            e.with_object(ObjectKind::Rpc).with_id(id)
+        })
+    }
+}
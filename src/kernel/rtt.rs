@@ -0,0 +1,230 @@
+// RTT-style in-memory debug channels
+//
+// Implements the SEGGER RTT control block layout so a debugger (J-Link,
+// probe-rs, OpenOCD) or a QEMU/GDB script can locate `_SEGGER_RTT` in RAM
+// and read log output while the target runs. No UART is required.
+
+use crate::kernel::types::config;
+use core::ptr;
+use core::sync::atomic::{fence, Ordering};
+
+/// Number of target -> host channels
+pub const MAX_UP_CHANNELS: usize = 1;
+
+/// Number of host -> target channels
+pub const MAX_DOWN_CHANNELS: usize = 1;
+
+/// Channel mode: drop the whole write if it does not fit
+pub const MODE_NO_BLOCK_SKIP: u32 = 0;
+
+/// Channel mode: write as much as fits, drop the rest
+pub const MODE_NO_BLOCK_TRIM: u32 = 1;
+
+/// Channel mode: spin until the host has drained enough space
+pub const MODE_BLOCK_IF_FULL: u32 = 2;
+
+/// One ring buffer descriptor (layout matches SEGGER_RTT_BUFFER_UP/DOWN)
+#[repr(C)]
+pub struct RttChannel {
+    /// Null-terminated channel name
+    name: *const u8,
+    /// Ring buffer storage
+    buffer: *mut u8,
+    /// Size of the ring buffer in bytes
+    size: u32,
+    /// Write offset (advanced by the producer)
+    write: u32,
+    /// Read offset (advanced by the consumer)
+    read: u32,
+    /// Operating mode (MODE_*)
+    flags: u32,
+}
+
+impl RttChannel {
+    const fn new() -> Self {
+        RttChannel {
+            name: ptr::null(),
+            buffer: ptr::null_mut(),
+            size: 0,
+            write: 0,
+            read: 0,
+            flags: 0,
+        }
+    }
+
+    /// Number of bytes that can be written without overtaking the reader
+    fn free_space(&self) -> usize {
+        let read = unsafe { ptr::read_volatile(&self.read) } as usize;
+        let write = self.write as usize;
+        let size = self.size as usize;
+
+        if read > write {
+            read - write - 1
+        } else {
+            size - (write - read) - 1
+        }
+    }
+
+    /// Copy bytes into the ring, returns the number of bytes written
+    fn write_bytes(&mut self, bytes: &[u8]) -> usize {
+        if self.buffer.is_null() {
+            return 0;
+        }
+
+        let mut written = 0;
+        loop {
+            let free = self.free_space();
+            let remaining = bytes.len() - written;
+
+            if self.flags == MODE_NO_BLOCK_SKIP && free < remaining {
+                return 0;
+            }
+            let chunk = core::cmp::min(free, remaining);
+
+            let mut write = self.write as usize;
+            for &b in &bytes[written..written + chunk] {
+                unsafe {
+                    ptr::write_volatile(self.buffer.add(write), b);
+                }
+                write += 1;
+                if write == self.size as usize {
+                    write = 0;
+                }
+            }
+            written += chunk;
+
+            // Data must be visible before the host sees the new write offset
+            fence(Ordering::SeqCst);
+            unsafe {
+                ptr::write_volatile(&mut self.write, write as u32);
+            }
+
+            if written == bytes.len() || self.flags != MODE_BLOCK_IF_FULL {
+                return written;
+            }
+        }
+    }
+
+    /// Copy bytes out of the ring, returns the number of bytes read
+    fn read_bytes(&mut self, out: &mut [u8]) -> usize {
+        if self.buffer.is_null() {
+            return 0;
+        }
+
+        let write = unsafe { ptr::read_volatile(&self.write) } as usize;
+        let mut read = self.read as usize;
+        let mut count = 0;
+
+        while read != write && count < out.len() {
+            out[count] = unsafe { ptr::read_volatile(self.buffer.add(read)) };
+            count += 1;
+            read += 1;
+            if read == self.size as usize {
+                read = 0;
+            }
+        }
+
+        fence(Ordering::SeqCst);
+        unsafe {
+            ptr::write_volatile(&mut self.read, read as u32);
+        }
+        count
+    }
+}
+
+/// RTT control block - the debugger scans RAM for the ID string
+#[repr(C)]
+pub struct RttControlBlock {
+    /// "SEGGER RTT" followed by zero padding
+    id: [u8; 16],
+    max_up_buffers: i32,
+    max_down_buffers: i32,
+    up: [RttChannel; MAX_UP_CHANNELS],
+    down: [RttChannel; MAX_DOWN_CHANNELS],
+}
+
+// ============================================================================
+// GLOBAL RTT INSTANCE
+// ============================================================================
+
+/// Control block, exported under the symbol name tools look for
+#[no_mangle]
+pub static mut _SEGGER_RTT: RttControlBlock = RttControlBlock {
+    id: [0; 16],
+    max_up_buffers: MAX_UP_CHANNELS as i32,
+    max_down_buffers: MAX_DOWN_CHANNELS as i32,
+    up: [RttChannel::new()],
+    down: [RttChannel::new()],
+};
+
+static mut UP_BUFFER: [u8; config::RTT_UP_BUFFER_SIZE] = [0; config::RTT_UP_BUFFER_SIZE];
+static mut DOWN_BUFFER: [u8; config::RTT_DOWN_BUFFER_SIZE] = [0; config::RTT_DOWN_BUFFER_SIZE];
+
+const RTT_ID: &[u8] = b"SEGGER RTT";
+
+/// Initialize the RTT control block
+///
+/// The ID string is written last so a debugger scanning memory never
+/// sees a half-initialized control block.
+pub fn rtt_init() {
+    unsafe {
+        let cb = &mut *ptr::addr_of_mut!(_SEGGER_RTT);
+
+        cb.up[0] = RttChannel {
+            name: c"Terminal".as_ptr().cast(),
+            buffer: ptr::addr_of_mut!(UP_BUFFER) as *mut u8,
+            size: config::RTT_UP_BUFFER_SIZE as u32,
+            write: 0,
+            read: 0,
+            flags: MODE_NO_BLOCK_TRIM,
+        };
+        cb.down[0] = RttChannel {
+            name: c"Terminal".as_ptr().cast(),
+            buffer: ptr::addr_of_mut!(DOWN_BUFFER) as *mut u8,
+            size: config::RTT_DOWN_BUFFER_SIZE as u32,
+            write: 0,
+            read: 0,
+            flags: MODE_NO_BLOCK_SKIP,
+        };
+
+        fence(Ordering::SeqCst);
+        for (i, &b) in RTT_ID.iter().enumerate() {
+            ptr::write_volatile(&mut cb.id[i], b);
+        }
+    }
+}
+
+/// Write bytes to an up channel
+///
+/// Returns the number of bytes accepted (may be less than `bytes.len()`
+/// depending on the channel mode)
+pub fn rtt_write(channel: usize, bytes: &[u8]) -> usize {
+    if channel >= MAX_UP_CHANNELS {
+        return 0;
+    }
+    unsafe { (*ptr::addr_of_mut!(_SEGGER_RTT)).up[channel].write_bytes(bytes) }
+}
+
+/// Write a string to an up channel
+pub fn rtt_write_str(channel: usize, s: &str) -> usize {
+    rtt_write(channel, s.as_bytes())
+}
+
+/// Read bytes sent by the host on a down channel
+///
+/// Returns the number of bytes copied into `out`
+pub fn rtt_read(channel: usize, out: &mut [u8]) -> usize {
+    if channel >= MAX_DOWN_CHANNELS {
+        return 0;
+    }
+    unsafe { (*ptr::addr_of_mut!(_SEGGER_RTT)).down[channel].read_bytes(out) }
+}
+
+/// Change the operating mode of an up channel
+pub fn rtt_set_mode(channel: usize, mode: u32) {
+    if channel < MAX_UP_CHANNELS && mode <= MODE_BLOCK_IF_FULL {
+        unsafe {
+            (*ptr::addr_of_mut!(_SEGGER_RTT)).up[channel].flags = mode;
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// Task run-length histograms
+//
+// For tasks that have one attached, the scheduler measures each
+// activation: the mcycles a task spends running from the moment it is
+// switched in after blocking until it blocks (or suspends) again. Time
+// spent preempted does not count; the activation simply continues when
+// the task is switched back in. The lengths go into log2 buckets plus an
+// exact maximum, which is the evidence a schedulability analysis needs
+// for the task's worst-case execution time.
+//
+// Histograms are opt-in per task (`set_task_run_histogram`) because each
+// one costs a few hundred bytes.
+
+use crate::kernel::types::config;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Run lengths of a task's activations, in mcycles
+///
+/// Bucket 0 counts zero-length runs; bucket i counts runs of
+/// [2^(i-1), 2^i) cycles. The last bucket also takes everything longer.
+///
+/// # Example
+/// ```
+/// static CONTROL_RUNS: RunHistogram = RunHistogram::new();
+/// set_task_run_histogram(control_tcb, Some(&CONTROL_RUNS));
+///
+/// // Later, from a diagnostics task:
+/// let wcet = CONTROL_RUNS.max_cycles();
+/// CONTROL_RUNS.for_each_bucket(|low, high, count| report(low, high, count));
+/// ```
+pub struct RunHistogram {
+    buckets: [AtomicU64; config::RUN_HISTOGRAM_BUCKETS],
+    activations: AtomicU64,
+    total_cycles: AtomicU64,
+    max_cycles: AtomicU64,
+}
+
+impl RunHistogram {
+    pub const fn new() -> Self {
+        RunHistogram {
+            buckets: [const { AtomicU64::new(0) }; config::RUN_HISTOGRAM_BUCKETS],
+            activations: AtomicU64::new(0),
+            total_cycles: AtomicU64::new(0),
+            max_cycles: AtomicU64::new(0),
+        }
+    }
+
+    fn bucket_index(cycles: u64) -> usize {
+        let bits = (u64::BITS - cycles.leading_zeros()) as usize;
+        bits.min(config::RUN_HISTOGRAM_BUCKETS - 1)
+    }
+
+    /// Cycle range [low, high) covered by a bucket (the last is open-ended)
+    pub fn bucket_range(index: usize) -> (u64, u64) {
+        let low = if index == 0 { 0 } else { 1u64 << (index - 1) };
+        let high = if index + 1 >= config::RUN_HISTOGRAM_BUCKETS {
+            u64::MAX
+        } else {
+            1u64 << index
+        };
+        (low, high)
+    }
+
+    /// Count one activation
+    pub fn record(&self, cycles: u64) {
+        self.buckets[Self::bucket_index(cycles)].fetch_add(1, Ordering::Relaxed);
+        self.activations.fetch_add(1, Ordering::Relaxed);
+        self.total_cycles.fetch_add(cycles, Ordering::Relaxed);
+        self.max_cycles.fetch_max(cycles, Ordering::Relaxed);
+    }
+
+    /// Activations recorded
+    pub fn activations(&self) -> u64 {
+        self.activations.load(Ordering::Relaxed)
+    }
+
+    /// Longest activation seen
+    pub fn max_cycles(&self) -> u64 {
+        self.max_cycles.load(Ordering::Relaxed)
+    }
+
+    /// Mean activation length (0 before the first one)
+    pub fn mean_cycles(&self) -> u64 {
+        self.total_cycles.load(Ordering::Relaxed) / self.activations().max(1)
+    }
+
+    /// Activations in one bucket
+    pub fn bucket(&self, index: usize) -> u64 {
+        self.buckets[index].load(Ordering::Relaxed)
+    }
+
+    /// Visit every non-empty bucket as (low, high, count)
+    pub fn for_each_bucket<F: FnMut(u64, u64, u64)>(&self, mut f: F) {
+        for index in 0..config::RUN_HISTOGRAM_BUCKETS {
+            let count = self.bucket(index);
+            if count > 0 {
+                let (low, high) = Self::bucket_range(index);
+                f(low, high, count);
+            }
+        }
+    }
+
+    /// Discard everything recorded
+    pub fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.activations.store(0, Ordering::Relaxed);
+        self.total_cycles.store(0, Ordering::Relaxed);
+        self.max_cycles.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Default for RunHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
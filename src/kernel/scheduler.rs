@@ -1,5 +1,8 @@
-use crate::kernel::list::List;
-use crate::kernel::task::TaskControlBlock;
+use crate::kernel::hooks::{call_hook, PickNextHook, ReadyView, SchedulerHooks};
+use crate::kernel::list::{List, ListCorruption};
+#[cfg(not(feature = "tiny"))]
+use crate::kernel::run_histogram::RunHistogram;
+use crate::kernel::task::{TaskControlBlock, TASK_FLAGS_ALL};
 use crate::kernel::types::*;
 use core::ptr;
 
@@ -10,6 +13,60 @@ fn debug_print_ready_lists(scheduler: &Scheduler, label: &str) {
     // We'll use the counters instead
 }
 
+/// Scheduling fairness counters (see `sched_stats`)
+#[cfg(not(feature = "tiny"))]
+#[derive(Copy, Clone)]
+pub struct SchedStats {
+    /// Times a task at each priority was selected to run
+    pub selections: [u64; config::MAX_PRIORITIES],
+    /// Task selected most recently
+    pub streak_task: *mut TaskControlBlock,
+    /// Consecutive selections of `streak_task` so far
+    pub streak: u32,
+    /// Longest run of consecutive selections of any one task
+    pub longest_streak: u32,
+}
+
+/// CPU time of one task (see `task_runtime_stats`)
+#[cfg(not(feature = "tiny"))]
+#[derive(Copy, Clone, Debug)]
+pub struct TaskRuntime {
+    /// mtime ticks the task has run since the last reset
+    pub runtime: u64,
+    /// mtime ticks elapsed since the last reset
+    pub elapsed: u64,
+}
+
+#[cfg(not(feature = "tiny"))]
+impl TaskRuntime {
+    /// Run time in microseconds
+    pub fn runtime_us(&self) -> u64 {
+        self.runtime * 1_000_000 / config::MTIME_FREQ_HZ
+    }
+
+    /// Share of the elapsed time spent running, in percent (rounded down)
+    pub fn cpu_percent(&self) -> u32 {
+        (self.cpu_permille() / 10) as u32
+    }
+
+    /// Share of the elapsed time spent running, in tenths of a percent
+    pub fn cpu_permille(&self) -> u64 {
+        self.runtime * 1000 / self.elapsed.max(1)
+    }
+}
+
+#[cfg(not(feature = "tiny"))]
+impl SchedStats {
+    pub const fn new() -> Self {
+        SchedStats {
+            selections: [0; config::MAX_PRIORITIES],
+            streak_task: ptr::null_mut(),
+            streak: 0,
+            longest_streak: 0,
+        }
+    }
+}
+
 pub struct Scheduler {
     /// Ready lists - one per priority level
     /// Index 0 = priority 0 (idle task)
@@ -26,11 +83,21 @@ pub struct Scheduler {
     /// Optimization: Don't scan all 32 lists, start from here
     top_ready_priority: Priority,
 
+    /// Delayed tasks sorted by wake tick: one list for the current tick
+    /// epoch and one for deadlines past the next counter wraparound
+    delayed_lists: [List; 2],
+
+    /// Index of the delayed list for the current epoch
+    delayed_current: usize,
+
+    /// Tasks taken out of scheduling by `suspend_task`
+    suspended_list: List,
+
     /// Total number of tasks in the system
     task_count: usize,
 
     /// Current system tick count (incremented by timer interrupt)
-    tick_count: TickType,
+    tick_count: TickCounter,
 
     /// Is the scheduler running?
     scheduler_running: bool,
@@ -39,6 +106,31 @@ pub struct Scheduler {
     /// 0 = not suspended, >0 = suspended
     /// Suspensions nest - must call resume same number of times
     suspend_depth: usize,
+
+    /// Instrumentation callbacks (not reset by init)
+    hooks: SchedulerHooks,
+
+    /// Bit N set = ready_lists[N] is non-empty
+    ready_bitmap: u64,
+
+    /// Application override of the pick-next decision (not reset by init)
+    pick_next_hook: Option<PickNextHook>,
+
+    /// A tick ended the running task's time slice; the next preemption
+    /// check rotates its ready list
+    slice_expired: bool,
+
+    /// Fairness counters
+    #[cfg(not(feature = "tiny"))]
+    stats: SchedStats,
+
+    /// mtime at the last task switch (run time is charged from here)
+    #[cfg(not(feature = "tiny"))]
+    last_switch_mtime: u64,
+
+    /// mtime when run time accounting was last reset
+    #[cfg(not(feature = "tiny"))]
+    runtime_epoch: u64,
 }
 
 impl Scheduler {
@@ -54,17 +146,44 @@ impl Scheduler {
             // Start at idle priority
             top_ready_priority: config::IDLE_PRIORITY,
 
+            // Nobody sleeping
+            delayed_lists: [EMPTY_LIST; 2],
+            delayed_current: 0,
+
+            // Nobody suspended
+            suspended_list: List::new(),
+
             // No tasks yet
             task_count: 0,
 
             // Time starts at 0
-            tick_count: TickType::zero(),
+            tick_count: TickCounter::new(),
 
             // Not running yet
             scheduler_running: false,
 
             // Not suspended
             suspend_depth: 0,
+
+            // No instrumentation
+            hooks: SchedulerHooks::none(),
+
+            // Nothing ready
+            ready_bitmap: 0,
+
+            // Built-in policy
+            pick_next_hook: None,
+
+            // No slice running yet
+            slice_expired: false,
+
+            // Nothing scheduled yet
+            #[cfg(not(feature = "tiny"))]
+            stats: SchedStats::new(),
+            #[cfg(not(feature = "tiny"))]
+            last_switch_mtime: 0,
+            #[cfg(not(feature = "tiny"))]
+            runtime_epoch: 0,
         }
     }
 
@@ -72,23 +191,48 @@ impl Scheduler {
         for list in &mut self.ready_lists {
             list.init();
         }
+        for list in &mut self.delayed_lists {
+            list.init();
+        }
+        self.delayed_current = 0;
+        self.suspended_list.init();
 
         self.current_task = ptr::null_mut();
         self.top_ready_priority = config::IDLE_PRIORITY;
         self.task_count = 0;
-        self.tick_count = TickType::zero();
+        self.tick_count.store(TickType::zero());
         self.scheduler_running = false;
         self.suspend_depth = 0;
+        self.ready_bitmap = 0;
+        self.slice_expired = false;
+        #[cfg(not(feature = "tiny"))]
+        {
+            self.stats = SchedStats::new();
+            self.last_switch_mtime = 0;
+            self.runtime_epoch = 0;
+        }
     }
 
     pub fn add_task_to_ready_list(&mut self, tcb: &mut TaskControlBlock) {
+        let was_ready = tcb.is_ready();
         tcb.state = TaskState::Ready;
+        tcb.ready_since = self.tick_count.load();
         let priority = tcb.priority;
 
         self.ready_lists[priority].insert_end(&mut tcb.state_list_item);
+        self.ready_bitmap |= 1 << priority;
         if priority > self.top_ready_priority {
             self.top_ready_priority = priority;
         }
+        if config::CHECK_LIST_INVARIANTS {
+            assert_eq!(self.ready_lists[priority].check_invariants(), Ok(()),
+                "ready list {} corrupted", priority);
+        }
+
+        // Only report real transitions, not round-robin re-insertion
+        if !was_ready {
+            call_hook(self.hooks.task_ready, tcb);
+        }
     }
 
     pub fn remove_task_from_ready_list(&mut self, tcb: &mut TaskControlBlock) -> bool {
@@ -96,10 +240,15 @@ impl Scheduler {
 
         // Try to remove from the list
         let removed = self.ready_lists[priority].remove(&mut tcb.state_list_item);
+        if config::CHECK_LIST_INVARIANTS {
+            assert_eq!(self.ready_lists[priority].check_invariants(), Ok(()),
+                "ready list {} corrupted", priority);
+        }
 
-        if removed {
+        if removed && self.ready_lists[priority].is_empty() {
+            self.ready_bitmap &= !(1 << priority);
             // If we just emptied the top priority list, find new top
-            if self.ready_lists[priority].is_empty() && priority == self.top_ready_priority {
+            if priority == self.top_ready_priority {
                 self.update_top_ready_priority();
             }
         }
@@ -107,6 +256,321 @@ impl Scheduler {
         removed
     }
 
+    /// Move a task to a different priority, migrating it between ready lists
+    ///
+    /// Works for tasks that are not in a ready list too (only the field changes).
+    /// The task's state (Ready/Running) is preserved.
+    pub fn move_task_to_priority(&mut self, tcb: &mut TaskControlBlock, new_priority: Priority) {
+        if tcb.priority == new_priority {
+            return;
+        }
+
+        let in_ready_list = tcb.state_list_item.get_container()
+            == &mut self.ready_lists[tcb.priority] as *mut List;
+
+        if in_ready_list {
+            self.remove_task_from_ready_list(tcb);
+        }
+
+        tcb.priority = new_priority;
+
+        // A waiting task must be re-sorted among the other waiters
+        let event_list = tcb.event_list_item.get_container();
+        if event_list.is_null() {
+            tcb.event_list_item
+                .set_value((config::MAX_PRIORITIES - new_priority) as u64);
+        } else {
+            unsafe {
+                (*event_list).remove(&mut tcb.event_list_item);
+                self.place_on_event_list(&mut *event_list, tcb);
+            }
+        }
+
+        if in_ready_list {
+            self.ready_lists[new_priority].insert_end(&mut tcb.state_list_item);
+            self.ready_bitmap |= 1 << new_priority;
+            if new_priority > self.top_ready_priority {
+                self.top_ready_priority = new_priority;
+            }
+        }
+    }
+
+    /// Take a task off every scheduler list it is on
+    ///
+    /// Used when a task is killed or restarted. Its state is left for the
+    /// caller to set.
+    pub fn detach_task(&mut self, tcb: &mut TaskControlBlock) {
+        if self.is_in_ready_list(tcb) {
+            self.remove_task_from_ready_list(tcb);
+        } else {
+            let container = tcb.state_list_item.get_container();
+            if !container.is_null() {
+                unsafe { (*container).remove(&mut tcb.state_list_item) };
+            }
+        }
+
+        let event_list = tcb.event_list_item.get_container();
+        if !event_list.is_null() {
+            unsafe { (*event_list).remove(&mut tcb.event_list_item) };
+        }
+    }
+
+    /// Boost tasks that have waited too long in the ready lists
+    ///
+    /// Each task that has been ready but not running for
+    /// AGING_THRESHOLD_TICKS gains one priority level, up to AGING_MAX_BOOST.
+    /// The boost is removed when the task is switched out after running.
+    fn age_ready_tasks(&mut self) {
+        let now = self.tick_count.load();
+        let current = self.current_task;
+        let mut starved = [ptr::null_mut::<TaskControlBlock>(); config::AGING_MAX_PER_SCAN];
+        let mut count = 0;
+
+        // Highest priority cannot be boosted further
+        for priority in (0..config::MAX_PRIORITIES - 1).rev() {
+            self.ready_lists[priority].for_each(|node| {
+                let tcb = node.get_owner::<TaskControlBlock>();
+                if tcb.is_null() || tcb == current || count == starved.len() {
+                    return;
+                }
+                let task = unsafe { &*tcb };
+                if task.base_priority != config::IDLE_PRIORITY
+                    && task.aging_boost < config::AGING_MAX_BOOST
+                    && now.elapsed_since(task.ready_since).as_u64() >= config::AGING_THRESHOLD_TICKS
+                {
+                    starved[count] = tcb;
+                    count += 1;
+                }
+            });
+        }
+
+        for &tcb in &starved[..count] {
+            let task = unsafe { &mut *tcb };
+            task.aging_boost += 1;
+            task.ready_since = now;
+            let boosted = task.priority + 1;
+            self.move_task_to_priority(task, boosted);
+        }
+    }
+
+    /// Drop any aging boost from a task that has had its turn
+    fn decay_aging_boost(&mut self, tcb: *mut TaskControlBlock) {
+        if tcb.is_null() {
+            return;
+        }
+        let task = unsafe { &mut *tcb };
+        if task.aging_boost > 0 {
+            let restored = task.priority - task.aging_boost;
+            task.aging_boost = 0;
+            self.move_task_to_priority(task, restored);
+        }
+    }
+
+    /// Insert a task into a blocking object's waiter list
+    ///
+    /// Waiters are ordered by effective priority (highest first, FIFO among
+    /// equals) unless config::EVENT_LIST_FIFO selects pure arrival order.
+    pub fn place_on_event_list(&mut self, event_list: &mut List, tcb: &mut TaskControlBlock) {
+        tcb.event_list_item
+            .set_value((config::MAX_PRIORITIES - tcb.priority) as u64);
+
+        if config::EVENT_LIST_FIFO {
+            event_list.insert_end(&mut tcb.event_list_item);
+        } else {
+            event_list.insert_sorted(&mut tcb.event_list_item);
+        }
+    }
+
+    /// Remove the first waiter from an event list and make it ready
+    ///
+    /// Returns the woken task, or null if nobody was waiting. A woken task
+    /// that was Blocked is moved to its ready list.
+    pub fn wake_first_waiter(&mut self, event_list: &mut List) -> *mut TaskControlBlock {
+        let tcb = match event_list.get_head() {
+            Some(node) => node.get_owner::<TaskControlBlock>(),
+            None => return ptr::null_mut(),
+        };
+        if tcb.is_null() {
+            return tcb;
+        }
+
+        unsafe {
+            let task = &mut *tcb;
+            event_list.remove(&mut task.event_list_item);
+            // A timed wait ends early
+            self.undelay_task(task);
+
+            if task.state == TaskState::Blocked && !task.state_list_item.is_in_list() {
+                self.add_task_to_ready_list(task);
+            }
+        }
+        tcb
+    }
+
+    /// Wake every waiter of an object that is being deleted
+    ///
+    /// Each woken task's blocking call fails with `ObjectDeleted`. Returns
+    /// the number of tasks woken.
+    pub fn wake_all_waiters_deleted(&mut self, event_list: &mut List) -> usize {
+        let mut woken = 0;
+        loop {
+            let tcb = self.wake_first_waiter(event_list);
+            match unsafe { tcb.as_mut() } {
+                Some(task) => task.wait_aborted = true,
+                None => return woken,
+            }
+            woken += 1;
+        }
+    }
+
+    /// Move a task from its ready list onto a blocking object's waiter list
+    ///
+    /// The task stays Blocked until `wake_first_waiter` (or a later
+    /// unblock path) puts it back in its ready list.
+    pub fn block_task(&mut self, tcb: &mut TaskControlBlock, event_list: &mut List) {
+        self.remove_task_from_ready_list(tcb);
+        tcb.state = TaskState::Blocked;
+        self.place_on_event_list(event_list, tcb);
+        call_hook(self.hooks.task_blocked, tcb);
+    }
+
+    /// Move a task from its ready list to the delayed list until `wake`
+    ///
+    /// Returns false, leaving the task untouched, if `wake` has already
+    /// been reached.
+    pub fn delay_task(&mut self, tcb: &mut TaskControlBlock, wake: TickType) -> bool {
+        let now = self.tick_count.load();
+        if now.has_reached(wake) {
+            return false;
+        }
+
+        self.remove_task_from_ready_list(tcb);
+        tcb.state = TaskState::Blocked;
+        self.insert_delayed(tcb, wake);
+        call_hook(self.hooks.task_blocked, tcb);
+        true
+    }
+
+    /// Put a task that is off the ready lists on the delayed list
+    fn insert_delayed(&mut self, tcb: &mut TaskControlBlock, wake: TickType) {
+        let now = self.tick_count.load();
+        tcb.delay_until = wake;
+        tcb.state_list_item.set_value(wake.as_u64());
+
+        // A deadline numerically below now lies past the wraparound
+        let list = if wake.as_u64() < now.as_u64() {
+            1 - self.delayed_current
+        } else {
+            self.delayed_current
+        };
+        self.delayed_lists[list].insert_sorted(&mut tcb.state_list_item);
+    }
+
+    /// Block a task on a waiter list with a deadline
+    ///
+    /// The task becomes ready at whichever comes first: a wakeup through
+    /// the waiter list or `deadline`, which also takes it off the list.
+    pub fn block_task_until(&mut self, tcb: &mut TaskControlBlock, event_list: &mut List, deadline: TickType) {
+        self.block_task(tcb, event_list);
+        self.insert_delayed(tcb, deadline);
+    }
+
+    /// Take a task off the delayed list before its deadline
+    ///
+    /// Returns false if it was not delayed.
+    pub fn undelay_task(&mut self, tcb: &mut TaskControlBlock) -> bool {
+        let container = tcb.state_list_item.get_container();
+        let delayed = self.delayed_lists.iter().any(|l| ptr::eq(l, container));
+        if !delayed {
+            return false;
+        }
+        unsafe { (*container).remove(&mut tcb.state_list_item) };
+        true
+    }
+
+    /// Make every delayed task whose deadline has passed ready
+    ///
+    /// All expired tasks are moved in one pass, so a tick that releases
+    /// several of them leads to a single scheduling decision. Returns the
+    /// number woken.
+    fn wake_delayed_tasks(&mut self, now: TickType) -> usize {
+        let mut woken = 0;
+        loop {
+            let tcb = match self.delayed_lists[self.delayed_current].get_head() {
+                Some(node) if node.get_value() <= now.as_u64() => node.get_owner::<TaskControlBlock>(),
+                _ => break,
+            };
+            if tcb.is_null() {
+                break;
+            }
+
+            let task = unsafe { &mut *tcb };
+            self.delayed_lists[self.delayed_current].remove(&mut task.state_list_item);
+            // A timed wait gives up on its blocking object
+            let event_list = task.event_list_item.get_container();
+            if !event_list.is_null() {
+                unsafe { (*event_list).remove(&mut task.event_list_item) };
+            }
+            self.add_task_to_ready_list(task);
+            woken += 1;
+        }
+        woken
+    }
+
+    /// Number of tasks sleeping on the delayed lists
+    pub fn delayed_task_count(&self) -> usize {
+        self.delayed_lists.iter().map(|l| l.len()).sum()
+    }
+
+    /// Park a task on the suspended list, wherever it was
+    ///
+    /// A ready task leaves its ready list; a blocked or delayed one gives
+    /// up its wait.
+    pub fn suspend_task(&mut self, tcb: &mut TaskControlBlock) {
+        self.detach_task(tcb);
+        tcb.state = TaskState::Suspended;
+        self.suspended_list.insert_end(&mut tcb.state_list_item);
+        call_hook(self.hooks.task_blocked, tcb);
+    }
+
+    /// Move a task from the suspended list back to its ready list
+    ///
+    /// Returns false if it was not suspended.
+    pub fn resume_task(&mut self, tcb: &mut TaskControlBlock) -> bool {
+        if !ptr::eq(tcb.state_list_item.get_container(), &self.suspended_list) {
+            return false;
+        }
+        self.suspended_list.remove(&mut tcb.state_list_item);
+        self.add_task_to_ready_list(tcb);
+        true
+    }
+
+    /// Number of suspended tasks
+    pub fn suspended_task_count(&self) -> usize {
+        self.suspended_list.len()
+    }
+
+    /// Check that a task pointer refers to a task in one of the ready lists
+    fn is_in_ready_list(&self, tcb: *mut TaskControlBlock) -> bool {
+        if tcb.is_null() {
+            return false;
+        }
+        let task = unsafe { &*tcb };
+        task.priority < config::MAX_PRIORITIES
+            && task.state_list_item.get_container() as *const List
+                == &self.ready_lists[task.priority] as *const List
+    }
+
+    /// Install or remove the pick-next policy hook
+    pub fn set_pick_next_hook(&mut self, hook: Option<PickNextHook>) {
+        self.pick_next_hook = hook;
+    }
+
+    /// Bit N set = at least one task is ready at priority N
+    pub fn get_ready_bitmap(&self) -> u64 {
+        self.ready_bitmap
+    }
+
     pub fn update_top_ready_priority(&mut self) {
         let mut priority = self.top_ready_priority;
 
@@ -121,10 +585,25 @@ impl Scheduler {
     }
 
     pub fn select_highest_priority_task(&mut self) -> *mut TaskControlBlock {
-        // Set previous running task back to Ready state
+        // Set previous running task back to Ready state (a task that just
+        // blocked or was deleted keeps its state)
         if !self.current_task.is_null() {
             unsafe {
-                (*self.current_task).state = TaskState::Ready;
+                if (*self.current_task).state == TaskState::Running {
+                    (*self.current_task).state = TaskState::Ready;
+                }
+            }
+        }
+
+        // Give an installed policy hook the first say
+        if let Some(hook) = self.pick_next_hook {
+            let view = ReadyView::new(&self.ready_lists, self.ready_bitmap);
+            let chosen = hook(&view, self.current_task);
+            if self.is_in_ready_list(chosen) {
+                unsafe {
+                    (*chosen).state = TaskState::Running;
+                }
+                return chosen;
             }
         }
 
@@ -152,13 +631,12 @@ impl Scheduler {
             // Move to next lower priority
             if priority == config::IDLE_PRIORITY {
                 // We've checked all priorities, no task found
-                // This should never happen if idle task exists!
                 break;
             }
             priority -= 1;
         }
 
-        // Should never reach here if idle task exists
+        // Only before spawn_idle_task: the idle task never blocks
         ptr::null_mut()
     }
 
@@ -182,28 +660,10 @@ impl Scheduler {
 
         unsafe {
             let current_ref = &mut *current;
-            let priority = current_ref.priority;
-
-            // Debug: Check if task is actually in the list before removing
-            let _in_list_before = current_ref.state_list_item.is_in_list();
 
             // Temporarily remove current task from ready list
             let removed = self.remove_task_from_ready_list(current_ref);
 
-            // CRITICAL DEBUG: The task should be in the list and removable
-            assert!(removed, "BUG: Failed to remove current task from ready list!");
-
-            // If remove succeeded, the list at priority 2 should now be empty
-            // (since Task1 is the only task at that priority)
-            if priority == 2 {
-                assert!(self.ready_lists[priority].is_empty(),
-                    "BUG: Task removed but list not empty!");
-            }
-
-            // Debug: Check list state after attempted removal
-            let _list_empty_after_remove = self.ready_lists[priority].is_empty();
-            let _in_list_after = current_ref.state_list_item.is_in_list();
-
             // Now select from remaining tasks (current excluded)
             let next = self.select_highest_priority_task();
 
@@ -225,6 +685,69 @@ impl Scheduler {
         }
     }
 
+    /// Hand the CPU directly to a specific task of the same priority
+    ///
+    /// If `target` is ready and shares the current task's priority, the
+    /// current task goes to the back of its ready list and `target` is
+    /// returned to run next, skipping the round-robin order. Otherwise this
+    /// behaves like `select_next_different_task`.
+    pub fn yield_to(&mut self, target: *mut TaskControlBlock) -> *mut TaskControlBlock {
+        let current = self.current_task;
+
+        let direct = !current.is_null()
+            && target != current
+            && self.is_in_ready_list(target)
+            && unsafe { (*target).priority == (*current).priority };
+        if !direct {
+            return self.select_next_different_task();
+        }
+
+        self.yield_task();
+        unsafe {
+            (*current).state = TaskState::Ready;
+            (*target).state = TaskState::Running;
+        }
+        target
+    }
+
+    /// Check whether the running task's time slice should end
+    ///
+    /// True when time slicing is enabled, the current task is round-robin
+    /// and another task of the same priority is ready. FIFO tasks keep
+    /// the CPU until they block or yield.
+    pub fn time_slice_due(&self) -> bool {
+        if !config::USE_TIME_SLICING || self.current_task.is_null() {
+            return false;
+        }
+        unsafe {
+            let current = &*self.current_task;
+            current.is_round_robin() && self.ready_lists[current.priority].len() > 1
+        }
+    }
+
+    /// Pick a task to preempt the running one at a tick
+    ///
+    /// Returns a ready task of higher priority than the current one or,
+    /// once a tick has ended the current task's time slice, the next task
+    /// of its own priority (the current one goes to the back of its ready
+    /// list). The returned task is marked Running; null means the current
+    /// task keeps the CPU. Never preempts while the scheduler is suspended
+    /// or before the first task has started.
+    pub fn preempt_check(&mut self) -> *mut TaskControlBlock {
+        if !config::USE_PREEMPTION || self.suspend_depth > 0 || self.current_task.is_null() {
+            return ptr::null_mut();
+        }
+        let current_priority = unsafe { (*self.current_task).priority };
+        if self.top_ready_priority > current_priority {
+            return self.select_highest_priority_task();
+        }
+        if core::mem::take(&mut self.slice_expired) && self.time_slice_due() {
+            self.yield_task();
+            return self.select_highest_priority_task();
+        }
+        ptr::null_mut()
+    }
+
     pub fn yield_task(&mut self) {
         if self.current_task.is_null() {
             return;
@@ -283,19 +806,163 @@ impl Scheduler {
     ///
     /// Called by context switcher
     pub fn set_current_task(&mut self, tcb: *mut TaskControlBlock) {
+        #[cfg(not(feature = "tiny"))]
+        self.record_selection(tcb);
+
+        if tcb != self.current_task {
+            self.slice_expired = false;
+            if config::CHECK_STACK_OVERFLOW {
+                crate::kernel::stack::check_stack(self.current_task);
+                crate::kernel::stack::check_stack(tcb);
+            }
+            call_hook(self.hooks.task_switched_out, self.current_task);
+            call_hook(self.hooks.task_switched_in, tcb);
+            #[cfg(not(feature = "tiny"))]
+            Self::measure_activation(self.current_task, tcb);
+            #[cfg(not(feature = "tiny"))]
+            self.charge_runtime();
+
+            if !self.current_task.is_null() {
+                unsafe {
+                    (*self.current_task).ready_since = self.tick_count.load();
+                }
+                if config::USE_PRIORITY_AGING {
+                    self.decay_aging_boost(self.current_task);
+                }
+            }
+        }
         self.current_task = tcb;
     }
 
+    /// Account run time for tasks with a run histogram
+    ///
+    /// The outgoing task's activation ends if it blocked or suspended
+    /// itself; if it was preempted the activation resumes at its next
+    /// switch-in.
+    #[cfg(not(feature = "tiny"))]
+    fn measure_activation(outgoing: *mut TaskControlBlock, incoming: *mut TaskControlBlock) {
+        let now = crate::arch::read_mcycle();
+
+        if let Some(task) = unsafe { outgoing.as_mut() } {
+            if let Some(histogram) = task.run_histogram {
+                task.activation_cycles += now.wrapping_sub(task.switched_in_cycle);
+                if matches!(task.state, TaskState::Blocked | TaskState::Suspended) {
+                    histogram.record(task.activation_cycles);
+                    task.activation_cycles = 0;
+                }
+            }
+        }
+        if let Some(task) = unsafe { incoming.as_mut() } {
+            task.switched_in_cycle = now;
+        }
+    }
+
+    /// Count a selection of `tcb` in the fairness statistics
+    #[cfg(not(feature = "tiny"))]
+    fn record_selection(&mut self, tcb: *mut TaskControlBlock) {
+        if tcb.is_null() {
+            return;
+        }
+        let task = unsafe { &mut *tcb };
+        let stats = &mut self.stats;
+
+        stats.selections[task.priority] += 1;
+        task.sched_count += 1;
+
+        if stats.streak_task == tcb {
+            stats.streak = stats.streak.saturating_add(1);
+        } else {
+            stats.streak_task = tcb;
+            stats.streak = 1;
+        }
+        stats.longest_streak = stats.longest_streak.max(stats.streak);
+        task.longest_streak = task.longest_streak.max(stats.streak);
+    }
+
+    /// Charge the time since the last switch to the outgoing task
+    #[cfg(not(feature = "tiny"))]
+    fn charge_runtime(&mut self) {
+        let now = crate::arch::read_mtime();
+        if let Some(task) = unsafe { self.current_task.as_mut() } {
+            task.runtime += now.wrapping_sub(self.last_switch_mtime);
+        } else if self.runtime_epoch == 0 {
+            // First task switched in: accounting starts now
+            self.runtime_epoch = now;
+        }
+        self.last_switch_mtime = now;
+    }
+
+    /// Run time of `tcb` and the time elapsed since the last reset,
+    /// counting the running task's current slice
+    #[cfg(not(feature = "tiny"))]
+    pub fn get_runtime(&self, tcb: &TaskControlBlock) -> TaskRuntime {
+        let now = crate::arch::read_mtime();
+        let mut runtime = tcb.runtime;
+        if ptr::eq(tcb, self.current_task) {
+            runtime += now.wrapping_sub(self.last_switch_mtime);
+        }
+        TaskRuntime { runtime, elapsed: now.wrapping_sub(self.runtime_epoch) }
+    }
+
+    /// Start a new run time measurement window
+    #[cfg(not(feature = "tiny"))]
+    pub fn reset_runtime(&mut self) {
+        let now = crate::arch::read_mtime();
+        self.runtime_epoch = now;
+        self.last_switch_mtime = now;
+        self.for_each_listed_task(|tcb| unsafe { (*tcb).runtime = 0 });
+    }
+
+    /// Snapshot of the fairness counters
+    #[cfg(not(feature = "tiny"))]
+    pub fn get_stats(&self) -> SchedStats {
+        self.stats
+    }
+
+    /// Zero the fairness counters (per-task ones included)
+    #[cfg(not(feature = "tiny"))]
+    pub fn reset_stats(&mut self) {
+        self.stats = SchedStats::new();
+        self.for_each_task(|tcb| unsafe {
+            (*tcb).sched_count = 0;
+            (*tcb).longest_streak = 0;
+        });
+    }
+
+    /// Install instrumentation hooks
+    pub fn set_hooks(&mut self, hooks: SchedulerHooks) {
+        self.hooks = hooks;
+    }
+
+    /// Get installed instrumentation hooks
+    pub fn get_hooks(&self) -> &SchedulerHooks {
+        &self.hooks
+    }
+
     /// Get current tick count
     pub fn get_tick_count(&self) -> TickType {
-        self.tick_count
+        self.tick_count.load()
     }
 
     /// Increment tick count
     ///
-    /// Called by timer interrupt handler (future implementation)
+    /// Called by the timer interrupt handler
     pub fn increment_tick(&mut self) {
-        self.tick_count = self.tick_count.wrapping_add(TickType::new(1));
+        let now = self.tick_count.increment();
+
+        // Counter wrapped: deadlines past the wraparound become current
+        if now.as_u64() == 0 {
+            self.delayed_current = 1 - self.delayed_current;
+        }
+        self.wake_delayed_tasks(now);
+
+        if self.time_slice_due() {
+            self.slice_expired = true;
+        }
+
+        if config::USE_PRIORITY_AGING && now.as_u64().is_multiple_of(config::AGING_SCAN_INTERVAL_TICKS) {
+            self.age_ready_tasks();
+        }
     }
 
     /// Check if scheduler is running
@@ -305,7 +972,7 @@ impl Scheduler {
 
     /// Mark scheduler as running
     ///
-    /// Called when scheduler starts (future implementation)
+    /// Called by `start_scheduler`
     pub fn set_running(&mut self, running: bool) {
         self.scheduler_running = running;
     }
@@ -338,6 +1005,42 @@ impl Scheduler {
         self.top_ready_priority
     }
 
+    /// Visit every task in the ready lists, highest priority first
+    pub fn for_each_task<F: FnMut(*mut TaskControlBlock)>(&self, mut f: F) {
+        for priority in (0..config::MAX_PRIORITIES).rev() {
+            self.ready_lists[priority].for_each(|node| {
+                let tcb = node.get_owner::<TaskControlBlock>();
+                if !tcb.is_null() {
+                    f(tcb);
+                }
+            });
+        }
+    }
+
+    /// Visit every task in a ready, delayed or suspended list
+    ///
+    /// Tasks blocked without a timeout are only on their object's waiter
+    /// list and are not visited.
+    pub fn for_each_listed_task<F: FnMut(*mut TaskControlBlock)>(&self, mut f: F) {
+        self.for_each_task(&mut f);
+        for list in self.delayed_lists.iter().chain(core::iter::once(&self.suspended_list)) {
+            list.for_each(|node| {
+                let tcb = node.get_owner::<TaskControlBlock>();
+                if !tcb.is_null() {
+                    f(tcb);
+                }
+            });
+        }
+    }
+
+    /// Verify every ready list, returning the first damaged priority
+    pub fn check_ready_lists(&self) -> core::result::Result<(), (Priority, ListCorruption)> {
+        for (priority, list) in self.ready_lists.iter().enumerate() {
+            list.check_invariants().map_err(|e| (priority, e))?;
+        }
+        Ok(())
+    }
+
     /// Debug: Check if a specific ready list is empty
     pub fn is_ready_list_empty(&self, priority: Priority) -> bool {
         if priority < config::MAX_PRIORITIES {
@@ -393,6 +1096,41 @@ pub fn init_scheduler() {
     }
 }
 
+/// Start multitasking (never returns)
+///
+/// Creates the idle task if it does not exist yet, starts the tick timer,
+/// marks the scheduler running and switches to the highest-priority ready
+/// task. Call once at the end of `main`, after `boot::driver_init` and
+/// `boot::spawn_init_task`.
+///
+/// # Example
+/// ```
+/// kernel::boot::early_init(dtb);
+/// kernel::boot::driver_init();
+/// kernel::boot::spawn_init_task();
+/// kernel::start_scheduler();
+/// ```
+pub fn start_scheduler() -> ! {
+    crate::arch::disable_interrupts();
+
+    if crate::kernel::idle::idle_task_handle().is_null() {
+        if let Err(e) = crate::kernel::idle::spawn_idle_task() {
+            panic!("cannot create idle task: {}", e);
+        }
+    }
+
+    unsafe {
+        // Never null: the idle task is always ready
+        let first = GLOBAL_SCHEDULER.select_highest_priority_task();
+        GLOBAL_SCHEDULER.set_current_task(first);
+        GLOBAL_SCHEDULER.set_running(true);
+
+        // Tick interrupts start as soon as the first task enables MIE
+        crate::arch::timer_init();
+        crate::arch::start_first_task(first)
+    }
+}
+
 /// Add a task to the scheduler
 ///
 /// The task will be added to the ready list for its priority
@@ -411,6 +1149,7 @@ pub fn add_task_to_scheduler(tcb: &mut TaskControlBlock) {
     unsafe {
         GLOBAL_SCHEDULER.add_task_to_ready_list(tcb);
         GLOBAL_SCHEDULER.increment_task_count();
+        call_hook(GLOBAL_SCHEDULER.get_hooks().task_created, tcb);
     }
 }
 
@@ -425,17 +1164,300 @@ pub fn remove_task_from_scheduler(tcb: &mut TaskControlBlock) -> bool {
         let removed = GLOBAL_SCHEDULER.remove_task_from_ready_list(tcb);
         if removed {
             GLOBAL_SCHEDULER.decrement_task_count();
+            call_hook(GLOBAL_SCHEDULER.get_hooks().task_deleted, tcb);
         }
         removed
     }
 }
 
-/// Yield the current task
+/// Take a task off all scheduler lists without deleting it
 ///
-/// Moves current task to end of its ready list
-/// Allows other tasks at same priority to run
+/// The task keeps its place in the task count; put it back with
+/// `make_task_ready`.
+pub fn detach_task(tcb: &mut TaskControlBlock) {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.detach_task(tcb); }
+    }
+}
+
+/// Delete a task wherever it is (ready, blocked or running)
 ///
-/// # Example
+/// The task is taken off all lists and marked Deleted. Its TCB and stack
+/// are not reclaimed; the caller owns them. The stack of a task other
+/// than the caller is poisoned (config::POISON_FREED_MEMORY); call
+/// `poison_reclaim` on it before reuse.
+pub fn delete_task(tcb: &mut TaskControlBlock) {
+    crate::critical_section! {
+        unsafe {
+            GLOBAL_SCHEDULER.detach_task(tcb);
+            tcb.state = TaskState::Deleted;
+            GLOBAL_SCHEDULER.decrement_task_count();
+            call_hook(GLOBAL_SCHEDULER.get_hooks().task_deleted, tcb);
+            crate::kernel::shm::shm_detach_task(tcb);
+
+            if tcb as *mut TaskControlBlock != get_current_task() {
+                let (low, high) = tcb.stack_bounds();
+                crate::kernel::poison::poison_free(low, high - low, tcb.name_str());
+            }
+        }
+    }
+}
+
+/// Put an existing (detached or blocked) task back in its ready list
+pub fn make_task_ready(tcb: &mut TaskControlBlock) {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.add_task_to_ready_list(tcb); }
+    }
+}
+
+/// Block the running task on a waiter list and switch to the next task
+///
+/// Returns once the task has been woken (`wake_first_waiter`) and switched
+/// back in. Fails with `InvalidParameter` outside task context, with
+/// `ResourceBusy` if no other task could run, in which case the task is
+/// left ready, and with `ObjectDeleted` if the object was deleted while
+/// the task waited; the caller must not touch the object then.
+pub fn block_current_task(event_list: &mut List) -> Result<()> {
+    crate::critical_section! {
+        let current = get_current_task();
+        if current.is_null() || crate::arch::in_interrupt() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+
+        unsafe {
+            let task = &mut *current;
+            GLOBAL_SCHEDULER.block_task(task, event_list);
+
+            if !switch_from_blocked(current) {
+                event_list.remove(&mut task.event_list_item);
+                GLOBAL_SCHEDULER.add_task_to_ready_list(task);
+                task.state = TaskState::Running;
+                return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+            }
+            take_wait_aborted(task)
+        }
+    }
+}
+
+/// Block the running task on a waiter list until woken or `deadline`
+///
+/// Like `block_current_task`, but also returns once the tick count reaches
+/// `deadline`. Callers re-check their condition after it returns either
+/// way. Fails with `Timeout`, without blocking, if the deadline has
+/// already passed.
+pub fn block_current_task_until(event_list: &mut List, deadline: TickType) -> Result<()> {
+    crate::critical_section! {
+        let current = get_current_task();
+        if current.is_null() || crate::arch::in_interrupt() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+        if get_tick_count().has_reached(deadline) {
+            return Err(ErrorKind::Timeout.on(ObjectKind::Task));
+        }
+
+        unsafe {
+            let task = &mut *current;
+            GLOBAL_SCHEDULER.block_task_until(task, event_list, deadline);
+
+            if !switch_from_blocked(current) {
+                event_list.remove(&mut task.event_list_item);
+                GLOBAL_SCHEDULER.undelay_task(task);
+                GLOBAL_SCHEDULER.add_task_to_ready_list(task);
+                task.state = TaskState::Running;
+                return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+            }
+            take_wait_aborted(task)
+        }
+    }
+}
+
+/// Fail a finished wait whose object was deleted meanwhile
+fn take_wait_aborted(task: &mut TaskControlBlock) -> Result<()> {
+    if core::mem::take(&mut task.wait_aborted) {
+        return Err(ErrorKind::ObjectDeleted.on(ObjectKind::Task));
+    }
+    Ok(())
+}
+
+/// Let a higher-priority task that was just made ready run
+///
+/// From a task the switch happens at once; from an interrupt handler it
+/// happens when the handler returns. Does nothing if the running task
+/// still has the highest priority.
+pub fn reschedule() {
+    if crate::arch::in_interrupt() {
+        crate::arch::trap::request_reschedule();
+        return;
+    }
+
+    crate::critical_section! {
+        let current = get_current_task();
+        let next = unsafe { GLOBAL_SCHEDULER.preempt_check() };
+        if !next.is_null() && next != current {
+            unsafe { crate::arch::switch_context(current, next) };
+        }
+    }
+}
+
+/// Switch away from the running task after it left the ready lists
+///
+/// Returns false, without switching, if no other task can run.
+///
+/// # Safety
+/// Must be called inside a critical section with `current` the running task
+unsafe fn switch_from_blocked(current: *mut TaskControlBlock) -> bool {
+    let next = GLOBAL_SCHEDULER.select_highest_priority_task();
+    if next.is_null() || next == current {
+        return false;
+    }
+    crate::arch::switch_context(current, next);
+    true
+}
+
+/// Block the running task until the tick count reaches `wake`
+fn delay_current_task(wake: TickType) -> Result<()> {
+    crate::critical_section! {
+        let current = get_current_task();
+        if current.is_null() || crate::arch::in_interrupt() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+
+        unsafe {
+            let task = &mut *current;
+            if !GLOBAL_SCHEDULER.delay_task(task, wake) {
+                return Ok(());
+            }
+            if !switch_from_blocked(current) {
+                GLOBAL_SCHEDULER.undelay_task(task);
+                GLOBAL_SCHEDULER.add_task_to_ready_list(task);
+                task.state = TaskState::Running;
+                return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Block the calling task for `ticks` ticks
+///
+/// The task becomes ready on the `ticks`-th tick from now, so the actual
+/// sleep is between `ticks - 1` and `ticks` tick periods. A delay of 0
+/// returns at once. Fails with `InvalidParameter` outside task context
+/// and with `ResourceBusy` if no other task could run.
+///
+/// # Example
+/// ```
+/// loop {
+///     blink_led();
+///     task_delay(TickType::from_ms(500))?;
+/// }
+/// ```
+pub fn task_delay(ticks: TickType) -> Result<()> {
+    if ticks == TickType::zero() {
+        return Ok(());
+    }
+    delay_current_task(get_tick_count().wrapping_add(ticks))
+}
+
+/// Block the calling task until `period` ticks after `wake_time`
+///
+/// `wake_time` is advanced by `period` on every call, so a loop calling
+/// this runs at a fixed rate regardless of how long each iteration takes.
+/// If the deadline has already passed (the loop overran) it returns at
+/// once without blocking.
+///
+/// # Example
+/// ```
+/// let mut wake = get_tick_count();
+/// loop {
+///     task_delay_until(&mut wake, TickType::from_hz(100))?;
+///     control_loop_step();
+/// }
+/// ```
+pub fn task_delay_until(wake_time: &mut TickType, period: TickType) -> Result<()> {
+    *wake_time = wake_time.wrapping_add(period);
+    delay_current_task(*wake_time)
+}
+
+/// Number of tasks currently sleeping in `task_delay` / `task_delay_until`
+pub fn delayed_task_count() -> usize {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.delayed_task_count() }
+    }
+}
+
+/// Take a task out of scheduling until `resume_task`
+///
+/// Works on the calling task (which switches away at once) and on any
+/// other ready, blocked or delayed task. A blocked task gives up its
+/// wait: `task_delay` returns early after resume, and blocking calls
+/// that re-check their condition simply wait again. Suspending an
+/// already suspended task does nothing.
+///
+/// Fails with `InvalidParameter` for a deleted task, the idle task, or
+/// the interrupted task when called from an interrupt handler.
+pub fn suspend_task(tcb: &mut TaskControlBlock) -> Result<()> {
+    let target = tcb as *mut TaskControlBlock;
+    crate::critical_section! {
+        if tcb.state == TaskState::Suspended {
+            return Ok(());
+        }
+        let current = get_current_task();
+        if tcb.state == TaskState::Deleted
+            || target == crate::kernel::idle::idle_task_handle()
+            || (target == current && crate::arch::in_interrupt())
+        {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+
+        unsafe {
+            GLOBAL_SCHEDULER.suspend_task(tcb);
+            if target == current && !switch_from_blocked(current) {
+                GLOBAL_SCHEDULER.resume_task(tcb);
+                tcb.state = TaskState::Running;
+                return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Make a suspended task ready again
+///
+/// If it has a higher priority than the caller it runs at once. Fails
+/// with `InvalidParameter` if the task is not suspended.
+pub fn resume_task(tcb: &mut TaskControlBlock) -> Result<()> {
+    let resumed = crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.resume_task(tcb) }
+    };
+    if !resumed {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+    }
+    reschedule();
+    Ok(())
+}
+
+/// Make a suspended task ready from an interrupt handler
+///
+/// A resumed task of higher priority than the interrupted one runs when
+/// the handler returns.
+pub fn resume_task_from_isr(tcb: &mut TaskControlBlock) -> Result<()> {
+    resume_task(tcb)
+}
+
+/// Number of suspended tasks
+pub fn suspended_task_count() -> usize {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.suspended_task_count() }
+    }
+}
+
+/// Yield the current task
+///
+/// Moves current task to end of its ready list
+/// Allows other tasks at same priority to run
+///
+/// # Example
 /// ```
 /// // In a task:
 /// loop {
@@ -491,22 +1513,236 @@ pub fn select_next_different_task() -> *mut TaskControlBlock {
     }
 }
 
+/// Select a specific task to run next (directed yield)
+///
+/// Returns `target` if it is ready at the caller's priority, otherwise the
+/// next task in round-robin order. The caller performs the switch.
+///
+/// # Example
+/// ```
+/// // Producer hands off straight to its consumer
+/// let current = get_current_task();
+/// let next = yield_to(consumer_tcb);
+/// if next != current {
+///     switch_context(current, next);
+/// }
+/// ```
+pub fn yield_to(target: *mut TaskControlBlock) -> *mut TaskControlBlock {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.yield_to(target) }
+    }
+}
+
+/// Run a closure with the current task's priority temporarily raised
+///
+/// The effective priority becomes at least `priority` for the duration
+/// of `f`; the base priority (used by priority inheritance) is untouched.
+/// Afterwards the previous priority is restored unless something else
+/// (e.g. inheritance) changed it in the meantime. Meant for short
+/// latency-critical sections, not as a substitute for a high-priority task.
+///
+/// # Example
+/// ```
+/// let sample = with_raised_priority(10, || read_adc_burst())?;
+/// ```
+pub fn with_raised_priority<R, F: FnOnce() -> R>(priority: Priority, f: F) -> Result<R> {
+    if priority >= config::MAX_PRIORITIES {
+        return Err(ErrorKind::InvalidPriority.on(ObjectKind::Task));
+    }
+    let current = get_current_task();
+    if current.is_null() {
+        return Ok(f());
+    }
+
+    let (previous, base) = crate::critical_section! {
+        unsafe {
+            let task = &mut *current;
+            let previous = task.priority;
+            if priority > previous {
+                GLOBAL_SCHEDULER.move_task_to_priority(task, priority);
+            }
+            (previous, task.base_priority)
+        }
+    };
+
+    let result = f();
+
+    crate::critical_section! {
+        unsafe {
+            let task = &mut *current;
+            if priority > previous && task.priority == priority {
+                // set_task_priority during `f` only changed the base
+                let restored = if task.base_priority == base { previous } else { task.base_priority };
+                GLOBAL_SCHEDULER.move_task_to_priority(task, restored);
+            }
+        }
+    }
+
+    // A task readied during `f` may now outrank the restored priority
+    reschedule();
+    Ok(result)
+}
+
+/// Change a task's base priority at run time
+///
+/// The task moves to the new priority's ready list (or is re-sorted
+/// among the waiters of whatever it is blocked on). While it runs at an
+/// inherited or temporarily raised priority above its base, only the
+/// base changes unless the new priority is higher still; the task drops
+/// to the new base when the boost ends. Any aging boost is discarded.
+///
+/// If a task with a higher priority than the caller becomes ready as a
+/// result, it runs at once (from an interrupt handler: when the handler
+/// returns). Fails with `InvalidPriority` for an out-of-range priority
+/// and `InvalidParameter` for a deleted task or the idle task.
+///
+/// # Example
+/// ```
+/// // Logging is falling behind: let it compete with the sensor tasks
+/// set_task_priority(logger_tcb, 6)?;
+/// ```
+pub fn set_task_priority(tcb: &mut TaskControlBlock, new_priority: Priority) -> Result<()> {
+    if new_priority >= config::MAX_PRIORITIES {
+        return Err(ErrorKind::InvalidPriority.on(ObjectKind::Task));
+    }
+    let target = tcb as *mut TaskControlBlock;
+
+    crate::critical_section! {
+        if tcb.state == TaskState::Deleted || target == crate::kernel::idle::idle_task_handle() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+
+        let effective = tcb.priority - tcb.aging_boost;
+        let inherited = effective > tcb.base_priority;
+        tcb.base_priority = new_priority;
+        tcb.aging_boost = 0;
+        let priority = if inherited { effective.max(new_priority) } else { new_priority };
+        unsafe { GLOBAL_SCHEDULER.move_task_to_priority(tcb, priority) };
+    }
+
+    reschedule();
+    Ok(())
+}
+
+/// Set a task's scheduling policy (FIFO or round-robin)
+///
+/// Takes effect at the next time-slice decision
+pub fn set_task_policy(tcb: &mut TaskControlBlock, policy: SchedPolicy) {
+    crate::critical_section! {
+        tcb.policy = policy;
+    }
+}
+
+/// Set attribute flags on a task (TASK_FLAG_*)
+///
+/// Returns InvalidParameter if undefined bits are given
+pub fn set_task_flags(tcb: &mut TaskControlBlock, flags: u32) -> Result<()> {
+    if flags & !TASK_FLAGS_ALL != 0 {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+    }
+    crate::critical_section! {
+        tcb.flags |= flags;
+    }
+    Ok(())
+}
+
+/// Clear attribute flags on a task
+pub fn clear_task_flags(tcb: &mut TaskControlBlock, flags: u32) {
+    crate::critical_section! {
+        tcb.flags &= !flags;
+    }
+}
+
+/// Rename a task
+///
+/// Lets generic worker tasks label themselves by their current role
+/// (e.g. "worker:tls-hs"). Names are truncated to MAX_TASK_NAME_LEN - 1 bytes.
+pub fn set_task_name(tcb: &mut TaskControlBlock, name: &str) {
+    crate::critical_section! {
+        tcb.set_name(name);
+    }
+}
+
+/// Rename the calling task
+///
+/// Returns TaskNotFound if called before the scheduler has a current task
+pub fn set_current_task_name(name: &str) -> Result<()> {
+    let current = get_current_task();
+    if current.is_null() {
+        return Err(ErrorKind::TaskNotFound.on(ObjectKind::Task));
+    }
+    set_task_name(unsafe { &mut *current }, name);
+    Ok(())
+}
+
+/// Get a task's attribute flags
+pub fn get_task_flags(tcb: &TaskControlBlock) -> u32 {
+    tcb.flags
+}
+
+/// Get a task's scheduling policy
+pub fn get_task_policy(tcb: &TaskControlBlock) -> SchedPolicy {
+    tcb.policy
+}
+
+/// Add a task to a blocking object's waiter list in wake order
+///
+/// Blocking primitives (queues, semaphores, ...) should use this rather
+/// than inserting into their lists directly, so wake order is consistent.
+pub fn place_on_event_list(event_list: &mut List, tcb: &mut TaskControlBlock) {
+    unsafe {
+        GLOBAL_SCHEDULER.place_on_event_list(event_list, tcb);
+    }
+}
+
+/// Wake the highest-priority (or oldest, with EVENT_LIST_FIFO) waiter
+///
+/// Returns the woken task, or null if the list was empty
+pub fn wake_first_waiter(event_list: &mut List) -> *mut TaskControlBlock {
+    unsafe { GLOBAL_SCHEDULER.wake_first_waiter(event_list) }
+}
+
+/// Wake all waiters of an object being deleted, failing their waits
+///
+/// Blocking primitives call this when they are dropped, so no task stays
+/// blocked on freed memory. The woken tasks run at the next reschedule.
+/// Returns the number of tasks woken.
+pub fn wake_all_waiters_deleted(event_list: &mut List) -> usize {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.wake_all_waiters_deleted(event_list) }
+    }
+}
+
 /// Get current system tick count
 ///
-/// Returns the number of timer ticks since scheduler started
+/// Returns the number of timer ticks since scheduler started.
+/// Safe from any context, including interrupt handlers.
 pub fn get_tick_count() -> TickType {
     unsafe { GLOBAL_SCHEDULER.get_tick_count() }
 }
 
 /// Increment system tick count
 ///
-/// Called by timer interrupt handler (future implementation)
+/// Called by the timer interrupt handler (arch::machine_timer_interrupt)
 pub fn increment_tick() {
     unsafe {
         GLOBAL_SCHEDULER.increment_tick();
     }
 }
 
+/// Decide at a tick whether the running task is preempted
+///
+/// Called by the tick interrupt once it is back at task level. A
+/// higher-priority ready task preempts; with config::USE_TIME_SLICING a
+/// round-robin task also gives way to the next ready task of its priority
+/// at every tick. Returns the task to switch to (the caller performs the
+/// switch), or null.
+pub fn preempt_check() -> *mut TaskControlBlock {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.preempt_check() }
+    }
+}
+
 /// Get total number of tasks in system
 pub fn get_task_count() -> usize {
     unsafe { GLOBAL_SCHEDULER.get_task_count() }
@@ -565,7 +1801,234 @@ pub fn debug_is_ready_list_empty(priority: Priority) -> bool {
     unsafe { GLOBAL_SCHEDULER.is_ready_list_empty(priority) }
 }
 
+/// Debug: Verify the structure of all ready lists
+///
+/// Returns the first corrupted priority and what was wrong with it
+pub fn debug_check_ready_lists() -> core::result::Result<(), (Priority, ListCorruption)> {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.check_ready_lists() }
+    }
+}
+
 /// Debug: Get the address of a specific ready list
 pub fn debug_get_ready_list_address(priority: Priority) -> usize {
     unsafe { GLOBAL_SCHEDULER.get_ready_list_address(priority) }
 }
+
+/// Visit every task known to the scheduler
+///
+/// Used by diagnostics (core dump, introspection)
+pub fn for_each_task<F: FnMut(*mut TaskControlBlock)>(f: F) {
+    unsafe { GLOBAL_SCHEDULER.for_each_task(f) }
+}
+
+/// Install scheduler instrumentation hooks
+///
+/// May be called before or after `init_scheduler()`; hooks survive init.
+///
+/// # Example
+/// ```
+/// fn on_switch_in(tcb: *mut TaskControlBlock) { /* record event */ }
+///
+/// set_scheduler_hooks(SchedulerHooks {
+///     task_switched_in: Some(on_switch_in),
+///     ..SchedulerHooks::none()
+/// });
+/// ```
+pub fn set_scheduler_hooks(hooks: SchedulerHooks) {
+    unsafe {
+        GLOBAL_SCHEDULER.set_hooks(hooks);
+    }
+}
+
+/// Override the scheduler's pick-next decision
+///
+/// Pass None to restore the built-in policy. The hook runs in scheduler
+/// context on every task selection, so keep it short.
+///
+/// # Example
+/// ```
+/// // Always run the oldest ready task at the top priority
+/// fn pick(view: &ReadyView, _current: *mut TaskControlBlock) -> *mut TaskControlBlock {
+///     let top = 63 - view.bitmap().leading_zeros() as usize;
+///     view.head_at(top)
+/// }
+/// set_pick_next_hook(Some(pick));
+/// ```
+pub fn set_pick_next_hook(hook: Option<PickNextHook>) {
+    crate::critical_section! {
+        unsafe {
+            GLOBAL_SCHEDULER.set_pick_next_hook(hook);
+        }
+    }
+}
+
+/// Get scheduling fairness statistics
+///
+/// Per-priority selection counts and the longest consecutive-run streak.
+/// Per-task counts are in each TCB (`sched_count`, `longest_streak`), so
+/// equal-priority tasks can be compared to check round-robin fairness.
+///
+/// # Example
+/// ```
+/// let stats = sched_stats();
+/// for_each_task(|tcb| unsafe {
+///     let share = (*tcb).sched_count * 100 / stats.selections[(*tcb).priority].max(1);
+/// });
+/// ```
+#[cfg(not(feature = "tiny"))]
+pub fn sched_stats() -> SchedStats {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.get_stats() }
+    }
+}
+
+/// Zero all scheduling fairness statistics
+#[cfg(not(feature = "tiny"))]
+pub fn reset_sched_stats() {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.reset_stats(); }
+    }
+}
+
+/// Measure a task's activation run lengths into `histogram`
+///
+/// `None` stops measuring. The activation in progress when a histogram
+/// is attached is counted from the task's next switch-in.
+#[cfg(not(feature = "tiny"))]
+pub fn set_task_run_histogram(tcb: &mut TaskControlBlock, histogram: Option<&'static RunHistogram>) {
+    crate::critical_section! {
+        tcb.run_histogram = histogram;
+        tcb.activation_cycles = 0;
+        tcb.switched_in_cycle = crate::arch::read_mcycle();
+    }
+}
+
+/// The run histogram attached to a task, if any
+#[cfg(not(feature = "tiny"))]
+pub fn task_run_histogram(tcb: &TaskControlBlock) -> Option<&'static RunHistogram> {
+    tcb.run_histogram
+}
+
+/// Report the CPU time of each task
+///
+/// Calls `f` with every task in a ready, delayed or suspended list and
+/// its run time since boot or the last `reset_task_runtime_stats`. Run
+/// time is measured with mtime at each context switch, so time spent in
+/// interrupt handlers is charged to the interrupted task. Tasks blocked
+/// without a timeout are not visited; their time is reported once they
+/// are ready again.
+///
+/// # Example
+/// ```
+/// task_runtime_stats(|tcb, rt| {
+///     log!("{:<16} {:>10}us {:>3}.{}%", tcb.name_str(), rt.runtime_us(),
+///          rt.cpu_permille() / 10, rt.cpu_permille() % 10);
+/// });
+/// ```
+#[cfg(not(feature = "tiny"))]
+pub fn task_runtime_stats<F: FnMut(&TaskControlBlock, TaskRuntime)>(mut f: F) {
+    crate::critical_section! {
+        unsafe {
+            GLOBAL_SCHEDULER.for_each_listed_task(|tcb| {
+                let task = &*tcb;
+                f(task, GLOBAL_SCHEDULER.get_runtime(task));
+            });
+        }
+    }
+}
+
+/// Zero all task run times and start measuring from now
+#[cfg(not(feature = "tiny"))]
+pub fn reset_task_runtime_stats() {
+    crate::critical_section! {
+        unsafe { GLOBAL_SCHEDULER.reset_runtime(); }
+    }
+}
+
+/// Get the ready-priority bitmap (bit N = a task is ready at priority N)
+pub fn get_ready_bitmap() -> u64 {
+    unsafe { GLOBAL_SCHEDULER.get_ready_bitmap() }
+}
+
+// ============================================================================
+// PROOFS (cargo kani --features verification)
+// ============================================================================
+
+#[cfg(all(kani, feature = "verification"))]
+mod proofs {
+    use super::*;
+
+    const TASKS: usize = 3;
+
+    /// top_ready_priority is the highest non-empty ready list (idle if all
+    /// are empty) and the bitmap mirrors which lists are non-empty
+    fn assert_ready_state(sched: &Scheduler) {
+        let highest = (0..config::MAX_PRIORITIES).rev().find(|&p| !sched.ready_lists[p].is_empty());
+        assert_eq!(sched.top_ready_priority, highest.unwrap_or(config::IDLE_PRIORITY));
+        for priority in 0..config::MAX_PRIORITIES {
+            let bit = sched.ready_bitmap & (1 << priority) != 0;
+            assert_eq!(bit, !sched.ready_lists[priority].is_empty());
+        }
+    }
+
+    /// Arbitrary adds, removes and priority changes keep the ready state
+    /// consistent
+    #[kani::proof]
+    #[kani::unwind(34)]
+    fn top_ready_priority_covers_non_empty_list() {
+        let mut sched = Scheduler::new();
+        sched.init();
+
+        let mut tcbs: [TaskControlBlock; TASKS] = core::array::from_fn(|_| {
+            let priority: Priority = kani::any();
+            kani::assume(priority < config::MAX_PRIORITIES);
+            TaskControlBlock::new("t", priority, ptr::null_mut(), config::MIN_STACK_SIZE)
+        });
+        for tcb in tcbs.iter_mut() {
+            unsafe { tcb.update_list_item_owners() };
+            sched.add_task_to_ready_list(tcb);
+            assert_ready_state(&sched);
+        }
+
+        for _ in 0..TASKS {
+            let index: usize = kani::any();
+            kani::assume(index < TASKS);
+            let tcb = &mut tcbs[index];
+            match kani::any::<u8>() % 3 {
+                0 => {
+                    sched.remove_task_from_ready_list(tcb);
+                }
+                1 => {
+                    if !tcb.state_list_item.is_in_list() {
+                        sched.add_task_to_ready_list(tcb);
+                    }
+                }
+                _ => {
+                    let priority: Priority = kani::any();
+                    kani::assume(priority < config::MAX_PRIORITIES);
+                    sched.move_task_to_priority(tcb, priority);
+                }
+            }
+            assert_ready_state(&sched);
+        }
+    }
+
+    /// Selection never returns a task that is not in a ready list
+    #[kani::proof]
+    #[kani::unwind(34)]
+    fn selection_returns_ready_task() {
+        let mut sched = Scheduler::new();
+        sched.init();
+
+        let priority: Priority = kani::any();
+        kani::assume(priority < config::MAX_PRIORITIES);
+        let mut tcb = TaskControlBlock::new("t", priority, ptr::null_mut(), config::MIN_STACK_SIZE);
+        unsafe { tcb.update_list_item_owners() };
+        sched.add_task_to_ready_list(&mut tcb);
+
+        let chosen = sched.select_highest_priority_task();
+        assert!(sched.is_in_ready_list(chosen));
+        assert_eq!(chosen, &mut tcb as *mut TaskControlBlock);
+    }
+}
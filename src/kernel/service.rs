@@ -0,0 +1,139 @@
+// Service registry and startup barriers
+//
+// Tasks that need a subsystem ("net", "fs", ...) wait for it by name
+// instead of sleeping for a guessed amount of time; the task providing it
+// calls `service_ready` once it is usable, which releases every waiter.
+// Either side may come first: a name is added to the registry the first
+// time it is waited on or signalled.
+
+use crate::kernel::list::List;
+use crate::kernel::scheduler::{block_current_task, wake_first_waiter};
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+
+/// Longest service name (bytes)
+pub const MAX_SERVICE_NAME_LEN: usize = 16;
+
+struct Service {
+    name: [u8; MAX_SERVICE_NAME_LEN],
+    name_len: usize,
+    ready: bool,
+    /// Tasks blocked until the service is ready
+    waiters: List,
+}
+
+impl Service {
+    const fn empty() -> Self {
+        Service {
+            name: [0; MAX_SERVICE_NAME_LEN],
+            name_len: 0,
+            ready: false,
+            waiters: List::new(),
+        }
+    }
+
+    fn in_use(&self) -> bool {
+        self.name_len > 0
+    }
+
+    fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("<invalid>")
+    }
+}
+
+static mut SERVICES: [Service; config::MAX_SERVICES] = [const { Service::empty() }; config::MAX_SERVICES];
+
+/// Find a service by name, adding it (not ready) if it is new
+///
+/// Must be called inside a critical section.
+fn lookup_or_add(name: &str) -> Result<&'static mut Service> {
+    if name.is_empty() || name.len() > MAX_SERVICE_NAME_LEN {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Service));
+    }
+
+    let services = unsafe { &mut *core::ptr::addr_of_mut!(SERVICES) };
+    if let Some(index) = services.iter().position(|s| s.in_use() && s.name() == name) {
+        return Ok(&mut services[index]);
+    }
+
+    let service = services
+        .iter_mut()
+        .find(|s| !s.in_use())
+        .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::Service))?;
+    service.name[..name.len()].copy_from_slice(name.as_bytes());
+    service.name_len = name.len();
+    service.ready = false;
+    service.waiters.init();
+    Ok(service)
+}
+
+/// Mark a service ready and release every task waiting for it
+///
+/// Fails with `InvalidParameter` for an empty or too long name and with
+/// `OutOfMemory` if the registry is full.
+pub fn service_ready(name: &str) -> Result<()> {
+    crate::critical_section! {
+        let service = lookup_or_add(name)?;
+        service.ready = true;
+        while !wake_first_waiter(&mut service.waiters).is_null() {}
+        Ok(())
+    }
+}
+
+/// Mark a service not ready (e.g. while it restarts)
+///
+/// Later calls to `wait_for_service` block again until `service_ready`.
+pub fn service_down(name: &str) -> Result<()> {
+    crate::critical_section! {
+        lookup_or_add(name)?.ready = false;
+        Ok(())
+    }
+}
+
+/// Check a service without waiting
+pub fn service_is_ready(name: &str) -> bool {
+    let services = unsafe { &*core::ptr::addr_of!(SERVICES) };
+    services.iter().any(|s| s.in_use() && s.name() == name && s.ready)
+}
+
+/// Block the calling task until a service is ready
+///
+/// Returns immediately if it already is. Must be called from a task.
+pub fn wait_for_service(name: &str) -> Result<()> {
+    crate::critical_section! {
+        let service = lookup_or_add(name)?;
+        while !service.ready {
+            block_current_task(&mut service.waiters)?;
+        }
+        Ok(())
+    }
+}
+
+/// Block the calling task until all of the given services are ready
+///
+/// # Example
+/// ```
+/// extern "C" fn http_task(_arg: *mut c_void) -> ! {
+///     wait_for_services(&["net", "fs"]).unwrap();
+///     // ...
+/// }
+///
+/// extern "C" fn net_task(_arg: *mut c_void) -> ! {
+///     net_init();
+///     service_ready("net").unwrap();
+///     // ...
+/// }
+/// ```
+pub fn wait_for_services(names: &[&str]) -> Result<()> {
+    for name in names {
+        wait_for_service(name)?;
+    }
+    Ok(())
+}
+
+/// Visit every known service with its readiness
+pub fn for_each_service<F: FnMut(&str, bool)>(mut f: F) {
+    let services = unsafe { &*core::ptr::addr_of!(SERVICES) };
+    for service in services.iter().filter(|s| s.in_use()) {
+        f(service.name(), service.ready);
+    }
+}
@@ -0,0 +1,293 @@
+// Named shared-memory regions
+//
+// A region is a statically reserved buffer with a name. Tasks attach to
+// it by name, read-only or read-write, and exchange large data in place
+// instead of copying it through queues (a queue message then only says
+// "frame ready at offset N").
+//
+// Each region has an access policy: a read-only region can be attached
+// read-write only by privileged tasks (TASK_FLAG_PRIVILEGED), e.g. the
+// driver that fills it. Attachments are recorded per task; a region
+// cannot be unregistered while attached, and deleting or restarting a
+// task drops its attachments.
+//
+// Permissions are checked when a task attaches and when it asks for a
+// writable view. They are not enforced by PMP: all tasks run in machine
+// mode, where PMP entries do not apply unless locked.
+
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use core::ptr;
+
+/// How a region is attached
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShmAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// A shared-memory region (declare with `shared_region!`)
+pub struct SharedRegion {
+    name: &'static str,
+    base: *mut u8,
+    len: usize,
+    /// Most an unprivileged task may attach with
+    access: ShmAccess,
+}
+
+// The buffer is only reached through attachments
+unsafe impl Sync for SharedRegion {}
+
+impl SharedRegion {
+    /// # Safety
+    /// `base` (`len` bytes) must be a static used by nothing but this region.
+    pub const unsafe fn new(name: &'static str, base: *mut u8, len: usize, access: ShmAccess) -> Self {
+        SharedRegion { name, base, len, access }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Size in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Access policy for unprivileged tasks
+    pub fn access(&self) -> ShmAccess {
+        self.access
+    }
+}
+
+/// Declare a static shared-memory region
+///
+/// The buffer is 8-byte aligned and zeroed at boot.
+///
+/// # Example
+/// ```
+/// shared_region! {
+///     pub static CAMERA_FRAMES = { name: "frames", size: 64 * 1024, access: ShmAccess::ReadOnly };
+/// }
+///
+/// shm_register(&CAMERA_FRAMES)?;
+/// ```
+#[macro_export]
+macro_rules! shared_region {
+    (
+        $vis:vis static $region:ident = { name: $name:expr, size: $size:expr, access: $access:expr $(,)? };
+    ) => {
+        $vis static $region: $crate::kernel::shm::SharedRegion = {
+            static mut BUFFER: [u64; ($size + 7) / 8] = [0; ($size + 7) / 8];
+            unsafe {
+                $crate::kernel::shm::SharedRegion::new(
+                    $name,
+                    core::ptr::addr_of_mut!(BUFFER) as *mut u8,
+                    $size,
+                    $access,
+                )
+            }
+        };
+    };
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+#[derive(Copy, Clone)]
+struct Attachment {
+    region: &'static SharedRegion,
+    /// Attaching task (null = attached before the scheduler started)
+    task: *mut TaskControlBlock,
+    access: ShmAccess,
+}
+
+static mut REGIONS: [Option<&'static SharedRegion>; config::MAX_SHM_REGIONS] =
+    [None; config::MAX_SHM_REGIONS];
+
+static mut ATTACHMENTS: [Option<Attachment>; config::MAX_SHM_ATTACHMENTS] =
+    [None; config::MAX_SHM_ATTACHMENTS];
+
+/// Make a region available for attaching
+///
+/// Fails with `InvalidParameter` if a region with the same name is
+/// registered and with `OutOfMemory` if the registry is full.
+pub fn shm_register(region: &'static SharedRegion) -> Result<()> {
+    crate::critical_section! {
+        let regions = unsafe { &mut *ptr::addr_of_mut!(REGIONS) };
+
+        if regions.iter().flatten().any(|r| r.name == region.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::ShmRegion));
+        }
+
+        match regions.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(region);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::ShmRegion)),
+        }
+    }
+}
+
+/// Withdraw a region by name
+///
+/// Fails with `NotFound` if no such region is registered and with
+/// `ResourceBusy` while any task is attached.
+pub fn shm_unregister(name: &str) -> Result<()> {
+    crate::critical_section! {
+        let regions = unsafe { &mut *ptr::addr_of_mut!(REGIONS) };
+        let attachments = unsafe { &*ptr::addr_of!(ATTACHMENTS) };
+
+        let slot = regions
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(r) if r.name == name))
+            .ok_or(ErrorKind::NotFound.on(ObjectKind::ShmRegion))?;
+        if attachments.iter().flatten().any(|a| a.region.name == name) {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::ShmRegion));
+        }
+        *slot = None;
+        Ok(())
+    }
+}
+
+/// Attach the calling task to a region
+///
+/// The attachment lasts until the returned mapping is dropped. Fails with
+/// `NotFound` for an unknown region, `InvalidParameter` if the region's
+/// policy forbids `access` for this task and `OutOfMemory` if the
+/// attachment table is full.
+pub fn shm_attach(name: &str, access: ShmAccess) -> Result<ShmMapping> {
+    let task = get_current_task();
+    let privileged = !task.is_null() && unsafe { (*task).is_privileged() };
+
+    crate::critical_section! {
+        let regions = unsafe { &*ptr::addr_of!(REGIONS) };
+        let attachments = unsafe { &mut *ptr::addr_of_mut!(ATTACHMENTS) };
+
+        let region = *regions
+            .iter()
+            .flatten()
+            .find(|r| r.name == name)
+            .ok_or(ErrorKind::NotFound.on(ObjectKind::ShmRegion))?;
+        if access == ShmAccess::ReadWrite && region.access == ShmAccess::ReadOnly && !privileged {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::ShmRegion));
+        }
+
+        let slot = attachments
+            .iter()
+            .position(|a| a.is_none())
+            .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::ShmRegion))?;
+        attachments[slot] = Some(Attachment { region, task, access });
+        Ok(ShmMapping { region, task, slot, access })
+    }
+}
+
+/// Drop every attachment held by a task (on task deletion or restart)
+pub fn shm_detach_task(tcb: *mut TaskControlBlock) {
+    crate::critical_section! {
+        let attachments = unsafe { &mut *ptr::addr_of_mut!(ATTACHMENTS) };
+        for slot in attachments.iter_mut() {
+            if matches!(slot, Some(a) if a.task == tcb) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// Visit every registered region with its number of attachments
+pub fn for_each_shm_region<F: FnMut(&'static SharedRegion, usize)>(mut f: F) {
+    let regions = unsafe { &*ptr::addr_of!(REGIONS) };
+    let attachments = unsafe { &*ptr::addr_of!(ATTACHMENTS) };
+    for region in regions.iter().flatten() {
+        let users = attachments.iter().flatten().filter(|a| ptr::eq(a.region, *region)).count();
+        f(region, users);
+    }
+}
+
+// ============================================================================
+// MAPPINGS
+// ============================================================================
+
+/// A task's attachment to a region; detaches when dropped
+///
+/// Several tasks may be attached at once. The kernel does not order their
+/// accesses: writers and readers agree on ownership of the data (e.g.
+/// by passing offsets through a queue).
+pub struct ShmMapping {
+    region: &'static SharedRegion,
+    task: *mut TaskControlBlock,
+    slot: usize,
+    access: ShmAccess,
+}
+
+impl ShmMapping {
+    pub fn region(&self) -> &'static SharedRegion {
+        self.region
+    }
+
+    pub fn access(&self) -> ShmAccess {
+        self.access
+    }
+
+    pub fn len(&self) -> usize {
+        self.region.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.region.len == 0
+    }
+
+    /// Start of the region
+    pub fn as_ptr(&self) -> *const u8 {
+        self.region.base
+    }
+
+    /// Start of the region for writing
+    ///
+    /// Fails with `InvalidParameter` for a read-only attachment.
+    pub fn as_mut_ptr(&mut self) -> Result<*mut u8> {
+        match self.access {
+            ShmAccess::ReadWrite => Ok(self.region.base),
+            ShmAccess::ReadOnly => Err(ErrorKind::InvalidParameter.on(ObjectKind::ShmRegion)),
+        }
+    }
+
+    /// The region as a byte slice
+    ///
+    /// # Safety
+    /// No other task may write the bytes read while the slice is alive.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        core::slice::from_raw_parts(self.region.base, self.region.len)
+    }
+
+    /// The region as a mutable byte slice
+    ///
+    /// Fails with `InvalidParameter` for a read-only attachment.
+    ///
+    /// # Safety
+    /// No other task may access the bytes written while the slice is alive.
+    pub unsafe fn as_mut_slice(&mut self) -> Result<&mut [u8]> {
+        let base = self.as_mut_ptr()?;
+        Ok(core::slice::from_raw_parts_mut(base, self.region.len))
+    }
+}
+
+impl Drop for ShmMapping {
+    fn drop(&mut self) {
+        crate::critical_section! {
+            let attachments = unsafe { &mut *ptr::addr_of_mut!(ATTACHMENTS) };
+            let slot = &mut attachments[self.slot];
+            // Already gone if the task was restarted
+            if matches!(slot, Some(a) if ptr::eq(a.region, self.region) && a.task == self.task) {
+                *slot = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Task stack overflow detection and high-water marks
+//
+// Every task stack is filled with config::STACK_FILL_BYTE when the task is
+// created (arch::initialize_task_stack). The lowest STACK_CANARY_WORDS
+// words are the canary: a task that has used its whole stack has
+// overwritten them. With config::CHECK_STACK_OVERFLOW the scheduler checks
+// the canary of the task being switched out, and the saved stack pointer
+// of the task being switched in, on every context switch.
+//
+// On an overflow the overflow hook (if any) is called with the task, then
+// the kernel panics: the memory below the stack is already corrupted, so
+// the task cannot safely continue. The hook is the place to record the
+// task or signal the fault before the panic path runs.
+//
+// Detection is after the fact and only as good as the fill: a frame that
+// skips over the canary without writing it goes unnoticed until something
+// below the stack breaks. The high-water mark tells how close each task
+// comes, so stack sizes can be set with a margin.
+
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::config;
+use core::ptr;
+
+/// Called with the offending task when a stack overflow is detected
+pub type StackOverflowHook = fn(&TaskControlBlock);
+
+static mut OVERFLOW_HOOK: Option<StackOverflowHook> = None;
+
+/// A stack word that has never been written since the task was created
+pub const STACK_FILL_WORD: usize =
+    usize::from_ne_bytes([config::STACK_FILL_BYTE; core::mem::size_of::<usize>()]);
+
+/// Install the stack overflow hook, returning the previous one
+///
+/// # Example
+/// ```
+/// fn blame(tcb: &TaskControlBlock) {
+///     persist_fault_reason(tcb.name_str());
+/// }
+/// set_stack_overflow_hook(Some(blame));
+/// ```
+pub fn set_stack_overflow_hook(hook: Option<StackOverflowHook>) -> Option<StackOverflowHook> {
+    crate::critical_section! {
+        unsafe { core::mem::replace(&mut *ptr::addr_of_mut!(OVERFLOW_HOOK), hook) }
+    }
+}
+
+/// Words at the bottom of a task's stack that have never been used
+///
+/// The lower the number, the closer the task has come to overflowing;
+/// 0 means the canary is gone.
+pub fn stack_high_water_mark(tcb: &TaskControlBlock) -> usize {
+    let (low, high) = tcb.stack_bounds();
+    let words = (high - low) / core::mem::size_of::<usize>();
+    let base = low as *const usize;
+    (0..words)
+        .take_while(|&i| unsafe { ptr::read_volatile(base.add(i)) } == STACK_FILL_WORD)
+        .count()
+}
+
+/// True if the task has run past the bottom of its stack
+///
+/// Either the canary is damaged or the saved stack pointer is below the
+/// stack.
+pub fn stack_overflowed(tcb: &TaskControlBlock) -> bool {
+    let (low, _) = tcb.stack_bounds();
+    if (tcb.stack_top as usize) < low {
+        return true;
+    }
+    let base = low as *const usize;
+    (0..config::STACK_CANARY_WORDS)
+        .any(|i| unsafe { ptr::read_volatile(base.add(i)) } != STACK_FILL_WORD)
+}
+
+/// Context-switch check of one task (null is ignored)
+pub(crate) fn check_stack(tcb: *mut TaskControlBlock) {
+    let Some(task) = (unsafe { tcb.as_ref() }) else {
+        return;
+    };
+    if !stack_overflowed(task) {
+        return;
+    }
+
+    if let Some(hook) = unsafe { *ptr::addr_of!(OVERFLOW_HOOK) } {
+        hook(task);
+    }
+    panic!("stack overflow in task '{}' ({} words)", task.name_str(), task.stack_size);
+}
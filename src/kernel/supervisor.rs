@@ -0,0 +1,265 @@
+// Task supervision
+//
+// Tasks registered with a restart policy are restarted from their entry
+// point when they fail instead of the failure halting the system. A
+// restart budget (max restarts per time window) stops a crash-looping
+// task; once it is exhausted the failure escalates as before.
+//
+// Restarting reuses the task's stack: a fresh initial frame is written at
+// the original stack pointer and the task goes back to its ready list at
+// its base priority with its files closed.
+//
+// With config::PANIC_CONTAINMENT, a failed task that is neither restarted
+// nor critical is killed on its own and the rest of the system keeps
+// running.
+//
+// A failure inside an interrupt handler is neither: the interrupted task
+// did not cause it, and the handler's nesting state cannot be abandoned by
+// switching to another task. It always escalates.
+
+use crate::arch::{self, TaskEntry};
+use crate::fs::vfs;
+use crate::kernel::scheduler::{
+    delete_task, detach_task, get_tick_count, make_task_ready, select_next_task, set_current_task,
+};
+use crate::kernel::shm::shm_detach_task;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind, TaskState, TickType};
+use core::ffi::c_void;
+
+/// When a supervised task is restarted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Never restart (supervision only records failures)
+    Never,
+    /// Restart after a panic or fatal exception
+    OnPanic,
+    /// Restart whenever the task ends, including a normal exit
+    Always,
+}
+
+/// How a task ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TaskFailure {
+    Panic,
+    Exception,
+    Exit,
+}
+
+#[derive(Copy, Clone)]
+struct Supervised {
+    tcb: *mut TaskControlBlock,
+    entry: TaskEntry,
+    arg: *mut c_void,
+    policy: RestartPolicy,
+    max_restarts: u32,
+    window: TickType,
+    window_start: TickType,
+    restarts_in_window: u32,
+    total_restarts: u32,
+}
+
+static mut SUPERVISED: [Option<Supervised>; config::MAX_SUPERVISED_TASKS] =
+    [None; config::MAX_SUPERVISED_TASKS];
+
+/// Place a task under supervision
+///
+/// `entry` and `arg` must be the ones the task was created with. At most
+/// `max_restarts` restarts are allowed within any `window`; the next
+/// failure after that escalates.
+///
+/// # Example
+/// ```
+/// // Up to 3 restarts per 10 seconds
+/// supervise(&mut NET_TCB, net_task, ptr::null_mut(),
+///           RestartPolicy::OnPanic, 3, TickType::from_ms(10_000))?;
+/// ```
+pub fn supervise(
+    tcb: &mut TaskControlBlock,
+    entry: TaskEntry,
+    arg: *mut c_void,
+    policy: RestartPolicy,
+    max_restarts: u32,
+    window: TickType,
+) -> Result<()> {
+    let tcb_ptr = tcb as *mut TaskControlBlock;
+    crate::critical_section! {
+        let table = unsafe { &mut *core::ptr::addr_of_mut!(SUPERVISED) };
+        if table.iter().flatten().any(|s| s.tcb == tcb_ptr) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+        let slot = table
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(ErrorKind::OutOfMemory.on(ObjectKind::Task))?;
+        *slot = Some(Supervised {
+            tcb: tcb_ptr,
+            entry,
+            arg,
+            policy,
+            max_restarts,
+            window,
+            window_start: get_tick_count(),
+            restarts_in_window: 0,
+            total_restarts: 0,
+        });
+        Ok(())
+    }
+}
+
+/// Remove a task from supervision
+pub fn unsupervise(tcb: *mut TaskControlBlock) {
+    crate::critical_section! {
+        let table = unsafe { &mut *core::ptr::addr_of_mut!(SUPERVISED) };
+        for slot in table.iter_mut() {
+            if slot.is_some_and(|s| s.tcb == tcb) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// Number of times a supervised task has been restarted
+pub fn restart_count(tcb: *mut TaskControlBlock) -> Option<u32> {
+    let table = unsafe { &*core::ptr::addr_of!(SUPERVISED) };
+    table.iter().flatten().find(|s| s.tcb == tcb).map(|s| s.total_restarts)
+}
+
+/// Decide whether a failure may be handled by a restart, consuming one
+/// unit of the restart budget if so
+fn claim_restart(tcb: *mut TaskControlBlock, failure: TaskFailure) -> Option<Supervised> {
+    let table = unsafe { &mut *core::ptr::addr_of_mut!(SUPERVISED) };
+    let entry = table.iter_mut().flatten().find(|s| s.tcb == tcb)?;
+
+    let allowed = match entry.policy {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnPanic => failure != TaskFailure::Exit,
+        RestartPolicy::Always => true,
+    };
+    if !allowed {
+        return None;
+    }
+
+    let now = get_tick_count();
+    if now.elapsed_since(entry.window_start).as_u64() >= entry.window.as_u64() {
+        entry.window_start = now;
+        entry.restarts_in_window = 0;
+    }
+    if entry.restarts_in_window >= entry.max_restarts {
+        return None;
+    }
+
+    entry.restarts_in_window += 1;
+    entry.total_restarts += 1;
+    Some(*entry)
+}
+
+/// Reset a task to its entry point and make it ready
+unsafe fn reset_task(s: &Supervised) {
+    let task = &mut *s.tcb;
+
+    detach_task(task);
+    vfs::close_all(&mut task.fds);
+    shm_detach_task(task);
+
+    // stack_base is the stack pointer the task was created with
+    task.stack_top = task.stack_base;
+    arch::init_context_frame(task.stack_top, s.entry, s.arg);
+
+    task.priority = task.base_priority;
+    task.aging_boost = 0;
+    task.state = TaskState::Suspended;
+    make_task_ready(task);
+}
+
+/// Restart a supervised task that is not currently running
+///
+/// Does not consume the restart budget. Use for watchdog-style recovery
+/// of a task that is stuck rather than crashed.
+pub fn restart_task(tcb: *mut TaskControlBlock) -> Result<()> {
+    if tcb.is_null() || tcb == crate::kernel::scheduler::get_current_task() {
+        return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+    }
+    let table = unsafe { &*core::ptr::addr_of!(SUPERVISED) };
+    let s = *table
+        .iter()
+        .flatten()
+        .find(|s| s.tcb == tcb)
+        .ok_or(ErrorKind::TaskNotFound.on(ObjectKind::Task))?;
+    crate::critical_section! {
+        unsafe { reset_task(&s) };
+    }
+    Ok(())
+}
+
+/// Abandon the failed current context and run the best ready task
+///
+/// Returns only if no task is ready to run.
+///
+/// # Safety
+/// Must be called from the failed task's own context (panic handler)
+pub(crate) unsafe fn run_next_task() {
+    arch::disable_interrupts();
+    set_current_task(core::ptr::null_mut());
+    let next = select_next_task();
+    if next.is_null() {
+        return;
+    }
+    set_current_task(next);
+    arch::enter_task(next)
+}
+
+/// Handle a fatal failure of the running task
+///
+/// If the task is supervised and its policy and restart budget allow, it
+/// is restarted and this function does not return. Otherwise (and always
+/// in an interrupt handler) it returns and the caller escalates (halt or
+/// reboot).
+pub fn handle_task_failure(tcb: *mut TaskControlBlock, failure: TaskFailure) {
+    if tcb.is_null() || arch::in_interrupt() {
+        return;
+    }
+
+    arch::disable_interrupts();
+    let Some(s) = claim_restart(tcb, failure) else {
+        return;
+    };
+
+    unsafe {
+        reset_task(&s);
+        run_next_task();
+    }
+}
+
+/// Check whether a failed task may be killed on its own
+///
+/// True when config::PANIC_CONTAINMENT is on, the failure is not in an
+/// interrupt handler, the task is not critical and another task is left
+/// to run.
+pub fn can_contain_failure(tcb: *mut TaskControlBlock) -> bool {
+    config::PANIC_CONTAINMENT
+        && !arch::in_interrupt()
+        && !tcb.is_null()
+        && unsafe { !(*tcb).is_critical() }
+        && crate::kernel::scheduler::get_task_count() > 1
+}
+
+/// Kill the failed running task and continue with the others
+///
+/// No unwinding: the task is deleted, its files are closed and the CPU
+/// goes to the next ready task. Returns only if containment does not
+/// apply (see `can_contain_failure`).
+pub fn contain_task_failure(tcb: *mut TaskControlBlock) {
+    if !can_contain_failure(tcb) {
+        return;
+    }
+
+    arch::disable_interrupts();
+    unsupervise(tcb);
+    unsafe {
+        let task = &mut *tcb;
+        delete_task(task);
+        vfs::close_all(&mut task.fds);
+        run_next_task();
+    }
+}
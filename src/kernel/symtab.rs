@@ -0,0 +1,88 @@
+// Embedded kernel symbol table
+//
+// The kernel reserves a .rtos_symtab section that `cargo xtask symtab`
+// fills after linking with the image's own function symbols, so panics,
+// crash dumps and the profiler can print names instead of bare addresses.
+// An image that was not post-processed has an empty table and every
+// lookup fails.
+//
+// Layout (little-endian):
+//   u32 magic ("SYMT"), u32 count,
+//   count x { u64 addr, u32 size, u32 name offset }, sorted by addr,
+//   NUL-terminated names
+
+use crate::kernel::link;
+use crate::kernel::types::config;
+
+const SYMTAB_MAGIC: u32 = 0x544d_5953;
+const HEADER_LEN: usize = 8;
+const ENTRY_LEN: usize = 16;
+
+/// Space reserved for the table; xtask fills it in place
+#[used]
+#[link_section = ".rtos_symtab"]
+static SYMTAB_RESERVE: [u8; config::SYMTAB_SIZE] = [0; config::SYMTAB_SIZE];
+
+/// Table bytes, read through the linker symbols so the compiler cannot
+/// assume the zero initializer
+fn table() -> &'static [u8] {
+    let section = link::symtab();
+    unsafe { core::slice::from_raw_parts(section.start as *const u8, section.len()) }
+}
+
+fn u32_at(data: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([data[off], data[off + 1], data[off + 2], data[off + 3]])
+}
+
+fn u64_at(data: &[u8], off: usize) -> u64 {
+    (u32_at(data, off) as u64) | ((u32_at(data, off + 4) as u64) << 32)
+}
+
+/// Number of symbols in the table (0 if it was never filled)
+pub fn symbol_count() -> usize {
+    let data = table();
+    if data.len() < HEADER_LEN || u32_at(data, 0) != SYMTAB_MAGIC {
+        return 0;
+    }
+    let count = u32_at(data, 4) as usize;
+    if HEADER_LEN + count * ENTRY_LEN > data.len() {
+        return 0;
+    }
+    count
+}
+
+/// Find the function containing `addr`
+///
+/// Returns the function name and the offset of `addr` into it.
+pub fn symbolize(addr: usize) -> Option<(&'static str, usize)> {
+    let data = table();
+    let count = symbol_count();
+    let entry = |i: usize| HEADER_LEN + i * ENTRY_LEN;
+
+    // Last symbol starting at or below addr
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if u64_at(data, entry(mid)) as usize <= addr {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo == 0 {
+        return None;
+    }
+
+    let e = entry(lo - 1);
+    let start = u64_at(data, e) as usize;
+    let size = u32_at(data, e + 8) as usize;
+    if size != 0 && addr >= start + size {
+        return None;
+    }
+
+    let names = &data[HEADER_LEN + count * ENTRY_LEN..];
+    let name = names.get(u32_at(data, e + 12) as usize..)?;
+    let len = name.iter().position(|&b| b == 0)?;
+    let name = core::str::from_utf8(&name[..len]).ok()?;
+    Some((name, addr - start))
+}
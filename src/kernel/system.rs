@@ -0,0 +1,175 @@
+// System shutdown
+//
+// `shutdown` stops the machine in a fixed order instead of cutting power
+// under running tasks:
+//
+//   1. new work is refused (`is_shutting_down`, tasklets fail to queue)
+//   2. every registered cancellation token is cancelled, and the caller
+//      sleeps for SHUTDOWN_GRACE_MS so tasks polling them can wind down
+//   3. open files are synced and the console sinks flushed
+//   4. boot hook shutdown functions run in reverse init order
+//   5. the machine powers off with the given exit code
+//
+// Tasks opt in to cooperative cancellation by registering a token and
+// checking it in their main loop.
+
+use crate::arch;
+use crate::fs::{self, LogLevel};
+use crate::kernel::boot;
+use crate::kernel::scheduler::{get_current_task, task_delay};
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind, TickType};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A cancellation flag a task polls to learn it should stop
+///
+/// # Example
+/// ```
+/// static LOGGER_STOP: CancelToken = CancelToken::new("logger");
+/// register_cancel_token(&LOGGER_STOP).unwrap();
+///
+/// while !LOGGER_STOP.is_cancelled() {
+///     flush_log_batch();
+///     task_delay(TickType::from_ms(10))?;
+/// }
+/// close_log_file();
+/// ```
+pub struct CancelToken {
+    name: &'static str,
+    cancelled: AtomicBool,
+}
+
+impl CancelToken {
+    pub const fn new(name: &'static str) -> Self {
+        CancelToken {
+            name,
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Ask the owner to stop (safe from any context)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Clear the flag so the token can be reused
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut TOKENS: [Option<&'static CancelToken>; config::MAX_CANCEL_TOKENS] =
+    [None; config::MAX_CANCEL_TOKENS];
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Register a token to be cancelled at shutdown
+///
+/// Fails with `InvalidParameter` if a token with the same name is
+/// registered, with `OutOfMemory` if the registry is full and with
+/// `ResourceBusy` once shutdown has begun.
+pub fn register_cancel_token(token: &'static CancelToken) -> Result<()> {
+    if is_shutting_down() {
+        return Err(ErrorKind::ResourceBusy.on(ObjectKind::CancelToken));
+    }
+
+    crate::critical_section! {
+        let tokens = unsafe { &mut *core::ptr::addr_of_mut!(TOKENS) };
+
+        if tokens.iter().flatten().any(|t| t.name == token.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::CancelToken));
+        }
+
+        match tokens.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(token);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::CancelToken)),
+        }
+    }
+}
+
+/// Remove a token from the registry by name
+pub fn unregister_cancel_token(name: &str) -> Result<()> {
+    crate::critical_section! {
+        let tokens = unsafe { &mut *core::ptr::addr_of_mut!(TOKENS) };
+        match tokens.iter_mut().find(|slot| matches!(slot, Some(t) if t.name == name)) {
+            Some(slot) => {
+                *slot = None;
+                Ok(())
+            }
+            None => Err(ErrorKind::NotFound.on(ObjectKind::CancelToken)),
+        }
+    }
+}
+
+/// Visit every registered token
+pub fn for_each_cancel_token<F: FnMut(&'static CancelToken)>(mut f: F) {
+    let tokens = unsafe { &*core::ptr::addr_of!(TOKENS) };
+    for token in tokens.iter().flatten() {
+        f(token);
+    }
+}
+
+/// True once `shutdown` has been called
+///
+/// Code that starts new work (spawners, request handlers) checks this
+/// and refuses.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Acquire)
+}
+
+// ============================================================================
+// SHUTDOWN
+// ============================================================================
+
+/// Stop the system cleanly and power off
+///
+/// `exit_code` is passed to `arch::system_poweroff` (non-zero makes QEMU
+/// exit with failure). From a task the caller sleeps through the grace
+/// period; from an interrupt handler or before the scheduler runs the
+/// grace period is skipped. A second caller just waits for the first.
+pub fn shutdown(exit_code: u16) -> ! {
+    if SHUTTING_DOWN.swap(true, Ordering::AcqRel) {
+        loop {
+            arch::wait_for_interrupt();
+        }
+    }
+
+    crate::klog!(LogLevel::Info, "shutdown: stopping (exit code {})\n", exit_code);
+
+    let tokens = unsafe { &*core::ptr::addr_of!(TOKENS) };
+    for token in tokens.iter().flatten() {
+        token.cancel();
+    }
+
+    if !arch::in_interrupt() && !get_current_task().is_null() {
+        let _ = task_delay(TickType::from_ms(config::SHUTDOWN_GRACE_MS));
+    }
+
+    arch::disable_interrupts();
+
+    if let Err(e) = fs::sync_all() {
+        crate::klog!(LogLevel::Error, "shutdown: sync failed: {}\n", e);
+    }
+    fs::console_flush();
+
+    boot::run_shutdown_hooks();
+
+    crate::klog!(LogLevel::Info, "shutdown: power off\n");
+    // Hooks may have logged since the first flush
+    fs::console_flush();
+    arch::system_poweroff(exit_code)
+}
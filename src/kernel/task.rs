@@ -1,7 +1,23 @@
 use crate::kernel::list::ListNode;
+#[cfg(not(feature = "tiny"))]
+use crate::kernel::run_histogram::RunHistogram;
 use crate::kernel::types::*;
 
-pub const MAX_TASK_NAME_LEN: usize = 16;
+/// Task name buffer including the terminator (names are dropped with the
+/// tiny feature)
+pub const MAX_TASK_NAME_LEN: usize = if cfg!(feature = "tiny") { 1 } else { 16 };
+
+/// Task may use privileged kernel services (checked by the ecall layer)
+pub const TASK_FLAG_PRIVILEGED: u32 = 1 << 0;
+
+/// Task is never time-sliced or preempted by equal-priority tasks
+pub const TASK_FLAG_NO_PREEMPT: u32 = 1 << 1;
+
+/// Failure of this task is fatal to the system (escalates to reboot)
+pub const TASK_FLAG_CRITICAL: u32 = 1 << 2;
+
+/// All defined task flags
+pub const TASK_FLAGS_ALL: u32 = TASK_FLAG_PRIVILEGED | TASK_FLAG_NO_PREEMPT | TASK_FLAG_CRITICAL;
 
 #[repr(C)]
 pub struct TaskControlBlock {
@@ -28,8 +44,42 @@ pub struct TaskControlBlock {
     pub state: TaskState,
     /// Delay until this tick (for task delays )
     pub delay_until: TickType,
+    /// Woken because the object it waited on was deleted
+    pub wait_aborted: bool,
     /// Number of mutexes held (for priority inheritance - Phase 2)
     pub mutexes_held: usize,
+    /// Scheduling policy among equal-priority tasks
+    pub policy: SchedPolicy,
+    /// Tick at which the task last started waiting in a ready list
+    pub ready_since: TickType,
+    /// Priority levels currently added by aging (0 = not boosted)
+    pub aging_boost: Priority,
+    /// Attribute flags (TASK_FLAG_*)
+    pub flags: u32,
+    /// File descriptor table (open-file indices, -1 = unused)
+    pub fds: [i16; config::MAX_TASK_FDS],
+    /// Times this task was selected to run
+    #[cfg(not(feature = "tiny"))]
+    pub sched_count: u64,
+    /// Longest run of consecutive selections of this task
+    #[cfg(not(feature = "tiny"))]
+    pub longest_streak: u32,
+    /// RCU read-side section nesting depth
+    pub rcu_nesting: u32,
+    /// Grace-period sequence when the outermost read-side section began
+    pub rcu_lock_seq: u64,
+    /// Where activation run lengths go (None = not measured)
+    #[cfg(not(feature = "tiny"))]
+    pub run_histogram: Option<&'static RunHistogram>,
+    /// mcycle at the last switch-in (measured tasks only)
+    #[cfg(not(feature = "tiny"))]
+    pub switched_in_cycle: u64,
+    /// Cycles run so far in the current activation
+    #[cfg(not(feature = "tiny"))]
+    pub activation_cycles: u64,
+    /// mtime ticks spent running (see `task_runtime_stats`)
+    #[cfg(not(feature = "tiny"))]
+    pub runtime: u64,
 }
 
 impl TaskControlBlock {
@@ -65,26 +115,58 @@ impl TaskControlBlock {
         // Event list: sorted by priority (same scheme)
         event_item.set_value((config::MAX_PRIORITIES - priority) as u64);
 
-        // Copy name with null termination
-        let mut name_buf = [0u8; MAX_TASK_NAME_LEN];
-        let name_bytes = name.as_bytes();
-        let copy_len = core::cmp::min(name_bytes.len(), MAX_TASK_NAME_LEN - 1);
-        name_buf[..copy_len].copy_from_slice(&name_bytes[..copy_len]);
-        // name_buf is already zero-initialized, so it's null-terminated
-
-        TaskControlBlock {
+        let mut tcb = TaskControlBlock {
             stack_top: stack,
             state_list_item: state_item,
             event_list_item: event_item,
             priority,
             base_priority: priority,
-            name: name_buf,
+            name: [0; MAX_TASK_NAME_LEN],
             stack_base: stack,
             stack_size,
             state: TaskState::Ready,
             delay_until: TickType::zero(),
+            wait_aborted: false,
             mutexes_held: 0,
+            policy: config::DEFAULT_SCHED_POLICY,
+            ready_since: TickType::zero(),
+            aging_boost: 0,
+            flags: 0,
+            fds: [-1; config::MAX_TASK_FDS],
+            #[cfg(not(feature = "tiny"))]
+            sched_count: 0,
+            #[cfg(not(feature = "tiny"))]
+            longest_streak: 0,
+            rcu_nesting: 0,
+            rcu_lock_seq: 0,
+            #[cfg(not(feature = "tiny"))]
+            run_histogram: None,
+            #[cfg(not(feature = "tiny"))]
+            switched_in_cycle: 0,
+            #[cfg(not(feature = "tiny"))]
+            activation_cycles: 0,
+            #[cfg(not(feature = "tiny"))]
+            runtime: 0,
+        };
+        tcb.set_name(name);
+        tcb
+    }
+
+    /// Replace the task name
+    ///
+    /// Names longer than MAX_TASK_NAME_LEN - 1 bytes are truncated at a
+    /// character boundary (to nothing with the tiny feature).
+    /// Readers (debug dump, coredump, profiler) pick up the new name the
+    /// next time they look at the TCB.
+    pub fn set_name(&mut self, name: &str) {
+        let mut copy_len = core::cmp::min(name.len(), MAX_TASK_NAME_LEN - 1);
+        while !name.is_char_boundary(copy_len) {
+            copy_len -= 1;
         }
+
+        // Copy name with null termination
+        self.name = [0; MAX_TASK_NAME_LEN];
+        self.name[..copy_len].copy_from_slice(&name.as_bytes()[..copy_len]);
     }
 
     /// Get task name as string
@@ -99,6 +181,17 @@ impl TaskControlBlock {
         core::str::from_utf8(&self.name[..len]).unwrap_or("<invalid>")
     }
 
+    /// Conservative [low, high) bounds of the task's stack memory
+    ///
+    /// Derived from the initial stack pointer and size. The top may have
+    /// been aligned down when the stack was initialized, so the low end is
+    /// moved up by the same margin to stay inside the stack.
+    pub fn stack_bounds(&self) -> (usize, usize) {
+        let high = self.stack_base as usize + crate::arch::CONTEXT_SIZE;
+        let low = high - self.stack_size * core::mem::size_of::<usize>() + crate::arch::STACK_ALIGNMENT;
+        (low, high)
+    }
+
     /// Check if task is ready to run
     pub fn is_ready(&self) -> bool {
         self.state == TaskState::Ready || self.state == TaskState::Running
@@ -119,6 +212,26 @@ impl TaskControlBlock {
         self.state == TaskState::Suspended
     }
 
+    /// Check if task may be time-sliced with equal-priority tasks
+    pub fn is_round_robin(&self) -> bool {
+        self.policy == SchedPolicy::RoundRobin && !self.has_flags(TASK_FLAG_NO_PREEMPT)
+    }
+
+    /// Check if all of the given flags are set
+    pub fn has_flags(&self, flags: u32) -> bool {
+        self.flags & flags == flags
+    }
+
+    /// Check if task failure must bring the system down
+    pub fn is_critical(&self) -> bool {
+        self.has_flags(TASK_FLAG_CRITICAL)
+    }
+
+    /// Check if task may use privileged services
+    pub fn is_privileged(&self) -> bool {
+        self.has_flags(TASK_FLAG_PRIVILEGED)
+    }
+
     /// Update list item owner pointers
     ///
     /// CRITICAL: Must be called IMMEDIATELY after TCB is placed in its final location
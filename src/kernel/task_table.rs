@@ -0,0 +1,249 @@
+// Build-time task table and static task creation
+//
+// An application declares its static task set in one place with
+// `task_table!`: name, entry point, priority, stack size and argument of
+// every task. The macro reserves each task's stack in .task_stacks and
+// its TCB as statics, and checks priorities and stack sizes at compile
+// time. The table is handed to the kernel with `register_task_table`
+// and instantiated at the end of the Driver boot phase, in table order,
+// before the scheduler starts.
+//
+// A single task can be created the same way at any point with
+// `create_task!`, which reserves the statics and makes the task ready in
+// one step.
+//
+// # Example
+// ```
+// task_table! {
+//     pub static APP_TASKS = [
+//         { name: "sensor", entry: sensor_task, priority: 3, stack: 1024 },
+//         { name: "logger", entry: logger_task, priority: 1, stack: 512, arg: 2 },
+//     ];
+// }
+//
+// register_task_table(APP_TASKS).unwrap();
+//
+// let shell = create_task!(name: "shell", entry: shell_task, priority: 2, stack: 2048)?;
+// ```
+
+use crate::arch::{initialize_task_stack, TaskEntry};
+use crate::kernel::scheduler::add_task_to_scheduler;
+use crate::kernel::system::is_shutting_down;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, Priority, Result, ErrorKind, ObjectKind, StackSize};
+
+/// One task of a task table (built by `task_table!`)
+pub struct TaskSpec {
+    name: &'static str,
+    entry: TaskEntry,
+    arg: usize,
+    priority: Priority,
+    stack: *mut usize,
+    stack_size: StackSize,
+    tcb: *mut Option<TaskControlBlock>,
+}
+
+// The stack and TCB pointers refer to statics owned by the table entry
+unsafe impl Sync for TaskSpec {}
+
+impl TaskSpec {
+    /// Describe a task; invalid priorities and stack sizes fail the build
+    ///
+    /// # Safety
+    /// `stack` (`stack_size` words) and `tcb` must be statics used by
+    /// nothing but this entry.
+    pub const unsafe fn new(
+        name: &'static str,
+        entry: TaskEntry,
+        arg: usize,
+        priority: Priority,
+        stack: *mut usize,
+        stack_size: StackSize,
+        tcb: *mut Option<TaskControlBlock>,
+    ) -> Self {
+        assert!(priority < config::MAX_PRIORITIES, "task table: priority exceeds MAX_PRIORITIES - 1");
+        assert!(stack_size >= config::MIN_STACK_SIZE, "task table: stack below MIN_STACK_SIZE");
+        TaskSpec { name, entry, arg, priority, stack, stack_size, tcb }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Stack size in words
+    pub fn stack_size(&self) -> StackSize {
+        self.stack_size
+    }
+
+    /// The task's TCB once the table has been instantiated
+    pub fn tcb(&self) -> Option<&'static mut TaskControlBlock> {
+        unsafe { (*self.tcb).as_mut() }
+    }
+
+    /// Build the task's stack frame and TCB and make it ready
+    ///
+    /// Fails with `ResourceBusy` if the task already exists or the system
+    /// is shutting down.
+    pub fn spawn(&self) -> Result<&'static mut TaskControlBlock> {
+        if is_shutting_down() {
+            return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+        }
+
+        crate::critical_section! {
+            unsafe {
+                let slot = &mut *self.tcb;
+                if slot.is_some() {
+                    return Err(ErrorKind::ResourceBusy.on(ObjectKind::Task));
+                }
+
+                let stack = core::slice::from_raw_parts_mut(self.stack, self.stack_size);
+                let sp = initialize_task_stack(self.entry, self.arg as *mut core::ffi::c_void, stack);
+                // List item owners must point at the TCB in its final place
+                let tcb = slot.insert(TaskControlBlock::new(self.name, self.priority, sp, self.stack_size));
+                tcb.update_list_item_owners();
+                add_task_to_scheduler(tcb);
+                Ok(tcb)
+            }
+        }
+    }
+}
+
+/// Create a task with a statically allocated stack and TCB
+///
+/// Takes the same fields as a `task_table!` entry and evaluates to
+/// `Result<&'static mut TaskControlBlock>`. Each invocation owns one set
+/// of statics, so it creates its task once; running it again fails with
+/// `ResourceBusy`.
+///
+/// # Example
+/// ```
+/// let tcb = create_task!(name: "blink", entry: blink_task, priority: 1, stack: 512)?;
+/// set_task_flags(tcb, TASK_FLAG_CRITICAL)?;
+/// ```
+#[macro_export]
+macro_rules! create_task {
+    (
+        name: $name:expr,
+        entry: $entry:expr,
+        priority: $priority:expr,
+        stack: $stack:expr
+        $(, arg: $arg:expr)?
+        $(,)?
+    ) => {{
+        // Evaluate the caller's expressions outside the unsafe block
+        const NAME: &str = $name;
+        const ENTRY: $crate::arch::TaskEntry = $entry;
+        const ARG: usize = 0 $(+ $arg)?;
+        const PRIORITY: $crate::kernel::Priority = $priority;
+        const STACK_SIZE: usize = $stack;
+        #[link_section = ".task_stacks"]
+        static mut STACK: [usize; STACK_SIZE] = [0; STACK_SIZE];
+        static mut TCB: Option<$crate::kernel::TaskControlBlock> = None;
+        static SPEC: $crate::kernel::task_table::TaskSpec = unsafe {
+            $crate::kernel::task_table::TaskSpec::new(
+                NAME,
+                ENTRY,
+                ARG,
+                PRIORITY,
+                core::ptr::addr_of_mut!(STACK) as *mut usize,
+                STACK_SIZE,
+                core::ptr::addr_of_mut!(TCB),
+            )
+        };
+        SPEC.spawn()
+    }};
+}
+
+/// Declare a static task table
+///
+/// Each entry takes `name`, `entry` (`extern "C" fn(*mut c_void) -> !`),
+/// `priority`, `stack` (words) and optionally `arg` (a `usize` passed as
+/// the entry argument).
+#[macro_export]
+macro_rules! task_table {
+    (
+        $vis:vis static $table:ident = [
+            $( {
+                name: $name:expr,
+                entry: $entry:expr,
+                priority: $priority:expr,
+                stack: $stack:expr
+                $(, arg: $arg:expr)?
+                $(,)?
+            } ),* $(,)?
+        ];
+    ) => {
+        $vis static $table: &[$crate::kernel::task_table::TaskSpec] = &[
+            $( {
+                // Evaluate the caller's expressions outside the unsafe block
+                const NAME: &str = $name;
+                const ENTRY: $crate::arch::TaskEntry = $entry;
+                const ARG: usize = 0 $(+ $arg)?;
+                const PRIORITY: $crate::kernel::Priority = $priority;
+                const STACK_SIZE: usize = $stack;
+                #[link_section = ".task_stacks"]
+                static mut STACK: [usize; STACK_SIZE] = [0; STACK_SIZE];
+                static mut TCB: Option<$crate::kernel::TaskControlBlock> = None;
+                unsafe {
+                    $crate::kernel::task_table::TaskSpec::new(
+                        NAME,
+                        ENTRY,
+                        ARG,
+                        PRIORITY,
+                        core::ptr::addr_of_mut!(STACK) as *mut usize,
+                        STACK_SIZE,
+                        core::ptr::addr_of_mut!(TCB),
+                    )
+                }
+            } ),*
+        ];
+    };
+}
+
+// ============================================================================
+// REGISTRATION
+// ============================================================================
+
+static mut TABLE: Option<&'static [TaskSpec]> = None;
+
+/// Hand the application's task table to the kernel
+///
+/// Call before `driver_init`. Fails with `InvalidParameter` if a table
+/// is already registered or two entries share a name.
+pub fn register_task_table(table: &'static [TaskSpec]) -> Result<()> {
+    for (i, spec) in table.iter().enumerate() {
+        if table[..i].iter().any(|other| other.name == spec.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+    }
+
+    crate::critical_section! {
+        let slot = unsafe { &mut *core::ptr::addr_of_mut!(TABLE) };
+        if slot.is_some() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Task));
+        }
+        *slot = Some(table);
+    }
+    Ok(())
+}
+
+/// The registered task table (empty if none)
+pub fn task_table() -> &'static [TaskSpec] {
+    unsafe { (*core::ptr::addr_of!(TABLE)).unwrap_or(&[]) }
+}
+
+/// Create every task of the registered table, in table order
+///
+/// Called by `boot::driver_init`. Entries already instantiated are
+/// skipped, so calling it twice is harmless.
+pub fn spawn_task_table() {
+    for spec in task_table() {
+        if spec.tcb().is_none() {
+            let _ = spec.spawn();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Tasklets: deferred run-to-completion work
+//
+// An interrupt handler that has more to do than it should do with other
+// interrupts masked schedules a tasklet and returns. Tasklets run from
+// the machine software interrupt, after every hardware ISR has returned
+// but before any task runs again, with interrupts enabled so ISRs can
+// still preempt them. They have no stack or TCB of their own and must not
+// block.
+//
+// A tasklet is queued at most once: scheduling it again before it has run
+// only replaces its argument, so bursts of interrupts coalesce into one run.
+
+use crate::arch;
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// A deferred function run from the software interrupt
+///
+/// # Example
+/// ```
+/// fn rx_process(_arg: usize) { /* drain the RX FIFO */ }
+/// static RX_TASKLET: Tasklet = Tasklet::new("uart_rx", rx_process);
+///
+/// // In the UART ISR:
+/// tasklet_schedule(&RX_TASKLET, 0).ok();
+/// ```
+pub struct Tasklet {
+    name: &'static str,
+    func: fn(usize),
+    arg: AtomicUsize,
+    queued: AtomicBool,
+    runs: AtomicU64,
+}
+
+impl Tasklet {
+    pub const fn new(name: &'static str, func: fn(usize)) -> Self {
+        Tasklet {
+            name,
+            func,
+            arg: AtomicUsize::new(0),
+            queued: AtomicBool::new(false),
+            runs: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Number of times the tasklet has run
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    /// True while queued and not yet run
+    pub fn is_queued(&self) -> bool {
+        self.queued.load(Ordering::Acquire)
+    }
+}
+
+// ============================================================================
+// PENDING QUEUE
+// ============================================================================
+
+static mut QUEUE: [Option<&'static Tasklet>; config::MAX_PENDING_TASKLETS] =
+    [None; config::MAX_PENDING_TASKLETS];
+
+/// Next slot to run / next free slot (indices grow without bound)
+static mut HEAD: usize = 0;
+static mut TAIL: usize = 0;
+
+/// Queue a tasklet to run once the current interrupt handlers are done
+///
+/// Safe from any context. Fails with `OutOfMemory` if the queue is full
+/// and with `ResourceBusy` once the system is shutting down.
+pub fn tasklet_schedule(tasklet: &'static Tasklet, arg: usize) -> Result<()> {
+    if crate::kernel::system::is_shutting_down() {
+        return Err(ErrorKind::ResourceBusy.on(ObjectKind::Tasklet));
+    }
+    crate::critical_section! {
+        tasklet.arg.store(arg, Ordering::Relaxed);
+        if tasklet.queued.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        unsafe {
+            let (head, tail) = (*core::ptr::addr_of!(HEAD), &mut *core::ptr::addr_of_mut!(TAIL));
+            if *tail - head == config::MAX_PENDING_TASKLETS {
+                return Err(ErrorKind::OutOfMemory.on(ObjectKind::Tasklet));
+            }
+            (*core::ptr::addr_of_mut!(QUEUE))[*tail % config::MAX_PENDING_TASKLETS] = Some(tasklet);
+            *tail += 1;
+        }
+        tasklet.queued.store(true, Ordering::Release);
+    }
+
+    arch::raise_soft_interrupt();
+    Ok(())
+}
+
+fn pop() -> Option<&'static Tasklet> {
+    crate::critical_section! {
+        unsafe {
+            let (head, tail) = (&mut *core::ptr::addr_of_mut!(HEAD), *core::ptr::addr_of!(TAIL));
+            if *head == tail {
+                return None;
+            }
+            let tasklet = (*core::ptr::addr_of_mut!(QUEUE))[*head % config::MAX_PENDING_TASKLETS].take();
+            *head += 1;
+            tasklet
+        }
+    }
+}
+
+/// Run queued tasklets in FIFO order until the queue is empty
+///
+/// Called by the software interrupt handler. Returns the number run.
+pub fn run_pending_tasklets() -> usize {
+    let mut ran = 0;
+    while let Some(tasklet) = pop() {
+        // Clear first so the tasklet (or an ISR) can queue it again
+        tasklet.queued.store(false, Ordering::Release);
+        (tasklet.func)(tasklet.arg.load(Ordering::Relaxed));
+        tasklet.runs.fetch_add(1, Ordering::Relaxed);
+        ran += 1;
+    }
+    ran
+}
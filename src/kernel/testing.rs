@@ -0,0 +1,333 @@
+// On-target test runner (feature "rtos-test")
+//
+// Kernel behaviour is best tested where it runs: on the target, with the
+// real tick, scheduler and allocator. A test is a plain function returning
+// `TestResult`, listed in a static table with `rtos_test!`:
+//
+//   fn queue_is_fifo() -> TestResult {
+//       QUEUE.try_send(1)?;
+//       test_check!(QUEUE.try_receive()? == 1);
+//       Ok(())
+//   }
+//
+//   pub static TESTS: &[TestCase] = &[rtos_test!(queue_is_fifo)];
+//
+// `spawn_test_runner` creates a runner task that runs each test in a task
+// of its own at config::TEST_TASK_PRIORITY and waits for it with the
+// test's timeout, measured on the scheduler tick. A test passes if it
+// returns Ok and fails if it returns an error or a `test_check!` fails. A
+// test that panics or takes an exception is killed on its own through the
+// supervisor's containment (config::PANIC_CONTAINMENT is on with this
+// feature) and reported as panicked; one still running at its timeout is
+// deleted and reported as timed out. Either way the runner continues
+// with the next test.
+//
+// After the last test the runner prints a summary and powers the machine
+// off; on QEMU the exit status is 0 if every test passed and 1 otherwise.
+//
+// Tests run one at a time and may create helper tasks (e.g. with
+// `create_task!`) at other priorities; a helper ends with `end_task`.
+
+use crate::arch::{self, initialize_task_stack};
+use crate::fs::LogLevel;
+use crate::kernel::list::List;
+use crate::kernel::poison::poison_reclaim;
+use crate::kernel::scheduler::{
+    add_task_to_scheduler, block_current_task_until, delete_task, get_current_task,
+    get_tick_count, wake_first_waiter,
+};
+use crate::kernel::supervisor;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::{config, RtosError, TickType};
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Why a test failed
+#[derive(Copy, Clone, Debug)]
+pub enum TestFailure {
+    /// A kernel call returned an error
+    Error(RtosError),
+    /// A `test_check!` condition was false
+    Check { expr: &'static str, file: &'static str, line: u32 },
+}
+
+impl From<RtosError> for TestFailure {
+    fn from(e: RtosError) -> Self {
+        TestFailure::Error(e)
+    }
+}
+
+impl fmt::Display for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestFailure::Error(e) => write!(f, "{}", e),
+            TestFailure::Check { expr, file, line } => write!(f, "check failed: {} ({}:{})", expr, file, line),
+        }
+    }
+}
+
+/// Outcome of a test function that ran to completion
+pub type TestResult = core::result::Result<(), TestFailure>;
+
+/// A test function and how long it may take
+pub struct TestCase {
+    name: &'static str,
+    func: fn() -> TestResult,
+    timeout: TickType,
+}
+
+impl TestCase {
+    /// A test with the default timeout (config::TEST_TIMEOUT_MS)
+    pub const fn new(name: &'static str, func: fn() -> TestResult) -> Self {
+        TestCase { name, func, timeout: TickType::from_ms(config::TEST_TIMEOUT_MS) }
+    }
+
+    pub const fn with_timeout(mut self, timeout: TickType) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A `TestCase` for a test function, named after its module path
+///
+/// # Example
+/// ```
+/// pub static TESTS: &[TestCase] = &[
+///     rtos_test!(queue_is_fifo),
+///     rtos_test!(timer_fires_once, timeout_ms: 200),
+/// ];
+/// ```
+#[macro_export]
+macro_rules! rtos_test {
+    ($func:path) => {
+        $crate::kernel::testing::TestCase::new(stringify!($func), $func)
+    };
+    ($func:path, timeout_ms: $ms:expr) => {
+        $crate::kernel::testing::TestCase::new(stringify!($func), $func)
+            .with_timeout($crate::kernel::TickType::from_ms($ms))
+    };
+}
+
+/// Fail the test unless `cond` holds
+#[macro_export]
+macro_rules! test_check {
+    ($cond:expr) => {
+        if !$cond {
+            return Err($crate::kernel::testing::TestFailure::Check {
+                expr: stringify!($cond),
+                file: file!(),
+                line: line!(),
+            });
+        }
+    };
+}
+
+/// How a test ended
+#[derive(Copy, Clone)]
+enum Outcome {
+    Passed,
+    Failed(TestFailure),
+    Panicked,
+    TimedOut,
+}
+
+// ============================================================================
+// TEST TASK
+// ============================================================================
+
+#[link_section = ".task_stacks"]
+static mut TEST_STACK: [usize; config::TEST_TASK_STACK_SIZE] = [0; config::TEST_TASK_STACK_SIZE];
+static mut TEST_TCB: Option<TaskControlBlock> = None;
+
+/// Tests being run
+static mut TESTS: &[TestCase] = &[];
+
+/// Result of the running test, set by the test task or the panic path
+static mut OUTCOME: Option<Outcome> = None;
+
+/// The runner waits here for the test task
+static mut DONE: List = List::new();
+static DONE_READY: AtomicBool = AtomicBool::new(false);
+
+fn done_list() -> &'static mut List {
+    let list = unsafe { &mut *ptr::addr_of_mut!(DONE) };
+    if !DONE_READY.swap(true, Ordering::Relaxed) {
+        list.init();
+    }
+    list
+}
+
+fn test_tcb() -> *mut TaskControlBlock {
+    match unsafe { &mut *ptr::addr_of_mut!(TEST_TCB) } {
+        Some(tcb) => tcb,
+        None => ptr::null_mut(),
+    }
+}
+
+/// Record how the running test ended and wake the runner
+fn finish_test(outcome: Outcome) {
+    crate::critical_section! {
+        unsafe { *ptr::addr_of_mut!(OUTCOME) = Some(outcome) };
+        wake_first_waiter(done_list());
+    }
+}
+
+/// End the calling task (a test helper); it does not run again
+pub fn end_task() -> ! {
+    unsafe {
+        let current = get_current_task();
+        arch::disable_interrupts();
+        delete_task(&mut *current);
+        supervisor::run_next_task();
+    }
+    loop {
+        arch::wait_for_interrupt();
+    }
+}
+
+extern "C" fn test_task(arg: *mut c_void) -> ! {
+    let test = unsafe { &(*ptr::addr_of!(TESTS))[arg as usize] };
+    let outcome = match (test.func)() {
+        Ok(()) => Outcome::Passed,
+        Err(failure) => Outcome::Failed(failure),
+    };
+    // The runner must not see the outcome before this task is gone
+    arch::disable_interrupts();
+    finish_test(outcome);
+    end_task()
+}
+
+/// Create the test task for test `index`
+fn spawn_test(index: usize) {
+    unsafe {
+        let stack = &mut *ptr::addr_of_mut!(TEST_STACK);
+        let sp = initialize_task_stack(test_task, index as *mut c_void, stack);
+        let slot = &mut *ptr::addr_of_mut!(TEST_TCB);
+        let tcb = slot.insert(TaskControlBlock::new(
+            "test",
+            config::TEST_TASK_PRIORITY,
+            sp,
+            config::TEST_TASK_STACK_SIZE,
+        ));
+        tcb.update_list_item_owners();
+        add_task_to_scheduler(tcb);
+    }
+}
+
+/// Note a panic or fatal exception of the running task
+///
+/// Called by the panic handler right before the failure is contained;
+/// leaves interrupts disabled so the runner only resumes once the task
+/// is gone. Does nothing unless `tcb` is the test task.
+pub fn test_task_failed(tcb: *mut TaskControlBlock) {
+    if !tcb.is_null() && tcb == test_tcb() {
+        arch::disable_interrupts();
+        finish_test(Outcome::Panicked);
+    }
+}
+
+// ============================================================================
+// RUNNER
+// ============================================================================
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    panicked: usize,
+    timed_out: usize,
+}
+
+/// Run one test in its own task and wait for it to end or time out
+fn run_test(index: usize, test: &TestCase) -> (Outcome, TickType) {
+    unsafe { *ptr::addr_of_mut!(OUTCOME) = None };
+    let start = get_tick_count();
+    let deadline = start.wrapping_add(test.timeout);
+    spawn_test(index);
+
+    let outcome = crate::critical_section! {
+        loop {
+            if let Some(outcome) = unsafe { *ptr::addr_of!(OUTCOME) } {
+                break outcome;
+            }
+            if get_tick_count().has_reached(deadline) {
+                // The test task is not running (we are): delete it where
+                // it is and clear the poison from its stack for reuse
+                let tcb = unsafe { &mut *test_tcb() };
+                delete_task(tcb);
+                let (low, high) = tcb.stack_bounds();
+                unsafe { poison_reclaim(low, high - low) };
+                break Outcome::TimedOut;
+            }
+            // Woken by the test task or the deadline; checked on the next pass
+            let _ = block_current_task_until(done_list(), deadline);
+        }
+    };
+    (outcome, get_tick_count().elapsed_since(start))
+}
+
+extern "C" fn runner_task(_arg: *mut c_void) -> ! {
+    let tests = unsafe { *ptr::addr_of!(TESTS) };
+    let mut summary = Summary::default();
+
+    crate::klog!(LogLevel::Info, "\nrunning {} tests\n", tests.len());
+    for (index, test) in tests.iter().enumerate() {
+        let (outcome, ticks) = run_test(index, test);
+        match outcome {
+            Outcome::Passed => {
+                summary.passed += 1;
+                crate::klog!(LogLevel::Info, "test {} ... ok ({} ms)\n", test.name, ticks.to_ms());
+            }
+            Outcome::Failed(failure) => {
+                summary.failed += 1;
+                crate::klog!(LogLevel::Error, "test {} ... FAILED: {}\n", test.name, failure);
+            }
+            Outcome::Panicked => {
+                summary.panicked += 1;
+                crate::klog!(LogLevel::Error, "test {} ... PANICKED\n", test.name);
+            }
+            Outcome::TimedOut => {
+                summary.timed_out += 1;
+                crate::klog!(
+                    LogLevel::Error,
+                    "test {} ... TIMED OUT after {} ms\n",
+                    test.name,
+                    test.timeout.to_ms()
+                );
+            }
+        }
+    }
+
+    let ok = summary.passed == tests.len();
+    crate::klog!(
+        LogLevel::Info,
+        "\ntest result: {}. {} passed; {} failed; {} panicked; {} timed out\n",
+        if ok { "ok" } else { "FAILED" },
+        summary.passed,
+        summary.failed,
+        summary.panicked,
+        summary.timed_out
+    );
+    crate::fs::console_flush();
+    arch::system_poweroff(if ok { 0 } else { 1 })
+}
+
+/// Create the task that runs `tests` once the scheduler starts
+///
+/// Call before `start_scheduler`. Fails like `create_task!`.
+pub fn spawn_test_runner(tests: &'static [TestCase]) -> crate::kernel::types::Result<()> {
+    unsafe { *ptr::addr_of_mut!(TESTS) = tests };
+    crate::create_task!(
+        name: "test_runner",
+        entry: runner_task,
+        priority: config::TEST_RUNNER_PRIORITY,
+        stack: config::TEST_RUNNER_STACK_SIZE
+    )?;
+    Ok(())
+}
@@ -0,0 +1,416 @@
+// Software timers
+//
+// A timer calls a function once (one-shot) or every period (periodic)
+// from the `timer` task, so periodic work such as sampling a sensor does
+// not need a task of its own. The timer task is created when the first
+// timer starts. It sleeps until the earliest deadline and is woken early
+// when a timer is started, stopped or changed.
+//
+// Callbacks run one after another on the timer task's stack at
+// config::TIMER_TASK_PRIORITY. They must not block: a callback that waits
+// delays every other timer. Hand longer work to a task through a queue.
+//
+// Periodic timers keep a fixed rate: the next deadline is the previous
+// deadline plus the period, not the time the callback ran. A timer that
+// falls more than a whole period behind skips the missed expiries (they
+// are counted as overruns) instead of firing back to back.
+//
+// Every timer that has been started stays in the registry, running or
+// not, so `timer_list` can say why a timer did not fire. A timer taken
+// from an object pool leaves it when it is destroyed; stop it first.
+
+use crate::kernel::list::List;
+use crate::kernel::scheduler::{
+    block_current_task, block_current_task_until, get_tick_count, reschedule, wake_first_waiter,
+};
+use crate::kernel::symtab::symbolize;
+use crate::kernel::types::{config, ErrorKind, ObjectKind, Result, TickType};
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::fmt::{self, Write};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Whether a timer fires once or repeatedly
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Fire once, `period` ticks after starting
+    OneShot,
+    /// Fire every `period` ticks until stopped
+    Periodic,
+}
+
+/// Function called when a timer expires (from the timer task)
+pub type TimerCallback = fn(&'static Timer);
+
+/// A software timer
+///
+/// # Example
+/// ```
+/// fn sample(_timer: &'static Timer) {
+///     SAMPLES.try_send(adc_read()).ok();
+/// }
+/// static SAMPLER: Timer = Timer::new(TickType::from_ms(10), sample).with_name("sampler");
+///
+/// SAMPLER.start()?;
+/// ```
+pub struct Timer {
+    name: &'static str,
+    callback: TimerCallback,
+    mode: TimerMode,
+    /// Value handed to the callback through `id()`
+    id: usize,
+    period: UnsafeCell<TickType>,
+    /// Next expiry while active
+    deadline: UnsafeCell<TickType>,
+    active: AtomicBool,
+    fires: AtomicU64,
+    overruns: AtomicU64,
+}
+
+// Period and deadline are only touched inside critical sections
+unsafe impl Sync for Timer {}
+
+impl Timer {
+    /// A periodic timer calling `callback` every `period` ticks once started
+    pub const fn new(period: TickType, callback: TimerCallback) -> Self {
+        Timer {
+            name: "timer",
+            callback,
+            mode: TimerMode::Periodic,
+            id: 0,
+            period: UnsafeCell::new(period),
+            deadline: UnsafeCell::new(TickType::zero()),
+            active: AtomicBool::new(false),
+            fires: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
+        }
+    }
+
+    /// Name shown by `timer_list`
+    pub const fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub const fn with_mode(mut self, mode: TimerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Value for a callback shared by several timers to tell them apart
+    pub const fn with_id(mut self, id: usize) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Address of the callback function
+    pub fn callback_addr(&self) -> usize {
+        self.callback as usize
+    }
+
+    pub fn period(&self) -> TickType {
+        crate::critical_section! {
+            unsafe { *self.period.get() }
+        }
+    }
+
+    /// True between `start` and `stop` (or a one-shot expiry)
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Ticks until the next expiry, `None` while stopped
+    pub fn remaining(&self) -> Option<TickType> {
+        crate::critical_section! {
+            if !self.is_active() {
+                return None;
+            }
+            let deadline = unsafe { *self.deadline.get() };
+            Some(get_tick_count().ticks_until(deadline).unwrap_or(TickType::zero()))
+        }
+    }
+
+    /// Number of times the callback has run
+    pub fn fires(&self) -> u64 {
+        self.fires.load(Ordering::Relaxed)
+    }
+
+    /// Periodic expiries skipped because the timer task fell behind
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+
+    // ========================================================================
+    // CONTROL
+    // ========================================================================
+
+    /// Start the timer; it expires `period` ticks from now
+    ///
+    /// Starting a running timer restarts it (see `reset`). Fails with
+    /// `InvalidParameter` for a zero period and with `OutOfMemory` if the
+    /// timer is new and config::MAX_TIMERS timers are registered.
+    pub fn start(&'static self) -> Result<()> {
+        crate::critical_section! {
+            let period = unsafe { *self.period.get() };
+            if period == TickType::zero() {
+                return Err(ErrorKind::InvalidParameter.on(ObjectKind::Timer));
+            }
+            register(self)?;
+            self.arm(period);
+        }
+        start_timer_task()?;
+        wake_timer_task();
+        Ok(())
+    }
+
+    /// Stop the timer; it does not fire again until started
+    ///
+    /// Stopping a timer that is not running does nothing.
+    pub fn stop(&self) {
+        self.active.store(false, Ordering::Release);
+        wake_timer_task();
+    }
+
+    /// Restart the timer so it expires a full period from now
+    ///
+    /// Starts a stopped timer. Calling this regularly makes a one-shot
+    /// timer a watchdog that fires only if the calls stop.
+    pub fn reset(&'static self) -> Result<()> {
+        self.start()
+    }
+
+    /// Set a new period and restart the timer from now
+    ///
+    /// Starts a stopped timer. Fails like `start`.
+    pub fn change_period(&'static self, period: TickType) -> Result<()> {
+        if period == TickType::zero() {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Timer));
+        }
+        crate::critical_section! {
+            unsafe { *self.period.get() = period };
+        }
+        self.start()
+    }
+
+    /// Set the deadline `period` ticks from now and mark active
+    /// (inside a critical section)
+    fn arm(&self, period: TickType) {
+        unsafe { *self.deadline.get() = get_tick_count().wrapping_add(period) };
+        self.active.store(true, Ordering::Release);
+    }
+
+    /// Take the expiry if the timer is due and set up the next one
+    ///
+    /// Returns true if the callback should run.
+    fn take_expiry(&self, now: TickType) -> bool {
+        crate::critical_section! {
+            if !self.is_active() {
+                return false;
+            }
+            let deadline = unsafe { &mut *self.deadline.get() };
+            if !now.has_reached(*deadline) {
+                return false;
+            }
+
+            match self.mode {
+                TimerMode::OneShot => self.active.store(false, Ordering::Release),
+                TimerMode::Periodic => {
+                    let period = unsafe { *self.period.get() };
+                    *deadline = deadline.wrapping_add(period);
+                    if now.has_reached(*deadline) {
+                        let behind = now.elapsed_since(*deadline).as_u64() / period.as_u64() + 1;
+                        self.overruns.fetch_add(behind, Ordering::Relaxed);
+                        *deadline = now.wrapping_add(period);
+                    }
+                }
+            }
+            true
+        }
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut TIMERS: [Option<&'static Timer>; config::MAX_TIMERS] = [None; config::MAX_TIMERS];
+
+/// Add a timer the first time it starts (inside a critical section)
+fn register(timer: &'static Timer) -> Result<()> {
+    let timers = unsafe { &mut *ptr::addr_of_mut!(TIMERS) };
+    if timers.iter().flatten().any(|t| ptr::eq(*t, timer)) {
+        return Ok(());
+    }
+    match timers.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(timer);
+            Ok(())
+        }
+        None => Err(ErrorKind::OutOfMemory.on(ObjectKind::Timer)),
+    }
+}
+
+/// Remove a dropped timer (pooled timers being destroyed)
+fn unregister(timer: &Timer) {
+    crate::critical_section! {
+        let timers = unsafe { &mut *ptr::addr_of_mut!(TIMERS) };
+        for slot in timers.iter_mut() {
+            if slot.is_some_and(|t| ptr::eq(t, timer)) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        unregister(self);
+    }
+}
+
+/// Visit every timer that has been started, running or not
+pub fn for_each_timer<F: FnMut(&'static Timer)>(mut f: F) {
+    let timers = unsafe { &*ptr::addr_of!(TIMERS) };
+    for timer in timers.iter().flatten() {
+        f(timer);
+    }
+}
+
+struct SinkWriter(fn(&[u8]));
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Print every registered timer with its state, period, time to the next
+/// expiry and callback
+///
+/// ```text
+/// NAME         STATE    MODE      PERIOD   REMAIN    FIRES  CALLBACK
+/// sampler      active   periodic      10        3     1520  0x80001a2c app::sample
+/// watchdog     stopped  one-shot     500        -        0  0x80001b10 app::bark
+/// ```
+pub fn timer_list(sink: fn(&[u8])) {
+    let mut out = SinkWriter(sink);
+    let _ = writeln!(
+        out,
+        "{:<12} {:<8} {:<8} {:>7} {:>8} {:>8}  CALLBACK",
+        "NAME", "STATE", "MODE", "PERIOD", "REMAIN", "FIRES"
+    );
+
+    for_each_timer(|timer| {
+        let state = if timer.is_active() { "active" } else { "stopped" };
+        let mode = match timer.mode() {
+            TimerMode::OneShot => "one-shot",
+            TimerMode::Periodic => "periodic",
+        };
+        let _ = write!(out, "{:<12} {:<8} {:<8} {:>7} ", timer.name(), state, mode, timer.period().as_u64());
+        let _ = match timer.remaining() {
+            Some(ticks) => write!(out, "{:>8}", ticks.as_u64()),
+            None => write!(out, "{:>8}", "-"),
+        };
+
+        let addr = timer.callback_addr();
+        let _ = match symbolize(addr) {
+            Some((func, _)) => writeln!(out, " {:>8}  {:#x} {}", timer.fires(), addr, func),
+            None => writeln!(out, " {:>8}  {:#x}", timer.fires(), addr),
+        };
+    });
+}
+
+// ============================================================================
+// TIMER TASK
+// ============================================================================
+
+/// The timer task sleeps here between expiries
+static mut WAKEUP: List = List::new();
+static WAKEUP_READY: AtomicBool = AtomicBool::new(false);
+
+/// Set once the timer task exists
+static TIMER_TASK_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn wakeup_list() -> &'static mut List {
+    let list = unsafe { &mut *ptr::addr_of_mut!(WAKEUP) };
+    if !WAKEUP_READY.swap(true, Ordering::Relaxed) {
+        list.init();
+    }
+    list
+}
+
+fn start_timer_task() -> Result<()> {
+    if TIMER_TASK_STARTED.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    let spawned = crate::create_task!(
+        name: "timer",
+        entry: timer_task,
+        priority: config::TIMER_TASK_PRIORITY,
+        stack: config::TIMER_TASK_STACK_SIZE
+    );
+    if let Err(e) = spawned {
+        TIMER_TASK_STARTED.store(false, Ordering::Release);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Let the timer task recompute its next deadline
+fn wake_timer_task() {
+    crate::critical_section! {
+        wake_first_waiter(wakeup_list());
+    }
+    reschedule();
+}
+
+/// Earliest deadline among active timers (inside a critical section)
+fn next_deadline(now: TickType) -> Option<TickType> {
+    let mut next: Option<TickType> = None;
+    for_each_timer(|timer| {
+        if !timer.is_active() {
+            return;
+        }
+        let deadline = unsafe { *timer.deadline.get() };
+        if next.is_none_or(|n| deadline.diff(now) < n.diff(now)) {
+            next = Some(deadline);
+        }
+    });
+    next
+}
+
+extern "C" fn timer_task(_arg: *mut c_void) -> ! {
+    loop {
+        let now = get_tick_count();
+        for_each_timer(|timer| {
+            if timer.take_expiry(now) {
+                (timer.callback)(timer);
+                timer.fires.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        // Deciding to sleep and sleeping in one critical section, so a
+        // timer started in between still wakes us
+        crate::critical_section! {
+            let now = get_tick_count();
+            let _ = match next_deadline(now) {
+                Some(deadline) => block_current_task_until(wakeup_list(), deadline),
+                None => block_current_task(wakeup_list()),
+            };
+        }
+    }
+}
@@ -0,0 +1,327 @@
+// Application trace markers
+//
+// Applications mark points and spans in their own code:
+//
+//   trace::marker(FRAME_DONE);
+//   trace::span_begin(DECODE);
+//   decode(frame);
+//   trace::span_end(DECODE);
+//
+// Each call records the mcycle count, the ID and the task or interrupt it
+// came from into a ring in the .trace_buffer section. Reading the ring
+// back (`trace_for_each`, `trace_dump`) gives latencies in cycles between
+// any two events. IDs are chosen by the application; the kernel gives
+// them no meaning.
+//
+// `trace_export` writes the ring in a versioned binary form for host
+// tools. All integers are little-endian:
+//
+//   u32  magic = "RTTR"
+//   u16  version = TRACE_EXPORT_VERSION
+//   u16  section count
+//   sections, each: u16 kind, u16 reserved (0), u32 length, [length] data
+//
+// Decoders must skip section kinds they do not know. Later versions
+// only append fields, so decoders also skip unknown trailing bytes of a
+// section or event record (the EVENTS section states its record size).
+//
+// Section kinds:
+//
+//   1 CLOCK   u64 cycle_hz (mcycle rate, 0 = unknown), u64 base_cycle
+//             (mcycle of the first event), u64 recorded (events since
+//             the last reset, including ones the ring overwrote)
+//   2 NAMES   u16 count, then per name: u16 index, u8 len, name bytes
+//             (UTF-8); names are interned once, events refer to index
+//   3 EVENTS  u32 count, u16 record size (16), then per event, oldest
+//             first: u64 cycles since base_cycle, u32 id, u8 kind
+//             (0 marker, 1 span begin, 2 span end), u8 context (0 boot,
+//             1 task, 2 interrupt), u16 name index (0xFFFF = none)
+
+use crate::arch::{in_interrupt, read_mcycle};
+use crate::kernel::scheduler::get_current_task;
+use crate::kernel::task::TaskControlBlock;
+use crate::kernel::types::config;
+use core::fmt::{self, Write};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Export schema version, bump on any layout change
+pub const TRACE_EXPORT_VERSION: u16 = 1;
+
+pub const SECTION_CLOCK: u16 = 1;
+pub const SECTION_NAMES: u16 = 2;
+pub const SECTION_EVENTS: u16 = 3;
+
+const EXPORT_MAGIC: &[u8; 4] = b"RTTR";
+const EXPORT_RECORD_SIZE: u16 = 16;
+const NO_NAME: u16 = 0xFFFF;
+
+/// What a trace record marks
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    Marker,
+    SpanBegin,
+    SpanEnd,
+}
+
+/// Where a trace record was made
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceContext {
+    /// Before the scheduler picked a first task
+    Boot,
+    /// From a task
+    Task(*mut TaskControlBlock),
+    /// From an interrupt handler
+    Interrupt,
+}
+
+/// One trace record
+#[derive(Copy, Clone)]
+pub struct TraceEvent {
+    /// mcycle at the time of the call
+    pub cycle: u64,
+    pub id: u32,
+    pub kind: TraceKind,
+    pub context: TraceContext,
+}
+
+// Not zeroed at boot: entries are only read below RECORDED
+#[link_section = ".trace_buffer"]
+static mut EVENTS: [TraceEvent; config::TRACE_SLOTS] = [TraceEvent {
+    cycle: 0,
+    id: 0,
+    kind: TraceKind::Marker,
+    context: TraceContext::Boot,
+}; config::TRACE_SLOTS];
+
+/// Total events recorded (next slot = RECORDED % SLOTS)
+static RECORDED: AtomicU64 = AtomicU64::new(0);
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+fn record(id: u32, kind: TraceKind) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let cycle = read_mcycle();
+    let context = if in_interrupt() {
+        TraceContext::Interrupt
+    } else {
+        match get_current_task() {
+            task if task.is_null() => TraceContext::Boot,
+            task => TraceContext::Task(task),
+        }
+    };
+
+    crate::critical_section! {
+        let seq = RECORDED.load(Ordering::Relaxed);
+        let slot = (seq % config::TRACE_SLOTS as u64) as usize;
+        unsafe {
+            (*ptr::addr_of_mut!(EVENTS))[slot] = TraceEvent { cycle, id, kind, context };
+        }
+        RECORDED.store(seq + 1, Ordering::Release);
+    }
+}
+
+/// Record a point event
+///
+/// Safe from tasks and interrupt handlers.
+#[inline]
+pub fn marker(id: u32) {
+    record(id, TraceKind::Marker);
+}
+
+/// Record the start of a span; close it with `span_end` and the same ID
+#[inline]
+pub fn span_begin(id: u32) {
+    record(id, TraceKind::SpanBegin);
+}
+
+/// Record the end of a span
+#[inline]
+pub fn span_end(id: u32) {
+    record(id, TraceKind::SpanEnd);
+}
+
+/// Turn recording on or off (on by default)
+pub fn trace_enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Release);
+}
+
+/// Discard recorded events
+pub fn trace_reset() {
+    RECORDED.store(0, Ordering::Release);
+}
+
+/// Number of events recorded since the last reset (including overwritten ones)
+pub fn trace_recorded() -> u64 {
+    RECORDED.load(Ordering::Acquire)
+}
+
+/// Visit retained events, oldest first
+pub fn trace_for_each<F: FnMut(&TraceEvent)>(f: F) {
+    for_each_up_to(RECORDED.load(Ordering::Acquire), f);
+}
+
+/// Visit the events still retained out of the first `recorded`
+fn for_each_up_to<F: FnMut(&TraceEvent)>(recorded: u64, mut f: F) {
+    let events = unsafe { &*ptr::addr_of!(EVENTS) };
+    let first = recorded.saturating_sub(config::TRACE_SLOTS as u64);
+
+    for seq in first..recorded {
+        f(&events[(seq % config::TRACE_SLOTS as u64) as usize]);
+    }
+}
+
+/// Cycles spent in the most recent complete span with this ID
+///
+/// Only spans whose begin and end are both still in the ring count.
+pub fn trace_last_span(id: u32) -> Option<u64> {
+    let mut begin = None;
+    let mut last = None;
+    trace_for_each(|e| {
+        if e.id != id {
+            return;
+        }
+        match e.kind {
+            TraceKind::SpanBegin => begin = Some(e.cycle),
+            TraceKind::SpanEnd => {
+                if let Some(start) = begin.take() {
+                    last = Some(e.cycle.wrapping_sub(start));
+                }
+            }
+            TraceKind::Marker => {}
+        }
+    });
+    last
+}
+
+struct SinkWriter(fn(&[u8]));
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Print retained events, one per line:
+/// "<cycle> <task|isr|boot> <M|B|E> <id>"
+pub fn trace_dump(sink: fn(&[u8])) {
+    let mut out = SinkWriter(sink);
+    trace_for_each(|e| {
+        let _ = write!(out, "{} ", e.cycle);
+        let _ = match e.context {
+            TraceContext::Boot => out.write_str("boot"),
+            TraceContext::Task(task) => out.write_str(unsafe { (*task).name_str() }),
+            TraceContext::Interrupt => out.write_str("isr"),
+        };
+        let kind = match e.kind {
+            TraceKind::Marker => 'M',
+            TraceKind::SpanBegin => 'B',
+            TraceKind::SpanEnd => 'E',
+        };
+        let _ = writeln!(out, " {} {}", kind, e.id);
+    });
+}
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+/// Task names an export refers to, in index order
+struct NameTable {
+    tasks: [*mut TaskControlBlock; config::TRACE_EXPORT_NAMES],
+    len: usize,
+}
+
+impl NameTable {
+    /// Index of `task`'s name, adding it if there is room
+    fn intern(&mut self, task: *mut TaskControlBlock) -> u16 {
+        if let Some(i) = self.tasks[..self.len].iter().position(|&t| t == task) {
+            return i as u16;
+        }
+        if self.len == self.tasks.len() {
+            return NO_NAME;
+        }
+        self.tasks[self.len] = task;
+        self.len += 1;
+        (self.len - 1) as u16
+    }
+
+    fn name(&self, index: usize) -> &'static str {
+        unsafe { (*self.tasks[index]).name_str() }
+    }
+}
+
+fn put_section_header(sink: fn(&[u8]), kind: u16, length: usize) {
+    sink(&kind.to_le_bytes());
+    sink(&0u16.to_le_bytes());
+    sink(&(length as u32).to_le_bytes());
+}
+
+/// Write retained events through `sink` in the export format above
+///
+/// Events recorded while the export runs may be overwritten under it;
+/// call `trace_enable(false)` first for a consistent snapshot.
+pub fn trace_export(sink: fn(&[u8])) {
+    let recorded = RECORDED.load(Ordering::Acquire);
+
+    // First pass: time base, event count and the names to intern
+    let mut names = NameTable { tasks: [ptr::null_mut(); config::TRACE_EXPORT_NAMES], len: 0 };
+    let mut base_cycle = None;
+    let mut count: u32 = 0;
+    for_each_up_to(recorded, |e| {
+        base_cycle.get_or_insert(e.cycle);
+        count += 1;
+        if let TraceContext::Task(task) = e.context {
+            names.intern(task);
+        }
+    });
+    let base_cycle = base_cycle.unwrap_or(0);
+
+    sink(EXPORT_MAGIC);
+    sink(&TRACE_EXPORT_VERSION.to_le_bytes());
+    sink(&3u16.to_le_bytes());
+
+    put_section_header(sink, SECTION_CLOCK, 24);
+    sink(&config::CYCLE_FREQ_HZ.to_le_bytes());
+    sink(&base_cycle.to_le_bytes());
+    sink(&recorded.to_le_bytes());
+
+    let names_length: usize = 2 + (0..names.len).map(|i| 3 + names.name(i).len()).sum::<usize>();
+    put_section_header(sink, SECTION_NAMES, names_length);
+    sink(&(names.len as u16).to_le_bytes());
+    for i in 0..names.len {
+        let name = names.name(i);
+        sink(&(i as u16).to_le_bytes());
+        sink(&[name.len() as u8]);
+        sink(name.as_bytes());
+    }
+
+    let events_length = 6 + count as usize * EXPORT_RECORD_SIZE as usize;
+    put_section_header(sink, SECTION_EVENTS, events_length);
+    sink(&count.to_le_bytes());
+    sink(&EXPORT_RECORD_SIZE.to_le_bytes());
+    for_each_up_to(recorded, |e| {
+        let (context, name) = match e.context {
+            TraceContext::Boot => (0u8, NO_NAME),
+            TraceContext::Task(task) => (1, names.intern(task)),
+            TraceContext::Interrupt => (2, NO_NAME),
+        };
+        let kind: u8 = match e.kind {
+            TraceKind::Marker => 0,
+            TraceKind::SpanBegin => 1,
+            TraceKind::SpanEnd => 2,
+        };
+
+        let mut record = [0u8; EXPORT_RECORD_SIZE as usize];
+        record[0..8].copy_from_slice(&e.cycle.wrapping_sub(base_cycle).to_le_bytes());
+        record[8..12].copy_from_slice(&e.id.to_le_bytes());
+        record[12] = kind;
+        record[13] = context;
+        record[14..16].copy_from_slice(&name.to_le_bytes());
+        sink(&record);
+    });
+}
@@ -0,0 +1,135 @@
+// Runtime tunables registry (sysctl-like)
+//
+// Subsystems declare named, range-checked parameters as statics and
+// register them at init. Values can then be read and written by name at
+// runtime without a rebuild.
+
+use crate::kernel::types::{config, Result, ErrorKind, ObjectKind};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A named runtime-adjustable parameter
+///
+/// # Example
+/// ```
+/// static TIME_SLICE: Tunable = Tunable::new("sched.time_slice", 10, 1, 1000);
+/// register_tunable(&TIME_SLICE).unwrap();
+/// let ticks = TIME_SLICE.get();
+/// ```
+pub struct Tunable {
+    name: &'static str,
+    value: AtomicUsize,
+    min: usize,
+    max: usize,
+    /// Called after a successful write with the new value
+    on_change: Option<fn(usize)>,
+}
+
+impl Tunable {
+    /// Create a tunable with an inclusive valid range
+    pub const fn new(name: &'static str, default: usize, min: usize, max: usize) -> Self {
+        Tunable {
+            name,
+            value: AtomicUsize::new(default),
+            min,
+            max,
+            on_change: None,
+        }
+    }
+
+    /// Create a tunable that notifies its owner on every change
+    pub const fn with_callback(
+        name: &'static str,
+        default: usize,
+        min: usize,
+        max: usize,
+        on_change: fn(usize),
+    ) -> Self {
+        Tunable {
+            name,
+            value: AtomicUsize::new(default),
+            min,
+            max,
+            on_change: Some(on_change),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get(&self) -> usize {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Valid range (inclusive)
+    pub fn range(&self) -> (usize, usize) {
+        (self.min, self.max)
+    }
+
+    /// Set a new value, rejecting values outside the valid range
+    pub fn set(&self, value: usize) -> Result<()> {
+        if value < self.min || value > self.max {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Tunable));
+        }
+        self.value.store(value, Ordering::Relaxed);
+        if let Some(f) = self.on_change {
+            f(value);
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// GLOBAL REGISTRY
+// ============================================================================
+
+static mut REGISTRY: [Option<&'static Tunable>; config::MAX_TUNABLES] =
+    [None; config::MAX_TUNABLES];
+
+/// Register a tunable so it can be found by name
+///
+/// Fails with `InvalidParameter` if the name is already taken and with
+/// `OutOfMemory` if the registry is full.
+pub fn register_tunable(tunable: &'static Tunable) -> Result<()> {
+    crate::critical_section! {
+        let registry = unsafe { &mut *core::ptr::addr_of_mut!(REGISTRY) };
+
+        if registry.iter().flatten().any(|t| t.name == tunable.name) {
+            return Err(ErrorKind::InvalidParameter.on(ObjectKind::Tunable));
+        }
+
+        match registry.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(tunable);
+                Ok(())
+            }
+            None => Err(ErrorKind::OutOfMemory.on(ObjectKind::Tunable)),
+        }
+    }
+}
+
+/// Look up a registered tunable by name
+pub fn find_tunable(name: &str) -> Option<&'static Tunable> {
+    let registry = unsafe { &*core::ptr::addr_of!(REGISTRY) };
+    registry.iter().flatten().find(|t| t.name == name).copied()
+}
+
+/// Read a tunable by name
+pub fn tunable_get(name: &str) -> Result<usize> {
+    find_tunable(name)
+        .map(|t| t.get())
+        .ok_or(ErrorKind::NotFound.on(ObjectKind::Tunable))
+}
+
+/// Write a tunable by name
+pub fn tunable_set(name: &str, value: usize) -> Result<()> {
+    find_tunable(name).ok_or(ErrorKind::NotFound.on(ObjectKind::Tunable))?.set(value)
+}
+
+/// Visit every registered tunable (e.g. to list them on a console)
+pub fn for_each_tunable<F: FnMut(&'static Tunable)>(mut f: F) {
+    let registry = unsafe { &*core::ptr::addr_of!(REGISTRY) };
+    for tunable in registry.iter().flatten() {
+        f(tunable);
+    }
+}
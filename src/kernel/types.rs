@@ -1,15 +1,34 @@
 // Core types for the RTOS
 
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
 /// Priority type - higher number = higher priority
 /// Range: 0 (idle) to MAX_PRIORITIES-1 (highest)
 pub type Priority = usize;
 
+/// Raw tick counter width (u32 with the `tick-u32` feature)
+#[cfg(not(feature = "tick-u32"))]
+pub type TickRaw = u64;
+#[cfg(feature = "tick-u32")]
+pub type TickRaw = u32;
+
+/// Signed counterpart of TickRaw, for wraparound-correct differences
+#[cfg(not(feature = "tick-u32"))]
+pub type TickDiff = i64;
+#[cfg(feature = "tick-u32")]
+pub type TickDiff = i32;
+
 /// Tick counter type - wraps around for overflow handling
+///
+/// Note: the derived ordering compares raw values and is NOT wraparound
+/// safe. Compare deadlines with `is_after` / `ticks_until` instead.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct TickType(pub u64);
+pub struct TickType(pub TickRaw);
 
 impl TickType {
-    pub const fn new(value: u64) -> Self {
+    pub const fn new(value: TickRaw) -> Self {
         TickType(value)
     }
 
@@ -18,7 +37,7 @@ impl TickType {
     }
 
     pub const fn max() -> Self {
-        TickType(u64::MAX)
+        TickType(TickRaw::MAX)
     }
 
     pub fn wrapping_add(self, other: TickType) -> TickType {
@@ -29,15 +48,160 @@ impl TickType {
         TickType(self.0.wrapping_sub(earlier.0))
     }
 
-    /// Convert from milliseconds (assuming 1ms tick)
-    pub fn from_ms(ms: u64) -> Self {
-        TickType(ms)
+    /// Signed distance from `other` to `self`
+    ///
+    /// Correct as long as the two points are less than half the counter
+    /// range apart (~24 days at 1 kHz with a 32-bit tick).
+    pub const fn diff(self, other: TickType) -> TickDiff {
+        self.0.wrapping_sub(other.0) as TickDiff
+    }
+
+    /// True if `self` is strictly later than `other`, across wraparound
+    pub const fn is_after(self, other: TickType) -> bool {
+        self.diff(other) > 0
+    }
+
+    /// True if `self` is at or after `deadline` (deadline has passed)
+    pub const fn has_reached(self, deadline: TickType) -> bool {
+        self.diff(deadline) >= 0
+    }
+
+    /// Ticks remaining from `self` (now) until `deadline`, or None if it
+    /// has already passed
+    pub const fn ticks_until(self, deadline: TickType) -> Option<TickType> {
+        let d = deadline.diff(self);
+        if d > 0 {
+            Some(TickType(d as TickRaw))
+        } else {
+            None
+        }
+    }
+
+    /// Raw value widened to u64
+    #[allow(clippy::unnecessary_cast)] // TickRaw is u32 with `tick-u32`
+    pub const fn as_u64(self) -> u64 {
+        self.0 as u64
     }
 
-    /// Convert to milliseconds (assuming 1ms tick)
-    pub fn to_ms(self) -> u64 {
-        self.0
+    /// Convert from milliseconds at config::TICK_RATE_HZ
+    ///
+    /// Rounded per config::TICK_ROUNDING
+    pub const fn from_ms(ms: u64) -> Self {
+        TickType(scale(ms as u128, config::TICK_RATE_HZ as u128, 1_000))
     }
+
+    /// Convert from microseconds at config::TICK_RATE_HZ
+    pub const fn from_us(us: u64) -> Self {
+        TickType(scale(us as u128, config::TICK_RATE_HZ as u128, 1_000_000))
+    }
+
+    /// Ticks in one period of a frequency, e.g. `from_hz(50)` = 20ms
+    pub const fn from_hz(hz: u64) -> Self {
+        TickType(scale(1, config::TICK_RATE_HZ as u128, hz as u128))
+    }
+
+    /// Convert to milliseconds (truncating)
+    pub const fn to_ms(self) -> u64 {
+        ((self.0 as u128 * 1_000) / config::TICK_RATE_HZ as u128) as u64
+    }
+
+    /// Convert to microseconds (truncating)
+    pub const fn to_us(self) -> u64 {
+        ((self.0 as u128 * 1_000_000) / config::TICK_RATE_HZ as u128) as u64
+    }
+}
+
+/// Tick counter that can be read from any context without locking
+///
+/// A seqlock: the single writer (the tick interrupt) makes the sequence
+/// odd while it updates the value, and readers retry if they saw an odd
+/// sequence or it changed under them. This keeps 64-bit reads untorn on
+/// RV32 and consistent against the tick ISR, without disabling interrupts.
+///
+/// The writer must not be interrupted by a reader on the same hart (true
+/// for the tick handler, which runs with interrupts disabled).
+pub struct TickCounter {
+    seq: AtomicUsize,
+    value: UnsafeCell<TickRaw>,
+}
+
+impl TickCounter {
+    pub const fn new() -> Self {
+        TickCounter {
+            seq: AtomicUsize::new(0),
+            value: UnsafeCell::new(0),
+        }
+    }
+
+    /// Read a consistent snapshot
+    pub fn load(&self) -> TickType {
+        loop {
+            let before = self.seq.load(Ordering::Acquire);
+            if before & 1 != 0 {
+                core::hint::spin_loop();
+                continue;
+            }
+            let value = unsafe { core::ptr::read_volatile(self.value.get()) };
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == before {
+                return TickType(value);
+            }
+        }
+    }
+
+    /// Replace the value (single writer only)
+    pub fn store(&self, tick: TickType) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        unsafe { core::ptr::write_volatile(self.value.get(), tick.0) };
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Advance by one tick and return the new value (single writer only)
+    pub fn increment(&self) -> TickType {
+        let next = TickType(unsafe { core::ptr::read_volatile(self.value.get()) }.wrapping_add(1));
+        self.store(next);
+        next
+    }
+}
+
+/// How time -> tick conversions round
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TickRounding {
+    /// Never shorter than requested (safe default for timeouts)
+    Up,
+    /// Truncate (FreeRTOS pdMS_TO_TICKS behavior)
+    Down,
+    /// Round half up
+    Nearest,
+}
+
+/// value * num / den with config::TICK_ROUNDING, saturating at u64::MAX
+const fn scale(value: u128, num: u128, den: u128) -> TickRaw {
+    let product = value * num;
+    let ticks = match config::TICK_ROUNDING {
+        TickRounding::Up => product.div_ceil(den),
+        TickRounding::Down => product / den,
+        TickRounding::Nearest => (product + den / 2) / den,
+    };
+    if ticks > TickRaw::MAX as u128 {
+        TickRaw::MAX
+    } else {
+        ticks as TickRaw
+    }
+}
+
+/// Parse a decimal build-time parameter
+const fn parse_u64(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    let mut value: u64 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u64;
+        i += 1;
+    }
+    value
 }
 
 /// Task states
@@ -50,17 +214,209 @@ pub enum TaskState {
     Deleted,
 }
 
+/// Scheduling policy among tasks of equal priority
+///
+/// Mirrors POSIX SCHED_FIFO / SCHED_RR. Both are preempted by higher
+/// priority tasks; they differ only in time slicing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// Runs until it blocks or yields, never time-sliced
+    Fifo,
+    /// Rotated with equal-priority tasks when its time slice expires
+    RoundRobin,
+}
+
 pub type StackSize = usize;
 
-/// Error types for RTOS operations
+/// What went wrong in an RTOS operation
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum RtosError {
+pub enum ErrorKind {
     OutOfMemory,
     InvalidPriority,
     TaskNotFound,
     InvalidParameter,
     Timeout,
     ResourceBusy,
+    NotFound,
+    /// The object was deleted (while waited on, or since a handle was taken)
+    ObjectDeleted,
+}
+
+impl ErrorKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::OutOfMemory => "out of memory",
+            ErrorKind::InvalidPriority => "invalid priority",
+            ErrorKind::TaskNotFound => "task not found",
+            ErrorKind::InvalidParameter => "invalid parameter",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::ResourceBusy => "resource busy",
+            ErrorKind::NotFound => "not found",
+            ErrorKind::ObjectDeleted => "object deleted",
+        }
+    }
+
+    /// An error of this kind about an object of kind `object`
+    #[track_caller]
+    pub fn on(self, object: ObjectKind) -> RtosError {
+        RtosError::new(self).with_object(object)
+    }
+}
+
+/// Kind of kernel object an error is about
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    Task,
+    Queue,
+    Tasklet,
+    RcuCallback,
+    BootHook,
+    IdleChore,
+    Service,
+    Tunable,
+    CancelToken,
+    ShmRegion,
+    EventCounter,
+    ConsoleSink,
+    Mount,
+    /// File descriptor (the ID is the descriptor)
+    File,
+    Device,
+    /// External interrupt source (the ID is the PLIC source number)
+    Irq,
+    Timer,
+    /// Request/response call (the ID is the correlation ID)
+    Rpc,
+    /// Object pool registry (kernel::pool)
+    Pool,
+    /// Kernel heap region (kernel::heap)
+    HeapRegion,
+    /// DMA buffer (kernel::dma)
+    DmaBuffer,
+}
+
+impl ObjectKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ObjectKind::Task => "task",
+            ObjectKind::Queue => "queue",
+            ObjectKind::Tasklet => "tasklet",
+            ObjectKind::RcuCallback => "rcu callback",
+            ObjectKind::BootHook => "boot hook",
+            ObjectKind::IdleChore => "idle chore",
+            ObjectKind::Service => "service",
+            ObjectKind::Tunable => "tunable",
+            ObjectKind::CancelToken => "cancel token",
+            ObjectKind::ShmRegion => "shm region",
+            ObjectKind::EventCounter => "event counter",
+            ObjectKind::ConsoleSink => "console sink",
+            ObjectKind::Mount => "mount",
+            ObjectKind::File => "fd",
+            ObjectKind::Device => "device",
+            ObjectKind::Irq => "irq",
+            ObjectKind::Timer => "timer",
+            ObjectKind::Rpc => "rpc",
+            ObjectKind::Pool => "pool",
+            ObjectKind::HeapRegion => "heap region",
+            ObjectKind::DmaBuffer => "dma buffer",
+        }
+    }
+}
+
+/// Error returned by RTOS operations
+///
+/// Besides the `ErrorKind` it says which kind of object the failure is
+/// about and, for objects addressed by number (descriptors, slots), which
+/// one. With the `error-location` feature it also records the file and
+/// line that raised it, so an error passed up through several `?` still
+/// points at its origin. `Display` prints all of it on one line, e.g.
+/// `resource busy: mount 1 (src/fs/vfs.rs:231)`.
+///
+/// Errors compare equal by kind, object and ID; the location is ignored.
+///
+/// # Example
+/// ```
+/// match READINGS.receive(Some(TickType::from_ms(100))) {
+///     Ok(sample) => process(sample),
+///     Err(e) if e == ErrorKind::Timeout => report_stall(),
+///     Err(e) => klog!(LogLevel::Error, "sensor: {}\n", e),
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct RtosError {
+    kind: ErrorKind,
+    object: Option<ObjectKind>,
+    id: Option<u32>,
+    #[cfg(feature = "error-location")]
+    location: &'static core::panic::Location<'static>,
+}
+
+impl RtosError {
+    #[track_caller]
+    pub fn new(kind: ErrorKind) -> Self {
+        RtosError {
+            kind,
+            object: None,
+            id: None,
+            #[cfg(feature = "error-location")]
+            location: core::panic::Location::caller(),
+        }
+    }
+
+    pub const fn with_object(self, object: ObjectKind) -> Self {
+        RtosError { object: Some(object), ..self }
+    }
+
+    pub const fn with_id(self, id: u32) -> Self {
+        RtosError { id: Some(id), ..self }
+    }
+
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub const fn object(&self) -> Option<ObjectKind> {
+        self.object
+    }
+
+    pub const fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    /// Where the error was raised
+    #[cfg(feature = "error-location")]
+    pub const fn location(&self) -> &'static core::panic::Location<'static> {
+        self.location
+    }
+}
+
+impl PartialEq for RtosError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.object == other.object && self.id == other.id
+    }
+}
+
+impl Eq for RtosError {}
+
+impl PartialEq<ErrorKind> for RtosError {
+    fn eq(&self, kind: &ErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+impl fmt::Display for RtosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind.as_str())?;
+        match (self.object, self.id) {
+            (Some(object), Some(id)) => write!(f, ": {} {}", object.as_str(), id)?,
+            (Some(object), None) => write!(f, ": {}", object.as_str())?,
+            (None, Some(id)) => write!(f, ": #{}", id)?,
+            (None, None) => {}
+        }
+        #[cfg(feature = "error-location")]
+        write!(f, " ({}:{})", self.location.file(), self.location.line())?;
+        Ok(())
+    }
 }
 
 pub type Result<T> = core::result::Result<T, RtosError>;
@@ -69,8 +425,14 @@ pub type Result<T> = core::result::Result<T, RtosError>;
 pub mod config {
     use super::*;
 
-    /// Maximum number of priority levels
-    pub const MAX_PRIORITIES: usize = 32;
+    // The tiny feature shrinks the sizes below that give a tiny value and
+    // turns off the debug checks, for parts with a few KiB of RAM.
+
+    /// Maximum number of priority levels (tiny: 8)
+    pub const MAX_PRIORITIES: usize = if cfg!(feature = "tiny") { 8 } else { 32 };
+
+    // The scheduler tracks non-empty ready lists in a u64 bitmap
+    const _: () = assert!(MAX_PRIORITIES <= 64, "MAX_PRIORITIES must fit the ready bitmap");
 
     /// Idle task priority (always 0)
     pub const IDLE_PRIORITY: Priority = 0;
@@ -82,14 +444,271 @@ pub mod config {
     pub const MIN_STACK_SIZE: StackSize = 256;
 
     /// System tick frequency in Hz
-    pub const TICK_RATE_HZ: u64 = 1000; // 1ms tick
+    ///
+    /// Set at build time with RTOS_TICK_RATE_HZ (default 1000 = 1ms tick)
+    pub const TICK_RATE_HZ: u64 = parse_u64(env!("RTOS_TICK_RATE_HZ"));
+
+    /// Rounding used by TickType::from_ms / from_us / from_hz
+    ///
+    /// Set at build time with RTOS_TICK_ROUNDING = up | down | nearest
+    pub const TICK_ROUNDING: TickRounding = match env!("RTOS_TICK_ROUNDING").as_bytes() {
+        b"down" => TickRounding::Down,
+        b"nearest" => TickRounding::Nearest,
+        _ => TickRounding::Up,
+    };
+
+    /// CLINT mtime frequency (QEMU virt runs mtime at 10 MHz)
+    pub const MTIME_FREQ_HZ: u64 = 10_000_000;
+
+    /// mtime increments per kernel tick (the mtimecmp reload value)
+    pub const MTIME_TICKS_PER_TICK: u64 = MTIME_FREQ_HZ / TICK_RATE_HZ;
+
+    const _: () = assert!(TICK_RATE_HZ > 0, "RTOS_TICK_RATE_HZ must be non-zero");
+    const _: () = assert!(
+        MTIME_FREQ_HZ.is_multiple_of(TICK_RATE_HZ),
+        "RTOS_TICK_RATE_HZ must divide MTIME_FREQ_HZ or the tick drifts"
+    );
 
     /// Enable/disable preemption
     pub const USE_PREEMPTION: bool = true;
 
-    /// Enable/disable time slicing
+    /// Enable/disable time slicing: at each tick a round-robin task gives
+    /// way to the next ready task of the same priority (needs preemption)
     pub const USE_TIME_SLICING: bool = true;
 
+    /// Policy given to new tasks
+    pub const DEFAULT_SCHED_POLICY: SchedPolicy = SchedPolicy::RoundRobin;
+
+    /// Wake waiters in arrival order instead of highest priority first
+    pub const EVENT_LIST_FIFO: bool = false;
+
+    /// Enable/disable priority aging (starvation protection)
+    pub const USE_PRIORITY_AGING: bool = false;
+
+    /// Ticks a task may wait in a ready list before it is boosted
+    pub const AGING_THRESHOLD_TICKS: u64 = 500;
+
+    /// Maximum number of priority levels aging can add to a task
+    pub const AGING_MAX_BOOST: Priority = 4;
+
+    /// How often (in ticks) the ready lists are scanned for starved tasks
+    pub const AGING_SCAN_INTERVAL_TICKS: u64 = 10;
+
+    /// Maximum number of tasks boosted per scan (bounds tick handler work)
+    pub const AGING_MAX_PER_SCAN: usize = 8;
+
+    /// Verify ready-list structure on every insert/remove (debug builds)
+    pub const CHECK_LIST_INVARIANTS: bool = cfg!(debug_assertions) && !cfg!(feature = "tiny");
+
     /// Stack fill pattern for debugging
     pub const STACK_FILL_BYTE: u8 = 0xa5;
+
+    /// Check task stacks for overflow on every context switch (kernel::stack)
+    pub const CHECK_STACK_OVERFLOW: bool = true;
+
+    /// Words at the bottom of each task stack that must keep the fill pattern
+    pub const STACK_CANARY_WORDS: usize = 4;
+
+    /// RTT target -> host buffer size (in bytes; tiny: 256)
+    pub const RTT_UP_BUFFER_SIZE: usize = if cfg!(feature = "tiny") { 256 } else { 1024 };
+
+    /// RTT host -> target buffer size (in bytes)
+    pub const RTT_DOWN_BUFFER_SIZE: usize = 16;
+
+    /// Core dump record buffer size (in bytes; tiny: 1024)
+    pub const COREDUMP_BUFFER_SIZE: usize = if cfg!(feature = "tiny") { 1024 } else { 4096 };
+
+    /// Words of the panicking stack included in a core dump
+    pub const COREDUMP_STACK_WINDOW_WORDS: usize = 64;
+
+    /// Words of each suspended task's saved frame included in a core dump
+    pub const COREDUMP_TASK_WINDOW_WORDS: usize = 32;
+
+    /// Maximum number of registered runtime tunables (tiny: 0)
+    pub const MAX_TUNABLES: usize = if cfg!(feature = "tiny") { 0 } else { 16 };
+
+    /// Maximum number of entries in the memory map (tiny: 16)
+    pub const MAX_MEMORY_REGIONS: usize = if cfg!(feature = "tiny") { 16 } else { 32 };
+
+    /// Maximum number of heap regions (kernel::heap; tiny: 2)
+    pub const MAX_HEAP_REGIONS: usize = if cfg!(feature = "tiny") { 2 } else { 4 };
+
+    /// Allocations up to this size try fast (REGION_FAST) heap regions first
+    pub const HEAP_FAST_ALLOC_MAX: usize = 256;
+
+    /// Maximum number of mounted filesystems
+    pub const MAX_MOUNTS: usize = 8;
+
+    /// Maximum number of files open system-wide (tiny: 8)
+    pub const MAX_OPEN_FILES: usize = if cfg!(feature = "tiny") { 8 } else { 32 };
+
+    /// File descriptors per task (tiny: 4)
+    pub const MAX_TASK_FDS: usize = if cfg!(feature = "tiny") { 4 } else { 8 };
+
+    /// Records buffered by klog_from_isr! before the logger task drains them (tiny: 8)
+    pub const ISR_LOG_SLOTS: usize = if cfg!(feature = "tiny") { 8 } else { 32 };
+
+    /// Maximum length of one ISR log message (longer ones are truncated)
+    pub const ISR_LOG_MSG_LEN: usize = 80;
+
+    /// Distinct (pc, task) pairs the sampling profiler can track
+    pub const PROFILER_SLOTS: usize = 512;
+
+    /// Maximum number of registered idle chores
+    pub const MAX_IDLE_CHORES: usize = 8;
+
+    /// mcycles of housekeeping per idle pass before the idle task sleeps
+    pub const IDLE_CHORE_BUDGET_CYCLES: u64 = 50_000;
+
+    /// Stack size of the built-in idle task (in words)
+    pub const IDLE_TASK_STACK_SIZE: StackSize = 512;
+
+    /// Maximum number of tasks under supervision
+    pub const MAX_SUPERVISED_TASKS: usize = 8;
+
+    /// A panic in a non-critical task kills only that task (on with
+    /// feature "rtos-test", so a failing test does not stop the run)
+    pub const PANIC_CONTAINMENT: bool = cfg!(feature = "rtos-test");
+
+    /// Maximum number of registered boot hooks (tiny: 8)
+    pub const MAX_BOOT_HOOKS: usize = if cfg!(feature = "tiny") { 8 } else { 16 };
+
+    /// Stack size of the init task that runs App boot hooks (in words)
+    pub const INIT_TASK_STACK_SIZE: StackSize = 1024;
+
+    /// Maximum number of RCU reclaims waiting for a grace period
+    pub const MAX_RCU_CALLBACKS: usize = 16;
+
+    /// Ticks between idle-task checks for finished grace periods
+    pub const RCU_POLL_INTERVAL_TICKS: u64 = 10;
+
+    /// Size of the kernel message ring in bytes (tiny: 512)
+    pub const DMESG_SIZE: usize = if cfg!(feature = "tiny") { 512 } else { 4096 };
+
+    /// Maximum number of console sinks (the dmesg ring takes one)
+    pub const MAX_CONSOLE_SINKS: usize = 6;
+
+    /// Largest payload of one console mux frame (fs::mux)
+    pub const MUX_MAX_PAYLOAD: usize = 240;
+
+    /// Maximum number of named services in the service registry
+    pub const MAX_SERVICES: usize = 16;
+
+    /// Default mcycle budget for kernel polling loops (SpinGuard)
+    pub const SPIN_TIMEOUT_CYCLES: u64 = 100_000_000;
+
+    /// Bytes reserved for the embedded symbol table (kernel::symtab; tiny: 0)
+    pub const SYMTAB_SIZE: usize = if cfg!(feature = "tiny") { 0 } else { 64 * 1024 };
+
+    /// Most stack words a backtrace scans for return addresses
+    /// (kernel::backtrace; tiny: 256)
+    pub const BACKTRACE_SCAN_WORDS: usize = if cfg!(feature = "tiny") { 256 } else { 1024 };
+
+    /// Most frames in a backtrace (tiny: 8)
+    pub const BACKTRACE_MAX_FRAMES: usize = if cfg!(feature = "tiny") { 8 } else { 16 };
+
+    /// Poison freed heap blocks and deleted task stacks (kernel::poison)
+    pub const POISON_FREED_MEMORY: bool = cfg!(debug_assertions) && !cfg!(feature = "tiny");
+
+    /// Fill pattern for freed memory
+    pub const POISON_BYTE: u8 = 0xde;
+
+    /// Maximum number of freed regions checked in the background
+    pub const MAX_POISONED_REGIONS: usize = 16;
+
+    /// Ticks between background checks of a poisoned region
+    pub const POISON_SCRUB_INTERVAL_TICKS: u64 = 100;
+
+    /// Maximum number of tasklets queued at once
+    pub const MAX_PENDING_TASKLETS: usize = 16;
+
+    /// Register accesses kept by the MMIO audit ring (`mmio-audit` feature)
+    pub const MMIO_AUDIT_SLOTS: usize = 256;
+
+    /// Events kept by the application trace ring (kernel::trace; tiny: 32)
+    pub const TRACE_SLOTS: usize = if cfg!(feature = "tiny") { 32 } else { 128 };
+
+    /// Task names one trace export can intern; later tasks are exported
+    /// without a name
+    pub const TRACE_EXPORT_NAMES: usize = 32;
+
+    /// mcycle rate written into trace exports (0 = unknown, as on QEMU)
+    pub const CYCLE_FREQ_HZ: u64 = 0;
+
+    /// Maximum number of registered cancellation tokens (kernel::system)
+    pub const MAX_CANCEL_TOKENS: usize = 16;
+
+    /// Time `shutdown` gives cancelled tasks to stop
+    pub const SHUTDOWN_GRACE_MS: u64 = 100;
+
+    /// Log2 buckets in a task run-length histogram (kernel::run_histogram)
+    pub const RUN_HISTOGRAM_BUCKETS: usize = 40;
+
+    /// Maximum number of registered shared-memory regions (kernel::shm)
+    pub const MAX_SHM_REGIONS: usize = 8;
+
+    /// Maximum number of task attachments across all shared-memory regions
+    pub const MAX_SHM_ATTACHMENTS: usize = 16;
+
+    /// Run the power-on self test (kernel::post) before the App hooks
+    /// (feature "post")
+    pub const POWER_ON_SELF_TEST: bool = cfg!(feature = "post");
+
+    /// A failed self test stops boot with a panic instead of a warning
+    /// (feature "post-fatal")
+    pub const POST_FAILURE_FATAL: bool = cfg!(feature = "post-fatal");
+
+    /// UART receive ring size in bytes (power of two, drivers::uart)
+    pub const UART_RX_BUFFER_SIZE: usize = 256;
+
+    /// UART transmit ring size in bytes (power of two; tiny: 256)
+    pub const UART_TX_BUFFER_SIZE: usize = if cfg!(feature = "tiny") { 256 } else { 1024 };
+
+    /// PLIC priority of the UART interrupt
+    pub const UART_IRQ_PRIORITY: u32 = 2;
+
+    /// Window over which external interrupt rate limits are counted
+    pub const IRQ_RATE_WINDOW_MS: u64 = 10;
+
+    /// How long a source over its rate limit stays masked
+    pub const IRQ_THROTTLE_MS: u64 = 100;
+
+    /// Polling period for throttled interrupt sources
+    pub const IRQ_POLL_INTERVAL_MS: u64 = 1;
+
+    /// Priority of the task polling throttled interrupt sources
+    pub const IRQ_POLL_TASK_PRIORITY: Priority = MAX_PRIORITIES - 2;
+
+    /// Stack of the interrupt polling task (in words)
+    pub const IRQ_POLL_TASK_STACK_SIZE: StackSize = 512;
+
+    /// Maximum number of software timers (kernel::timer; tiny: 4)
+    pub const MAX_TIMERS: usize = if cfg!(feature = "tiny") { 4 } else { 16 };
+
+    /// Priority of the task running timer callbacks
+    pub const TIMER_TASK_PRIORITY: Priority = MAX_PRIORITIES - 1;
+
+    /// Stack of the timer task (in words); timer callbacks run on it
+    pub const TIMER_TASK_STACK_SIZE: StackSize = 1024;
+
+    /// Maximum number of object pools (kernel::pool; tiny: 4)
+    pub const MAX_POOLS: usize = if cfg!(feature = "tiny") { 4 } else { 8 };
+
+    /// Log2 buckets in an interrupt latency histogram (kernel::irq_latency)
+    pub const IRQ_LATENCY_BUCKETS: usize = 20;
+
+    /// Priority of the on-target test runner (kernel::testing)
+    pub const TEST_RUNNER_PRIORITY: Priority = MAX_PRIORITIES - 2;
+
+    /// Stack of the test runner task (in words)
+    pub const TEST_RUNNER_STACK_SIZE: StackSize = 1024;
+
+    /// Priority tests run at; helpers may use the levels around it
+    pub const TEST_TASK_PRIORITY: Priority = MAX_PRIORITIES - 4;
+
+    /// Stack of the task each test runs in (in words)
+    pub const TEST_TASK_STACK_SIZE: StackSize = 2048;
+
+    /// Time a test may take unless its `TestCase` says otherwise
+    pub const TEST_TIMEOUT_MS: u64 = 1000;
 }
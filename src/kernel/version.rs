@@ -0,0 +1,76 @@
+// Kernel version and feature capability queries
+
+use crate::kernel::types::config;
+
+/// Semantic version plus the git revision the kernel was built from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KernelVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    /// Short git hash, "unknown" when built outside a git checkout
+    pub git_hash: &'static str,
+    /// True if the working tree had uncommitted changes at build time
+    pub dirty: bool,
+}
+
+/// Optional kernel subsystems and behaviors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Feature {
+    /// Tick-driven preemption (config::USE_PREEMPTION)
+    Preemption,
+    /// Round-robin among equal priorities (config::USE_TIME_SLICING)
+    TimeSlicing,
+    /// RTT in-memory debug channels
+    Rtt,
+    /// Binary core dump on fatal errors
+    CoreDump,
+    /// Panic message retained across soft reset
+    PanicPersist,
+    /// Runtime tunables registry
+    Tunables,
+    /// Scheduler instrumentation hooks
+    SchedulerHooks,
+    /// Power-on self test at boot (config::POWER_ON_SELF_TEST)
+    SelfTest,
+}
+
+const fn parse_u16(s: &str) -> u16 {
+    let bytes = s.as_bytes();
+    let mut value: u16 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u16;
+        i += 1;
+    }
+    value
+}
+
+const VERSION: KernelVersion = KernelVersion {
+    major: parse_u16(env!("CARGO_PKG_VERSION_MAJOR")),
+    minor: parse_u16(env!("CARGO_PKG_VERSION_MINOR")),
+    patch: parse_u16(env!("CARGO_PKG_VERSION_PATCH")),
+    git_hash: env!("RTOS_GIT_HASH"),
+    dirty: matches!(env!("RTOS_GIT_DIRTY").as_bytes(), b"1"),
+};
+
+/// Get the running kernel's version
+pub const fn version() -> KernelVersion {
+    VERSION
+}
+
+/// Full version string, e.g. "0.1.0"
+pub const fn version_str() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Check whether a feature is available in this build
+pub const fn has_feature(feature: Feature) -> bool {
+    match feature {
+        Feature::Preemption => config::USE_PREEMPTION,
+        Feature::TimeSlicing => config::USE_TIME_SLICING,
+        Feature::SelfTest => config::POWER_ON_SELF_TEST,
+        Feature::Tunables => config::MAX_TUNABLES != 0,
+        Feature::Rtt | Feature::CoreDump | Feature::PanicPersist | Feature::SchedulerHooks => true,
+    }
+}
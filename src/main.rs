@@ -1,18 +1,23 @@
 #![no_std]              // No standard library (embedded)
 #![no_main]             // Custom entry point
 
+extern crate alloc;      // Box, Vec etc. on the kernel heap
+
+use core::ffi::c_void;
 use core::panic::PanicInfo;
 use riscv_rt::entry;     // Provides #[entry] macro
 
 mod kernel;              // Your kernel modules
 mod arch;                // Your architecture code
+mod drivers;             // Device drivers
+mod fs;                  // Virtual filesystem
+#[cfg(feature = "rtos-test")]
+mod tests;               // On-target kernel tests
 
 // Import what we need from kernel
 use kernel::{
-    TaskControlBlock,         // TCB struct
-    init_scheduler,           // Initialize scheduler
-    add_task_to_scheduler,    // Add task to ready list
-    select_next_task,         // Pick next task to run
+    BootHook,                 // Board/app init hooks
+    BootPhase,
     select_next_different_task,
     get_task_count,
     get_top_ready_priority
@@ -20,25 +25,47 @@ use kernel::{
 
 // Import what we need from arch
 use arch::{
-    initialize_task_stack,    // Setup task's initial stack
     switch_context,           // Perform context switch
-    start_first_task,         // Start the first task
 };
 
-const UART_BASE: usize = 0x10000000;
-
+/// Console output: every attached sink (UART0, RTT, dmesg)
 fn uart_putc(c: u8) {
-    unsafe {
-        core::ptr::write_volatile(UART_BASE as *mut u8, c);
-    }
+    fs::console_write(&[c]);
 }
 
 fn uart_puts(s: &str) {
-    for b in s.bytes() {
-        uart_putc(b);
+    fs::console_write(s.as_bytes());
+}
+
+/// Core dump output: raw bytes on UART0 only
+fn uart_put_raw(c: u8) {
+    drivers::UART0.write(&[c]);
+}
+
+/// Console sink: UART0
+fn uart_write(bytes: &[u8]) {
+    drivers::UART0.write(bytes);
+}
+
+/// Console flush: wait until UART0 has sent everything
+fn uart_flush() {
+    drivers::UART0.flush();
+}
+
+/// Console sink: RTT terminal channel
+fn rtt_console_write(bytes: &[u8]) {
+    kernel::rtt_write(0, bytes);
+}
+
+/// Console input for tasks' stdin (host -> target RTT channel, then UART0)
+fn console_read(buf: &mut [u8]) -> usize {
+    match kernel::rtt_read(0, buf) {
+        0 => drivers::UART0.read(buf),
+        n => n,
     }
 }
 
+#[cfg(not(feature = "rtos-test"))]
 fn uart_puthex(value: usize) {
     uart_puts("0x");
     for i in (0..16).rev() {
@@ -75,39 +102,9 @@ fn uart_putdec(value: usize) {
 // TASK FUNCTIONS
 // ============================================================================
 
-/// Idle task - runs when no other tasks are ready
-extern "C" fn idle_task() -> !{
-    uart_puts("[Idle] Starting\r\n");
-
-    let mut count:usize = 0;
-
-    loop{
-        for _ in 0..100000{
-            unsafe {
-                core::arch::asm!("nop");
-            }
-        }
-        uart_puts("[Idle] Count: ");
-        uart_puthex(count);
-        uart_puts("\r\n");
-        count += 1;
-
-        unsafe {
-            let current = kernel::get_current_task();
-            let next = kernel::select_next_different_task();
-
-            if next != current && !next.is_null(){
-                uart_puts("[Idle] Yielding\r\n");
-                kernel::yield_current_task();
-                switch_context(current, next);
-            }
-        }
-    }
-}
-
-
 /// Task 1 - High priority task WITH DEBUG OUTPUT
-extern "C" fn task1() -> ! {
+#[cfg(not(feature = "rtos-test"))]
+extern "C" fn task1(_arg: *mut c_void) -> ! {
     uart_puts("[Task 1] Starting (Priority 2)\r\n");
     
     let mut count: usize = 0;
@@ -196,7 +193,8 @@ extern "C" fn task1() -> ! {
 }
 
 /// Task 2 - Medium priority task
-extern "C" fn task2() -> ! {
+#[cfg(not(feature = "rtos-test"))]
+extern "C" fn task2(_arg: *mut c_void) -> ! {
     uart_puts("[Task 2] Starting (Priority 1)\r\n");
     
     let mut count: usize = 0;
@@ -224,182 +222,180 @@ extern "C" fn task2() -> ! {
     }
 }
 
+// ============================================================================
+// BOARD INIT
+// ============================================================================
+
+static UART_SINK: fs::ConsoleSink =
+    fs::ConsoleSink::new("uart", uart_write, fs::LogLevel::Debug).with_flush(uart_flush);
+static RTT_SINK: fs::ConsoleSink = fs::ConsoleSink::new("rtt", rtt_console_write, fs::LogLevel::Debug);
+
+/// Console on UART0 (mirrored to RTT) for tasks' stdio and the kernel log
+fn board_console_init() -> kernel::Result<()> {
+    fs::attach_console_sink(&UART_SINK)?;
+    fs::attach_console_sink(&RTT_SINK)?;
+    fs::set_console_input(Some(console_read));
+    Ok(())
+}
+
+/// Measure the core clock for busy-wait delays
+fn board_clock_init() -> kernel::Result<()> {
+    arch::delay_calibrate();
+    Ok(())
+}
+
+/// UART0 on interrupts instead of polling
+fn board_uart_init() -> kernel::Result<()> {
+    drivers::UART0.init(drivers::uart::uart0_irq)
+}
+
+fn board_uart_self_test() -> kernel::Result<()> {
+    drivers::UART0.self_test()
+}
+
+/// Idle policy: sleep in `wfi` until the next tick or device interrupt
+fn board_power_init() -> kernel::Result<()> {
+    kernel::set_idle_policy(kernel::IdlePolicy::Wfi);
+    Ok(())
+}
+
+static BOARD_CONSOLE: BootHook = BootHook::new("console", BootPhase::Early, board_console_init);
+static BOARD_CLOCK: BootHook = BootHook::new("clock", BootPhase::Early, board_clock_init);
+static BOARD_POWER: BootHook = BootHook::new("power", BootPhase::Early, board_power_init);
+static BOARD_UART: BootHook =
+    BootHook::new("uart", BootPhase::Driver, board_uart_init).with_self_test(board_uart_self_test);
+
+/// Create the two demo tasks and show where they sit in the ready lists
+#[cfg(not(feature = "rtos-test"))]
+fn spawn_demo_tasks() {
+    // Create task1 (priority 2)
+    uart_puts("[Init] Creating task 1...\r\n");
+    let task1_tcb = create_task!(name: "task1", entry: task1, priority: 2, stack: 1024)
+        .expect("task 1");
+    uart_puts("[Init] Task 1 added\r\n");
+
+    // Create task2 (priority 1)
+    uart_puts("[Init] Creating task 2...\r\n");
+    let task2_tcb = create_task!(name: "task2", entry: task2, priority: 1, stack: 1024)
+        .expect("task 2");
+    uart_puts("[Init] Task 2 added\r\n");
+
+    uart_puts("\r\n");
+    uart_puts("[DEBUG] ========== SCHEDULER STATE ==========\r\n");
+    uart_puts("[DEBUG] Task count: ");
+    uart_putdec(kernel::get_task_count());
+    uart_puts("\r\n");
+    uart_puts("[DEBUG] Top ready priority: ");
+    uart_putdec(kernel::get_top_ready_priority());
+    uart_puts("\r\n");
+
+    // Check container pointers for each task and compare with actual ready_list addresses
+    uart_puts("[DEBUG] Ready list addresses:\r\n");
+    uart_puts("[DEBUG] ready_lists[0]: 0x");
+    uart_puthex(kernel::debug_get_ready_list_address(0));
+    uart_puts("\r\n");
+    uart_puts("[DEBUG] ready_lists[1]: 0x");
+    uart_puthex(kernel::debug_get_ready_list_address(1));
+    uart_puts("\r\n");
+    uart_puts("[DEBUG] ready_lists[2]: 0x");
+    uart_puthex(kernel::debug_get_ready_list_address(2));
+    uart_puts("\r\n");
+
+    uart_puts("\r\n[DEBUG] Task container pointers:\r\n");
+    {
+        let tcb = &*task1_tcb;
+        uart_puts("[DEBUG] Task1 (pri 2) - container: 0x");
+        uart_puthex(tcb.state_list_item.get_container() as usize);
+        let expected = kernel::debug_get_ready_list_address(2);
+        uart_puts(", expected: 0x");
+        uart_puthex(expected);
+        if tcb.state_list_item.get_container() as usize == expected {
+            uart_puts(" ✓ MATCH");
+        } else {
+            uart_puts(" ✗ MISMATCH!");
+        }
+        uart_puts("\r\n");
+    }
+    {
+        let tcb = &*task2_tcb;
+        uart_puts("[DEBUG] Task2 (pri 1) - container: 0x");
+        uart_puthex(tcb.state_list_item.get_container() as usize);
+        let expected = kernel::debug_get_ready_list_address(1);
+        uart_puts(", expected: 0x");
+        uart_puthex(expected);
+        if tcb.state_list_item.get_container() as usize == expected {
+            uart_puts(" ✓ MATCH");
+        } else {
+            uart_puts(" ✗ MISMATCH!");
+        }
+        uart_puts("\r\n");
+    }
+    uart_puts("[DEBUG] ========================================\r\n\r\n");
+}
+
 // ============================================================================
 // MAIN
 // ============================================================================
 
 #[entry]
-fn main() -> ! {
+fn main(_hart_id: usize, dtb: usize) -> ! {
+    kernel::register_boot_hook(&BOARD_CONSOLE).unwrap();
+    kernel::register_boot_hook(&BOARD_CLOCK).unwrap();
+    kernel::register_boot_hook(&BOARD_POWER).unwrap();
+    kernel::register_boot_hook(&BOARD_UART).unwrap();
+    kernel::boot::early_init(dtb);
+
     uart_puts("\r\n");
     uart_puts("========================================\r\n");
     uart_puts("  RTOS Step 5: Context Switching Demo\r\n");
     uart_puts("  Tasks Will Actually RUN!\r\n");
     uart_puts("========================================\r\n");
     uart_puts("\r\n");
-    
-    // Initialize scheduler
-    uart_puts("[Init] Initializing scheduler...\r\n");
-    init_scheduler();
 
-    unsafe {
+    let version = kernel::version();
+    uart_puts("[Init] Kernel v");
+    uart_puts(kernel::version_str());
+    uart_puts(" (");
+    uart_puts(version.git_hash);
+    if version.dirty {
+        uart_puts("-dirty");
+    }
+    uart_puts(")\r\n");
 
-// Task stacks
-        static mut IDLE_STACK: [usize; 512] = [0; 512];
-        static mut TASK1_STACK: [usize; 1024] = [0; 1024];
-        static mut TASK2_STACK: [usize; 1024] = [0; 1024];
-        
-        // Task TCBs
-        static mut IDLE_TCB: Option<TaskControlBlock> = None;
-        static mut TASK1_TCB: Option<TaskControlBlock> = None;
-        static mut TASK2_TCB: Option<TaskControlBlock> = None;
-        
-        // Create idle task (priority 0)
-        uart_puts("[Init] Creating idle task...\r\n");
-        let idle_sp = initialize_task_stack(idle_task, &mut IDLE_STACK);
-        let idle_tcb = TaskControlBlock::new(
-            "idle",
-            0,  // Priority 0 (lowest)
-            idle_sp,
-            IDLE_STACK.len(),
-        );
-        IDLE_TCB = Some(idle_tcb);
-
-        // CRITICAL: Update list item owners AFTER TCB is in final location
-        if let Some(ref mut tcb) = IDLE_TCB {
-            tcb.update_list_item_owners();
-            add_task_to_scheduler(tcb);
-            uart_puts("[Init] Idle task added\r\n");
-        }
-        
-        // Create task1 (priority 2)
-        uart_puts("[Init] Creating task 1...\r\n");
-        let task1_sp = initialize_task_stack(task1, &mut TASK1_STACK);
-        let task1_tcb = TaskControlBlock::new(
-            "task1",
-            2,  // Priority 2
-            task1_sp,
-            TASK1_STACK.len(),
-        );
-        TASK1_TCB = Some(task1_tcb);
-
-        // CRITICAL: Update list item owners AFTER TCB is in final location
-        if let Some(ref mut tcb) = TASK1_TCB {
-            tcb.update_list_item_owners();
-            add_task_to_scheduler(tcb);
-            uart_puts("[Init] Task 1 added\r\n");
-        }
-        
-        // Create task2 (priority 1)
-        uart_puts("[Init] Creating task 2...\r\n");
-        let task2_sp = initialize_task_stack(task2, &mut TASK2_STACK);
-        let task2_tcb = TaskControlBlock::new(
-            "task2",
-            1,  // Priority 1
-            task2_sp,
-            TASK2_STACK.len(),
-        );
-        TASK2_TCB = Some(task2_tcb);
-
-        // CRITICAL: Update list item owners AFTER TCB is in final location
-        if let Some(ref mut tcb) = TASK2_TCB {
-            tcb.update_list_item_owners();
-            add_task_to_scheduler(tcb);
-            uart_puts("[Init] Task 2 added\r\n");
-        }
+    // Report a panic that happened before the last soft reset
+    if let Some(message) = kernel::panic_persist::take_panic_message() {
+        uart_puts("[Init] Previous run panicked: ");
+        uart_puts(message);
+        uart_puts("\r\n\r\n");
+    }
+    
+    // Initialize scheduler and drivers
+    uart_puts("[Init] Initializing scheduler...\r\n");
+    kernel::boot::driver_init();
 
-        uart_puts("\r\n");
-        uart_puts("[DEBUG] ========== SCHEDULER STATE ==========\r\n");
-        uart_puts("[DEBUG] Task count: ");
-        uart_putdec(kernel::get_task_count());
-        uart_puts("\r\n");
-        uart_puts("[DEBUG] Top ready priority: ");
-        uart_putdec(kernel::get_top_ready_priority());
-        uart_puts("\r\n");
+    #[cfg(not(feature = "rtos-test"))]
+    spawn_demo_tasks();
+    #[cfg(feature = "rtos-test")]
+    kernel::testing::spawn_test_runner(tests::ALL).expect("test runner");
 
-        // Check container pointers for each task and compare with actual ready_list addresses
-        uart_puts("[DEBUG] Ready list addresses:\r\n");
-        uart_puts("[DEBUG] ready_lists[0]: 0x");
-        uart_puthex(kernel::debug_get_ready_list_address(0));
-        uart_puts("\r\n");
-        uart_puts("[DEBUG] ready_lists[1]: 0x");
-        uart_puthex(kernel::debug_get_ready_list_address(1));
-        uart_puts("\r\n");
-        uart_puts("[DEBUG] ready_lists[2]: 0x");
-        uart_puthex(kernel::debug_get_ready_list_address(2));
-        uart_puts("\r\n");
+    // Application init runs first, in the init task
+    kernel::boot::spawn_init_task();
 
-        uart_puts("\r\n[DEBUG] Task container pointers:\r\n");
-        if let Some(ref tcb) = IDLE_TCB {
-            uart_puts("[DEBUG] Idle (pri 0) - container: 0x");
-            uart_puthex(tcb.state_list_item.get_container() as usize);
-            let expected = kernel::debug_get_ready_list_address(0);
-            uart_puts(", expected: 0x");
-            uart_puthex(expected);
-            if tcb.state_list_item.get_container() as usize == expected {
-                uart_puts(" ✓ MATCH");
-            } else {
-                uart_puts(" ✗ MISMATCH!");
-            }
-            uart_puts("\r\n");
-        }
-        if let Some(ref tcb) = TASK1_TCB {
-            uart_puts("[DEBUG] Task1 (pri 2) - container: 0x");
-            uart_puthex(tcb.state_list_item.get_container() as usize);
-            let expected = kernel::debug_get_ready_list_address(2);
-            uart_puts(", expected: 0x");
-            uart_puthex(expected);
-            if tcb.state_list_item.get_container() as usize == expected {
-                uart_puts(" ✓ MATCH");
-            } else {
-                uart_puts(" ✗ MISMATCH!");
-            }
-            uart_puts("\r\n");
-        }
-        if let Some(ref tcb) = TASK2_TCB {
-            uart_puts("[DEBUG] Task2 (pri 1) - container: 0x");
-            uart_puthex(tcb.state_list_item.get_container() as usize);
-            let expected = kernel::debug_get_ready_list_address(1);
-            uart_puts(", expected: 0x");
-            uart_puthex(expected);
-            if tcb.state_list_item.get_container() as usize == expected {
-                uart_puts(" ✓ MATCH");
-            } else {
-                uart_puts(" ✗ MISMATCH!");
-            }
-            uart_puts("\r\n");
-        }
-        uart_puts("[DEBUG] ========================================\r\n\r\n");
+    uart_puts("[Init] Starting scheduler...\r\n");
+    uart_puts("========================================\r\n");
+    uart_puts("\r\n");
 
-        uart_puts("[Init] Starting scheduler...\r\n");
-        uart_puts("========================================\r\n");
-        uart_puts("\r\n");
-        
-        // Select first task to run (should be task1 - highest priority)
-        let first_task = select_next_task();
-        
-        if !first_task.is_null() {
-            uart_puts("[Init] First task selected: ");
-            uart_puts((*first_task).name_str());
-            uart_puts("\r\n\r\n");
-            
-            // Set as current task
-            kernel::set_current_task(first_task);
-            
-            // Start the first task!
-            // This will never return - we'll be in task-land forever
-            uart_puts("[Init] Jumping to first task...\r\n\r\n");
-            start_first_task(first_task);
-        } else {
-            uart_puts("[Init] ERROR: No tasks to run!\r\n");
-        }
-    }
-    
-    // Should never reach here
-    panic!("Scheduler failed to start!");
+    // Creates the idle task and jumps to the highest-priority task
+    kernel::start_scheduler();
 }
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    let regs = kernel::coredump::CrashRegisters::capture();
+    kernel::panic_persist::persist_panic(info);
+    // The report must not depend on interrupts still being serviced
+    drivers::UART0.enter_polled_mode();
+
     uart_puts("\r\n\r\n");
     uart_puts("========================================\r\n");
     uart_puts("           *** PANIC! ***\r\n");
@@ -421,15 +417,58 @@ fn panic(info: &PanicInfo) -> ! {
     struct UartWriter;
     impl core::fmt::Write for UartWriter {
         fn write_str(&mut self, s: &str) -> core::fmt::Result {
-            uart_puts(s);
+            for (i, line) in s.split('\n').enumerate() {
+                if i > 0 {
+                    uart_puts("\r\n");
+                }
+                uart_puts(line);
+            }
             Ok(())
         }
     }
     use core::fmt::Write;
     let _ = write!(UartWriter, "{}", info.message());
     uart_puts("\r\n");
+
+    uart_puts("Backtrace:\r\n");
+    let _ = kernel::backtrace::write_backtrace(&mut UartWriter, 0, regs.ra, regs.sp);
     
     uart_puts("========================================\r\n");
+
+    // Binary crash record for host-side tooling
+    kernel::coredump::write_core_dump(info, &regs, uart_put_raw);
+
+    // A handler failure is not the interrupted task's: never contained
+    if arch::in_interrupt() {
+        uart_puts("Panic in an interrupt handler.\r\n");
+    }
+
+    // Contained or restarted, the system goes on with an interrupt-driven UART
+    drivers::UART0.leave_polled_mode();
+
+    // A supervised task is restarted instead; returns only if it is not
+    let current = kernel::get_current_task();
+    kernel::supervisor::handle_task_failure(current, kernel::TaskFailure::Panic);
+
+    // In containment mode only the failed task dies
+    if kernel::supervisor::can_contain_failure(current) {
+        #[cfg(feature = "rtos-test")]
+        kernel::testing::test_task_failed(current);
+        uart_puts("Killing task '");
+        uart_puts(unsafe { (*current).name_str() });
+        uart_puts("', system continues.\r\n");
+        kernel::supervisor::contain_task_failure(current);
+    }
+
+    // Escalating: the rest goes out polled again
+    drivers::UART0.enter_polled_mode();
+
+    // Failure of a critical task escalates to a reboot
+    if !current.is_null() && unsafe { (*current).is_critical() } {
+        uart_puts("Critical task failed - rebooting.\r\n");
+        arch::system_reset();
+    }
+
     uart_puts("System halted.\r\n");
     
     loop {
@@ -0,0 +1,81 @@
+// Kernel heap tests
+
+use crate::kernel::testing::TestResult;
+use crate::kernel::memmap::{REGION_EXEC, REGION_WRITE};
+use crate::kernel::{for_each_heap_region, heap_alloc, heap_free, heap_stats, set_alloc_failure_hook};
+use crate::test_check;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::hint::black_box;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub fn free_restores_usage() -> TestResult {
+    let before = heap_stats();
+
+    let block = black_box(Box::new([0xa5u8; 100]));
+    let during = heap_stats();
+    test_check!(during.used >= before.used + 100);
+    test_check!(during.live_allocations == before.live_allocations + 1);
+    test_check!(block.iter().all(|&b| b == 0xa5));
+
+    drop(block);
+    let after = heap_stats();
+    test_check!(after.used == before.used);
+    test_check!(after.live_allocations == before.live_allocations);
+    Ok(())
+}
+
+pub fn freed_blocks_coalesce() -> TestResult {
+    let before = heap_stats();
+
+    let a: Vec<u8> = black_box(Vec::with_capacity(256));
+    let b: Vec<u8> = black_box(Vec::with_capacity(256));
+    let c: Vec<u8> = black_box(Vec::with_capacity(256));
+    // Freeing the middle block first leaves a hole...
+    drop(b);
+    test_check!(heap_stats().free_blocks > before.free_blocks);
+
+    // ...that merges with its neighbours once they are freed too
+    drop(a);
+    drop(c);
+    let after = heap_stats();
+    test_check!(after.free_blocks == before.free_blocks);
+    test_check!(after.largest_free == before.largest_free);
+    Ok(())
+}
+
+pub fn failure_hook_runs() -> TestResult {
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    fn count_failure(_layout: Layout) -> bool {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    let before = heap_stats();
+    let previous = set_alloc_failure_hook(Some(count_failure));
+    let mut huge: Vec<u8> = Vec::new();
+    let result = huge.try_reserve_exact(before.size + 1);
+    set_alloc_failure_hook(previous);
+
+    test_check!(result.is_err());
+    test_check!(CALLS.load(Ordering::Relaxed) == 1);
+    test_check!(heap_stats().failures == before.failures + 1);
+    Ok(())
+}
+
+pub fn region_flags_are_honoured() -> TestResult {
+    let layout = Layout::from_size_align(64, 16).unwrap();
+
+    // Every heap region is writable, so this lands in one of them...
+    let block = heap_alloc(layout, REGION_WRITE);
+    test_check!(!block.is_null());
+    let mut found = false;
+    for_each_heap_region(|r| found |= (block as usize) >= r.start && (block as usize) < r.end);
+    test_check!(found);
+    unsafe { heap_free(block, layout) };
+
+    // ...while no heap region is executable
+    test_check!(heap_alloc(layout, REGION_EXEC).is_null());
+    Ok(())
+}
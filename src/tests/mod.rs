@@ -0,0 +1,32 @@
+// On-target kernel tests (feature "rtos-test")
+//
+// Each module tests one kernel facility through its public API, on the
+// target, with the real tick and scheduler; kernel::testing runs them.
+// Objects a test uses are statics local to that test, so tests do not
+// depend on each other's leftovers.
+
+use crate::kernel::testing::TestCase;
+use crate::rtos_test;
+
+mod heap;
+mod pool;
+mod queue;
+mod supervisor;
+mod timer;
+
+/// Every test, in run order
+pub static ALL: &[TestCase] = &[
+    rtos_test!(queue::fifo_order_and_full),
+    rtos_test!(queue::receive_times_out),
+    rtos_test!(queue::send_wakes_blocked_receiver),
+    rtos_test!(timer::one_shot_fires_once),
+    rtos_test!(timer::periodic_fires_until_stopped),
+    rtos_test!(pool::capacity_and_reuse),
+    rtos_test!(pool::stale_handle_is_rejected),
+    rtos_test!(pool::destroy_wakes_waiters),
+    rtos_test!(heap::free_restores_usage),
+    rtos_test!(heap::freed_blocks_coalesce),
+    rtos_test!(heap::failure_hook_runs),
+    rtos_test!(heap::region_flags_are_honoured),
+    rtos_test!(supervisor::handler_failure_escalates),
+];
@@ -0,0 +1,81 @@
+// Object pool tests
+
+use crate::kernel::testing::{end_task, TestResult};
+use crate::kernel::{config, reschedule, ErrorKind, Handle, ObjectKind, Pool, Queue};
+use crate::{create_task, test_check};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub fn capacity_and_reuse() -> TestResult {
+    static POOL: Pool<u32, 2> = Pool::new("test-u32", ObjectKind::Pool);
+
+    let a = POOL.create(1)?;
+    let b = POOL.create(2)?;
+    test_check!(*a == 1 && *b == 2);
+    test_check!(POOL.available() == 0);
+    test_check!(matches!(POOL.create(3), Err(e) if e == ErrorKind::OutOfMemory));
+    test_check!(POOL.info().failures() == 1);
+
+    unsafe { POOL.destroy(a)? };
+    test_check!(POOL.available() == 1);
+    // A second destroy of the same object is refused
+    test_check!(matches!(unsafe { POOL.destroy(a) }, Err(e) if e == ErrorKind::InvalidParameter));
+
+    let c = POOL.create(3)?;
+    test_check!(*c == 3);
+    test_check!(POOL.info().peak() == 2);
+    unsafe {
+        POOL.destroy(b)?;
+        POOL.destroy(c)?;
+    }
+    Ok(())
+}
+
+pub fn stale_handle_is_rejected() -> TestResult {
+    static POOL: Pool<u32, 1> = Pool::new("test-handle", ObjectKind::Pool);
+
+    let old = POOL.create_handle(7)?;
+    test_check!(*POOL.get(old)? == 7);
+    unsafe { POOL.destroy_handle(old)? };
+    test_check!(matches!(POOL.get(old), Err(e) if e == ErrorKind::ObjectDeleted));
+    test_check!(matches!(unsafe { POOL.destroy_handle(old) }, Err(e) if e == ErrorKind::ObjectDeleted));
+
+    // The slot is reused, but the old handle still does not reach it
+    let new = POOL.create_handle(8)?;
+    test_check!(new != old);
+    test_check!(matches!(POOL.get(old), Err(e) if e == ErrorKind::ObjectDeleted));
+    test_check!(*POOL.get(new)? == 8);
+    unsafe { POOL.destroy_handle(new)? };
+    Ok(())
+}
+
+static QUEUES: Pool<Queue<u32, 1>, 1> = Pool::new("test-queues", ObjectKind::Queue);
+static mut WAITED_ON: Option<Handle<Queue<u32, 1>>> = None;
+static SAW_DELETED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn waiter(_arg: *mut c_void) -> ! {
+    if let Some(handle) = unsafe { *core::ptr::addr_of!(WAITED_ON) } {
+        if let Ok(queue) = QUEUES.get(handle) {
+            let result = queue.receive(None);
+            SAW_DELETED.store(matches!(result, Err(e) if e == ErrorKind::ObjectDeleted), Ordering::Release);
+        }
+    }
+    end_task()
+}
+
+pub fn destroy_wakes_waiters() -> TestResult {
+    let handle = QUEUES.create_handle(Queue::new())?;
+    unsafe { *core::ptr::addr_of_mut!(WAITED_ON) = Some(handle) };
+
+    create_task!(name: "waiter", entry: waiter, priority: config::TEST_TASK_PRIORITY + 1, stack: 1024)?;
+    // The waiter runs and blocks on the empty queue
+    reschedule();
+    test_check!(!SAW_DELETED.load(Ordering::Acquire));
+
+    // Destroying the queue wakes it with ObjectDeleted; it has the higher
+    // priority, so it has run by the time destroy returns
+    unsafe { QUEUES.destroy_handle(handle)? };
+    test_check!(SAW_DELETED.load(Ordering::Acquire));
+    test_check!(QUEUES.available() == 1);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+// Message queue tests
+
+use crate::kernel::testing::{end_task, TestResult};
+use crate::kernel::{config, reschedule, get_tick_count, ErrorKind, Queue, TickType};
+use crate::{create_task, test_check};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub fn fifo_order_and_full() -> TestResult {
+    static QUEUE: Queue<u32, 3> = Queue::new();
+
+    for value in 1..=3 {
+        QUEUE.try_send(value)?;
+    }
+    test_check!(QUEUE.is_full());
+    test_check!(matches!(QUEUE.try_send(4), Err(e) if e == ErrorKind::Timeout));
+    test_check!(QUEUE.dropped() == 1);
+
+    for value in 1..=3 {
+        test_check!(QUEUE.try_receive()? == value);
+    }
+    test_check!(matches!(QUEUE.try_receive(), Err(e) if e == ErrorKind::Timeout));
+    Ok(())
+}
+
+pub fn receive_times_out() -> TestResult {
+    static QUEUE: Queue<u32, 1> = Queue::new();
+
+    let timeout = TickType::from_ms(20);
+    let start = get_tick_count();
+    test_check!(matches!(QUEUE.receive(Some(timeout)), Err(e) if e == ErrorKind::Timeout));
+    test_check!(get_tick_count().elapsed_since(start).as_u64() >= timeout.as_u64());
+    Ok(())
+}
+
+static HANDOFF: Queue<u32, 1> = Queue::new();
+static RECEIVED: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn receiver(_arg: *mut c_void) -> ! {
+    if let Ok(value) = HANDOFF.receive(None) {
+        RECEIVED.store(value, Ordering::Release);
+    }
+    end_task()
+}
+
+pub fn send_wakes_blocked_receiver() -> TestResult {
+    create_task!(name: "receiver", entry: receiver, priority: config::TEST_TASK_PRIORITY + 1, stack: 1024)?;
+    // The receiver runs and blocks on the empty queue
+    reschedule();
+    test_check!(RECEIVED.load(Ordering::Acquire) == 0);
+
+    // It has the higher priority, so it has the message when send returns
+    HANDOFF.send(42, None)?;
+    test_check!(RECEIVED.load(Ordering::Acquire) == 42);
+    test_check!(HANDOFF.is_empty());
+    Ok(())
+}
@@ -0,0 +1,53 @@
+// Task supervisor tests
+
+use crate::arch::in_interrupt;
+use crate::kernel::supervisor::{can_contain_failure, handle_task_failure, restart_count, unsupervise};
+use crate::kernel::testing::{end_task, TestResult};
+use crate::kernel::{
+    get_current_task, supervise, task_delay, tasklet_schedule, RestartPolicy, TaskFailure, Tasklet, TickType,
+};
+use crate::test_check;
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+extern "C" fn never_started(_arg: *mut c_void) -> ! {
+    end_task()
+}
+
+pub fn handler_failure_escalates() -> TestResult {
+    static IN_HANDLER: AtomicBool = AtomicBool::new(false);
+    static CONTAINABLE: AtomicBool = AtomicBool::new(true);
+    // Runs from the software interrupt, where a handler panic would
+    fn fail_in_handler(_arg: usize) {
+        let current = get_current_task();
+        IN_HANDLER.store(in_interrupt(), Ordering::Relaxed);
+        CONTAINABLE.store(can_contain_failure(current), Ordering::Relaxed);
+        // Returns instead of restarting the interrupted task
+        handle_task_failure(current, TaskFailure::Panic);
+    }
+    static FAIL: Tasklet = Tasklet::new("test-fail", fail_in_handler);
+
+    let current = get_current_task();
+    test_check!(can_contain_failure(current));
+    supervise(
+        unsafe { &mut *current },
+        never_started,
+        core::ptr::null_mut(),
+        RestartPolicy::OnPanic,
+        1,
+        TickType::from_ms(1000),
+    )?;
+
+    tasklet_schedule(&FAIL, 0)?;
+    task_delay(TickType::from_ms(10))?;
+    let restarts = restart_count(current);
+    unsupervise(current);
+
+    test_check!(FAIL.runs() == 1);
+    test_check!(IN_HANDLER.load(Ordering::Relaxed));
+    test_check!(!CONTAINABLE.load(Ordering::Relaxed));
+    test_check!(restarts == Some(0));
+    // The handler's nesting count unwound as usual
+    test_check!(!in_interrupt());
+    Ok(())
+}
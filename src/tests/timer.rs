@@ -0,0 +1,46 @@
+// Software timer tests
+
+use crate::kernel::testing::TestResult;
+use crate::kernel::{task_delay, TickType, Timer, TimerMode};
+use crate::test_check;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub fn one_shot_fires_once() -> TestResult {
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    fn expire(_timer: &'static Timer) {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+    static ONCE: Timer = Timer::new(TickType::from_ms(10), expire)
+        .with_name("test-once")
+        .with_mode(TimerMode::OneShot);
+
+    ONCE.start()?;
+    test_check!(ONCE.is_active());
+    task_delay(TickType::from_ms(50))?;
+
+    test_check!(CALLS.load(Ordering::Relaxed) == 1);
+    test_check!(ONCE.fires() == 1);
+    test_check!(!ONCE.is_active());
+    test_check!(ONCE.remaining().is_none());
+    Ok(())
+}
+
+pub fn periodic_fires_until_stopped() -> TestResult {
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    fn tick(_timer: &'static Timer) {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+    static PERIODIC: Timer = Timer::new(TickType::from_ms(10), tick).with_name("test-periodic");
+
+    PERIODIC.start()?;
+    task_delay(TickType::from_ms(55))?;
+    PERIODIC.stop();
+
+    let fired = CALLS.load(Ordering::Relaxed);
+    test_check!((4..=6).contains(&fired));
+    test_check!(PERIODIC.fires() == fired as u64);
+
+    task_delay(TickType::from_ms(30))?;
+    test_check!(CALLS.load(Ordering::Relaxed) == fired);
+    Ok(())
+}
@@ -0,0 +1,218 @@
+// Minimal ELF64 little-endian reader
+//
+// Just enough to list symbols and sections and locate a section's bytes
+// in the file, so the kernel image can be patched and measured without
+// binutils.
+
+use std::ops::Range;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u64 = 1;
+const SHF_ALLOC: u64 = 2;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    sections: Vec<SectionHeader>,
+    shstrtab: usize,
+}
+
+#[derive(Clone, Copy)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+/// A function or data symbol
+pub struct Symbol {
+    pub addr: u64,
+    pub size: u64,
+    pub name: String,
+}
+
+/// A section occupying memory at run time
+pub struct Section {
+    pub name: String,
+    pub size: u64,
+    /// Contents stored in the image (not NOBITS)
+    pub in_image: bool,
+    pub writable: bool,
+}
+
+fn u16_at(data: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes(data[off..off + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(data[off..off + 8].try_into().unwrap())
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, String> {
+        if data.len() < 64 || &data[..4] != b"\x7fELF" {
+            return Err("not an ELF file".into());
+        }
+        if data[4] != 2 || data[5] != 1 {
+            return Err("only little-endian ELF64 is supported".into());
+        }
+
+        let shoff = u64_at(data, 0x28) as usize;
+        let shentsize = u16_at(data, 0x3a) as usize;
+        let shnum = u16_at(data, 0x3c) as usize;
+        let shstrndx = u16_at(data, 0x3e) as usize;
+
+        let mut sections = Vec::with_capacity(shnum);
+        for i in 0..shnum {
+            let h = shoff + i * shentsize;
+            if h + 64 > data.len() {
+                return Err("truncated section header table".into());
+            }
+            sections.push(SectionHeader {
+                name: u32_at(data, h),
+                kind: u32_at(data, h + 4),
+                flags: u64_at(data, h + 8),
+                offset: u64_at(data, h + 0x18),
+                size: u64_at(data, h + 0x20),
+                link: u32_at(data, h + 0x28),
+                entsize: u64_at(data, h + 0x38),
+            });
+        }
+
+        Ok(Elf { data, sections, shstrtab: shstrndx })
+    }
+
+    fn str_at(&self, table: usize, off: u32) -> &'a str {
+        let start = self.sections[table].offset as usize + off as usize;
+        let end = self.data[start..].iter().position(|&b| b == 0).map_or(self.data.len(), |n| start + n);
+        std::str::from_utf8(&self.data[start..end]).unwrap_or("")
+    }
+
+    /// File byte range of a section
+    pub fn section_range(&self, name: &str) -> Option<Range<usize>> {
+        self.sections
+            .iter()
+            .find(|s| self.str_at(self.shstrtab, s.name) == name)
+            .map(|s| s.offset as usize..(s.offset + s.size) as usize)
+    }
+
+    /// Sections allocated in memory, in file order
+    pub fn alloc_sections(&self) -> Vec<Section> {
+        self.sections
+            .iter()
+            .filter(|s| s.flags & SHF_ALLOC != 0 && s.size > 0)
+            .map(|s| Section {
+                name: self.str_at(self.shstrtab, s.name).to_string(),
+                size: s.size,
+                in_image: s.kind != SHT_NOBITS,
+                writable: s.flags & SHF_WRITE != 0,
+            })
+            .collect()
+    }
+
+    /// Defined function symbols, sorted by address
+    pub fn functions(&self) -> Vec<Symbol> {
+        self.symbols(STT_FUNC)
+    }
+
+    /// Defined data objects, sorted by address
+    pub fn objects(&self) -> Vec<Symbol> {
+        self.symbols(STT_OBJECT)
+    }
+
+    fn symbols(&self, kind: u8) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        for table in self.sections.iter().filter(|s| s.kind == SHT_SYMTAB) {
+            let count = (table.size / table.entsize.max(1)) as usize;
+            for i in 0..count {
+                let e = table.offset as usize + i * table.entsize as usize;
+                let info = self.data[e + 4];
+                let addr = u64_at(self.data, e + 8);
+                if info & 0xf != kind || addr == 0 {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    addr,
+                    size: u64_at(self.data, e + 16),
+                    name: demangle(self.str_at(table.link as usize, u32_at(self.data, e))),
+                });
+            }
+        }
+        symbols.sort_by_key(|s| s.addr);
+        symbols.dedup_by_key(|s| s.addr);
+        symbols
+    }
+}
+
+/// Demangle a legacy Rust symbol (`_ZN...E`), dropping the hash
+///
+/// Other names are returned unchanged.
+pub fn demangle(name: &str) -> String {
+    let Some(mut rest) = name.strip_prefix("_ZN").and_then(|n| n.strip_suffix('E')) else {
+        return name.to_string();
+    };
+
+    let mut parts = Vec::new();
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let Ok(len) = rest[..digits].parse::<usize>() else {
+            return name.to_string();
+        };
+        if digits + len > rest.len() {
+            return name.to_string();
+        }
+        parts.push(&rest[digits..digits + len]);
+        rest = &rest[digits + len..];
+    }
+
+    // Trailing hash component: h + 16 hex digits
+    if let Some(last) = parts.last() {
+        if last.len() == 17 && last.starts_with('h') && last[1..].bytes().all(|b| b.is_ascii_hexdigit()) {
+            parts.pop();
+        }
+    }
+
+    let joined = parts.join("::");
+    let mut out = String::with_capacity(joined.len());
+    let mut s = joined.as_str();
+    while let Some(i) = s.find('$') {
+        out.push_str(&s[..i]);
+        let tail = &s[i + 1..];
+        let Some(end) = tail.find('$') else {
+            out.push_str(&s[i..]);
+            s = "";
+            break;
+        };
+        out.push_str(match &tail[..end] {
+            "LT" => "<",
+            "GT" => ">",
+            "RF" => "&",
+            "BP" => "*",
+            "LP" => "(",
+            "RP" => ")",
+            "C" => ",",
+            "SP" => "@",
+            "u20" => " ",
+            "u27" => "'",
+            "u5b" => "[",
+            "u5d" => "]",
+            "u7b" => "{",
+            "u7d" => "}",
+            "u7e" => "~",
+            other => other,
+        });
+        s = &tail[end + 1..];
+    }
+    out.push_str(s);
+    out.replace("..", "::")
+}
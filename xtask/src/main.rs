@@ -0,0 +1,277 @@
+// Host-side tooling for the kernel
+//
+//   cargo xtask build  [--release] [--features F]   build the kernel
+//   cargo xtask symtab [--release]                  embed the symbol table
+//   cargo xtask disk   [--size MB] [FILE...]        FAT image with app ELFs
+//   cargo xtask image  [--release] [FILE...]        build + symtab + disk
+//   cargo xtask qemu   [--release] [--disk] [--gdb] run under QEMU virt
+//   cargo xtask size   [--release] [--features F]   build, report image/RAM use
+//
+// Outputs go to target/: the kernel ELF in the usual cargo location and
+// the disk image at target/disk.img. `disk` needs mkfs.fat and mcopy
+// (dosfstools, mtools) on the host.
+
+mod elf;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+const TARGET: &str = "riscv64imac-unknown-none-elf";
+const KERNEL: &str = "mindgrove-rtos";
+
+/// Section the kernel reserves for the symbol table (see kernel::symtab)
+const SYMTAB_SECTION: &str = ".rtos_symtab";
+const SYMTAB_MAGIC: u32 = 0x544d_5953; // "SYMT"
+
+const DEFAULT_DISK_MB: u64 = 16;
+
+type Result<T> = std::result::Result<T, String>;
+
+struct Options {
+    release: bool,
+    features: Option<String>,
+    disk_mb: u64,
+    with_disk: bool,
+    gdb: bool,
+    files: Vec<PathBuf>,
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(command) = args.next() else {
+        usage();
+    };
+
+    let mut opts = Options {
+        release: false,
+        features: None,
+        disk_mb: DEFAULT_DISK_MB,
+        with_disk: false,
+        gdb: false,
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => opts.release = true,
+            "--features" => opts.features = args.next(),
+            "--size" => opts.disk_mb = args.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| usage()),
+            "--disk" => opts.with_disk = true,
+            "--gdb" => opts.gdb = true,
+            _ if arg.starts_with("--") => usage(),
+            _ => opts.files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let result = match command.as_str() {
+        "build" => build(&opts),
+        "symtab" => embed_symtab(&opts),
+        "disk" => make_disk(&opts),
+        "image" => build(&opts).and_then(|_| embed_symtab(&opts)).and_then(|_| make_disk(&opts)),
+        "qemu" => qemu(&opts),
+        "size" => build(&opts).and_then(|_| size_report(&opts)),
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("xtask: {}", e);
+        exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: cargo xtask <build|symtab|disk|image|qemu|size> [--release] [--features F]");
+    eprintln!("                   [--size MB] [--disk] [--gdb] [FILE...]");
+    exit(2);
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+fn kernel_elf(opts: &Options) -> PathBuf {
+    let profile = if opts.release { "release" } else { "debug" };
+    root().join("target").join(TARGET).join(profile).join(KERNEL)
+}
+
+fn disk_image() -> PathBuf {
+    root().join("target").join("disk.img")
+}
+
+fn run(cmd: &mut Command) -> Result<()> {
+    let status = cmd.status().map_err(|e| format!("{:?}: {}", cmd.get_program(), e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{:?} failed ({})", cmd.get_program(), status))
+    }
+}
+
+// ============================================================================
+// BUILD
+// ============================================================================
+
+fn build(opts: &Options) -> Result<()> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let mut cmd = Command::new(cargo);
+    cmd.current_dir(root()).args(["build", "--package", KERNEL, "--target", TARGET]);
+    if opts.release {
+        cmd.arg("--release");
+    }
+    if let Some(features) = &opts.features {
+        cmd.args(["--features", features]);
+    }
+    run(&mut cmd)
+}
+
+// ============================================================================
+// SYMBOL TABLE
+// ============================================================================
+
+/// Serialize function symbols in the layout kernel::symtab reads:
+///
+///   u32 magic, u32 count,
+///   count x { u64 addr, u32 size, u32 name offset } sorted by addr,
+///   NUL-terminated names
+fn encode_symtab(symbols: &[elf::Symbol]) -> Vec<u8> {
+    let mut entries = Vec::new();
+    let mut names = Vec::new();
+    for sym in symbols {
+        entries.extend_from_slice(&sym.addr.to_le_bytes());
+        entries.extend_from_slice(&(sym.size.min(u32::MAX as u64) as u32).to_le_bytes());
+        entries.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        names.extend_from_slice(sym.name.as_bytes());
+        names.push(0);
+    }
+
+    let mut out = Vec::with_capacity(8 + entries.len() + names.len());
+    out.extend_from_slice(&SYMTAB_MAGIC.to_le_bytes());
+    out.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    out.extend_from_slice(&entries);
+    out.extend_from_slice(&names);
+    out
+}
+
+/// Write the kernel's own symbol table into its reserved section
+fn embed_symtab(opts: &Options) -> Result<()> {
+    let path = kernel_elf(opts);
+    let mut image = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let (symbols, range) = {
+        let elf = elf::Elf::parse(&image)?;
+        let range = elf
+            .section_range(SYMTAB_SECTION)
+            .ok_or_else(|| format!("{} has no {} section", path.display(), SYMTAB_SECTION))?;
+        (elf.functions(), range)
+    };
+
+    let blob = encode_symtab(&symbols);
+    if blob.len() > range.len() {
+        return Err(format!(
+            "symbol table is {} bytes but {} holds {}; raise config::SYMTAB_SIZE",
+            blob.len(),
+            SYMTAB_SECTION,
+            range.len()
+        ));
+    }
+
+    image[range.clone()].fill(0);
+    image[range.start..range.start + blob.len()].copy_from_slice(&blob);
+    fs::write(&path, image).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    println!("symtab: {} symbols, {}/{} bytes", symbols.len(), blob.len(), range.len());
+    Ok(())
+}
+
+// ============================================================================
+// SIZE REPORT
+// ============================================================================
+
+/// Data objects listed by the size report
+const SIZE_REPORT_OBJECTS: usize = 10;
+
+/// riscv-rt gives the boot stack all RAM left over, so it is not counted
+const STACK_SECTION: &str = ".stack";
+
+/// Print what the kernel occupies per section and its largest statics
+///
+/// "image" is what must be stored (flash on a part that executes in
+/// place: code, constants and the initial values of .data); "ram" is what
+/// is writable at run time (.data, .bss and the NOLOAD buffers, heap and
+/// stacks). Compare a default build with `--features tiny`.
+fn size_report(opts: &Options) -> Result<()> {
+    let path = kernel_elf(opts);
+    let data = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let elf = elf::Elf::parse(&data)?;
+
+    println!("{:<20} {:>10} {:>10}", "section", "image", "ram");
+    let (mut image, mut ram) = (0, 0);
+    for section in elf.alloc_sections() {
+        let stored = if section.in_image { section.size } else { 0 };
+        let writable = if section.writable { section.size } else { 0 };
+        if section.name == STACK_SECTION {
+            println!("{:<20} {:>10} {:>10}", section.name, "-", "(rest)");
+            continue;
+        }
+        println!("{:<20} {:>10} {:>10}", section.name, stored, writable);
+        image += stored;
+        ram += writable;
+    }
+    println!("{:<20} {:>10} {:>10}", "total", image, ram);
+
+    let mut objects = elf.objects();
+    objects.sort_by_key(|sym| std::cmp::Reverse(sym.size));
+    println!();
+    println!("largest statics:");
+    for sym in objects.iter().take(SIZE_REPORT_OBJECTS) {
+        println!("{:>10}  {}", sym.size, sym.name);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// DISK IMAGE
+// ============================================================================
+
+/// Create a FAT image and copy the given files (app ELFs) into its root
+fn make_disk(opts: &Options) -> Result<()> {
+    let image = disk_image();
+    let _ = fs::remove_file(&image);
+
+    run(Command::new("mkfs.fat")
+        .args(["-C", "-n", "RTOS"])
+        .arg(&image)
+        .arg((opts.disk_mb * 1024).to_string()))?;
+
+    for file in &opts.files {
+        run(Command::new("mcopy").arg("-i").arg(&image).arg(file).arg("::/"))?;
+    }
+
+    println!("disk: {} ({} MB, {} files)", image.display(), opts.disk_mb, opts.files.len());
+    Ok(())
+}
+
+// ============================================================================
+// QEMU
+// ============================================================================
+
+fn qemu(opts: &Options) -> Result<()> {
+    let mut cmd = Command::new("qemu-system-riscv64");
+    cmd.args(["-machine", "virt", "-nographic", "-bios", "none", "-kernel"])
+        .arg(kernel_elf(opts));
+
+    if opts.with_disk {
+        let mut drive = std::ffi::OsString::from("file=");
+        drive.push(disk_image());
+        drive.push(",if=none,format=raw,id=hd0");
+        cmd.arg("-drive").arg(drive);
+        cmd.args(["-device", "virtio-blk-device,drive=hd0"]);
+    }
+    if opts.gdb {
+        // Wait for `target remote :1234`
+        cmd.args(["-s", "-S"]);
+    }
+
+    run(&mut cmd)
+}